@@ -17,9 +17,17 @@ fn split(path: &str) -> (PathBuf, PathBuf) {
     }
 }
 
-fn list_entry(name: &str, entry: &cfb::Entry, long: bool) {
+fn display_name(name: &str, msi: bool) -> String {
+    if msi {
+        cfb::msi::decode_name(name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn list_entry(name: &str, entry: &cfb::Entry, long: bool, msi: bool) {
     if !long {
-        println!("{}", entry.name());
+        println!("{}", display_name(entry.name(), msi));
         return;
     }
     let length = if entry.len() >= 10_000_000_000 {
@@ -31,11 +39,13 @@ fn list_entry(name: &str, entry: &cfb::Entry, long: bool) {
     } else {
         format!("{} B ", entry.len())
     };
-    let last_modified = {
-        let timestamp = entry.created().max(entry.modified());
-        let datetime = OffsetDateTime::from(timestamp);
-        let (year, month, day) = datetime.to_calendar_date();
-        format!("{:04}-{:02}-{:02}", year, month as u8, day)
+    let last_modified = match entry.created().max(entry.modified()) {
+        Some(timestamp) => {
+            let datetime = OffsetDateTime::from(timestamp);
+            let (year, month, day) = datetime.to_calendar_date();
+            format!("{:04}-{:02}-{:02}", year, month as u8, day)
+        }
+        None => "          ".to_string(),
     };
     println!(
         "{}{:08x}   {:>10}   {}   {}",
@@ -43,7 +53,7 @@ fn list_entry(name: &str, entry: &cfb::Entry, long: bool) {
         entry.state_bits(),
         length,
         last_modified,
-        name
+        display_name(name, msi)
     );
     if entry.is_storage() {
         println!(" {}", entry.clsid().hyphenated());
@@ -79,6 +89,11 @@ fn main() {
                         .short("l")
                         .help("Lists in long format"),
                 )
+                .arg(
+                    Arg::with_name("msi")
+                        .short("m")
+                        .help("Decodes Windows Installer stream names"),
+                )
                 .arg(Arg::with_name("path").multiple(true)),
         )
         .get_matches();
@@ -105,18 +120,19 @@ fn main() {
     } else if let Some(submatches) = matches.subcommand_matches("ls") {
         if let Some(paths) = submatches.values_of("path") {
             let long = submatches.is_present("long");
+            let msi = submatches.is_present("msi");
             for path in paths {
                 let (comp_path, inner_path) = split(path);
                 let comp = cfb::open(&comp_path).unwrap();
                 let entry = comp.entry(&inner_path).unwrap();
                 if entry.is_stream() {
-                    list_entry(entry.name(), &entry, long);
+                    list_entry(entry.name(), &entry, long, msi);
                 } else {
                     if submatches.is_present("all") {
-                        list_entry(".", &entry, long);
+                        list_entry(".", &entry, long, msi);
                     }
                     for subentry in comp.read_storage(&inner_path).unwrap() {
-                        list_entry(subentry.name(), &subentry, long);
+                        list_entry(subentry.name(), &subentry, long, msi);
                     }
                 }
             }
@@ -0,0 +1,75 @@
+#!/usr/bin/env python3
+"""Generates the UPPERCASE_RUNS table in src/internal/upcase.rs.
+
+Usage:
+
+    curl -O https://www.unicode.org/Public/14.0.0/ucd/UnicodeData.txt
+    python3 scripts/upcase_table.py UnicodeData.txt
+
+The output replaces the `static UPPERCASE_RUNS` item in upcase.rs.  Only the
+simple uppercase mappings (field 12 of UnicodeData.txt) of BMP code points
+are used, since MS-CFB uppercases each UTF-16 code unit on its own.
+"""
+
+import sys
+
+
+def read_mappings(path):
+    """Returns a dict from each BMP code point to its simple uppercase
+    mapping, for code points whose mapping is to a different BMP code
+    point."""
+    mappings = {}
+    with open(path, encoding='utf-8') as file:
+        for line in file:
+            fields = line.rstrip('\n').split(';')
+            code_point = int(fields[0], 16)
+            if code_point > 0xFFFF or not fields[12]:
+                continue
+            upper = int(fields[12], 16)
+            if upper <= 0xFFFF and upper != code_point:
+                mappings[code_point] = upper
+    return mappings
+
+
+def make_runs(mappings):
+    """Groups the mappings into runs of `(start, count, stride, delta)`.  A
+    run has a stride of 2 only if the code points it skips over have no
+    mappings of their own, so that the runs stay sorted and disjoint."""
+    delta = {code: (upper - code) & 0xFFFF for code, upper in mappings.items()}
+
+    def continues(code, stride, run_delta):
+        return (delta.get(code) == run_delta and
+                (stride == 1 or code - 1 not in delta))
+
+    runs = []
+    covered = set()
+    for start in sorted(delta):
+        if start in covered:
+            continue
+        run_delta = delta[start]
+        if continues(start + 1, 1, run_delta):
+            stride = 1
+        elif continues(start + 2, 2, run_delta):
+            stride = 2
+        else:
+            stride = 1
+        count = 1
+        while continues(start + count * stride, stride, run_delta):
+            count += 1
+        covered.update(start + i * stride for i in range(count))
+        runs.append((start, count, stride, run_delta))
+    return runs
+
+
+def main():
+    if len(sys.argv) != 2:
+        sys.exit('Usage: {} path/to/UnicodeData.txt'.format(sys.argv[0]))
+    runs = make_runs(read_mappings(sys.argv[1]))
+    print('static UPPERCASE_RUNS: &[(u16, u16, u16, u16)] = &[')
+    for run in runs:
+        print('    (0x{:04X}, {}, {}, 0x{:04X}),'.format(*run))
+    print('];')
+
+
+if __name__ == '__main__':
+    main()
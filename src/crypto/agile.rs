@@ -0,0 +1,513 @@
+use crate::crypto::primitives::{
+    fit_to_len, pad_to_block, random_bytes, Aes, HashAlgorithm, AES_BLOCK_LEN,
+};
+use crate::crypto::{split_package, EncryptionOptions};
+use base64::Engine;
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::convert::TryFrom;
+use std::io;
+
+//===========================================================================//
+
+const PASSWORD_KEY_ENCRYPTOR: &str =
+    "http://schemas.microsoft.com/office/2006/keyEncryptor/password";
+
+const BLOCK_KEY_VERIFIER_INPUT: [u8; 8] =
+    [0xfe, 0xa7, 0xd2, 0x76, 0x3b, 0x4b, 0x9e, 0x79];
+const BLOCK_KEY_VERIFIER_VALUE: [u8; 8] =
+    [0xd7, 0xaa, 0x0f, 0x6d, 0x30, 0x61, 0x34, 0x4e];
+const BLOCK_KEY_ENCRYPTED_KEY: [u8; 8] =
+    [0x14, 0x6e, 0x0b, 0xe7, 0xab, 0xac, 0xd0, 0xd6];
+const BLOCK_KEY_HMAC_KEY: [u8; 8] =
+    [0x5f, 0xb2, 0xad, 0x01, 0x0c, 0xb9, 0xe1, 0xf6];
+const BLOCK_KEY_HMAC_VALUE: [u8; 8] =
+    [0xa0, 0x67, 0x7f, 0x02, 0xb2, 0x2c, 0x84, 0x33];
+
+const BASE64: base64::engine::GeneralPurpose =
+    base64::engine::general_purpose::STANDARD;
+
+const SEGMENT_LEN: usize = 4096;
+const SALT_LEN: usize = 16;
+
+// MS-OFFCRYPTO section 2.3.4.10 caps the spin count at ten million; larger
+// values (which could otherwise make us hash for hours) are rejected.
+const MAX_SPIN_COUNT: u32 = 10_000_000;
+
+const VERSION_MAJOR: u16 = 4;
+const VERSION_MINOR: u16 = 4;
+const RESERVED: u32 = 0x40;
+
+//===========================================================================//
+
+/// The cipher and hash parameters shared by the `keyData` and
+/// `encryptedKey` elements of Agile encryption info.
+struct KeyParams {
+    salt: Vec<u8>,
+    block_len: usize,
+    key_len: usize,
+    hash: HashAlgorithm,
+}
+
+impl KeyParams {
+    fn from_element(element: &Element) -> io::Result<KeyParams> {
+        let cipher = element.attribute("cipherAlgorithm")?;
+        if cipher != "AES" {
+            invalid_data!("Unsupported cipher algorithm {:?}", cipher);
+        }
+        let chaining = element.attribute("cipherChaining")?;
+        if chaining != "ChainingModeCBC" {
+            invalid_data!("Unsupported cipher chaining mode {:?}", chaining);
+        }
+        let hash =
+            HashAlgorithm::from_name(element.attribute("hashAlgorithm")?)?;
+        let hash_len = element.number("hashSize")?;
+        if hash_len != hash.output_len() {
+            invalid_data!("Invalid hash size of {}", hash_len);
+        }
+        let block_len = element.number("blockSize")?;
+        if block_len != AES_BLOCK_LEN {
+            invalid_data!("Invalid block size of {}", block_len);
+        }
+        let salt = element.base64("saltValue")?;
+        if salt.len() != element.number("saltSize")? {
+            invalid_data!("Salt doesn't match salt size");
+        }
+        let key_bits = element.number("keyBits")?;
+        if !matches!(key_bits, 128 | 192 | 256) {
+            invalid_data!("Invalid AES key size of {} bits", key_bits);
+        }
+        Ok(KeyParams { salt, block_len, key_len: key_bits / 8, hash })
+    }
+
+    /// Creates parameters for AES-CBC with the given key size and hash
+    /// algorithm, and a random salt.
+    fn generate(key_bits: u32, hash: HashAlgorithm) -> io::Result<KeyParams> {
+        Ok(KeyParams {
+            salt: random_bytes(SALT_LEN)?,
+            block_len: AES_BLOCK_LEN,
+            key_len: key_bits as usize / 8,
+            hash,
+        })
+    }
+
+    /// Formats these parameters as XML attributes.
+    fn attributes(&self) -> String {
+        format!(
+            "saltSize=\"{}\" blockSize=\"{}\" keyBits=\"{}\" hashSize=\"{}\" \
+             cipherAlgorithm=\"AES\" cipherChaining=\"ChainingModeCBC\" \
+             hashAlgorithm=\"{}\" saltValue=\"{}\"",
+            self.salt.len(),
+            self.block_len,
+            self.key_len * 8,
+            self.hash.output_len(),
+            self.hash.name(),
+            encode_base64(&self.salt)
+        )
+    }
+
+    /// Returns the initialization vector for the given block key (or for the
+    /// salt itself if there is no block key).
+    fn iv(&self, block_key: Option<&[u8]>) -> Vec<u8> {
+        let iv = match block_key {
+            Some(block_key) => self.hash.digest(&[&self.salt, block_key]),
+            None => self.salt.clone(),
+        };
+        fit_to_len(iv, self.block_len)
+    }
+}
+
+//===========================================================================//
+
+/// The parameters of Agile encryption, parsed from the XML that follows the
+/// version number in the `EncryptionInfo` stream.
+pub(crate) struct AgileInfo {
+    key_data: KeyParams,
+    encrypted_hmac_key: Option<Vec<u8>>,
+    encrypted_hmac_value: Option<Vec<u8>>,
+    password: KeyParams,
+    spin_count: u32,
+    encrypted_verifier_input: Vec<u8>,
+    encrypted_verifier_value: Vec<u8>,
+    encrypted_key: Vec<u8>,
+}
+
+impl AgileInfo {
+    pub(crate) fn from_xml(xml: &[u8]) -> io::Result<AgileInfo> {
+        let xml = match std::str::from_utf8(xml) {
+            Ok(xml) => xml,
+            Err(_) => invalid_data!("Encryption info XML is not valid UTF-8"),
+        };
+        let key_data = Element::find(xml, 0, "keyData")?;
+        let integrity = Element::find(xml, 0, "dataIntegrity").ok();
+        // There may be several key encryptors (e.g. one for a certificate as
+        // well as one for the password); find the password one.
+        let mut start = 0;
+        let encryptor = loop {
+            let encryptor = match Element::find(xml, start, "keyEncryptor") {
+                Ok(encryptor) => encryptor,
+                Err(_) => invalid_data!("Package is not password-encrypted"),
+            };
+            if encryptor.attribute("uri").ok() == Some(PASSWORD_KEY_ENCRYPTOR)
+            {
+                break encryptor;
+            }
+            start = encryptor.end;
+        };
+        let password = Element::find(xml, encryptor.end, "encryptedKey")?;
+        let spin_count = password.number("spinCount")?;
+        let spin_count = match u32::try_from(spin_count) {
+            Ok(spin_count) if spin_count <= MAX_SPIN_COUNT => spin_count,
+            _ => invalid_data!("Spin count of {} is too large", spin_count),
+        };
+        Ok(AgileInfo {
+            key_data: KeyParams::from_element(&key_data)?,
+            encrypted_hmac_key: match integrity {
+                Some(ref element) => Some(element.base64("encryptedHmacKey")?),
+                None => None,
+            },
+            encrypted_hmac_value: match integrity {
+                Some(ref element) => {
+                    Some(element.base64("encryptedHmacValue")?)
+                }
+                None => None,
+            },
+            spin_count,
+            encrypted_verifier_input: password
+                .base64("encryptedVerifierHashInput")?,
+            encrypted_verifier_value: password
+                .base64("encryptedVerifierHashValue")?,
+            encrypted_key: password.base64("encryptedKeyValue")?,
+            password: KeyParams::from_element(&password)?,
+        })
+    }
+
+    /// Encrypts a package with a password, returning the contents of the
+    /// `EncryptionInfo` and `EncryptedPackage` streams.
+    pub(crate) fn encrypt(
+        package: &[u8],
+        password: &str,
+        options: &EncryptionOptions,
+    ) -> io::Result<(Vec<u8>, Vec<u8>)> {
+        let hash = HashAlgorithm::Sha512;
+        let key_data = KeyParams::generate(options.key_bits(), hash)?;
+        let intermediate_key = random_bytes(key_data.key_len)?;
+        let aes = Aes::new(&intermediate_key)?;
+        let mut encrypted = Vec::with_capacity(8 + package.len() + 16);
+        encrypted.write_u64::<LittleEndian>(package.len() as u64)?;
+        for (index, segment) in package.chunks(SEGMENT_LEN).enumerate() {
+            let block_key = (index as u32).to_le_bytes();
+            let mut segment = pad_to_block(segment.to_vec());
+            aes.encrypt_cbc(&key_data.iv(Some(&block_key)), &mut segment)?;
+            encrypted.extend_from_slice(&segment);
+        }
+
+        let hmac_key = random_bytes(hash.output_len())?;
+        let hmac_value = hash.hmac(&hmac_key, &encrypted);
+        let encrypt_value = |block_key: &[u8], value: &[u8]| {
+            let mut data = pad_to_block(value.to_vec());
+            aes.encrypt_cbc(&key_data.iv(Some(block_key)), &mut data)?;
+            Ok::<_, io::Error>(data)
+        };
+        let encrypted_hmac_key =
+            encrypt_value(&BLOCK_KEY_HMAC_KEY, &hmac_key)?;
+        let encrypted_hmac_value =
+            encrypt_value(&BLOCK_KEY_HMAC_VALUE, &hmac_value)?;
+
+        let mut info = AgileInfo {
+            key_data,
+            encrypted_hmac_key: Some(encrypted_hmac_key),
+            encrypted_hmac_value: Some(encrypted_hmac_value),
+            password: KeyParams::generate(options.key_bits(), hash)?,
+            spin_count: options.spin_count(),
+            encrypted_verifier_input: Vec::new(),
+            encrypted_verifier_value: Vec::new(),
+            encrypted_key: Vec::new(),
+        };
+        let params = &info.password;
+        let password_hash =
+            params.hash.hash_password(&params.salt, password, info.spin_count);
+        let verifier_input = random_bytes(SALT_LEN)?;
+        let verifier_value = hash.digest(&[&verifier_input]);
+        info.encrypted_verifier_input = info.encrypt_with_password(
+            &password_hash,
+            &BLOCK_KEY_VERIFIER_INPUT,
+            &verifier_input,
+        )?;
+        info.encrypted_verifier_value = info.encrypt_with_password(
+            &password_hash,
+            &BLOCK_KEY_VERIFIER_VALUE,
+            &verifier_value,
+        )?;
+        info.encrypted_key = info.encrypt_with_password(
+            &password_hash,
+            &BLOCK_KEY_ENCRYPTED_KEY,
+            &intermediate_key,
+        )?;
+        Ok((info.to_bytes()?, encrypted))
+    }
+
+    /// Serializes the contents of the `EncryptionInfo` stream.
+    fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\r\n\
+             <encryption \
+             xmlns=\"http://schemas.microsoft.com/office/2006/encryption\" \
+             xmlns:p=\"http://schemas.microsoft.com/office/2006/keyEncryptor/password\">",
+        );
+        xml.push_str(&format!("<keyData {}/>", self.key_data.attributes()));
+        if let (Some(key), Some(value)) =
+            (&self.encrypted_hmac_key, &self.encrypted_hmac_value)
+        {
+            xml.push_str(&format!(
+                "<dataIntegrity encryptedHmacKey=\"{}\" \
+                 encryptedHmacValue=\"{}\"/>",
+                encode_base64(key),
+                encode_base64(value)
+            ));
+        }
+        xml.push_str(&format!(
+            "<keyEncryptors><keyEncryptor uri=\"{}\"><p:encryptedKey \
+             spinCount=\"{}\" {} encryptedVerifierHashInput=\"{}\" \
+             encryptedVerifierHashValue=\"{}\" encryptedKeyValue=\"{}\"/>\
+             </keyEncryptor></keyEncryptors></encryption>",
+            PASSWORD_KEY_ENCRYPTOR,
+            self.spin_count,
+            self.password.attributes(),
+            encode_base64(&self.encrypted_verifier_input),
+            encode_base64(&self.encrypted_verifier_value),
+            encode_base64(&self.encrypted_key)
+        ));
+        let mut data = Vec::with_capacity(8 + xml.len());
+        data.write_u16::<LittleEndian>(VERSION_MAJOR)?;
+        data.write_u16::<LittleEndian>(VERSION_MINOR)?;
+        data.write_u32::<LittleEndian>(RESERVED)?;
+        data.extend_from_slice(xml.as_bytes());
+        Ok(data)
+    }
+
+    /// Returns the cipher for one of the password-protected values, using a
+    /// key derived from the password hash and the given block key.
+    fn password_cipher(
+        &self,
+        password_hash: &[u8],
+        block_key: &[u8],
+    ) -> io::Result<Aes> {
+        let params = &self.password;
+        let key = params.hash.digest(&[password_hash, block_key]);
+        Aes::new(&fit_to_len(key, params.key_len))
+    }
+
+    fn encrypt_with_password(
+        &self,
+        password_hash: &[u8],
+        block_key: &[u8],
+        value: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        let aes = self.password_cipher(password_hash, block_key)?;
+        let mut data = pad_to_block(value.to_vec());
+        aes.encrypt_cbc(&self.password.iv(None), &mut data)?;
+        Ok(data)
+    }
+
+    fn decrypt_with_password(
+        &self,
+        password_hash: &[u8],
+        block_key: &[u8],
+        encrypted: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        let aes = self.password_cipher(password_hash, block_key)?;
+        let mut data = encrypted.to_vec();
+        aes.decrypt_cbc(&self.password.iv(None), &mut data)?;
+        Ok(data)
+    }
+
+    /// Checks the password against the verifier, returning the intermediate
+    /// key that the package is encrypted with if it's correct.
+    fn intermediate_key(&self, password: &str) -> io::Result<Vec<u8>> {
+        let params = &self.password;
+        let hash =
+            params.hash.hash_password(&params.salt, password, self.spin_count);
+        let mut input = self.decrypt_with_password(
+            &hash,
+            &BLOCK_KEY_VERIFIER_INPUT,
+            &self.encrypted_verifier_input,
+        )?;
+        input.truncate(params.salt.len());
+        let mut value = self.decrypt_with_password(
+            &hash,
+            &BLOCK_KEY_VERIFIER_VALUE,
+            &self.encrypted_verifier_value,
+        )?;
+        value.truncate(params.hash.output_len());
+        if params.hash.digest(&[&input]) != value {
+            permission_denied!("Incorrect password");
+        }
+        let mut key = self.decrypt_with_password(
+            &hash,
+            &BLOCK_KEY_ENCRYPTED_KEY,
+            &self.encrypted_key,
+        )?;
+        if key.len() < self.key_data.key_len {
+            invalid_data!("Encrypted key is too short");
+        }
+        key.truncate(self.key_data.key_len);
+        Ok(key)
+    }
+
+    /// Checks the HMAC of the encrypted package, if there is one.
+    fn check_integrity(&self, aes: &Aes, package: &[u8]) -> io::Result<()> {
+        let (encrypted_key, encrypted_value) =
+            match (&self.encrypted_hmac_key, &self.encrypted_hmac_value) {
+                (Some(key), Some(value)) => (key, value),
+                _ => return Ok(()),
+            };
+        let hash = self.key_data.hash;
+        let mut hmac_key = encrypted_key.clone();
+        aes.decrypt_cbc(
+            &self.key_data.iv(Some(&BLOCK_KEY_HMAC_KEY)),
+            &mut hmac_key,
+        )?;
+        hmac_key.truncate(hash.output_len());
+        let mut hmac_value = encrypted_value.clone();
+        aes.decrypt_cbc(
+            &self.key_data.iv(Some(&BLOCK_KEY_HMAC_VALUE)),
+            &mut hmac_value,
+        )?;
+        hmac_value.truncate(hash.output_len());
+        if hash.hmac(&hmac_key, package) != hmac_value {
+            invalid_data!("Encrypted package failed its integrity check");
+        }
+        Ok(())
+    }
+
+    pub(crate) fn decrypt(
+        &self,
+        password: &str,
+        package: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        let aes = Aes::new(&self.intermediate_key(password)?)?;
+        self.check_integrity(&aes, package)?;
+        let (size, encrypted) = split_package(package)?;
+        let mut data = Vec::with_capacity(encrypted.len());
+        for (index, segment) in encrypted.chunks(SEGMENT_LEN).enumerate() {
+            let block_key = (index as u32).to_le_bytes();
+            let aligned_len = segment.len() - segment.len() % AES_BLOCK_LEN;
+            let start = data.len();
+            data.extend_from_slice(&segment[..aligned_len]);
+            aes.decrypt_cbc(
+                &self.key_data.iv(Some(&block_key)),
+                &mut data[start..],
+            )?;
+        }
+        if size > data.len() as u64 {
+            invalid_data!(
+                "Encrypted package size of {} is larger than its data",
+                size
+            );
+        }
+        data.truncate(size as usize);
+        Ok(data)
+    }
+}
+
+//===========================================================================//
+
+/// The attributes of an XML element.  Agile encryption info is simple
+/// enough (a handful of elements whose attributes hold numbers, names, and
+/// base64 data) that it's parsed by just scanning for the elements needed.
+struct Element<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, &'a str)>,
+    /// The byte offset just past the end of the element's start tag.
+    end: usize,
+}
+
+impl<'a> Element<'a> {
+    /// Finds the first element at or after the given byte offset with the
+    /// given local name (ignoring any namespace prefix).
+    fn find(
+        xml: &'a str,
+        start: usize,
+        name: &str,
+    ) -> io::Result<Element<'a>> {
+        let mut rest = &xml[start..];
+        while let Some(index) = rest.find('<') {
+            rest = &rest[(index + 1)..];
+            let tag_end = rest
+                .find(|chr: char| {
+                    chr.is_whitespace() || chr == '/' || chr == '>'
+                })
+                .unwrap_or(rest.len());
+            let tag = &rest[..tag_end];
+            let local_name = tag.rsplit(':').next().unwrap_or(tag);
+            if local_name == name {
+                let end = match rest.find('>') {
+                    Some(end) => end,
+                    None => break,
+                };
+                let attributes = parse_attributes(&rest[tag_end..end])?;
+                let end = xml.len() - rest.len() + end + 1;
+                return Ok(Element { name: local_name, attributes, end });
+            }
+        }
+        invalid_data!("Encryption info has no <{}> element", name)
+    }
+
+    fn attribute(&self, name: &str) -> io::Result<&'a str> {
+        match self.attributes.iter().find(|&&(key, _)| key == name) {
+            Some(&(_, value)) => Ok(value),
+            None => invalid_data!(
+                "Encryption info <{}> element has no {} attribute",
+                self.name,
+                name
+            ),
+        }
+    }
+
+    fn number(&self, name: &str) -> io::Result<usize> {
+        match self.attribute(name)?.parse() {
+            Ok(number) => Ok(number),
+            Err(_) => invalid_data!("Invalid {} in encryption info", name),
+        }
+    }
+
+    fn base64(&self, name: &str) -> io::Result<Vec<u8>> {
+        match BASE64.decode(self.attribute(name)?) {
+            Ok(bytes) => Ok(bytes),
+            Err(_) => invalid_data!("Invalid {} in encryption info", name),
+        }
+    }
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    BASE64.encode(bytes)
+}
+
+fn parse_attributes(mut rest: &str) -> io::Result<Vec<(&str, &str)>> {
+    let mut attributes = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(|chr: char| chr.is_whitespace());
+        if rest.is_empty() || rest.starts_with('/') {
+            return Ok(attributes);
+        }
+        let equals = match rest.find('=') {
+            Some(equals) => equals,
+            None => invalid_data!("Malformed encryption info XML"),
+        };
+        let name = rest[..equals].trim_end();
+        rest = rest[(equals + 1)..].trim_start();
+        let quote = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => quote,
+            _ => invalid_data!("Malformed encryption info XML"),
+        };
+        rest = &rest[1..];
+        let value_end = match rest.find(quote) {
+            Some(value_end) => value_end,
+            None => invalid_data!("Malformed encryption info XML"),
+        };
+        attributes.push((name, &rest[..value_end]));
+        rest = &rest[(value_end + 1)..];
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,211 @@
+use crate::crypto::ENCRYPTED_PACKAGE_STREAM;
+use crate::CompoundFile;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Seek, Write};
+use uuid::Uuid;
+
+//===========================================================================//
+
+/// The transform ID of the password-based encryption transform.  Any other
+/// transform (such as that used for rights-managed documents) can't be
+/// undone with a password.
+pub const STRONG_ENCRYPTION_TRANSFORM: Uuid =
+    Uuid::from_u128(0xff9a3f03_56ef_4613_bdd5_5a41c1d07246);
+
+const VERSION_STREAM: &str = "/\u{6}DataSpaces/Version";
+const DATA_SPACE_MAP_STREAM: &str = "/\u{6}DataSpaces/DataSpaceMap";
+const DATA_SPACE_INFO_STORAGE: &str = "/\u{6}DataSpaces/DataSpaceInfo";
+const TRANSFORM_INFO_STORAGE: &str = "/\u{6}DataSpaces/TransformInfo";
+const PRIMARY_STREAM: &str = "\u{6}Primary";
+
+const DATA_SPACES_FEATURE: &str = "Microsoft.Container.DataSpaces";
+const STRONG_ENCRYPTION_DATA_SPACE: &str = "StrongEncryptionDataSpace";
+const STRONG_ENCRYPTION_TRANSFORM_NAME: &str = "StrongEncryptionTransform";
+const ENCRYPTION_TRANSFORM_CLASS: &str =
+    "Microsoft.Container.EncryptionTransform";
+
+const TRANSFORM_TYPE: u32 = 1;
+const REFERENCE_COMPONENT_STREAM: u32 = 0;
+
+//===========================================================================//
+
+/// Checks that every transform recorded under `"\u{6}DataSpaces"` (if
+/// present) is the password-based encryption transform.
+pub(crate) fn check_transforms<F: Read + Seek>(
+    comp: &mut CompoundFile<F>,
+) -> io::Result<()> {
+    if !comp.is_storage(TRANSFORM_INFO_STORAGE) {
+        return Ok(());
+    }
+    let paths: Vec<_> = comp
+        .read_storage(TRANSFORM_INFO_STORAGE)?
+        .filter(|entry| entry.is_storage())
+        .map(|entry| entry.path().join(PRIMARY_STREAM))
+        .collect();
+    for path in paths {
+        if !comp.is_stream(&path) {
+            continue;
+        }
+        let transform_id =
+            read_transform_id(&comp.read_stream_to_vec(&path)?)?;
+        if transform_id != STRONG_ENCRYPTION_TRANSFORM {
+            invalid_data!(
+                "Unsupported data space transform {{{}}} (the document may \
+                 be rights-managed rather than password-protected)",
+                transform_id
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Parses the transform ID from the `TransformInfoHeader` at the start of a
+/// `"\u{6}Primary"` stream.
+fn read_transform_id(mut data: &[u8]) -> io::Result<Uuid> {
+    let _transform_len = data.read_u32::<LittleEndian>()?;
+    let _transform_type = data.read_u32::<LittleEndian>()?;
+    let id_len = data.read_u32::<LittleEndian>()? as usize;
+    if id_len > data.len() || id_len % 2 != 0 {
+        invalid_data!("Invalid transform ID length of {}", id_len);
+    }
+    let units: Vec<u16> = data[..id_len]
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    let id = String::from_utf16_lossy(&units);
+    match Uuid::parse_str(&id) {
+        Ok(uuid) => Ok(uuid),
+        Err(_) => invalid_data!("Invalid transform ID {:?}", id),
+    }
+}
+
+//===========================================================================//
+
+/// Writes the `"\u{6}DataSpaces"` storage hierarchy declaring that the
+/// `EncryptedPackage` stream has the password-based encryption transform
+/// applied to it.
+pub(crate) fn write_data_spaces<F: Read + Write + Seek>(
+    comp: &mut CompoundFile<F>,
+) -> io::Result<()> {
+    comp.create_storage_all(DATA_SPACE_INFO_STORAGE)?;
+    let transform_storage = format!(
+        "{}/{}",
+        TRANSFORM_INFO_STORAGE, STRONG_ENCRYPTION_TRANSFORM_NAME
+    );
+    comp.create_storage_all(&transform_storage)?;
+
+    // DataSpaceVersionInfo (MS-OFFCRYPTO 2.1.5).
+    let mut data = Vec::new();
+    write_unicode_lp_p4(&mut data, DATA_SPACES_FEATURE)?;
+    write_versions(&mut data)?;
+    comp.create_stream(VERSION_STREAM)?.write_all(&data)?;
+
+    // DataSpaceMap (MS-OFFCRYPTO 2.1.6), mapping the EncryptedPackage
+    // stream to the strong encryption data space.
+    let mut entry = Vec::new();
+    entry.write_u32::<LittleEndian>(1)?;
+    entry.write_u32::<LittleEndian>(REFERENCE_COMPONENT_STREAM)?;
+    write_unicode_lp_p4(
+        &mut entry,
+        ENCRYPTED_PACKAGE_STREAM.trim_start_matches('/'),
+    )?;
+    write_unicode_lp_p4(&mut entry, STRONG_ENCRYPTION_DATA_SPACE)?;
+    let mut data = Vec::new();
+    data.write_u32::<LittleEndian>(8)?;
+    data.write_u32::<LittleEndian>(1)?;
+    data.write_u32::<LittleEndian>(4 + entry.len() as u32)?;
+    data.extend_from_slice(&entry);
+    comp.create_stream(DATA_SPACE_MAP_STREAM)?.write_all(&data)?;
+
+    // DataSpaceDefinition (MS-OFFCRYPTO 2.1.7), listing the transforms that
+    // make up the data space.
+    let mut data = Vec::new();
+    data.write_u32::<LittleEndian>(8)?;
+    data.write_u32::<LittleEndian>(1)?;
+    write_unicode_lp_p4(&mut data, STRONG_ENCRYPTION_TRANSFORM_NAME)?;
+    let path = format!(
+        "{}/{}",
+        DATA_SPACE_INFO_STORAGE, STRONG_ENCRYPTION_DATA_SPACE
+    );
+    comp.create_stream(path)?.write_all(&data)?;
+
+    // EncryptionTransformInfo (MS-OFFCRYPTO 2.1.8 and 2.1.9).
+    let mut data = Vec::new();
+    let transform_id =
+        format!("{{{}}}", STRONG_ENCRYPTION_TRANSFORM).to_ascii_uppercase();
+    let mut header = Vec::new();
+    header.write_u32::<LittleEndian>(TRANSFORM_TYPE)?;
+    write_unicode_lp_p4(&mut header, &transform_id)?;
+    data.write_u32::<LittleEndian>(4 + header.len() as u32)?;
+    data.extend_from_slice(&header);
+    write_unicode_lp_p4(&mut data, ENCRYPTION_TRANSFORM_CLASS)?;
+    write_versions(&mut data)?;
+    data.write_u32::<LittleEndian>(0)?; // EncryptionName (empty)
+    data.write_u32::<LittleEndian>(0)?; // EncryptionBlockSize
+    data.write_u32::<LittleEndian>(0)?; // CipherMode
+    data.write_u32::<LittleEndian>(4)?; // Reserved
+    let path = format!("{}/{}", transform_storage, PRIMARY_STREAM);
+    comp.create_stream(path)?.write_all(&data)?;
+    Ok(())
+}
+
+/// Writes a `UNICODE-LP-P4` string: a byte length, UTF-16 data, and padding
+/// to a multiple of four bytes.
+fn write_unicode_lp_p4(data: &mut Vec<u8>, string: &str) -> io::Result<()> {
+    let units: Vec<u16> = string.encode_utf16().collect();
+    data.write_u32::<LittleEndian>(2 * units.len() as u32)?;
+    for unit in units {
+        data.write_u16::<LittleEndian>(unit)?;
+    }
+    data.resize(data.len().div_ceil(4) * 4, 0);
+    Ok(())
+}
+
+/// Writes the reader, updater, and writer versions (all 1.0).
+fn write_versions(data: &mut Vec<u8>) -> io::Result<()> {
+    for _ in 0..3 {
+        data.write_u16::<LittleEndian>(1)?;
+        data.write_u16::<LittleEndian>(0)?;
+    }
+    Ok(())
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        read_transform_id, write_data_spaces, STRONG_ENCRYPTION_TRANSFORM,
+    };
+    use crate::CompoundFile;
+    use std::io::{Cursor, Read};
+
+    #[test]
+    fn data_spaces_round_trip() {
+        let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+        write_data_spaces(&mut comp).unwrap();
+        let mut data = Vec::new();
+        comp.open_stream(
+            "/\u{6}DataSpaces/TransformInfo/StrongEncryptionTransform/\
+             \u{6}Primary",
+        )
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+        assert_eq!(&data[..4], &[0x58, 0, 0, 0]);
+        assert_eq!(
+            read_transform_id(&data).unwrap(),
+            STRONG_ENCRYPTION_TRANSFORM
+        );
+
+        let mut data = Vec::new();
+        comp.open_stream("/\u{6}DataSpaces/DataSpaceMap")
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data.len(), 112);
+        assert!(read_transform_id(&[0, 0, 0, 0, 1, 0, 0, 0, 3, 0]).is_err());
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,199 @@
+//! Decryption of password-protected Office documents, as described in
+//! [MS-OFFCRYPTO](
+//! https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-offcrypto).
+//! This module is only available when the `crypto` feature is enabled.
+//!
+//! When an Office Open XML document (such as a `.docx` or `.xlsx` file) is
+//! protected with a password, Office saves it as a compound file instead:
+//! the `EncryptionInfo` stream describes how the key is derived from the
+//! password, the `EncryptedPackage` stream holds the encrypted ZIP package,
+//! and the `"\u{6}DataSpaces"` storage records which transform was applied.
+//! [`decrypt_package`](fn.decrypt_package.html) recovers the original
+//! package from such a file, for both Standard encryption (AES in ECB mode,
+//! as used by Office 2007) and Agile encryption (AES in CBC mode with a
+//! SHA-512 key derivation, as used by Office 2010 and later).
+//! [`encrypt_package`](fn.encrypt_package.html) does the reverse, using
+//! Agile encryption.
+//!
+//! # Example usage
+//!
+//! ```no_run
+//! let mut comp = cfb::open("path/to/encrypted.docx").unwrap();
+//! let package = cfb::crypto::decrypt_package(&mut comp, "password").unwrap();
+//! std::fs::write("path/to/decrypted.docx", &package).unwrap();
+//!
+//! let options = cfb::crypto::EncryptionOptions::new();
+//! let comp =
+//!     cfb::crypto::encrypt_package(&package, "new password", &options)
+//!         .unwrap();
+//! std::fs::write("path/to/reencrypted.docx", comp.into_inner().into_inner())
+//!     .unwrap();
+//! ```
+
+use crate::crypto::agile::AgileInfo;
+use crate::crypto::standard::StandardInfo;
+use crate::CompoundFile;
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{self, Cursor, Read, Seek, Write};
+
+pub use crate::crypto::dataspace::STRONG_ENCRYPTION_TRANSFORM;
+
+mod agile;
+mod dataspace;
+mod primitives;
+mod standard;
+
+//===========================================================================//
+
+/// The path of the stream that describes how a package is encrypted.
+pub const ENCRYPTION_INFO_STREAM: &str = "/EncryptionInfo";
+/// The path of the stream that holds the encrypted package.
+pub const ENCRYPTED_PACKAGE_STREAM: &str = "/EncryptedPackage";
+/// The path of the storage that describes the data spaces (and the
+/// transforms applied to them) in an encrypted document.
+pub const DATA_SPACES_STORAGE: &str = "/\u{6}DataSpaces";
+
+const DEFAULT_SPIN_COUNT: u32 = 100_000;
+const DEFAULT_KEY_BITS: u32 = 256;
+
+//===========================================================================//
+
+/// Options for [`encrypt_package`](fn.encrypt_package.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EncryptionOptions {
+    spin_count: u32,
+    key_bits: u32,
+}
+
+impl EncryptionOptions {
+    /// Returns the default options, matching those used by Office: AES-256
+    /// with a SHA-512 key derivation of 100000 iterations.
+    pub fn new() -> EncryptionOptions {
+        EncryptionOptions {
+            spin_count: DEFAULT_SPIN_COUNT,
+            key_bits: DEFAULT_KEY_BITS,
+        }
+    }
+
+    /// Returns the number of times the password hash is rehashed when
+    /// deriving the key.
+    pub fn spin_count(&self) -> u32 {
+        self.spin_count
+    }
+
+    /// Sets the number of times the password hash is rehashed when deriving
+    /// the key.  Higher values make guessing the password slower.
+    pub fn set_spin_count(&mut self, spin_count: u32) {
+        self.spin_count = spin_count;
+    }
+
+    /// Returns the AES key size, in bits.
+    pub fn key_bits(&self) -> u32 {
+        self.key_bits
+    }
+
+    /// Sets the AES key size, in bits.  Returns an error if the size isn't
+    /// 128, 192, or 256.
+    pub fn set_key_bits(&mut self, key_bits: u32) -> io::Result<()> {
+        if !matches!(key_bits, 128 | 192 | 256) {
+            invalid_input!("Invalid AES key size of {} bits", key_bits);
+        }
+        self.key_bits = key_bits;
+        Ok(())
+    }
+}
+
+impl Default for EncryptionOptions {
+    fn default() -> EncryptionOptions {
+        EncryptionOptions::new()
+    }
+}
+
+//===========================================================================//
+
+/// Returns true if the compound file appears to be an encrypted Office
+/// document, i.e. it has both `EncryptionInfo` and `EncryptedPackage`
+/// streams.
+pub fn is_encrypted_package<F>(comp: &CompoundFile<F>) -> bool {
+    comp.is_stream(ENCRYPTION_INFO_STREAM)
+        && comp.is_stream(ENCRYPTED_PACKAGE_STREAM)
+}
+
+/// Decrypts the `EncryptedPackage` stream of a password-protected Office
+/// document, returning the original package data (usually a ZIP file).
+///
+/// Returns an error of kind `PermissionDenied` if the password is
+/// incorrect, `NotFound` if the compound file isn't an encrypted document,
+/// and `InvalidData` if the encryption info is malformed or uses an
+/// unsupported algorithm.
+pub fn decrypt_package<F: Read + Seek>(
+    comp: &mut CompoundFile<F>,
+    password: &str,
+) -> io::Result<Vec<u8>> {
+    dataspace::check_transforms(comp)?;
+    let info = comp.read_stream_to_vec(ENCRYPTION_INFO_STREAM)?;
+    let package = comp.read_stream_to_vec(ENCRYPTED_PACKAGE_STREAM)?;
+    match EncryptionInfo::from_bytes(&info)? {
+        EncryptionInfo::Standard(info) => info.decrypt(password, &package),
+        EncryptionInfo::Agile(info) => info.decrypt(password, &package),
+    }
+}
+
+/// Encrypts an Office Open XML package (usually a ZIP file) with a password
+/// using Agile encryption, returning a new in-memory compound file with the
+/// `EncryptionInfo` and `EncryptedPackage` streams and the
+/// `"\u{6}DataSpaces"` storage that Office expects.
+pub fn encrypt_package(
+    package: &[u8],
+    password: &str,
+    options: &EncryptionOptions,
+) -> io::Result<CompoundFile<Cursor<Vec<u8>>>> {
+    let (info, encrypted) = AgileInfo::encrypt(package, password, options)?;
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new()))?;
+    dataspace::write_data_spaces(&mut comp)?;
+    comp.create_stream(ENCRYPTION_INFO_STREAM)?.write_all(&info)?;
+    comp.create_stream(ENCRYPTED_PACKAGE_STREAM)?.write_all(&encrypted)?;
+    comp.flush()?;
+    Ok(comp)
+}
+
+/// Splits the contents of an `EncryptedPackage` stream into the size of
+/// the decrypted package and the encrypted data.
+fn split_package(mut package: &[u8]) -> io::Result<(u64, &[u8])> {
+    let size = package.read_u64::<LittleEndian>()?;
+    Ok((size, package))
+}
+
+//===========================================================================//
+
+/// The parsed contents of an `EncryptionInfo` stream.
+enum EncryptionInfo {
+    Standard(StandardInfo),
+    Agile(AgileInfo),
+}
+
+impl EncryptionInfo {
+    fn from_bytes(mut data: &[u8]) -> io::Result<EncryptionInfo> {
+        let major = data.read_u16::<LittleEndian>()?;
+        let minor = data.read_u16::<LittleEndian>()?;
+        match (major, minor) {
+            (2..=4, 2) => {
+                Ok(EncryptionInfo::Standard(StandardInfo::from_bytes(data)?))
+            }
+            (4, 4) => {
+                let _reserved = data.read_u32::<LittleEndian>()?;
+                Ok(EncryptionInfo::Agile(AgileInfo::from_xml(data)?))
+            }
+            (3..=4, 3) => {
+                invalid_data!("Extensible encryption is not supported")
+            }
+            _ => invalid_data!(
+                "Unsupported encryption version {}.{}",
+                major,
+                minor
+            ),
+        }
+    }
+}
+
+//===========================================================================//
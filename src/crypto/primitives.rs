@@ -0,0 +1,285 @@
+use aes::cipher::generic_array::GenericArray;
+use aes::cipher::{BlockDecrypt, BlockEncrypt, KeyInit};
+use sha2::Digest;
+use std::io;
+
+//===========================================================================//
+
+/// The block size of AES, in bytes.
+pub(crate) const AES_BLOCK_LEN: usize = 16;
+
+//===========================================================================//
+
+/// A hash algorithm that can be used for key derivation.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum HashAlgorithm {
+    Sha1,
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    /// Parses a hash algorithm name, as used in Agile encryption info.
+    pub(crate) fn from_name(name: &str) -> io::Result<HashAlgorithm> {
+        match name {
+            "SHA1" | "SHA-1" => Ok(HashAlgorithm::Sha1),
+            "SHA256" => Ok(HashAlgorithm::Sha256),
+            "SHA384" => Ok(HashAlgorithm::Sha384),
+            "SHA512" => Ok(HashAlgorithm::Sha512),
+            _ => invalid_data!("Unsupported hash algorithm {:?}", name),
+        }
+    }
+
+    /// Returns the name of this algorithm, as used in Agile encryption info.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha1 => "SHA1",
+            HashAlgorithm::Sha256 => "SHA256",
+            HashAlgorithm::Sha384 => "SHA384",
+            HashAlgorithm::Sha512 => "SHA512",
+        }
+    }
+
+    /// Returns the length of this algorithm's hash values, in bytes.
+    pub(crate) fn output_len(self) -> usize {
+        match self {
+            HashAlgorithm::Sha1 => 20,
+            HashAlgorithm::Sha256 => 32,
+            HashAlgorithm::Sha384 => 48,
+            HashAlgorithm::Sha512 => 64,
+        }
+    }
+
+    fn block_len(self) -> usize {
+        match self {
+            HashAlgorithm::Sha1 | HashAlgorithm::Sha256 => 64,
+            HashAlgorithm::Sha384 | HashAlgorithm::Sha512 => 128,
+        }
+    }
+
+    /// Hashes the concatenation of the given byte strings.
+    pub(crate) fn digest(self, parts: &[&[u8]]) -> Vec<u8> {
+        fn digest<D: Digest>(parts: &[&[u8]]) -> Vec<u8> {
+            let mut hasher = D::new();
+            for part in parts {
+                hasher.update(part);
+            }
+            hasher.finalize().to_vec()
+        }
+        match self {
+            HashAlgorithm::Sha1 => digest::<sha1::Sha1>(parts),
+            HashAlgorithm::Sha256 => digest::<sha2::Sha256>(parts),
+            HashAlgorithm::Sha384 => digest::<sha2::Sha384>(parts),
+            HashAlgorithm::Sha512 => digest::<sha2::Sha512>(parts),
+        }
+    }
+
+    /// Computes the HMAC of the data with the given key.
+    pub(crate) fn hmac(self, key: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut block = if key.len() > self.block_len() {
+            self.digest(&[key])
+        } else {
+            key.to_vec()
+        };
+        block.resize(self.block_len(), 0);
+        let inner_pad: Vec<u8> =
+            block.iter().map(|byte| byte ^ 0x36).collect();
+        let outer_pad: Vec<u8> =
+            block.iter().map(|byte| byte ^ 0x5c).collect();
+        let inner = self.digest(&[&inner_pad, data]);
+        self.digest(&[&outer_pad, &inner])
+    }
+
+    /// Hashes a password with a salt and then rehashes the result
+    /// `spin_count` times, as the first step of deriving an encryption key
+    /// from a password.
+    pub(crate) fn hash_password(
+        self,
+        salt: &[u8],
+        password: &str,
+        spin_count: u32,
+    ) -> Vec<u8> {
+        let password: Vec<u8> =
+            password.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let mut hash = self.digest(&[salt, &password]);
+        for iteration in 0..spin_count {
+            hash = self.digest(&[&iteration.to_le_bytes(), &hash]);
+        }
+        hash
+    }
+}
+
+/// Pads the given value with zeros to a multiple of the AES block size.
+pub(crate) fn pad_to_block(mut bytes: Vec<u8>) -> Vec<u8> {
+    bytes.resize(bytes.len().div_ceil(AES_BLOCK_LEN) * AES_BLOCK_LEN, 0);
+    bytes
+}
+
+/// Returns the given number of cryptographically secure random bytes.
+pub(crate) fn random_bytes(len: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0u8; len];
+    if let Err(error) = getrandom::getrandom(&mut bytes) {
+        return Err(io::Error::other(format!(
+            "Failed to generate random bytes: {}",
+            error
+        )));
+    }
+    Ok(bytes)
+}
+
+/// Truncates or pads (with 0x36 bytes) the given value to the given length,
+/// as is done when a hash is used as a key or initialization vector.
+pub(crate) fn fit_to_len(mut bytes: Vec<u8>, len: usize) -> Vec<u8> {
+    bytes.resize(len, 0x36);
+    bytes
+}
+
+//===========================================================================//
+
+/// An AES cipher with a 128-, 192-, or 256-bit key.
+pub(crate) enum Aes {
+    Aes128(aes::Aes128),
+    Aes192(aes::Aes192),
+    Aes256(aes::Aes256),
+}
+
+impl Aes {
+    pub(crate) fn new(key: &[u8]) -> io::Result<Aes> {
+        match key.len() {
+            16 => Ok(Aes::Aes128(aes::Aes128::new(key.into()))),
+            24 => Ok(Aes::Aes192(aes::Aes192::new(key.into()))),
+            32 => Ok(Aes::Aes256(aes::Aes256::new(key.into()))),
+            len => invalid_data!("Invalid AES key length of {} bytes", len),
+        }
+    }
+
+    fn encrypt_block(&self, block: &mut [u8]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            Aes::Aes128(cipher) => cipher.encrypt_block(block),
+            Aes::Aes192(cipher) => cipher.encrypt_block(block),
+            Aes::Aes256(cipher) => cipher.encrypt_block(block),
+        }
+    }
+
+    fn decrypt_block(&self, block: &mut [u8]) {
+        let block = GenericArray::from_mut_slice(block);
+        match self {
+            Aes::Aes128(cipher) => cipher.decrypt_block(block),
+            Aes::Aes192(cipher) => cipher.decrypt_block(block),
+            Aes::Aes256(cipher) => cipher.decrypt_block(block),
+        }
+    }
+
+    /// Decrypts the data in place in ECB mode.
+    pub(crate) fn decrypt_ecb(&self, data: &mut [u8]) -> io::Result<()> {
+        check_block_aligned(data)?;
+        for block in data.chunks_exact_mut(AES_BLOCK_LEN) {
+            self.decrypt_block(block);
+        }
+        Ok(())
+    }
+
+    /// Encrypts the data in place in CBC mode.
+    pub(crate) fn encrypt_cbc(
+        &self,
+        iv: &[u8],
+        data: &mut [u8],
+    ) -> io::Result<()> {
+        check_block_aligned(data)?;
+        let mut previous = &iv[..AES_BLOCK_LEN];
+        for block in data.chunks_exact_mut(AES_BLOCK_LEN) {
+            for (byte, prev) in block.iter_mut().zip(previous.iter()) {
+                *byte ^= prev;
+            }
+            self.encrypt_block(block);
+            previous = block;
+        }
+        Ok(())
+    }
+
+    /// Decrypts the data in place in CBC mode.
+    pub(crate) fn decrypt_cbc(
+        &self,
+        iv: &[u8],
+        data: &mut [u8],
+    ) -> io::Result<()> {
+        check_block_aligned(data)?;
+        let mut previous = [0u8; AES_BLOCK_LEN];
+        previous.copy_from_slice(&iv[..AES_BLOCK_LEN]);
+        for block in data.chunks_exact_mut(AES_BLOCK_LEN) {
+            let mut ciphertext = [0u8; AES_BLOCK_LEN];
+            ciphertext.copy_from_slice(block);
+            self.decrypt_block(block);
+            for (byte, prev) in block.iter_mut().zip(previous.iter()) {
+                *byte ^= prev;
+            }
+            previous = ciphertext;
+        }
+        Ok(())
+    }
+}
+
+fn check_block_aligned(data: &[u8]) -> io::Result<()> {
+    if data.len() % AES_BLOCK_LEN != 0 {
+        invalid_data!(
+            "Encrypted data length of {} is not a multiple of the AES block \
+             size",
+            data.len()
+        );
+    }
+    Ok(())
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{Aes, HashAlgorithm};
+
+    fn hex(string: &str) -> Vec<u8> {
+        (0..string.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&string[i..(i + 2)], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn hmac() {
+        // Test case 2 from RFC 4231, and the equivalent for SHA-1 from
+        // RFC 2202.
+        let (key, data) = (b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            HashAlgorithm::Sha1.hmac(key, data),
+            hex("effcdf6ae5eb2fa2d27416d5f184df9c259a7c79")
+        );
+        assert_eq!(
+            HashAlgorithm::Sha256.hmac(key, data),
+            hex("5bdcc146bf60754e6a042426089575c7\
+                 5a003f089d2739839dec58b964ec3843")
+        );
+        assert_eq!(HashAlgorithm::Sha512.hmac(&[0xaa; 131], data).len(), 64);
+    }
+
+    #[test]
+    fn aes() {
+        // The AES-128 example from FIPS-197, appendix C.1.
+        let aes = Aes::new(&hex("000102030405060708090a0b0c0d0e0f")).unwrap();
+        let mut data = hex("69c4e0d86a7b0430d8cdb78070b4c55a");
+        aes.decrypt_ecb(&mut data).unwrap();
+        assert_eq!(data, hex("00112233445566778899aabbccddeeff"));
+        assert!(aes.decrypt_ecb(&mut [0u8; 15]).is_err());
+
+        let iv = [7u8; 16];
+        let plaintext: Vec<u8> = (0..64).collect();
+        let mut data = plaintext.clone();
+        aes.encrypt_cbc(&iv, &mut data).unwrap();
+        assert_ne!(data, plaintext);
+        aes.decrypt_cbc(&iv, &mut data).unwrap();
+        assert_eq!(data, plaintext);
+        assert!(Aes::new(&[0u8; 20]).is_err());
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,153 @@
+use crate::crypto::primitives::{Aes, HashAlgorithm, AES_BLOCK_LEN};
+use crate::crypto::split_package;
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{self, Read};
+
+//===========================================================================//
+
+const FLAG_CRYPTO_API: u32 = 0x04;
+const FLAG_EXTERNAL: u32 = 0x10;
+const FLAG_AES: u32 = 0x20;
+
+const ALG_ID_AES_128: u32 = 0x660e;
+const ALG_ID_AES_192: u32 = 0x660f;
+const ALG_ID_AES_256: u32 = 0x6610;
+const ALG_ID_HASH_SHA1: u32 = 0x8004;
+
+const SPIN_COUNT: u32 = 50_000;
+const SALT_LEN: usize = 16;
+const VERIFIER_HASH_LEN: usize = 20;
+const ENCRYPTED_VERIFIER_HASH_LEN: usize = 32;
+
+//===========================================================================//
+
+/// The parameters of Standard encryption: an `EncryptionHeader` and
+/// `EncryptionVerifier`, following the version number in the
+/// `EncryptionInfo` stream.
+pub(crate) struct StandardInfo {
+    key_len: usize,
+    salt: Vec<u8>,
+    encrypted_verifier: Vec<u8>,
+    encrypted_verifier_hash: Vec<u8>,
+}
+
+impl StandardInfo {
+    pub(crate) fn from_bytes(mut reader: &[u8]) -> io::Result<StandardInfo> {
+        let _flags = reader.read_u32::<LittleEndian>()?;
+        let header_len = reader.read_u32::<LittleEndian>()? as usize;
+        if header_len > reader.len() {
+            invalid_data!(
+                "Encryption header length of {} is too large",
+                header_len
+            );
+        }
+        let (mut header, mut verifier) = reader.split_at(header_len);
+        let flags = header.read_u32::<LittleEndian>()?;
+        let _size_extra = header.read_u32::<LittleEndian>()?;
+        let alg_id = header.read_u32::<LittleEndian>()?;
+        let alg_id_hash = header.read_u32::<LittleEndian>()?;
+        let key_bits = header.read_u32::<LittleEndian>()?;
+        if flags & FLAG_EXTERNAL != 0 {
+            invalid_data!("Extensible encryption is not supported");
+        }
+        if flags & (FLAG_CRYPTO_API | FLAG_AES) != FLAG_CRYPTO_API | FLAG_AES {
+            invalid_data!(
+                "Standard encryption with flags 0x{:x} is not supported",
+                flags
+            );
+        }
+        let key_len = match (alg_id, key_bits) {
+            (ALG_ID_AES_128, 128) => 16,
+            (ALG_ID_AES_192, 192) => 24,
+            (ALG_ID_AES_256, 256) => 32,
+            _ => invalid_data!(
+                "Unsupported encryption algorithm 0x{:x} with {}-bit keys",
+                alg_id,
+                key_bits
+            ),
+        };
+        // Some writers leave the hash algorithm ID as zero, meaning SHA-1.
+        if alg_id_hash != ALG_ID_HASH_SHA1 && alg_id_hash != 0 {
+            invalid_data!("Unsupported hash algorithm 0x{:x}", alg_id_hash);
+        }
+        let salt_len = verifier.read_u32::<LittleEndian>()? as usize;
+        if salt_len != SALT_LEN {
+            invalid_data!("Invalid encryption salt length of {}", salt_len);
+        }
+        let salt = read_bytes(&mut verifier, SALT_LEN)?;
+        let encrypted_verifier = read_bytes(&mut verifier, SALT_LEN)?;
+        let hash_len = verifier.read_u32::<LittleEndian>()? as usize;
+        if hash_len != VERIFIER_HASH_LEN {
+            invalid_data!("Invalid verifier hash length of {}", hash_len);
+        }
+        let encrypted_verifier_hash =
+            read_bytes(&mut verifier, ENCRYPTED_VERIFIER_HASH_LEN)?;
+        Ok(StandardInfo {
+            key_len,
+            salt,
+            encrypted_verifier,
+            encrypted_verifier_hash,
+        })
+    }
+
+    /// Derives the encryption key from the password (section 2.3.4.7 of
+    /// MS-OFFCRYPTO).
+    fn derive_key(&self, password: &str) -> Vec<u8> {
+        let sha1 = HashAlgorithm::Sha1;
+        let hash = sha1.hash_password(&self.salt, password, SPIN_COUNT);
+        let hash = sha1.digest(&[&hash, &0u32.to_le_bytes()]);
+        let mut key = Vec::with_capacity(2 * hash.len());
+        for &pad in &[0x36u8, 0x5c] {
+            let mut buffer = [pad; 64];
+            for (byte, hash_byte) in buffer.iter_mut().zip(hash.iter()) {
+                *byte ^= hash_byte;
+            }
+            key.extend(sha1.digest(&[&buffer]));
+        }
+        key.truncate(self.key_len);
+        key
+    }
+
+    /// Checks the password against the verifier, returning the cipher to
+    /// use if it's correct.
+    fn cipher(&self, password: &str) -> io::Result<Aes> {
+        let aes = Aes::new(&self.derive_key(password))?;
+        let mut verifier = self.encrypted_verifier.clone();
+        aes.decrypt_ecb(&mut verifier)?;
+        let mut verifier_hash = self.encrypted_verifier_hash.clone();
+        aes.decrypt_ecb(&mut verifier_hash)?;
+        let expected = HashAlgorithm::Sha1.digest(&[&verifier]);
+        if verifier_hash[..VERIFIER_HASH_LEN] != expected[..] {
+            permission_denied!("Incorrect password");
+        }
+        Ok(aes)
+    }
+
+    pub(crate) fn decrypt(
+        &self,
+        password: &str,
+        package: &[u8],
+    ) -> io::Result<Vec<u8>> {
+        let aes = self.cipher(password)?;
+        let (size, encrypted) = split_package(package)?;
+        let aligned_len = encrypted.len() - encrypted.len() % AES_BLOCK_LEN;
+        let mut data = encrypted[..aligned_len].to_vec();
+        aes.decrypt_ecb(&mut data)?;
+        if size > data.len() as u64 {
+            invalid_data!(
+                "Encrypted package size of {} is larger than its data",
+                size
+            );
+        }
+        data.truncate(size as usize);
+        Ok(data)
+    }
+}
+
+fn read_bytes(reader: &mut &[u8], len: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0u8; len];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+//===========================================================================//
@@ -47,6 +47,28 @@ impl<F> Allocator<F> {
         self.sectors.sector_len()
     }
 
+    /// Returns the number of regular sectors in the underlying file.  This
+    /// can be more than the length of the FAT, since trailing free FAT
+    /// entries are dropped when a file is opened.
+    pub fn num_sectors(&self) -> u32 {
+        self.sectors.num_sectors()
+    }
+
+    /// Returns the sector IDs of the DIFAT sectors (not including the part
+    /// of the DIFAT stored in the header).
+    pub fn difat_sector_ids(&self) -> &[u32] {
+        &self.difat_sector_ids
+    }
+
+    /// Returns the full DIFAT, i.e. the list of FAT sector IDs.
+    pub fn difat(&self) -> &[u32] {
+        &self.difat
+    }
+
+    pub fn fat(&self) -> &[u32] {
+        &self.fat
+    }
+
     pub fn next(&self, sector_id: u32) -> io::Result<u32> {
         let index = sector_id as usize;
         if index >= self.fat.len() {
@@ -74,7 +96,7 @@ impl<F> Allocator<F> {
         &mut self,
         start_sector_id: u32,
         init: SectorInit,
-    ) -> io::Result<Chain<F>> {
+    ) -> io::Result<Chain<'_, F>> {
         Chain::new(self, start_sector_id, init)
     }
 
@@ -153,11 +175,14 @@ impl<F: Seek> Allocator<F> {
     pub fn seek_within_header(
         &mut self,
         offset_within_header: u64,
-    ) -> io::Result<Sector<F>> {
+    ) -> io::Result<Sector<'_, F>> {
         self.sectors.seek_within_header(offset_within_header)
     }
 
-    pub fn seek_to_sector(&mut self, sector_id: u32) -> io::Result<Sector<F>> {
+    pub fn seek_to_sector(
+        &mut self,
+        sector_id: u32,
+    ) -> io::Result<Sector<'_, F>> {
         self.sectors.seek_to_sector(sector_id)
     }
 
@@ -165,7 +190,7 @@ impl<F: Seek> Allocator<F> {
         &mut self,
         sector_id: u32,
         offset_within_sector: u64,
-    ) -> io::Result<Sector<F>> {
+    ) -> io::Result<Sector<'_, F>> {
         self.sectors.seek_within_sector(sector_id, offset_within_sector)
     }
 
@@ -175,7 +200,7 @@ impl<F: Seek> Allocator<F> {
         subsector_index_within_sector: u32,
         subsector_len: usize,
         offset_within_subsector: u64,
-    ) -> io::Result<Sector<F>> {
+    ) -> io::Result<Sector<'_, F>> {
         let subsector_start =
             subsector_index_within_sector as usize * subsector_len;
         let offset_within_sector =
@@ -0,0 +1,174 @@
+use crate::internal::layout::Tree;
+use crate::internal::{ObjType, Timestamp, TimestampPolicy, Version};
+use std::io::{self, Write};
+use std::path::Path;
+use uuid::Uuid;
+
+//===========================================================================//
+
+/// Builds a complete compound file in memory, and then writes it out with an
+/// optimal layout.
+///
+/// Creating storages and streams one at a time on a
+/// [`CompoundFile`](struct.CompoundFile.html) allocates sectors as they are
+/// needed, which tends to leave stream chains fragmented and directory
+/// sectors interleaved with stream data.  A `CompoundFileBuilder` instead
+/// collects the whole tree first; calling [`build`](#method.build) then lays
+/// out the directory, the MiniFAT, the mini stream, and each regular stream
+/// as single contiguous runs of sectors, with each storage's children
+/// arranged into a balanced red-black tree.
+///
+/// # Example
+///
+/// ```
+/// use std::io::Cursor;
+///
+/// let mut builder = cfb::CompoundFileBuilder::new();
+/// builder.create_storage("/foo").unwrap();
+/// builder.create_stream("/foo/bar", vec![0u8; 10000]).unwrap();
+/// builder.append_to_stream("/foo/bar", b"more data").unwrap();
+/// let data = builder.build(Vec::new()).unwrap();
+///
+/// let comp = cfb::CompoundFile::open_strict(Cursor::new(data)).unwrap();
+/// assert_eq!(comp.entry("/foo/bar").unwrap().len(), 10009);
+/// ```
+pub struct CompoundFileBuilder {
+    version: Version,
+    timestamp_policy: TimestampPolicy,
+    tree: Tree,
+    data: Vec<Vec<u8>>,
+}
+
+impl CompoundFileBuilder {
+    /// Creates a new, empty builder for a version 4 compound file.
+    pub fn new() -> CompoundFileBuilder {
+        CompoundFileBuilder::with_version(Version::V4)
+    }
+
+    /// Creates a new, empty builder for a compound file of the given version.
+    pub fn with_version(version: Version) -> CompoundFileBuilder {
+        CompoundFileBuilder {
+            version,
+            timestamp_policy: TimestampPolicy::default(),
+            tree: Tree::new(),
+            data: vec![Vec::new()],
+        }
+    }
+
+    /// Returns the CFB format version that will be built.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Sets the policy for the timestamps written into directory entries.
+    /// With the default policy, `TimestampPolicy::Now`, each object gets the
+    /// time at which it was created in the builder; any other policy gives
+    /// every object the same timestamp (with `Preserve` acting like `Zero`,
+    /// since there are no existing timestamps to preserve).
+    pub fn set_timestamp_policy(&mut self, policy: TimestampPolicy) {
+        self.timestamp_policy = policy;
+    }
+
+    /// Creates a new, empty storage object at the provided path.  The parent
+    /// storage object must already exist.
+    pub fn create_storage<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> io::Result<()> {
+        self.insert(path.as_ref(), ObjType::Storage, Vec::new())
+    }
+
+    /// Creates a new stream object at the provided path, with the given
+    /// contents.  The parent storage object must already exist.
+    pub fn create_stream<P, D>(&mut self, path: P, data: D) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        D: Into<Vec<u8>>,
+    {
+        self.insert(path.as_ref(), ObjType::Stream, data.into())
+    }
+
+    fn insert(
+        &mut self,
+        path: &Path,
+        obj_type: ObjType,
+        data: Vec<u8>,
+    ) -> io::Result<()> {
+        let index = self.tree.insert(path, obj_type, Timestamp::now())?;
+        debug_assert_eq!(index, self.data.len());
+        self.tree.dir_entry_mut(index).stream_len = data.len() as u64;
+        self.data.push(data);
+        Ok(())
+    }
+
+    /// Appends data to the end of the existing stream object at the provided
+    /// path.
+    pub fn append_to_stream<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        data: &[u8],
+    ) -> io::Result<()> {
+        let index = self.stream_index(path.as_ref())?;
+        let stream_data = &mut self.data[index];
+        stream_data.extend_from_slice(data);
+        let stream_len = stream_data.len() as u64;
+        self.tree.dir_entry_mut(index).stream_len = stream_len;
+        Ok(())
+    }
+
+    fn stream_index(&self, path: &Path) -> io::Result<usize> {
+        let index = self.tree.index_for_path(path)?;
+        if self.tree.dir_entry(index).obj_type != ObjType::Stream {
+            invalid_input!("Not a stream: {:?}", path);
+        }
+        Ok(index)
+    }
+
+    /// Sets the CLSID for the storage object at the provided path.  (To set
+    /// the CLSID for the root storage object, pass `"/"` for the path.)
+    pub fn set_storage_clsid<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        clsid: Uuid,
+    ) -> io::Result<()> {
+        let index = self.tree.index_for_path(path.as_ref())?;
+        let dir_entry = self.tree.dir_entry_mut(index);
+        if dir_entry.obj_type == ObjType::Stream {
+            invalid_input!("Not a storage: {:?}", path.as_ref());
+        }
+        dir_entry.clsid = clsid;
+        Ok(())
+    }
+
+    /// Sets the user-defined bitflags for the object at the provided path.
+    pub fn set_state_bits<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        bits: u32,
+    ) -> io::Result<()> {
+        let index = self.tree.index_for_path(path.as_ref())?;
+        self.tree.dir_entry_mut(index).state_bits = bits;
+        Ok(())
+    }
+
+    /// Writes out the complete compound file to the given writer, and
+    /// returns the writer.  The builder is left unchanged, so this can be
+    /// called more than once.
+    pub fn build<W: Write>(&self, mut writer: W) -> io::Result<W> {
+        let layout = self.tree.layout(self.version, self.timestamp_policy)?;
+        layout.write_to(&mut writer, |index, writer| {
+            let data = &self.data[index];
+            writer.write_all(data)?;
+            Ok(data.len() as u64)
+        })?;
+        Ok(writer)
+    }
+}
+
+impl Default for CompoundFileBuilder {
+    fn default() -> CompoundFileBuilder {
+        CompoundFileBuilder::new()
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,439 @@
+use crate::internal;
+use std::borrow::Borrow;
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+use std::path::Path;
+use std::str::FromStr;
+
+//===========================================================================//
+
+/// A path to a stream or storage object within a compound file.
+///
+/// Unlike `std::path::Path`, a `CfbPath` behaves identically on every
+/// platform: `/` is the only separator, and every other character
+/// (including `\`) is part of a name.  A `CfbPath` is always in canonical
+/// form; that is, it is either `/` (the root storage) or a `/` followed by
+/// one or more valid object names separated by single `/` characters.  To
+/// construct a path from less tidy input, use
+/// [`CfbPathBuf::parse`](struct.CfbPathBuf.html#method.parse).
+///
+/// When displayed, control characters within names (such as the `\x05` at
+/// the start of `\x05SummaryInformation`) are escaped as `\xNN`, and
+/// `CfbPathBuf::parse` accepts the same escapes.
+///
+/// `CfbPath` implements `AsRef<Path>`, so it can be passed to any of the
+/// `CompoundFile` methods that take a path.
+#[derive(Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[repr(transparent)]
+pub struct CfbPath {
+    inner: str,
+}
+
+impl CfbPath {
+    /// Wraps a string as a `CfbPath`, returning an error if the string isn't
+    /// a canonical compound file path.
+    pub fn new<S: AsRef<str> + ?Sized>(string: &S) -> io::Result<&CfbPath> {
+        let string = string.as_ref();
+        if string != "/" {
+            if !string.starts_with('/') {
+                invalid_input!("Invalid path (must start with '/')");
+            }
+            for name in string[1..].split('/') {
+                validate_component(name)?;
+            }
+        }
+        Ok(CfbPath::from_str_unchecked(string))
+    }
+
+    /// Wraps a string as a `CfbPath` without checking that it's canonical.
+    /// This is only for strings that are canonical by construction (such as
+    /// those built up by `CfbPathBuf::push`); anything else must go through
+    /// `CfbPath::new`.
+    pub(crate) fn from_str_unchecked(string: &str) -> &CfbPath {
+        // SAFETY: CfbPath is a repr(transparent) wrapper around str, so the
+        // two types have the same layout.
+        unsafe { &*(string as *const str as *const CfbPath) }
+    }
+
+    /// Returns the path to the root storage.
+    pub fn root() -> &'static CfbPath {
+        CfbPath::from_str_unchecked("/")
+    }
+
+    /// Returns the path as an unescaped string.
+    pub fn as_str(&self) -> &str {
+        &self.inner
+    }
+
+    /// Returns the path as a `std::path::Path`.
+    pub fn as_path(&self) -> &Path {
+        Path::new(&self.inner)
+    }
+
+    /// Returns true if this is the path to the root storage.
+    pub fn is_root(&self) -> bool {
+        &self.inner == "/"
+    }
+
+    /// Returns an iterator over the names of the objects leading from the
+    /// root storage down to the object this path refers to.
+    pub fn names(&self) -> PathNames<'_> {
+        let mut split = self.inner.split('/');
+        split.next();
+        if self.is_root() {
+            split.next();
+        }
+        PathNames { split }
+    }
+
+    /// Returns the name of the object this path refers to, or `None` for
+    /// the root storage.
+    pub fn file_name(&self) -> Option<&str> {
+        if self.is_root() {
+            None
+        } else {
+            self.inner.rsplit('/').next()
+        }
+    }
+
+    /// Returns the path to the storage containing the object this path
+    /// refers to, or `None` for the root storage.
+    pub fn parent(&self) -> Option<&CfbPath> {
+        if self.is_root() {
+            return None;
+        }
+        let index = self.inner.rfind('/').unwrap();
+        Some(CfbPath::from_str_unchecked(if index == 0 {
+            "/"
+        } else {
+            &self.inner[..index]
+        }))
+    }
+
+    /// Returns a new path referring to the object with the given name within
+    /// the storage this path refers to.  Returns an error if the name is
+    /// invalid.
+    pub fn join(&self, name: &str) -> io::Result<CfbPathBuf> {
+        let mut path = self.to_cfb_path_buf();
+        path.push(name)?;
+        Ok(path)
+    }
+
+    /// Copies this path into a new `CfbPathBuf`.
+    pub fn to_cfb_path_buf(&self) -> CfbPathBuf {
+        CfbPathBuf { inner: self.inner.to_string() }
+    }
+}
+
+impl AsRef<CfbPath> for CfbPath {
+    fn as_ref(&self) -> &CfbPath {
+        self
+    }
+}
+
+impl AsRef<Path> for CfbPath {
+    fn as_ref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl AsRef<str> for CfbPath {
+    fn as_ref(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl ToOwned for CfbPath {
+    type Owned = CfbPathBuf;
+
+    fn to_owned(&self) -> CfbPathBuf {
+        self.to_cfb_path_buf()
+    }
+}
+
+impl fmt::Debug for CfbPath {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("\"")?;
+        write_escaped(formatter, &self.inner)?;
+        formatter.write_str("\"")
+    }
+}
+
+impl fmt::Display for CfbPath {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write_escaped(formatter, &self.inner)
+    }
+}
+
+//===========================================================================//
+
+/// An owned, mutable [`CfbPath`](struct.CfbPath.html).
+#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct CfbPathBuf {
+    inner: String,
+}
+
+impl CfbPathBuf {
+    /// Returns a new path referring to the root storage.
+    pub fn new() -> CfbPathBuf {
+        CfbPathBuf { inner: "/".to_string() }
+    }
+
+    /// Parses a path, with the following differences from
+    /// [`CfbPath::new`](struct.CfbPath.html#method.new):
+    ///
+    /// * Relative paths are treated as relative to the root storage.
+    /// * Empty and `.` components are ignored.
+    /// * Within names, `\xNN` (where `NN` is two hex digits) is replaced by
+    ///   the character with that code point, so that control characters can
+    ///   be written easily.
+    ///
+    /// As with `CfbPath::new`, `..` components are rejected, as are invalid
+    /// names.
+    pub fn parse(string: &str) -> io::Result<CfbPathBuf> {
+        let mut path = CfbPathBuf::new();
+        for component in string.split('/') {
+            if component.is_empty() || component == "." {
+                continue;
+            }
+            path.push(&unescape(component)?)?;
+        }
+        Ok(path)
+    }
+
+    /// Returns a borrowed view of this path.
+    pub fn as_cfb_path(&self) -> &CfbPath {
+        CfbPath::from_str_unchecked(&self.inner)
+    }
+
+    /// Appends the given name to the end of this path.  Returns an error
+    /// (leaving this path unchanged) if the name is invalid.
+    pub fn push(&mut self, name: &str) -> io::Result<()> {
+        validate_component(name)?;
+        if !self.is_root() {
+            self.inner.push('/');
+        }
+        self.inner.push_str(name);
+        Ok(())
+    }
+
+    /// Removes the last name from this path, returning false if this path
+    /// was already the root.
+    pub fn pop(&mut self) -> bool {
+        match self.parent().map(|parent| parent.inner.len()) {
+            Some(len) => {
+                self.inner.truncate(len);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Consumes this path and returns the underlying unescaped string.
+    pub fn into_string(self) -> String {
+        self.inner
+    }
+}
+
+impl Default for CfbPathBuf {
+    fn default() -> CfbPathBuf {
+        CfbPathBuf::new()
+    }
+}
+
+impl Deref for CfbPathBuf {
+    type Target = CfbPath;
+
+    fn deref(&self) -> &CfbPath {
+        self.as_cfb_path()
+    }
+}
+
+impl Borrow<CfbPath> for CfbPathBuf {
+    fn borrow(&self) -> &CfbPath {
+        self.as_cfb_path()
+    }
+}
+
+impl AsRef<CfbPath> for CfbPathBuf {
+    fn as_ref(&self) -> &CfbPath {
+        self.as_cfb_path()
+    }
+}
+
+impl AsRef<Path> for CfbPathBuf {
+    fn as_ref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl AsRef<str> for CfbPathBuf {
+    fn as_ref(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl<'a> From<&'a CfbPath> for CfbPathBuf {
+    fn from(path: &'a CfbPath) -> CfbPathBuf {
+        path.to_cfb_path_buf()
+    }
+}
+
+impl FromStr for CfbPathBuf {
+    type Err = io::Error;
+
+    fn from_str(string: &str) -> io::Result<CfbPathBuf> {
+        CfbPathBuf::parse(string)
+    }
+}
+
+impl fmt::Debug for CfbPathBuf {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_cfb_path(), formatter)
+    }
+}
+
+impl fmt::Display for CfbPathBuf {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_cfb_path(), formatter)
+    }
+}
+
+//===========================================================================//
+
+/// An iterator over the names in a [`CfbPath`](struct.CfbPath.html),
+/// created by [`CfbPath::names`](struct.CfbPath.html#method.names).
+pub struct PathNames<'a> {
+    split: std::str::Split<'a, char>,
+}
+
+impl<'a> Iterator for PathNames<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        self.split.next()
+    }
+}
+
+//===========================================================================//
+
+fn validate_component(name: &str) -> io::Result<()> {
+    if name.is_empty() {
+        invalid_input!("Invalid path (must not contain empty names)");
+    } else if name == "." || name == ".." {
+        invalid_input!("Invalid path (must not contain {:?})", name);
+    }
+    internal::path::validate_name(name)?;
+    Ok(())
+}
+
+fn should_escape(chr: char) -> bool {
+    chr < ' ' || chr == '\x7f'
+}
+
+fn write_escaped(formatter: &mut fmt::Formatter, string: &str) -> fmt::Result {
+    for chr in string.chars() {
+        if should_escape(chr) {
+            write!(formatter, "\\x{:02X}", chr as u32)?;
+        } else {
+            fmt::Write::write_char(formatter, chr)?;
+        }
+    }
+    Ok(())
+}
+
+fn unescape(component: &str) -> io::Result<String> {
+    let mut name = String::with_capacity(component.len());
+    let mut chars = component.chars();
+    while let Some(chr) = chars.next() {
+        if chr != '\\' {
+            name.push(chr);
+            continue;
+        }
+        if chars.next() != Some('x') {
+            invalid_input!("Invalid escape in path (expected \\xNN)");
+        }
+        let digits: String = chars.by_ref().take(2).collect();
+        match u8::from_str_radix(&digits, 16) {
+            Ok(byte) if digits.len() == 2 => name.push(byte as char),
+            _ => invalid_input!("Invalid escape in path (expected \\xNN)"),
+        }
+    }
+    Ok(name)
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{CfbPath, CfbPathBuf};
+
+    #[test]
+    fn canonical_paths() {
+        assert!(CfbPath::new("/").unwrap().is_root());
+        assert_eq!(CfbPath::new("/foo/bar").unwrap().as_str(), "/foo/bar");
+        assert!(CfbPath::new("").is_err());
+        assert!(CfbPath::new("foo").is_err());
+        assert!(CfbPath::new("/foo/").is_err());
+        assert!(CfbPath::new("//foo").is_err());
+        assert!(CfbPath::new("/foo/./bar").is_err());
+        assert!(CfbPath::new("/foo/../bar").is_err());
+        assert!(CfbPath::new("/foo:bar").is_err());
+        // Backslash is never a separator, so it's an invalid name character.
+        assert!(CfbPath::new("/foo\\bar").is_err());
+    }
+
+    #[test]
+    fn parse_paths() {
+        let path = CfbPathBuf::parse("foo//./bar/").unwrap();
+        assert_eq!(path.as_str(), "/foo/bar");
+        let path = CfbPathBuf::parse("/\\x05SummaryInformation").unwrap();
+        assert_eq!(path.as_str(), "/\u{5}SummaryInformation");
+        let path: CfbPathBuf = "\\x01CompObj".parse().unwrap();
+        assert_eq!(path.as_str(), "/\u{1}CompObj");
+        assert!(CfbPathBuf::parse("").unwrap().is_root());
+        assert!(CfbPathBuf::parse("/foo/../bar").is_err());
+        assert!(CfbPathBuf::parse("/\\x5").is_err());
+        assert!(CfbPathBuf::parse("/\\q05").is_err());
+        assert!(CfbPathBuf::parse("/\\x5G").is_err());
+    }
+
+    #[test]
+    fn display_escapes_control_characters() {
+        let path = CfbPathBuf::parse("/ObjectPool/_1/\\x01Ole").unwrap();
+        assert_eq!(path.to_string(), "/ObjectPool/_1/\\x01Ole");
+        assert_eq!(format!("{:?}", path), "\"/ObjectPool/_1/\\x01Ole\"");
+        let path = CfbPath::new("/\u{5}Summary\u{7f}").unwrap();
+        assert_eq!(path.to_string(), "/\\x05Summary\\x7F");
+        let displayed = path.to_string();
+        assert_eq!(&*CfbPathBuf::parse(&displayed).unwrap(), path);
+    }
+
+    #[test]
+    fn names_and_parents() {
+        let root = CfbPath::root();
+        assert_eq!(root.names().count(), 0);
+        assert_eq!(root.file_name(), None);
+        assert_eq!(root.parent(), None);
+        let path = CfbPath::new("/foo/bar").unwrap();
+        assert_eq!(path.names().collect::<Vec<_>>(), vec!["foo", "bar"]);
+        assert_eq!(path.file_name(), Some("bar"));
+        let parent = path.parent().unwrap();
+        assert_eq!(parent.as_str(), "/foo");
+        assert_eq!(parent.parent(), Some(root));
+
+        let mut path = root.join("foo").unwrap();
+        path.push("bar").unwrap();
+        assert_eq!(path.as_str(), "/foo/bar");
+        assert!(path.push("a/b").is_err());
+        assert!(path.push("..").is_err());
+        assert_eq!(path.as_str(), "/foo/bar");
+        assert!(path.pop());
+        assert!(path.pop());
+        assert!(!path.pop());
+        assert!(path.is_root());
+    }
+}
+
+//===========================================================================//
@@ -6,11 +6,14 @@ use crate::internal::consts;
 /// red-black tree).
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Color {
+    /// A red node.
     Red,
+    /// A black node.
     Black,
 }
 
 impl Color {
+    /// Returns the byte value used to represent this in a directory entry.
     pub fn as_byte(&self) -> u8 {
         match self {
             Color::Red => consts::COLOR_RED,
@@ -18,6 +21,8 @@ impl Color {
         }
     }
 
+    /// Parses the byte value used to represent this in a directory entry,
+    /// returning `None` if the byte is not a valid value.
     pub fn from_byte(byte: u8) -> Option<Color> {
         if byte == consts::COLOR_RED {
             Some(Color::Red)
@@ -0,0 +1,160 @@
+use crate::internal::{
+    self, consts, Entry, EntryId, MiniAllocator, ObjType, Stream,
+};
+use std::cell::RefCell;
+use std::io;
+use std::rc::{Rc, Weak};
+
+//===========================================================================//
+
+/// A handle to a stream or storage object within a compound file, which can
+/// be used to navigate the storage tree.
+///
+/// Unlike the [`Entries`](struct.Entries.html) iterator, an `EntryCursor`
+/// does not borrow the `CompoundFile` (it holds only a weak reference to it,
+/// just like a [`Stream`](struct.Stream.html)), so the compound file can be
+/// freely read from and modified while cursors are alive.
+///
+/// A cursor refers to one specific object.  If that object is later removed
+/// from the compound file, then all methods on the cursor will return an
+/// error of kind `NotFound` (even if a new object is created with the same
+/// path).  Lists of children returned by
+/// [`children`](struct.EntryCursor.html#method.children) are snapshots, and
+/// are not affected by later changes to the storage tree.
+pub struct EntryCursor<F> {
+    minialloc: Weak<RefCell<MiniAllocator<F>>>,
+    stream_id: u32,
+    serial: u64,
+}
+
+impl<F> EntryCursor<F> {
+    pub(crate) fn new(
+        minialloc: &Rc<RefCell<MiniAllocator<F>>>,
+        stream_id: u32,
+    ) -> EntryCursor<F> {
+        let serial = minialloc.borrow().serial(stream_id);
+        EntryCursor { minialloc: Rc::downgrade(minialloc), stream_id, serial }
+    }
+
+    /// Returns the compound file's allocator, checking that the object this
+    /// cursor refers to still exists.
+    fn minialloc(&self) -> io::Result<Rc<RefCell<MiniAllocator<F>>>> {
+        let minialloc = self
+            .minialloc
+            .upgrade()
+            .ok_or_else(|| io::Error::other("CompoundFile was dropped"))?;
+        {
+            let minialloc = minialloc.borrow();
+            if minialloc.serial(self.stream_id) != self.serial
+                || minialloc.dir_entry(self.stream_id).obj_type
+                    == ObjType::Unallocated
+            {
+                not_found!("Object was removed from the compound file");
+            }
+        }
+        Ok(minialloc)
+    }
+
+    /// Returns the ID of the object that this cursor refers to.
+    pub fn id(&self) -> EntryId {
+        EntryId::new(self.stream_id, self.serial)
+    }
+
+    /// Returns current information about the object that this cursor refers
+    /// to.
+    pub fn entry(&self) -> io::Result<Entry> {
+        let minialloc = self.minialloc()?;
+        let minialloc = minialloc.borrow();
+        let path = match minialloc.name_chain_for_stream_id(self.stream_id) {
+            Some(names) => internal::path::path_from_name_chain(&names),
+            None => not_found!("Object was removed from the compound file"),
+        };
+        Ok(Entry::new(&minialloc, self.stream_id, path))
+    }
+
+    /// Returns a cursor for the storage containing this object, or `None` if
+    /// this cursor refers to the root storage.
+    pub fn parent(&self) -> io::Result<Option<EntryCursor<F>>> {
+        let minialloc = self.minialloc()?;
+        if self.stream_id == consts::ROOT_STREAM_ID {
+            return Ok(None);
+        }
+        let parent_id = minialloc.borrow().parent_id(self.stream_id);
+        if parent_id == consts::NO_STREAM {
+            not_found!("Object was removed from the compound file");
+        }
+        Ok(Some(EntryCursor::new(&minialloc, parent_id)))
+    }
+
+    /// Returns cursors for all objects directly within this storage, in
+    /// sorted order.  Returns an error if this cursor refers to a stream.
+    pub fn children(&self) -> io::Result<Vec<EntryCursor<F>>> {
+        let minialloc = self.minialloc()?;
+        let child_ids = {
+            let minialloc = minialloc.borrow();
+            if minialloc.dir_entry(self.stream_id).obj_type == ObjType::Stream
+            {
+                invalid_input!("Not a storage");
+            }
+            minialloc.child_ids(self.stream_id)
+        };
+        Ok(child_ids
+            .into_iter()
+            .map(|stream_id| EntryCursor::new(&minialloc, stream_id))
+            .collect())
+    }
+
+    /// Returns a cursor for the object with the given name directly within
+    /// this storage.  Returns an error if this cursor refers to a stream, or
+    /// if there is no such object.
+    pub fn child(&self, name: &str) -> io::Result<EntryCursor<F>> {
+        let name_utf16: Vec<u16> = name.encode_utf16().collect();
+        self.child_utf16(&name_utf16)
+    }
+
+    /// Like [`child`](struct.EntryCursor.html#method.child), but takes the
+    /// name as raw UTF-16 code units, which need not be valid UTF-16.
+    pub fn child_utf16(&self, name: &[u16]) -> io::Result<EntryCursor<F>> {
+        let minialloc = self.minialloc()?;
+        let child_id = {
+            let minialloc = minialloc.borrow();
+            if minialloc.dir_entry(self.stream_id).obj_type == ObjType::Stream
+            {
+                invalid_input!("Not a storage");
+            }
+            match minialloc.child_id(self.stream_id, name) {
+                Some(child_id) => child_id,
+                None => not_found!(
+                    "No such object: {:?}",
+                    String::from_utf16_lossy(name)
+                ),
+            }
+        };
+        Ok(EntryCursor::new(&minialloc, child_id))
+    }
+
+    /// Opens the stream that this cursor refers to for reading and/or
+    /// writing (depending on what the underlying file supports).  Returns an
+    /// error if this cursor refers to a storage.
+    pub fn open_stream(&self) -> io::Result<Stream<F>> {
+        let minialloc = self.minialloc()?;
+        if minialloc.borrow().dir_entry(self.stream_id).obj_type
+            != ObjType::Stream
+        {
+            invalid_input!("Not a stream");
+        }
+        Ok(Stream::new(&minialloc, self.stream_id))
+    }
+}
+
+impl<F> Clone for EntryCursor<F> {
+    fn clone(&self) -> EntryCursor<F> {
+        EntryCursor {
+            minialloc: self.minialloc.clone(),
+            stream_id: self.stream_id,
+            serial: self.serial,
+        }
+    }
+}
+
+//===========================================================================//
@@ -1,6 +1,6 @@
 use crate::internal::{
     self, consts, Allocator, Chain, DirEntry, ObjType, Sector, SectorInit,
-    Timestamp, Version,
+    TimestampPolicy, Version,
 };
 use byteorder::{LittleEndian, WriteBytesExt};
 use fnv::FnvHashSet;
@@ -23,7 +23,11 @@ macro_rules! malformed {
 pub struct Directory<F> {
     allocator: Allocator<F>,
     dir_entries: Vec<DirEntry>,
+    parent_ids: Vec<u32>,
+    serials: Vec<u64>,
+    next_serial: u64,
     dir_start_sector: u32,
+    timestamp_policy: TimestampPolicy,
 }
 
 impl<F> Directory<F> {
@@ -32,8 +36,20 @@ impl<F> Directory<F> {
         dir_entries: Vec<DirEntry>,
         dir_start_sector: u32,
     ) -> io::Result<Directory<F>> {
-        let directory = Directory { allocator, dir_entries, dir_start_sector };
+        let mut directory = Directory {
+            allocator,
+            dir_entries,
+            parent_ids: Vec::new(),
+            serials: Vec::new(),
+            next_serial: 0,
+            dir_start_sector,
+            timestamp_policy: TimestampPolicy::default(),
+        };
         directory.validate()?;
+        directory.parent_ids = directory.compute_parent_ids();
+        let num_dir_entries = directory.dir_entries.len() as u64;
+        directory.serials = (0..num_dir_entries).collect();
+        directory.next_serial = num_dir_entries;
         Ok(directory)
     }
 
@@ -45,25 +61,133 @@ impl<F> Directory<F> {
         self.allocator.sector_len()
     }
 
+    pub fn timestamp_policy(&self) -> TimestampPolicy {
+        self.timestamp_policy
+    }
+
+    pub fn set_timestamp_policy(&mut self, policy: TimestampPolicy) {
+        self.timestamp_policy = policy;
+    }
+
+    pub fn allocator(&self) -> &Allocator<F> {
+        &self.allocator
+    }
+
+    pub fn dir_start_sector(&self) -> u32 {
+        self.dir_start_sector
+    }
+
+    pub fn num_dir_entries(&self) -> usize {
+        self.dir_entries.len()
+    }
+
+    pub fn next_sector(&self, sector_id: u32) -> io::Result<u32> {
+        self.allocator.next(sector_id)
+    }
+
     pub fn into_inner(self) -> F {
         self.allocator.into_inner()
     }
 
+    /// Returns the stream ID of the storage containing the given entry, or
+    /// `NO_STREAM` for the root entry and for entries that aren't in the
+    /// tree at all.
+    pub fn parent_id(&self, stream_id: u32) -> u32 {
+        self.parent_ids[stream_id as usize]
+    }
+
+    /// Returns a number that uniquely identifies the object currently stored
+    /// in the given directory entry slot; it changes whenever the slot is
+    /// reused for a new object.
+    pub fn serial(&self, stream_id: u32) -> u64 {
+        self.serials[stream_id as usize]
+    }
+
+    /// Returns the stream ID of the entry with the given name directly
+    /// within the given storage, if any.
+    pub fn child_id(&self, parent_id: u32, name: &[u16]) -> Option<u32> {
+        let mut stream_id = self.dir_entry(parent_id).child;
+        loop {
+            if stream_id == consts::NO_STREAM {
+                return None;
+            }
+            let dir_entry = self.dir_entry(stream_id);
+            match internal::path::compare_names(name, &dir_entry.name) {
+                Ordering::Equal => return Some(stream_id),
+                Ordering::Less => stream_id = dir_entry.left_sibling,
+                Ordering::Greater => stream_id = dir_entry.right_sibling,
+            }
+        }
+    }
+
+    /// Returns the stream IDs of the entries directly within the given
+    /// storage, in sorted order.
+    pub fn child_ids(&self, parent_id: u32) -> Vec<u32> {
+        let mut child_ids = Vec::new();
+        let mut stack = Vec::<u32>::new();
+        let mut current_id = self.dir_entry(parent_id).child;
+        while current_id != consts::NO_STREAM || !stack.is_empty() {
+            while current_id != consts::NO_STREAM {
+                stack.push(current_id);
+                current_id = self.dir_entry(current_id).left_sibling;
+            }
+            let stream_id = stack.pop().unwrap();
+            child_ids.push(stream_id);
+            current_id = self.dir_entry(stream_id).right_sibling;
+        }
+        child_ids
+    }
+
+    /// Returns the chain of names leading from the root to the given entry,
+    /// or `None` if there is no such entry in the tree.  Names that aren't
+    /// valid UTF-16 are converted lossily.
+    pub fn name_chain_for_stream_id(
+        &self,
+        stream_id: u32,
+    ) -> Option<Vec<String>> {
+        if stream_id as usize >= self.dir_entries.len() {
+            return None;
+        }
+        let mut names = Vec::new();
+        let mut current_id = stream_id;
+        while current_id != consts::ROOT_STREAM_ID {
+            let dir_entry = self.dir_entry(current_id);
+            if dir_entry.obj_type == ObjType::Unallocated {
+                return None;
+            }
+            names.push(dir_entry.name_string());
+            current_id = self.parent_id(current_id);
+            if current_id == consts::NO_STREAM {
+                return None;
+            }
+        }
+        names.reverse();
+        Some(names)
+    }
+
+    /// Returns true if the name of the given entry, and of every storage
+    /// containing it, can be represented exactly in a `/`-separated string
+    /// path; that is, if each is valid UTF-16 and doesn't contain a `/`.
+    pub fn name_chain_is_exact(&self, stream_id: u32) -> bool {
+        let mut current_id = stream_id;
+        while current_id != consts::ROOT_STREAM_ID
+            && current_id != consts::NO_STREAM
+        {
+            let name = &self.dir_entry(current_id).name;
+            match String::from_utf16(name) {
+                Ok(name) if !name.contains('/') => {}
+                _ => return false,
+            }
+            current_id = self.parent_id(current_id);
+        }
+        true
+    }
+
     pub fn stream_id_for_name_chain(&self, names: &[&str]) -> Option<u32> {
         let mut stream_id = consts::ROOT_STREAM_ID;
         for name in names.iter() {
-            stream_id = self.dir_entry(stream_id).child;
-            loop {
-                if stream_id == consts::NO_STREAM {
-                    return None;
-                }
-                let dir_entry = self.dir_entry(stream_id);
-                match internal::path::compare_names(name, &dir_entry.name) {
-                    Ordering::Equal => break,
-                    Ordering::Less => stream_id = dir_entry.left_sibling,
-                    Ordering::Greater => stream_id = dir_entry.right_sibling,
-                }
-            }
+            let name: Vec<u16> = name.encode_utf16().collect();
+            stream_id = self.child_id(stream_id, &name)?;
         }
         Some(stream_id)
     }
@@ -72,7 +196,7 @@ impl<F> Directory<F> {
         &mut self,
         start_sector_id: u32,
         init: SectorInit,
-    ) -> io::Result<Chain<F>> {
+    ) -> io::Result<Chain<'_, F>> {
         self.allocator.open_chain(start_sector_id, init)
     }
 
@@ -88,6 +212,27 @@ impl<F> Directory<F> {
         &mut self.dir_entries[stream_id as usize]
     }
 
+    fn compute_parent_ids(&self) -> Vec<u32> {
+        let mut parent_ids = vec![consts::NO_STREAM; self.dir_entries.len()];
+        let mut storages = vec![consts::ROOT_STREAM_ID];
+        while let Some(storage_id) = storages.pop() {
+            let mut stack = vec![self.dir_entry(storage_id).child];
+            while let Some(stream_id) = stack.pop() {
+                if stream_id == consts::NO_STREAM {
+                    continue;
+                }
+                parent_ids[stream_id as usize] = storage_id;
+                let dir_entry = self.dir_entry(stream_id);
+                stack.push(dir_entry.left_sibling);
+                stack.push(dir_entry.right_sibling);
+                if dir_entry.obj_type == ObjType::Storage {
+                    storages.push(stream_id);
+                }
+            }
+        }
+        parent_ids
+    }
+
     fn validate(&self) -> io::Result<()> {
         if self.dir_entries.is_empty() {
             malformed!("root entry is missing");
@@ -139,8 +284,8 @@ impl<F> Directory<F> {
                 {
                     malformed!(
                         "name ordering, {:?} vs {:?}",
-                        dir_entry.name,
-                        entry.name
+                        dir_entry.name_string(),
+                        entry.name_string()
                     );
                 }
                 stack.push(left_sibling);
@@ -159,8 +304,8 @@ impl<F> Directory<F> {
                 {
                     malformed!(
                         "name ordering, {:?} vs {:?}",
-                        dir_entry.name,
-                        entry.name
+                        dir_entry.name_string(),
+                        entry.name_string()
                     );
                 }
                 stack.push(right_sibling);
@@ -185,11 +330,14 @@ impl<F: Seek> Directory<F> {
     pub fn seek_within_header(
         &mut self,
         offset_within_header: u64,
-    ) -> io::Result<Sector<F>> {
+    ) -> io::Result<Sector<'_, F>> {
         self.allocator.seek_within_header(offset_within_header)
     }
 
-    fn seek_to_dir_entry(&mut self, stream_id: u32) -> io::Result<Sector<F>> {
+    fn seek_to_dir_entry(
+        &mut self,
+        stream_id: u32,
+    ) -> io::Result<Sector<'_, F>> {
         self.seek_within_dir_entry(stream_id, 0)
     }
 
@@ -197,7 +345,7 @@ impl<F: Seek> Directory<F> {
         &mut self,
         stream_id: u32,
         offset_within_dir_entry: usize,
-    ) -> io::Result<Sector<F>> {
+    ) -> io::Result<Sector<'_, F>> {
         let dir_entries_per_sector =
             self.version().dir_entries_per_sector() as u32;
         let index_within_sector = stream_id % dir_entries_per_sector;
@@ -243,16 +391,21 @@ impl<F: Write + Seek> Directory<F> {
     pub fn insert_dir_entry(
         &mut self,
         parent_id: u32,
-        name: &str,
+        name: &[u16],
         obj_type: ObjType,
     ) -> io::Result<u32> {
         debug_assert!(
             obj_type == ObjType::Storage || obj_type == ObjType::Stream
         );
+        debug_assert!(internal::path::validate_name_utf16(name).is_ok());
         // Create a new directory entry.
         let stream_id = self.allocate_dir_entry()?;
-        let now = Timestamp::now();
-        *self.dir_entry_mut(stream_id) = DirEntry::new(name, obj_type, now);
+        let timestamp = self.timestamp_policy.creation_timestamp();
+        *self.dir_entry_mut(stream_id) =
+            DirEntry::new_utf16(name.to_vec(), obj_type, timestamp);
+        self.parent_ids[stream_id as usize] = parent_id;
+        self.serials[stream_id as usize] = self.next_serial;
+        self.next_serial += 1;
 
         // Insert the new entry into the tree.
         let mut sibling_id = self.dir_entry(parent_id).child;
@@ -299,7 +452,7 @@ impl<F: Write + Seek> Directory<F> {
     pub fn remove_dir_entry(
         &mut self,
         parent_id: u32,
-        name: &str,
+        name: &[u16],
     ) -> io::Result<()> {
         // Find the directory entry with the given name below the parent.
         let mut stream_ids = Vec::new();
@@ -317,39 +470,43 @@ impl<F: Write + Seek> Directory<F> {
         }
         debug_assert_eq!(self.dir_entry(stream_id).child, consts::NO_STREAM);
 
-        // Restructure the tree.
-        let mut replacement_id = consts::NO_STREAM;
-        loop {
-            let left_sibling = self.dir_entry(stream_id).left_sibling;
-            let right_sibling = self.dir_entry(stream_id).right_sibling;
-            if left_sibling == consts::NO_STREAM
-                && right_sibling == consts::NO_STREAM
-            {
-                break;
-            } else if left_sibling == consts::NO_STREAM {
-                replacement_id = right_sibling;
-                break;
-            } else if right_sibling == consts::NO_STREAM {
-                replacement_id = left_sibling;
-                break;
-            }
+        // Restructure the tree.  If the entry has two children, its in-order
+        // predecessor is moved into its place in the tree (rather than
+        // copying the predecessor's contents into this entry's slot), so that
+        // the stream IDs of all remaining entries stay the same.
+        let left_sibling = self.dir_entry(stream_id).left_sibling;
+        let right_sibling = self.dir_entry(stream_id).right_sibling;
+        let replacement_id = if left_sibling == consts::NO_STREAM {
+            right_sibling
+        } else if right_sibling == consts::NO_STREAM {
+            left_sibling
+        } else {
+            let mut pred_parent_id = stream_id;
             let mut predecessor_id = left_sibling;
             loop {
-                stream_ids.push(predecessor_id);
                 let next_id = self.dir_entry(predecessor_id).right_sibling;
                 if next_id == consts::NO_STREAM {
                     break;
                 }
+                pred_parent_id = predecessor_id;
                 predecessor_id = next_id;
             }
-            let mut pred_entry = self.dir_entry(predecessor_id).clone();
-            debug_assert_eq!(pred_entry.right_sibling, consts::NO_STREAM);
-            pred_entry.left_sibling = left_sibling;
-            pred_entry.right_sibling = right_sibling;
-            pred_entry.write_to(&mut self.seek_to_dir_entry(stream_id)?)?;
-            *self.dir_entry_mut(stream_id) = pred_entry;
-            stream_id = predecessor_id;
-        }
+            if pred_parent_id != stream_id {
+                let pred_left = self.dir_entry(predecessor_id).left_sibling;
+                self.with_dir_entry_mut(pred_parent_id, |dir_entry| {
+                    dir_entry.right_sibling = pred_left;
+                })?;
+                self.with_dir_entry_mut(predecessor_id, |dir_entry| {
+                    dir_entry.left_sibling = left_sibling;
+                })?;
+            }
+            let color = self.dir_entry(stream_id).color;
+            self.with_dir_entry_mut(predecessor_id, |dir_entry| {
+                dir_entry.right_sibling = right_sibling;
+                dir_entry.color = color;
+            })?;
+            predecessor_id
+        };
         // TODO: recolor nodes
 
         // Remove the entry.
@@ -398,6 +555,9 @@ impl<F: Write + Seek> Directory<F> {
         // Add a new entry to the end of the directory and return it.
         let stream_id = self.dir_entries.len() as u32;
         self.dir_entries.push(unallocated_dir_entry);
+        self.parent_ids.push(consts::NO_STREAM);
+        self.serials.push(self.next_serial);
+        self.next_serial += 1;
         Ok(stream_id)
     }
 
@@ -407,6 +567,7 @@ impl<F: Write + Seek> Directory<F> {
         let dir_entry = DirEntry::unallocated();
         dir_entry.write_to(&mut self.seek_to_dir_entry(stream_id)?)?;
         *self.dir_entry_mut(stream_id) = dir_entry;
+        self.parent_ids[stream_id as usize] = consts::NO_STREAM;
         // TODO: Truncate directory chain if last directory sector is now all
         //       unallocated.
         Ok(())
@@ -557,6 +718,28 @@ mod tests {
         storage2.color = Color::Red;
         make_directory(vec![root_entry, storage1, storage2]);
     }
+
+    #[test]
+    fn name_chain_is_exact() {
+        let mut root_entry = DirEntry::empty_root_entry();
+        root_entry.child = 1;
+        let mut storage = DirEntry::new_utf16(
+            vec![0x41, 0xd800],
+            ObjType::Storage,
+            Timestamp::zero(),
+        );
+        storage.child = 2;
+        storage.right_sibling = 3;
+        let stream = DirEntry::new("bar", ObjType::Stream, Timestamp::zero());
+        let storage2 =
+            DirEntry::new("foo", ObjType::Storage, Timestamp::zero());
+        let directory =
+            make_directory(vec![root_entry, storage, stream, storage2]);
+        assert!(directory.name_chain_is_exact(consts::ROOT_STREAM_ID));
+        assert!(!directory.name_chain_is_exact(1));
+        assert!(!directory.name_chain_is_exact(2));
+        assert!(directory.name_chain_is_exact(3));
+    }
 }
 
 //===========================================================================//
@@ -18,7 +18,7 @@ macro_rules! malformed {
 
 #[derive(Clone)]
 pub struct DirEntry {
-    pub name: String,
+    pub name: Vec<u16>,
     pub obj_type: ObjType,
     pub color: Color,
     pub left_sibling: u32,
@@ -37,10 +37,18 @@ impl DirEntry {
         name: &str,
         obj_type: ObjType,
         timestamp: Timestamp,
+    ) -> DirEntry {
+        DirEntry::new_utf16(name.encode_utf16().collect(), obj_type, timestamp)
+    }
+
+    pub fn new_utf16(
+        name: Vec<u16>,
+        obj_type: ObjType,
+        timestamp: Timestamp,
     ) -> DirEntry {
         debug_assert_ne!(obj_type, ObjType::Unallocated);
         DirEntry {
-            name: name.to_string(),
+            name,
             obj_type,
             color: Color::Black,
             left_sibling: consts::NO_STREAM,
@@ -61,12 +69,18 @@ impl DirEntry {
         }
     }
 
+    /// Returns the name of this entry as a string, replacing any unpaired
+    /// surrogates with U+FFFD.
+    pub fn name_string(&self) -> String {
+        String::from_utf16_lossy(&self.name)
+    }
+
     pub fn unallocated() -> DirEntry {
         // According to the MS-CFB spec section 2.6.3, unallocated directory
         // entries must consist of all zeros except for the sibling and child
         // fields, which must be NO_STREAM.
         DirEntry {
-            name: String::new(),
+            name: Vec::new(),
             obj_type: ObjType::Unallocated,
             color: Color::Red,
             left_sibling: NO_STREAM,
@@ -108,7 +122,7 @@ impl DirEntry {
         version: Version,
         validation: Validation,
     ) -> io::Result<DirEntry> {
-        let mut name: String = {
+        let mut name: Vec<u16> = {
             let mut name_chars: Vec<u16> = Vec::with_capacity(32);
             for _ in 0..32 {
                 name_chars.push(reader.read_u16::<LittleEndian>()?);
@@ -135,10 +149,11 @@ impl DirEntry {
             if validation.is_strict() && name_chars[name_len_chars] != 0 {
                 malformed!("name not null-terminated");
             }
-            match String::from_utf16(&name_chars[0..name_len_chars]) {
-                Ok(name) => name,
-                Err(_) => malformed!("name not valid UTF-16"),
-            }
+            // Names are kept as raw UTF-16 code units rather than decoded,
+            // since some files in the wild contain names with unpaired
+            // surrogates, and those must survive being written back out.
+            name_chars.truncate(name_len_chars);
+            name_chars
         };
 
         let obj_type = {
@@ -156,18 +171,20 @@ impl DirEntry {
         // instead, for the root entry we just ignore the actual name in the
         // file and treat it as though it were what it's supposed to be.
         if obj_type == ObjType::Root {
-            if name != consts::ROOT_DIR_NAME {
+            let root_name: Vec<u16> =
+                consts::ROOT_DIR_NAME.encode_utf16().collect();
+            if name != root_name {
                 if validation.is_strict() {
                     malformed!(
                         "root entry name is {:?}, but should be {:?}",
-                        name,
+                        String::from_utf16_lossy(&name),
                         consts::ROOT_DIR_NAME
                     );
                 }
-                name = consts::ROOT_DIR_NAME.to_string();
+                name = root_name;
             }
         } else {
-            internal::path::validate_name(&name)?;
+            internal::path::validate_name_utf16(&name)?;
         }
 
         let color = {
@@ -250,16 +267,15 @@ impl DirEntry {
     }
 
     pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
-        debug_assert!(internal::path::validate_name(&self.name).is_ok());
-        let name_utf16: Vec<u16> = self.name.encode_utf16().collect();
-        debug_assert!(name_utf16.len() < 32);
-        for &chr in name_utf16.iter() {
+        debug_assert!(internal::path::validate_name_utf16(&self.name).is_ok());
+        debug_assert!(self.name.len() < 32);
+        for &chr in self.name.iter() {
             writer.write_u16::<LittleEndian>(chr)?;
         }
-        for _ in name_utf16.len()..32 {
+        for _ in self.name.len()..32 {
             writer.write_u16::<LittleEndian>(0)?;
         }
-        writer.write_u16::<LittleEndian>((name_utf16.len() as u16 + 1) * 2)?;
+        writer.write_u16::<LittleEndian>((self.name.len() as u16 + 1) * 2)?;
         writer.write_u8(self.obj_type.as_byte())?;
         writer.write_u8(self.color.as_byte())?;
         writer.write_u32::<LittleEndian>(self.left_sibling)?;
@@ -313,7 +329,7 @@ mod tests {
             Validation::Permissive,
         )
         .unwrap();
-        assert_eq!(&dir_entry.name, "Foobar");
+        assert_eq!(dir_entry.name_string(), "Foobar");
         assert_eq!(dir_entry.obj_type, ObjType::Storage);
         assert_eq!(dir_entry.color, Color::Black);
         assert_eq!(dir_entry.left_sibling, 12);
@@ -357,7 +373,7 @@ mod tests {
             Validation::Strict,
         )
         .unwrap();
-        assert_eq!(&dir_entry.name, "Foobar");
+        assert_eq!(dir_entry.name_string(), "Foobar");
         assert_eq!(dir_entry.obj_type, ObjType::Storage);
         assert_eq!(dir_entry.color, Color::Black);
         assert_eq!(dir_entry.left_sibling, 12);
@@ -528,7 +544,7 @@ mod tests {
             Validation::Permissive,
         )
         .unwrap();
-        assert_eq!(dir_entry.name, "Foobar");
+        assert_eq!(dir_entry.name_string(), "Foobar");
     }
 
     #[test]
@@ -638,7 +654,7 @@ mod tests {
         )
         .unwrap();
         assert_eq!(dir_entry.obj_type, ObjType::Root);
-        assert_eq!(dir_entry.name, "Root Entry");
+        assert_eq!(dir_entry.name_string(), "Root Entry");
     }
 }
 
@@ -1,4 +1,6 @@
-use crate::internal::{consts, DirEntry, MiniAllocator, ObjType, Timestamp};
+use crate::internal::{
+    self, consts, CfbPath, DirEntry, MiniAllocator, ObjType, Timestamp,
+};
 use std::cell::RefCell;
 use std::path::{Path, PathBuf};
 use std::rc::Rc;
@@ -7,11 +9,43 @@ use uuid::Uuid;
 
 //===========================================================================//
 
+/// An opaque identifier for a stream or storage object within a compound
+/// file.
+///
+/// An `EntryId` can be used to look up or open an object again without
+/// re-resolving its path.  It remains valid for as long as the object exists;
+/// once the object is removed, using the ID results in an error of kind
+/// `NotFound`, even if a new object has since been created in its place.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct EntryId {
+    stream_id: u32,
+    serial: u64,
+}
+
+impl EntryId {
+    pub(crate) fn new(stream_id: u32, serial: u64) -> EntryId {
+        EntryId { stream_id, serial }
+    }
+
+    pub(crate) fn stream_id(self) -> u32 {
+        self.stream_id
+    }
+
+    pub(crate) fn serial(self) -> u64 {
+        self.serial
+    }
+}
+
+//===========================================================================//
+
 /// Metadata about a single object (storage or stream) in a compound file.
 #[derive(Clone)]
 pub struct Entry {
+    id: EntryId,
     name: String,
+    name_utf16: Vec<u16>,
     path: PathBuf,
+    path_is_exact: bool,
     obj_type: ObjType,
     clsid: Uuid,
     state_bits: u32,
@@ -21,10 +55,18 @@ pub struct Entry {
 }
 
 impl Entry {
-    pub(crate) fn new(dir_entry: &DirEntry, path: PathBuf) -> Entry {
+    pub(crate) fn new<F>(
+        minialloc: &MiniAllocator<F>,
+        stream_id: u32,
+        path: PathBuf,
+    ) -> Entry {
+        let dir_entry = minialloc.dir_entry(stream_id);
         Entry {
-            name: dir_entry.name.clone(),
+            id: minialloc.entry_id(stream_id),
+            name: dir_entry.name_string(),
+            name_utf16: dir_entry.name.clone(),
             path,
+            path_is_exact: minialloc.name_chain_is_exact(stream_id),
             obj_type: dir_entry.obj_type,
             clsid: dir_entry.clsid,
             state_bits: dir_entry.state_bits,
@@ -34,16 +76,46 @@ impl Entry {
         }
     }
 
-    /// Returns the name of the object that this entry represents.
+    /// Returns an identifier for the object that this entry represents, which
+    /// can be used to access the object again without looking up its path.
+    pub fn id(&self) -> EntryId {
+        self.id
+    }
+
+    /// Returns the name of the object that this entry represents.  If the
+    /// name isn't valid UTF-16 (e.g. it contains unpaired surrogates), any
+    /// invalid code units are replaced with U+FFFD; use
+    /// [`name_utf16`](#method.name_utf16) to get the exact name.
     pub fn name(&self) -> &str {
         &self.name
     }
 
+    /// Returns the name of the object that this entry represents, as the raw
+    /// UTF-16 code units stored in the compound file.
+    pub fn name_utf16(&self) -> &[u16] {
+        &self.name_utf16
+    }
+
     /// Returns the full path to the object that this entry represents.
     pub fn path(&self) -> &Path {
         &self.path
     }
 
+    /// Returns the full path to the object that this entry represents, as a
+    /// [`CfbPath`](struct.CfbPath.html) (which, when displayed, escapes any
+    /// control characters in names).  Returns `None` if the path can't be
+    /// represented exactly as a `CfbPath`: that is, if the name of this
+    /// object or of any storage containing it isn't valid UTF-16, or isn't
+    /// a valid `CfbPath` name (such as `.` or `..`).  Such objects can still
+    /// be accessed using their [`id`](#method.id) or their raw
+    /// [`name_utf16`](#method.name_utf16).
+    pub fn cfb_path(&self) -> Option<&CfbPath> {
+        if !self.path_is_exact {
+            return None;
+        }
+        CfbPath::new(self.path.to_str()?).ok()
+    }
+
     /// Returns whether this entry is for a stream object (i.e. a "file" within
     /// the compound file).
     pub fn is_stream(&self) -> bool {
@@ -84,16 +156,28 @@ impl Entry {
     }
 
     /// Returns the time when the object that this entry represents was
-    /// created.
-    pub fn created(&self) -> SystemTime {
+    /// created, or `None` if the creation time is not set.
+    pub fn created(&self) -> Option<SystemTime> {
         self.creation_time.to_system_time()
     }
 
     /// Returns the time when the object that this entry represents was last
-    /// modified.
-    pub fn modified(&self) -> SystemTime {
+    /// modified, or `None` if the modification time is not set.
+    pub fn modified(&self) -> Option<SystemTime> {
         self.modified_time.to_system_time()
     }
+
+    /// Returns the raw creation timestamp of the object that this entry
+    /// represents.
+    pub fn created_timestamp(&self) -> Timestamp {
+        self.creation_time
+    }
+
+    /// Returns the raw modification timestamp of the object that this entry
+    /// represents.
+    pub fn modified_timestamp(&self) -> Timestamp {
+        self.modified_time
+    }
 }
 
 //===========================================================================//
@@ -162,7 +246,7 @@ impl<'a, F> Iterator for Entries<'a, F> {
             {
                 self.stack_left_spine(&path, dir_entry.child);
             }
-            Some(Entry::new(dir_entry, path))
+            Some(Entry::new(&minialloc, stream_id, path))
         } else {
             None
         }
@@ -175,7 +259,7 @@ fn join_path(parent_path: &Path, dir_entry: &DirEntry) -> PathBuf {
     if dir_entry.obj_type == ObjType::Root {
         parent_path.to_path_buf()
     } else {
-        parent_path.join(&dir_entry.name)
+        internal::path::join_name(parent_path, &dir_entry.name_string())
     }
 }
 
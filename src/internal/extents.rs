@@ -0,0 +1,150 @@
+use crate::internal::{consts, MiniAllocator, ObjType};
+use std::cmp;
+use std::io;
+
+//===========================================================================//
+
+/// Describes where the data for a stream physically lives within a compound
+/// file.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Extents {
+    stream_id: u32,
+    is_mini: bool,
+    start_sector: u32,
+    runs: Vec<(u64, u64)>,
+}
+
+impl Extents {
+    /// Returns the stream ID (i.e. the index of the directory entry) of the
+    /// stream.
+    pub fn stream_id(&self) -> u32 {
+        self.stream_id
+    }
+
+    /// Returns true if the stream's data is stored in mini sectors within the
+    /// mini stream, rather than in regular sectors.
+    pub fn is_mini(&self) -> bool {
+        self.is_mini
+    }
+
+    /// Returns the starting sector of the stream's chain.  This is a mini
+    /// sector index if `is_mini()` is true, and a regular sector index
+    /// otherwise.  Empty streams have a starting sector of `0xfffffffe`
+    /// (`ENDOFCHAIN`).
+    pub fn start_sector(&self) -> u32 {
+        self.start_sector
+    }
+
+    /// Returns the contiguous runs of bytes that make up the stream's data,
+    /// in stream order, as `(file_offset, len)` pairs.  The file offsets are
+    /// absolute positions within the underlying file (resolved through the
+    /// mini stream's own chain for mini streams), and the lengths add up to
+    /// the length of the stream.
+    pub fn runs(&self) -> &[(u64, u64)] {
+        &self.runs
+    }
+}
+
+//===========================================================================//
+
+impl<F> MiniAllocator<F> {
+    /// Computes the physical extents of the given stream (or, for the root
+    /// entry, of the mini stream itself).
+    pub fn extents(&self, stream_id: u32) -> io::Result<Extents> {
+        let dir_entry = self.dir_entry(stream_id);
+        let is_mini = dir_entry.obj_type == ObjType::Stream
+            && dir_entry.stream_len < consts::MINI_STREAM_CUTOFF as u64;
+        let start_sector = dir_entry.start_sector;
+        let stream_len = dir_entry.stream_len;
+        let sector_len = self.version().sector_len() as u64;
+        let mut runs = Vec::<(u64, u64)>::new();
+        if stream_len == 0 {
+            return Ok(Extents { stream_id, is_mini, start_sector, runs });
+        }
+        let mut remaining = stream_len;
+        if is_mini {
+            let mini_stream_sectors = chain_sector_ids(
+                self.root_dir_entry().start_sector,
+                |sector_id| self.next_sector(sector_id),
+            )?;
+            let mini_sectors = chain_sector_ids(start_sector, |sector_id| {
+                self.next_mini_sector(sector_id)
+            })?;
+            let mini_sector_len = consts::MINI_SECTOR_LEN as u64;
+            for mini_sector in mini_sectors {
+                if remaining == 0 {
+                    break;
+                }
+                let offset = mini_sector as u64 * mini_sector_len;
+                let index = (offset / sector_len) as usize;
+                let sector_id = match mini_stream_sectors.get(index) {
+                    Some(&sector_id) => sector_id,
+                    None => invalid_data!(
+                        "Mini sector {} is beyond the end of the mini stream",
+                        mini_sector
+                    ),
+                };
+                let file_offset =
+                    (sector_id as u64 + 1) * sector_len + offset % sector_len;
+                let len = cmp::min(remaining, mini_sector_len);
+                push_run(&mut runs, file_offset, len);
+                remaining -= len;
+            }
+        } else {
+            let sectors = chain_sector_ids(start_sector, |sector_id| {
+                self.next_sector(sector_id)
+            })?;
+            for sector_id in sectors {
+                if remaining == 0 {
+                    break;
+                }
+                let file_offset = (sector_id as u64 + 1) * sector_len;
+                let len = cmp::min(remaining, sector_len);
+                push_run(&mut runs, file_offset, len);
+                remaining -= len;
+            }
+        }
+        if remaining > 0 {
+            invalid_data!(
+                "Chain for stream {} is too short for its length of {} bytes",
+                stream_id,
+                stream_len
+            );
+        }
+        Ok(Extents { stream_id, is_mini, start_sector, runs })
+    }
+}
+
+pub fn chain_sector_ids<N>(
+    start_sector_id: u32,
+    next: N,
+) -> io::Result<Vec<u32>>
+where
+    N: Fn(u32) -> io::Result<u32>,
+{
+    let mut sector_ids = Vec::<u32>::new();
+    let mut current_sector_id = start_sector_id;
+    while current_sector_id != consts::END_OF_CHAIN {
+        sector_ids.push(current_sector_id);
+        current_sector_id = next(current_sector_id)?;
+        if current_sector_id == start_sector_id {
+            invalid_data!(
+                "Chain contained duplicate sector id {}",
+                current_sector_id
+            );
+        }
+    }
+    Ok(sector_ids)
+}
+
+fn push_run(runs: &mut Vec<(u64, u64)>, file_offset: u64, len: u64) {
+    if let Some(last) = runs.last_mut() {
+        if last.0 + last.1 == file_offset {
+            last.1 += len;
+            return;
+        }
+    }
+    runs.push((file_offset, len));
+}
+
+//===========================================================================//
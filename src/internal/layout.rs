@@ -0,0 +1,660 @@
+use crate::internal::{
+    self, consts, Color, DirEntry, Header, ObjType, Timestamp,
+    TimestampPolicy, Version,
+};
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::cmp::Ordering;
+use std::io::{self, Read, Write};
+use std::mem::size_of;
+use std::path::Path;
+
+//===========================================================================//
+
+/// An in-memory description of a compound file's directory tree, for writing
+/// out a whole compound file at once (rather than mutating one in place).
+pub struct Tree {
+    dir_entries: Vec<DirEntry>,
+    children: Vec<Vec<usize>>,
+}
+
+impl Tree {
+    /// Creates a new tree containing only the root storage.
+    pub fn new() -> Tree {
+        Tree {
+            dir_entries: vec![DirEntry::empty_root_entry()],
+            children: vec![Vec::new()],
+        }
+    }
+
+    pub fn dir_entry(&self, index: usize) -> &DirEntry {
+        &self.dir_entries[index]
+    }
+
+    pub fn dir_entry_mut(&mut self, index: usize) -> &mut DirEntry {
+        &mut self.dir_entries[index]
+    }
+
+    /// Returns the index of the entry at the end of the given name chain, or
+    /// `None` if there is no such entry.
+    pub fn index_for_name_chain(&self, names: &[&str]) -> Option<usize> {
+        let mut index = 0;
+        for name in names.iter() {
+            let name: Vec<u16> = name.encode_utf16().collect();
+            index = *self.children[index].iter().find(|&&child| {
+                internal::path::compare_names(
+                    &name,
+                    &self.dir_entries[child].name,
+                ) == Ordering::Equal
+            })?;
+        }
+        Some(index)
+    }
+
+    /// Returns the index of the existing entry at the given path, or an
+    /// error if there is no such entry.
+    pub fn index_for_path(&self, path: &Path) -> io::Result<usize> {
+        let names = internal::path::name_chain_from_path(path)?;
+        match self.index_for_name_chain(&names) {
+            Some(index) => Ok(index),
+            None => not_found!(
+                "No such object: {:?}",
+                internal::path::path_from_name_chain(&names)
+            ),
+        }
+    }
+
+    /// Adds a new, empty storage or stream entry at the given path, and
+    /// returns its index.  The parent storage must already exist.
+    pub fn insert(
+        &mut self,
+        path: &Path,
+        obj_type: ObjType,
+        timestamp: Timestamp,
+    ) -> io::Result<usize> {
+        debug_assert!(
+            obj_type == ObjType::Storage || obj_type == ObjType::Stream
+        );
+        let mut names = internal::path::name_chain_from_path(path)?;
+        if let Some(index) = self.index_for_name_chain(&names) {
+            let kind = if self.dir_entries[index].obj_type == ObjType::Stream {
+                "stream"
+            } else {
+                "storage"
+            };
+            already_exists!(
+                "Cannot create {} at {:?} because a {} already exists there",
+                if obj_type == ObjType::Stream { "stream" } else { "storage" },
+                internal::path::path_from_name_chain(&names),
+                kind
+            );
+        }
+        // If names is empty, that means we're trying to create the root.  But
+        // the root always already exists and will have been rejected above.
+        debug_assert!(!names.is_empty());
+        let name = names.pop().unwrap();
+        internal::path::validate_name(name)?;
+        let parent = match self.index_for_name_chain(&names) {
+            Some(parent) => parent,
+            None => not_found!("Parent storage doesn't exist"),
+        };
+        if self.dir_entries[parent].obj_type == ObjType::Stream {
+            invalid_input!(
+                "Not a storage: {:?}",
+                internal::path::path_from_name_chain(&names)
+            );
+        }
+        let index = self.dir_entries.len();
+        self.dir_entries.push(DirEntry::new(name, obj_type, timestamp));
+        self.children.push(Vec::new());
+        self.children[parent].push(index);
+        Ok(index)
+    }
+
+    /// Computes a contiguous sector layout for a compound file with this tree
+    /// and the current stream lengths.  Unless the timestamp policy is
+    /// `Now`, the timestamps recorded when each entry was inserted are
+    /// replaced according to the policy.
+    pub fn layout(
+        &self,
+        version: Version,
+        policy: TimestampPolicy,
+    ) -> io::Result<Layout> {
+        Layout::new(version, policy, self)
+    }
+}
+
+//===========================================================================//
+
+/// A complete sector layout for a compound file, computed up front so that
+/// the file can be written out in a single forward pass.
+///
+/// The sectors are arranged in the following order: FAT sectors, DIFAT
+/// sectors, the directory, the MiniFAT, the mini stream, and then each regular
+/// stream in turn, with every chain occupying a single contiguous run.
+pub struct Layout {
+    version: Version,
+    header: Header,
+    fat: Vec<u32>,
+    difat: Vec<u32>,
+    num_difat_sectors: u32,
+    dir_entries: Vec<DirEntry>,
+    minifat: Vec<u32>,
+    mini_streams: Vec<usize>,
+    regular_streams: Vec<usize>,
+}
+
+impl Layout {
+    fn new(
+        version: Version,
+        policy: TimestampPolicy,
+        tree: &Tree,
+    ) -> io::Result<Layout> {
+        let sector_len = version.sector_len() as u64;
+        let mut dir_entries = tree.dir_entries.clone();
+        if policy != TimestampPolicy::Now {
+            // There are no existing timestamps to preserve for a brand new
+            // file, so every policy other than Now uses a single fixed value.
+            let timestamp = policy.creation_timestamp();
+            for dir_entry in dir_entries.iter_mut().skip(1) {
+                dir_entry.creation_time = timestamp;
+                dir_entry.modified_time = timestamp;
+            }
+        }
+
+        // Arrange each storage's children into a balanced red-black tree.
+        for (index, children) in tree.children.iter().enumerate() {
+            if dir_entries[index].obj_type == ObjType::Stream {
+                continue;
+            }
+            let mut sorted = children.clone();
+            sorted.sort_by(|&a, &b| {
+                internal::path::compare_names(
+                    &dir_entries[a].name,
+                    &dir_entries[b].name,
+                )
+            });
+            dir_entries[index].child = build_balanced_tree(
+                &mut dir_entries,
+                &sorted,
+                0,
+                max_depth(sorted.len()),
+            );
+        }
+
+        // Assign each non-empty stream either to the mini stream or to a
+        // regular chain.
+        let mut minifat = Vec::<u32>::new();
+        let mut mini_streams = Vec::<usize>::new();
+        let mut regular_streams = Vec::<usize>::new();
+        let mut num_regular_stream_sectors: u64 = 0;
+        for (index, dir_entry) in dir_entries.iter_mut().enumerate() {
+            if dir_entry.obj_type != ObjType::Stream {
+                continue;
+            }
+            let stream_len = dir_entry.stream_len;
+            if stream_len > version.stream_len_mask() {
+                invalid_input!(
+                    "Stream length of {} bytes is too large for CFB version \
+                     {}",
+                    stream_len,
+                    version.number()
+                );
+            }
+            if stream_len == 0 {
+                dir_entry.start_sector = consts::END_OF_CHAIN;
+            } else if stream_len < consts::MINI_STREAM_CUTOFF as u64 {
+                let num_mini_sectors =
+                    stream_len.div_ceil(consts::MINI_SECTOR_LEN as u64);
+                dir_entry.start_sector = minifat.len() as u32;
+                append_chain(&mut minifat, num_mini_sectors as u32);
+                mini_streams.push(index);
+            } else {
+                num_regular_stream_sectors += stream_len.div_ceil(sector_len);
+                regular_streams.push(index);
+            }
+        }
+
+        // Count up how many sectors we need for everything other than the
+        // FAT and DIFAT.
+        let num_dir_sectors = (dir_entries.len() as u64)
+            .div_ceil(version.dir_entries_per_sector() as u64);
+        let num_minifat_sectors =
+            ((minifat.len() * size_of::<u32>()) as u64).div_ceil(sector_len);
+        let mini_stream_len = (minifat.len() * consts::MINI_SECTOR_LEN) as u64;
+        let num_mini_stream_sectors = mini_stream_len.div_ceil(sector_len);
+        let num_data_sectors = num_dir_sectors
+            + num_minifat_sectors
+            + num_mini_stream_sectors
+            + num_regular_stream_sectors;
+
+        // Find the smallest number of FAT sectors (and the DIFAT sectors
+        // needed to list them) that can cover every sector in the file,
+        // including the FAT and DIFAT sectors themselves.
+        let fat_entries_per_sector = sector_len / size_of::<u32>() as u64;
+        let difat_entries_per_sector = fat_entries_per_sector - 1;
+        let mut num_fat_sectors: u64 = 0;
+        let mut num_difat_sectors: u64 = 0;
+        while num_fat_sectors * fat_entries_per_sector
+            < num_data_sectors + num_fat_sectors + num_difat_sectors
+        {
+            num_fat_sectors += 1;
+            num_difat_sectors = num_fat_sectors
+                .saturating_sub(consts::NUM_DIFAT_ENTRIES_IN_HEADER as u64)
+                .div_ceil(difat_entries_per_sector);
+        }
+        let num_sectors =
+            num_data_sectors + num_fat_sectors + num_difat_sectors;
+        if num_sectors > (consts::MAX_REGULAR_SECTOR as u64) + 1 {
+            invalid_input!(
+                "Compound file would need {} sectors, but the maximum is {}",
+                num_sectors,
+                (consts::MAX_REGULAR_SECTOR as u64) + 1
+            );
+        }
+
+        // Build the FAT.
+        let mut fat = Vec::<u32>::with_capacity(
+            (num_fat_sectors * fat_entries_per_sector) as usize,
+        );
+        let difat: Vec<u32> = (0..num_fat_sectors as u32).collect();
+        fat.extend(difat.iter().map(|_| consts::FAT_SECTOR));
+        fat.extend((0..num_difat_sectors).map(|_| consts::DIFAT_SECTOR));
+        let first_dir_sector = fat.len() as u32;
+        append_chain(&mut fat, num_dir_sectors as u32);
+        let first_minifat_sector = if num_minifat_sectors > 0 {
+            let start = fat.len() as u32;
+            append_chain(&mut fat, num_minifat_sectors as u32);
+            start
+        } else {
+            consts::END_OF_CHAIN
+        };
+        let mini_stream_start_sector = if num_mini_stream_sectors > 0 {
+            let start = fat.len() as u32;
+            append_chain(&mut fat, num_mini_stream_sectors as u32);
+            start
+        } else {
+            consts::END_OF_CHAIN
+        };
+        for &index in regular_streams.iter() {
+            let dir_entry = &mut dir_entries[index];
+            dir_entry.start_sector = fat.len() as u32;
+            let num_stream_sectors = dir_entry.stream_len.div_ceil(sector_len);
+            append_chain(&mut fat, num_stream_sectors as u32);
+        }
+        debug_assert_eq!(fat.len() as u64, num_sectors);
+        fat.resize(
+            (num_fat_sectors * fat_entries_per_sector) as usize,
+            consts::FREE_SECTOR,
+        );
+
+        let root_entry = &mut dir_entries[consts::ROOT_STREAM_ID as usize];
+        root_entry.start_sector = mini_stream_start_sector;
+        root_entry.stream_len = mini_stream_len;
+
+        let mut initial_difat_entries =
+            [consts::FREE_SECTOR; consts::NUM_DIFAT_ENTRIES_IN_HEADER];
+        for (entry, &sector_id) in
+            initial_difat_entries.iter_mut().zip(difat.iter())
+        {
+            *entry = sector_id;
+        }
+        let header = Header {
+            version,
+            // According to section 2.2 of the MS-CFB spec, the number of
+            // directory sectors "MUST be zero" for version 3.
+            num_dir_sectors: match version {
+                Version::V3 => 0,
+                Version::V4 => num_dir_sectors as u32,
+            },
+            num_fat_sectors: num_fat_sectors as u32,
+            first_dir_sector,
+            first_minifat_sector,
+            num_minifat_sectors: num_minifat_sectors as u32,
+            first_difat_sector: if num_difat_sectors > 0 {
+                num_fat_sectors as u32
+            } else {
+                consts::END_OF_CHAIN
+            },
+            num_difat_sectors: num_difat_sectors as u32,
+            initial_difat_entries,
+        };
+
+        Ok(Layout {
+            version,
+            header,
+            fat,
+            difat,
+            num_difat_sectors: num_difat_sectors as u32,
+            dir_entries,
+            minifat,
+            mini_streams,
+            regular_streams,
+        })
+    }
+
+    /// Writes out the whole compound file.  The `write_stream` function will
+    /// be called once for each non-empty stream (identified by its index in
+    /// the original `Tree`), and must write exactly that stream's data to the
+    /// writer and return the number of bytes written.
+    pub fn write_to<W, S>(
+        &self,
+        writer: &mut W,
+        mut write_stream: S,
+    ) -> io::Result<()>
+    where
+        W: Write,
+        S: FnMut(usize, &mut W) -> io::Result<u64>,
+    {
+        let sector_len = self.version.sector_len();
+
+        // Write the header, padded with zeroes to the length of a sector.
+        self.header.write_to(writer)?;
+        write_padding(writer, (sector_len - consts::HEADER_LEN) as u64)?;
+
+        // Write the FAT and DIFAT sectors.
+        for &entry in self.fat.iter() {
+            writer.write_u32::<LittleEndian>(entry)?;
+        }
+        let difat_entries_per_sector = sector_len / size_of::<u32>() - 1;
+        let extra_difat =
+            if self.difat.len() > consts::NUM_DIFAT_ENTRIES_IN_HEADER {
+                &self.difat[consts::NUM_DIFAT_ENTRIES_IN_HEADER..]
+            } else {
+                &[]
+            };
+        for index in 0..self.num_difat_sectors {
+            let start = index as usize * difat_entries_per_sector;
+            for offset in 0..difat_entries_per_sector {
+                let entry = extra_difat
+                    .get(start + offset)
+                    .copied()
+                    .unwrap_or(consts::FREE_SECTOR);
+                writer.write_u32::<LittleEndian>(entry)?;
+            }
+            let next = if index + 1 < self.num_difat_sectors {
+                self.header.first_difat_sector + index + 1
+            } else {
+                consts::END_OF_CHAIN
+            };
+            writer.write_u32::<LittleEndian>(next)?;
+        }
+
+        // Write the directory sectors.
+        for dir_entry in self.dir_entries.iter() {
+            dir_entry.write_to(writer)?;
+        }
+        let dir_entries_per_sector = self.version.dir_entries_per_sector();
+        let remainder = self.dir_entries.len() % dir_entries_per_sector;
+        if remainder != 0 {
+            let unallocated = DirEntry::unallocated();
+            for _ in remainder..dir_entries_per_sector {
+                unallocated.write_to(writer)?;
+            }
+        }
+
+        // Write the MiniFAT sectors.
+        let minifat_entries_per_sector = sector_len / size_of::<u32>();
+        for &entry in self.minifat.iter() {
+            writer.write_u32::<LittleEndian>(entry)?;
+        }
+        let remainder = self.minifat.len() % minifat_entries_per_sector;
+        if remainder != 0 {
+            for _ in remainder..minifat_entries_per_sector {
+                writer.write_u32::<LittleEndian>(consts::FREE_SECTOR)?;
+            }
+        }
+
+        // Write the mini stream, followed by each regular stream.
+        let mini_sector_len = consts::MINI_SECTOR_LEN as u64;
+        for &index in self.mini_streams.iter() {
+            let stream_len =
+                self.write_stream_data(writer, index, &mut write_stream)?;
+            write_padding(writer, padding_len(stream_len, mini_sector_len))?;
+        }
+        let mini_stream_len =
+            self.dir_entries[consts::ROOT_STREAM_ID as usize].stream_len;
+        write_padding(
+            writer,
+            padding_len(mini_stream_len, sector_len as u64),
+        )?;
+        for &index in self.regular_streams.iter() {
+            let stream_len =
+                self.write_stream_data(writer, index, &mut write_stream)?;
+            write_padding(writer, padding_len(stream_len, sector_len as u64))?;
+        }
+        Ok(())
+    }
+
+    fn write_stream_data<W, S>(
+        &self,
+        writer: &mut W,
+        index: usize,
+        write_stream: &mut S,
+    ) -> io::Result<u64>
+    where
+        W: Write,
+        S: FnMut(usize, &mut W) -> io::Result<u64>,
+    {
+        let expected_len = self.dir_entries[index].stream_len;
+        let actual_len = write_stream(index, writer)?;
+        if actual_len != expected_len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!(
+                    "Stream {:?} should have had {} bytes of data, but had {}",
+                    self.dir_entries[index].name_string(),
+                    expected_len,
+                    actual_len
+                ),
+            ));
+        }
+        Ok(expected_len)
+    }
+}
+
+//===========================================================================//
+
+/// Appends a new contiguous chain of the given length to the end of a FAT or
+/// MiniFAT.
+fn append_chain(fat: &mut Vec<u32>, num_sectors: u32) {
+    let start = fat.len() as u32;
+    for sector_id in start..(start + num_sectors) {
+        let next = if sector_id + 1 < start + num_sectors {
+            sector_id + 1
+        } else {
+            consts::END_OF_CHAIN
+        };
+        fat.push(next);
+    }
+}
+
+/// Returns the depth of the deepest node in a balanced binary tree with the
+/// given number of nodes (where the root has depth zero).
+fn max_depth(num_nodes: usize) -> u32 {
+    if num_nodes == 0 {
+        0
+    } else {
+        usize::BITS - 1 - num_nodes.leading_zeros()
+    }
+}
+
+/// Links the given (sorted) directory entries into a balanced binary tree,
+/// and returns the stream ID of the tree's root.  Since splitting at the
+/// median puts every leaf at either the deepest level or the one above it,
+/// coloring just the deepest level red (unless it's the root) satisfies the
+/// red-black invariants required by section 2.6.4 of the MS-CFB spec.
+fn build_balanced_tree(
+    dir_entries: &mut [DirEntry],
+    sorted: &[usize],
+    depth: u32,
+    red_depth: u32,
+) -> u32 {
+    if sorted.is_empty() {
+        return consts::NO_STREAM;
+    }
+    let middle = sorted.len() / 2;
+    let left = build_balanced_tree(
+        dir_entries,
+        &sorted[..middle],
+        depth + 1,
+        red_depth,
+    );
+    let right = build_balanced_tree(
+        dir_entries,
+        &sorted[(middle + 1)..],
+        depth + 1,
+        red_depth,
+    );
+    let dir_entry = &mut dir_entries[sorted[middle]];
+    dir_entry.left_sibling = left;
+    dir_entry.right_sibling = right;
+    dir_entry.color = if depth == red_depth && depth > 0 {
+        Color::Red
+    } else {
+        Color::Black
+    };
+    sorted[middle] as u32
+}
+
+fn padding_len(len: u64, block_len: u64) -> u64 {
+    (block_len - len % block_len) % block_len
+}
+
+fn write_padding<W: Write>(writer: &mut W, len: u64) -> io::Result<()> {
+    io::copy(&mut io::repeat(0).take(len), writer)?;
+    Ok(())
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{max_depth, Tree};
+    use crate::internal::Version;
+    use crate::internal::{
+        consts, Color, DirEntry, ObjType, Timestamp, TimestampPolicy,
+    };
+    use std::path::Path;
+
+    /// Checks that the sibling tree rooted at `stream_id` is a valid red-black
+    /// tree, and returns its black height.
+    fn black_height(dir_entries: &[DirEntry], stream_id: u32) -> usize {
+        if stream_id == consts::NO_STREAM {
+            return 1;
+        }
+        let dir_entry = &dir_entries[stream_id as usize];
+        for &sibling in &[dir_entry.left_sibling, dir_entry.right_sibling] {
+            if dir_entry.color == Color::Red && sibling != consts::NO_STREAM {
+                assert_eq!(dir_entries[sibling as usize].color, Color::Black);
+            }
+        }
+        let left = black_height(dir_entries, dir_entry.left_sibling);
+        let right = black_height(dir_entries, dir_entry.right_sibling);
+        assert_eq!(left, right);
+        left + (dir_entry.color == Color::Black) as usize
+    }
+
+    #[test]
+    fn depth_of_balanced_tree() {
+        assert_eq!(max_depth(0), 0);
+        assert_eq!(max_depth(1), 0);
+        assert_eq!(max_depth(2), 1);
+        assert_eq!(max_depth(3), 1);
+        assert_eq!(max_depth(4), 2);
+        assert_eq!(max_depth(7), 2);
+        assert_eq!(max_depth(8), 3);
+    }
+
+    #[test]
+    fn sibling_trees_are_red_black() {
+        for num_children in 0..40 {
+            let mut tree = Tree::new();
+            for index in 0..num_children {
+                let path = format!("/{}", index);
+                tree.insert(
+                    Path::new(&path),
+                    ObjType::Stream,
+                    Timestamp::zero(),
+                )
+                .unwrap();
+            }
+            let layout =
+                tree.layout(Version::V3, TimestampPolicy::Now).unwrap();
+            let dir_entries = &layout.dir_entries;
+            let child = dir_entries[0].child;
+            if child != consts::NO_STREAM {
+                assert_eq!(dir_entries[child as usize].color, Color::Black);
+            }
+            black_height(dir_entries, child);
+        }
+    }
+
+    #[test]
+    fn sectors_are_contiguous() {
+        let mut tree = Tree::new();
+        let mut add_stream = |path: &str, len: u64| {
+            let index = tree
+                .insert(Path::new(path), ObjType::Stream, Timestamp::zero())
+                .unwrap();
+            tree.dir_entry_mut(index).stream_len = len;
+        };
+        add_stream("/big1", 5000);
+        add_stream("/small1", 100);
+        add_stream("/big2", 20000);
+        add_stream("/small2", 64);
+        add_stream("/empty", 0);
+        let layout = tree.layout(Version::V3, TimestampPolicy::Now).unwrap();
+        let header = &layout.header;
+        let fat = &layout.fat;
+        assert_eq!(header.num_fat_sectors, 1);
+        assert_eq!(header.first_dir_sector, 1);
+        assert_eq!(fat[1], 2);
+        assert_eq!(fat[2], consts::END_OF_CHAIN);
+        assert_eq!(header.first_minifat_sector, 3);
+        let root = &layout.dir_entries[0];
+        assert_eq!(root.start_sector, 4);
+        assert_eq!(root.stream_len, 3 * 64);
+        let entries = &layout.dir_entries;
+        // Mini streams are packed consecutively into the mini stream.
+        assert_eq!(entries[2].start_sector, 0);
+        assert_eq!(entries[4].start_sector, 2);
+        assert_eq!(
+            &layout.minifat[..],
+            &[1, consts::END_OF_CHAIN, consts::END_OF_CHAIN]
+        );
+        // Regular streams follow the mini stream, in one run each.
+        assert_eq!(entries[1].start_sector, 5);
+        assert_eq!(entries[3].start_sector, 15);
+        assert_eq!(entries[5].start_sector, consts::END_OF_CHAIN);
+        assert_eq!(fat[0], consts::FAT_SECTOR);
+        for sector in (5..14).chain(15..54) {
+            assert_eq!(fat[sector], sector as u32 + 1);
+        }
+        assert_eq!(fat[14], consts::END_OF_CHAIN);
+        assert_eq!(fat[54], consts::END_OF_CHAIN);
+        assert_eq!(fat[55], consts::FREE_SECTOR);
+    }
+
+    #[test]
+    #[should_panic(expected = "Parent storage doesn't exist")]
+    fn insert_without_parent() {
+        let mut tree = Tree::new();
+        tree.insert(Path::new("/foo/bar"), ObjType::Stream, Timestamp::zero())
+            .unwrap();
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Cannot create stream at \\\"/FOO\\\" because a storage \
+                    already exists there"
+    )]
+    fn insert_duplicate() {
+        let mut tree = Tree::new();
+        tree.insert(Path::new("/foo"), ObjType::Storage, Timestamp::zero())
+            .unwrap();
+        tree.insert(Path::new("/FOO"), ObjType::Stream, Timestamp::zero())
+            .unwrap();
+    }
+}
+
+//===========================================================================//
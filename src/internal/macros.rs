@@ -43,4 +43,15 @@ macro_rules! not_found {
     };
 }
 
+macro_rules! permission_denied {
+    ($e:expr) => {
+        return Err(::std::io::Error::new(
+            ::std::io::ErrorKind::PermissionDenied, $e))
+    };
+    ($fmt:expr, $($arg:tt)+) => {
+        return Err(::std::io::Error::new(
+            ::std::io::ErrorKind::PermissionDenied, format!($fmt, $($arg)+)))
+    };
+}
+
 // ========================================================================= //
@@ -1,6 +1,6 @@
 use crate::internal::{
-    consts, Chain, DirEntry, Directory, MiniChain, ObjType, Sector,
-    SectorInit, Version,
+    self, consts, Chain, DirEntry, Directory, EntryId, MiniChain, ObjType,
+    Sector, SectorInit, TimestampPolicy, Version,
 };
 use byteorder::{LittleEndian, WriteBytesExt};
 use fnv::FnvHashSet;
@@ -24,6 +24,8 @@ pub struct MiniAllocator<F> {
     directory: Directory<F>,
     minifat: Vec<u32>,
     minifat_start_sector: u32,
+    read_only: bool,
+    poisoned: bool,
 }
 
 impl<F> MiniAllocator<F> {
@@ -32,8 +34,13 @@ impl<F> MiniAllocator<F> {
         minifat: Vec<u32>,
         minifat_start_sector: u32,
     ) -> io::Result<MiniAllocator<F>> {
-        let minialloc =
-            MiniAllocator { directory, minifat, minifat_start_sector };
+        let minialloc = MiniAllocator {
+            directory,
+            minifat,
+            minifat_start_sector,
+            read_only: false,
+            poisoned: false,
+        };
         minialloc.validate()?;
         Ok(minialloc)
     }
@@ -42,6 +49,78 @@ impl<F> MiniAllocator<F> {
         self.directory.version()
     }
 
+    pub fn timestamp_policy(&self) -> TimestampPolicy {
+        self.directory.timestamp_policy()
+    }
+
+    pub fn set_timestamp_policy(&mut self, policy: TimestampPolicy) {
+        self.directory.set_timestamp_policy(policy);
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn set_read_only(&mut self) {
+        self.read_only = true;
+    }
+
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Returns an error if the compound file is read-only or poisoned.  This
+    /// must be called before making any changes (in memory or on disk), so
+    /// that the in-memory structures never get out of sync with the
+    /// underlying file.
+    pub fn check_writable(&self) -> io::Result<()> {
+        if self.read_only {
+            permission_denied!("Compound file was opened read-only");
+        }
+        self.check_not_poisoned()
+    }
+
+    fn check_not_poisoned(&self) -> io::Result<()> {
+        if self.poisoned {
+            return Err(io::Error::other(
+                "Compound file is poisoned (an earlier write failed partway \
+                 through, so it may be inconsistent)",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Passes through the result of an operation that may have modified the
+    /// compound file.  If the operation failed, it may have done so partway
+    /// through, leaving the in-memory structures out of sync with the
+    /// underlying file, so the compound file is marked as poisoned and all
+    /// further modifications will be rejected.
+    pub fn poison_on_error<T>(
+        &mut self,
+        result: io::Result<T>,
+    ) -> io::Result<T> {
+        if result.is_err() {
+            self.poisoned = true;
+        }
+        result
+    }
+
+    pub fn directory(&self) -> &Directory<F> {
+        &self.directory
+    }
+
+    pub fn minifat(&self) -> &[u32] {
+        &self.minifat
+    }
+
+    pub fn minifat_start_sector(&self) -> u32 {
+        self.minifat_start_sector
+    }
+
+    pub fn next_sector(&self, sector_id: u32) -> io::Result<u32> {
+        self.directory.next_sector(sector_id)
+    }
+
     pub fn next_mini_sector(&self, sector_id: u32) -> io::Result<u32> {
         let index = sector_id as usize;
         if index >= self.minifat.len() {
@@ -70,18 +149,49 @@ impl<F> MiniAllocator<F> {
         self.directory.stream_id_for_name_chain(names)
     }
 
+    pub fn serial(&self, stream_id: u32) -> u64 {
+        self.directory.serial(stream_id)
+    }
+
+    pub fn entry_id(&self, stream_id: u32) -> EntryId {
+        EntryId::new(stream_id, self.serial(stream_id))
+    }
+
+    pub fn child_id(&self, parent_id: u32, name: &[u16]) -> Option<u32> {
+        self.directory.child_id(parent_id, name)
+    }
+
+    pub fn child_ids(&self, parent_id: u32) -> Vec<u32> {
+        self.directory.child_ids(parent_id)
+    }
+
+    pub fn parent_id(&self, stream_id: u32) -> u32 {
+        self.directory.parent_id(stream_id)
+    }
+
+    pub fn name_chain_for_stream_id(
+        &self,
+        stream_id: u32,
+    ) -> Option<Vec<String>> {
+        self.directory.name_chain_for_stream_id(stream_id)
+    }
+
+    pub fn name_chain_is_exact(&self, stream_id: u32) -> bool {
+        self.directory.name_chain_is_exact(stream_id)
+    }
+
     pub fn open_chain(
         &mut self,
         start_sector_id: u32,
         init: SectorInit,
-    ) -> io::Result<Chain<F>> {
+    ) -> io::Result<Chain<'_, F>> {
         self.directory.open_chain(start_sector_id, init)
     }
 
     pub fn open_mini_chain(
         &mut self,
         start_sector_id: u32,
-    ) -> io::Result<MiniChain<F>> {
+    ) -> io::Result<MiniChain<'_, F>> {
         MiniChain::new(self, start_sector_id)
     }
 
@@ -137,7 +247,7 @@ impl<F: Seek> MiniAllocator<F> {
         &mut self,
         mini_sector: u32,
         offset_within_mini_sector: u64,
-    ) -> io::Result<Sector<F>> {
+    ) -> io::Result<Sector<'_, F>> {
         debug_assert!(
             offset_within_mini_sector < consts::MINI_SECTOR_LEN as u64
         );
@@ -157,7 +267,8 @@ impl<F: Seek> MiniAllocator<F> {
 impl<F: Write + Seek> MiniAllocator<F> {
     /// Given the start sector of a chain, deallocates the entire chain.
     pub fn free_chain(&mut self, start_sector_id: u32) -> io::Result<()> {
-        self.directory.free_chain(start_sector_id)
+        let result = self.directory.free_chain(start_sector_id);
+        self.poison_on_error(result)
     }
 
     /// Inserts a new directory entry into the tree under the specified parent
@@ -165,19 +276,23 @@ impl<F: Write + Seek> MiniAllocator<F> {
     pub fn insert_dir_entry(
         &mut self,
         parent_id: u32,
-        name: &str,
+        name: &[u16],
         obj_type: ObjType,
     ) -> io::Result<u32> {
-        self.directory.insert_dir_entry(parent_id, name, obj_type)
+        internal::path::validate_name_utf16(name)?;
+        let result =
+            self.directory.insert_dir_entry(parent_id, name, obj_type);
+        self.poison_on_error(result)
     }
 
     /// Removes a directory entry from the tree and deallocates it.
     pub fn remove_dir_entry(
         &mut self,
         parent_id: u32,
-        name: &str,
+        name: &[u16],
     ) -> io::Result<()> {
-        self.directory.remove_dir_entry(parent_id, name)
+        let result = self.directory.remove_dir_entry(parent_id, name);
+        self.poison_on_error(result)
     }
 
     /// Calls the given function with a mutable reference to the specified
@@ -191,7 +306,8 @@ impl<F: Write + Seek> MiniAllocator<F> {
     where
         W: FnOnce(&mut DirEntry),
     {
-        self.directory.with_dir_entry_mut(stream_id, func)
+        let result = self.directory.with_dir_entry_mut(stream_id, func);
+        self.poison_on_error(result)
     }
 
     /// Allocates a new mini chain with one sector, and returns the starting
@@ -315,6 +431,14 @@ impl<F: Write + Seek> MiniAllocator<F> {
     pub fn free_mini_chain(
         &mut self,
         start_mini_sector: u32,
+    ) -> io::Result<()> {
+        let result = self.free_mini_chain_internal(start_mini_sector);
+        self.poison_on_error(result)
+    }
+
+    fn free_mini_chain_internal(
+        &mut self,
+        start_mini_sector: u32,
     ) -> io::Result<()> {
         let mut mini_sector = start_mini_sector;
         while mini_sector != consts::END_OF_CHAIN {
@@ -358,7 +482,13 @@ impl<F: Write + Seek> MiniAllocator<F> {
 
     /// Flushes all changes to the underlying file.
     pub fn flush(&mut self) -> io::Result<()> {
-        self.directory.flush()
+        if self.read_only {
+            // Nothing can have changed, so there's nothing to flush.
+            return Ok(());
+        }
+        self.check_not_poisoned()?;
+        let result = self.directory.flush();
+        self.poison_on_error(result)
     }
 }
 
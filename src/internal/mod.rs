@@ -2,35 +2,51 @@
 mod macros;
 
 mod alloc;
+mod builder;
+mod cfbpath;
 mod chain;
 mod color;
 pub mod consts;
+mod cursor;
 mod directory;
 mod direntry;
 mod entry;
+mod extents;
 mod header;
+mod layout;
 mod minialloc;
 mod minichain;
 mod objtype;
 pub mod path;
 mod sector;
+mod stats;
 mod stream;
+mod streaming;
 mod timestamp;
+mod upcase;
 mod validate;
 mod version;
+mod walk;
 
 pub use self::alloc::Allocator;
+pub use self::builder::CompoundFileBuilder;
+pub use self::cfbpath::{CfbPath, CfbPathBuf, PathNames};
 pub use self::chain::Chain;
 pub use self::color::Color;
+pub use self::cursor::EntryCursor;
 pub use self::directory::Directory;
 pub use self::direntry::DirEntry;
-pub use self::entry::{Entries, EntriesOrder, Entry};
+pub use self::entry::{Entries, EntriesOrder, Entry, EntryId};
+pub use self::extents::{chain_sector_ids, Extents};
 pub use self::header::Header;
 pub use self::minialloc::MiniAllocator;
 pub use self::minichain::MiniChain;
 pub use self::objtype::ObjType;
 pub use self::sector::{Sector, SectorInit, Sectors};
+pub use self::stats::{Stats, StorageStats, StreamStats};
 pub use self::stream::Stream;
-pub use self::timestamp::Timestamp;
+pub use self::streaming::StreamingWriter;
+pub use self::timestamp::{Timestamp, TimestampPolicy};
 pub use self::validate::Validation;
 pub use self::version::Version;
+pub use self::walk::{Walk, WalkOptions, WalkOrder};
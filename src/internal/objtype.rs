@@ -5,13 +5,18 @@ use crate::internal::consts;
 /// The type of a directory entry.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum ObjType {
+    /// An unused directory entry.
     Unallocated,
+    /// A storage object (other than the root).
     Storage,
+    /// A stream object.
     Stream,
+    /// The root storage object.
     Root,
 }
 
 impl ObjType {
+    /// Returns the byte value used to represent this in a directory entry.
     pub fn as_byte(&self) -> u8 {
         match self {
             ObjType::Unallocated => consts::OBJ_TYPE_UNALLOCATED,
@@ -21,6 +26,8 @@ impl ObjType {
         }
     }
 
+    /// Parses the byte value used to represent this in a directory entry,
+    /// returning `None` if the byte is not a valid value.
     pub fn from_byte(byte: u8) -> Option<ObjType> {
         if byte == consts::OBJ_TYPE_UNALLOCATED {
             Some(ObjType::Unallocated)
@@ -1,6 +1,7 @@
+use crate::internal;
 use std::cmp::Ordering;
 use std::io;
-use std::path::{Component, Path, PathBuf};
+use std::path::{Path, PathBuf};
 
 // ========================================================================= //
 
@@ -12,71 +13,101 @@ const MAX_NAME_LEN: usize = 31;
 /// case-insensitive, and which always puts shorter names before longer names,
 /// as encoded in UTF-16 (i.e. [shortlex
 /// order](https://en.wikipedia.org/wiki/Shortlex_order), rather than
-/// dictionary order).
-pub fn compare_names(name1: &str, name2: &str) -> Ordering {
-    match name1.encode_utf16().count().cmp(&name2.encode_utf16().count()) {
-        // This is actually not 100% correct -- the MS-CFB spec specifies a
-        // particular way of doing the uppercasing on individual UTF-16 code
-        // units, along with a list of weird exceptions and corner cases.  But
-        // hopefully this is good enough for 99+% of the time.
-        Ordering::Equal => name1.to_uppercase().cmp(&name2.to_uppercase()),
-        other => other,
-    }
+/// dictionary order).  Uppercasing is done on individual UTF-16 code units,
+/// exactly as specified by MS-CFB, so names need not be valid UTF-16.
+pub fn compare_names(name1: &[u16], name2: &[u16]) -> Ordering {
+    internal::upcase::compare_utf16(
+        name1.iter().copied(),
+        name2.iter().copied(),
+    )
 }
 
 /// Converts a storage/stream name to UTF-16, or returns an error if the name
 /// is invalid.
 pub fn validate_name(name: &str) -> io::Result<Vec<u16>> {
-    let name_utf16: Vec<u16> =
-        name.encode_utf16().take(MAX_NAME_LEN + 1).collect();
-    if name_utf16.len() > MAX_NAME_LEN {
+    let name_utf16: Vec<u16> = name.encode_utf16().collect();
+    validate_name_utf16(&name_utf16)?;
+    Ok(name_utf16)
+}
+
+/// Returns an error if the given storage/stream name, as raw UTF-16 code
+/// units, is invalid.  Unpaired surrogates are permitted, since they do
+/// occur in real files.
+pub fn validate_name_utf16(name: &[u16]) -> io::Result<()> {
+    if name.len() > MAX_NAME_LEN {
         invalid_input!(
             "Object name cannot be more than {} UTF-16 code units (was {})",
             MAX_NAME_LEN,
-            name.encode_utf16().count()
+            name.len()
         );
     }
     for &chr in &['/', '\\', ':', '!'] {
-        if name.contains(chr) {
+        if name.contains(&(chr as u16)) {
             invalid_input!("Object name cannot contain {} character", chr);
         }
     }
-    Ok(name_utf16)
+    Ok(())
 }
 
 // ========================================================================= //
 
 /// Given a path within a compound file, turns it into a list of child names
 /// descending from the root.  Returns an error if the name is invalid.
+///
+/// Paths are parsed the same way on every platform: `/` is the only
+/// separator (so on Windows, `\\` is treated as part of a name rather than
+/// as a separator), and there is no notion of a path prefix.
+///
+/// As with `std::path::Path` (which this API has always accepted), empty and
+/// `.` components are ignored and `..` refers to the parent storage, so
+/// existing callers that pass paths like `"/foo/../bar"` keep working.  This
+/// means that objects actually named `.` or `..` can't be reached by path;
+/// they can be reached through the `*_utf16` and `*_by_id` methods instead.
+/// (`CfbPath`, which is newer, rejects these components outright.)
 pub fn name_chain_from_path(path: &Path) -> io::Result<Vec<&str>> {
+    let path = match path.to_str() {
+        Some(path) => path,
+        None => invalid_input!("Non UTF-8 path"),
+    };
     let mut names: Vec<&str> = Vec::new();
-    for component in path.components() {
+    for component in path.split('/') {
         match component {
-            Component::Prefix(_) => {
-                invalid_input!("Invalid path (must not have prefix)");
-            }
-            Component::RootDir => names.clear(),
-            Component::CurDir => {}
-            Component::ParentDir => {
+            "" | "." => {}
+            ".." => {
                 if names.pop().is_none() {
                     invalid_input!("Invalid path (must be within root)");
                 }
             }
-            Component::Normal(osstr) => match osstr.to_str() {
-                Some(name) => names.push(name),
-                None => invalid_input!("Non UTF-8 path"),
-            },
+            name => names.push(name),
         }
     }
     Ok(names)
 }
 
-pub fn path_from_name_chain(names: &[&str]) -> PathBuf {
-    let mut path = PathBuf::from("/");
+/// Given a list of child names descending from the root, returns the
+/// absolute path to that object.  The path always uses `/` as its separator,
+/// regardless of platform.
+pub fn path_from_name_chain<S: AsRef<str>>(names: &[S]) -> PathBuf {
+    let mut path = String::new();
     for name in names {
-        path.push(name);
+        path.push('/');
+        path.push_str(name.as_ref());
+    }
+    if path.is_empty() {
+        path.push('/');
     }
-    path
+    PathBuf::from(path)
+}
+
+/// Appends a name to an absolute path returned by `path_from_name_chain`,
+/// again using `/` as the separator regardless of platform.
+pub fn join_name(parent: &Path, name: &str) -> PathBuf {
+    let mut path = parent.to_string_lossy().into_owned();
+    if !path.ends_with('/') {
+        path.push('/');
+    }
+    path.push_str(name);
+    PathBuf::from(path)
 }
 
 // ========================================================================= //
@@ -84,17 +115,42 @@ pub fn path_from_name_chain(names: &[&str]) -> PathBuf {
 #[cfg(test)]
 mod tests {
     use super::{
-        compare_names, name_chain_from_path, path_from_name_chain,
-        validate_name,
+        compare_names, join_name, name_chain_from_path, path_from_name_chain,
+        validate_name, validate_name_utf16,
     };
     use std::cmp::Ordering;
     use std::path::{Path, PathBuf};
 
+    fn compare(name1: &str, name2: &str) -> Ordering {
+        let name1: Vec<u16> = name1.encode_utf16().collect();
+        let name2: Vec<u16> = name2.encode_utf16().collect();
+        compare_names(&name1, &name2)
+    }
+
     #[test]
     fn name_ordering() {
-        assert_eq!(compare_names("foobar", "FOOBAR"), Ordering::Equal);
-        assert_eq!(compare_names("foo", "barfoo"), Ordering::Less);
-        assert_eq!(compare_names("Foo", "bar"), Ordering::Greater);
+        assert_eq!(compare("foobar", "FOOBAR"), Ordering::Equal);
+        assert_eq!(compare("foo", "barfoo"), Ordering::Less);
+        assert_eq!(compare("Foo", "bar"), Ordering::Greater);
+    }
+
+    #[test]
+    fn name_ordering_with_unpaired_surrogates() {
+        assert_eq!(compare_names(&[0xd800], &[0xd800]), Ordering::Equal);
+        assert_eq!(
+            compare_names(&[0x61, 0xd800], &[0x41, 0xd800]),
+            Ordering::Equal
+        );
+        assert_eq!(compare_names(&[0xd800], &[0xdc00]), Ordering::Less);
+        assert_eq!(compare_names(&[0xdc00], &[0x41, 0x42]), Ordering::Less);
+    }
+
+    #[test]
+    fn name_with_unpaired_surrogate_is_valid() {
+        assert!(validate_name_utf16(&[0x41, 0xd800, 0x42]).is_ok());
+        assert!(validate_name_utf16(&[0xdc00; 31]).is_ok());
+        assert!(validate_name_utf16(&[0xdc00; 32]).is_err());
+        assert!(validate_name_utf16(&[0xd800, 0x2f]).is_err());
     }
 
     #[test]
@@ -123,7 +179,7 @@ mod tests {
     #[test]
     fn absolute_path_is_valid() {
         assert_eq!(
-            name_chain_from_path(&Path::new("/foo/bar/baz/")).unwrap(),
+            name_chain_from_path(Path::new("/foo/bar/baz/")).unwrap(),
             vec!["foo", "bar", "baz"]
         );
     }
@@ -131,7 +187,7 @@ mod tests {
     #[test]
     fn relative_path_is_valid() {
         assert_eq!(
-            name_chain_from_path(&Path::new("foo/bar/baz")).unwrap(),
+            name_chain_from_path(Path::new("foo/bar/baz")).unwrap(),
             vec!["foo", "bar", "baz"]
         );
     }
@@ -139,7 +195,7 @@ mod tests {
     #[test]
     fn path_with_parents_is_valid() {
         assert_eq!(
-            name_chain_from_path(&Path::new("foo/bar/../baz")).unwrap(),
+            name_chain_from_path(Path::new("foo/bar/../baz")).unwrap(),
             vec!["foo", "baz"]
         );
     }
@@ -147,13 +203,30 @@ mod tests {
     #[test]
     #[should_panic(expected = "Invalid path (must be within root)")]
     fn parent_of_root_is_invalid() {
-        name_chain_from_path(&Path::new("foo/../../baz")).unwrap();
+        name_chain_from_path(Path::new("foo/../../baz")).unwrap();
+    }
+
+    #[test]
+    fn backslash_is_not_a_separator() {
+        assert_eq!(
+            name_chain_from_path(Path::new("/foo\\bar/baz")).unwrap(),
+            vec!["foo\\bar", "baz"]
+        );
+    }
+
+    #[test]
+    fn joined_paths_use_forward_slashes() {
+        let path = path_from_name_chain(&["foo", "bar"]);
+        assert_eq!(path.to_str(), Some("/foo/bar"));
+        assert_eq!(join_name(&path, "baz").to_str(), Some("/foo/bar/baz"));
+        let root = path_from_name_chain::<&str>(&[]);
+        assert_eq!(join_name(&root, "baz").to_str(), Some("/baz"));
     }
 
     #[test]
     fn canonical_path_is_absolute() {
         let path = Path::new("foo/bar/../baz");
-        let names = name_chain_from_path(&path).unwrap();
+        let names = name_chain_from_path(path).unwrap();
         assert_eq!(path_from_name_chain(&names), PathBuf::from("/foo/baz"));
     }
 }
@@ -0,0 +1,299 @@
+use crate::internal::{
+    self, chain_sector_ids, consts, MiniAllocator, ObjType,
+};
+use std::io;
+use std::path::{Path, PathBuf};
+
+//===========================================================================//
+
+/// Space-usage statistics for a compound file.
+#[derive(Clone, Debug)]
+pub struct Stats {
+    num_sectors: u32,
+    num_free_sectors: u32,
+    num_fat_sectors: u32,
+    num_difat_sectors: u32,
+    num_minifat_sectors: u32,
+    num_dir_sectors: u32,
+    mini_stream_len: u64,
+    num_free_mini_sectors: u32,
+    num_dir_entries: u32,
+    num_unallocated_dir_entries: u32,
+    streams: Vec<StreamStats>,
+    storages: Vec<StorageStats>,
+}
+
+impl Stats {
+    /// Returns the total number of regular sectors in the file (not counting
+    /// the header).
+    pub fn num_sectors(&self) -> u32 {
+        self.num_sectors
+    }
+
+    /// Returns the number of regular sectors that are in use (by anything,
+    /// including the FAT, DIFAT, MiniFAT, directory, and mini stream).
+    pub fn num_used_sectors(&self) -> u32 {
+        self.num_sectors - self.num_free_sectors
+    }
+
+    /// Returns the number of regular sectors that are marked as free.
+    pub fn num_free_sectors(&self) -> u32 {
+        self.num_free_sectors
+    }
+
+    /// Returns the number of sectors used for the FAT.
+    pub fn num_fat_sectors(&self) -> u32 {
+        self.num_fat_sectors
+    }
+
+    /// Returns the number of sectors used for the DIFAT (not counting the
+    /// part of the DIFAT stored in the header).
+    pub fn num_difat_sectors(&self) -> u32 {
+        self.num_difat_sectors
+    }
+
+    /// Returns the number of sectors used for the MiniFAT.
+    pub fn num_minifat_sectors(&self) -> u32 {
+        self.num_minifat_sectors
+    }
+
+    /// Returns the number of sectors used for the directory.
+    pub fn num_dir_sectors(&self) -> u32 {
+        self.num_dir_sectors
+    }
+
+    /// Returns the length of the mini stream, in bytes.
+    pub fn mini_stream_len(&self) -> u64 {
+        self.mini_stream_len
+    }
+
+    /// Returns the number of mini sectors within the mini stream that are
+    /// not allocated to any stream.
+    pub fn num_free_mini_sectors(&self) -> u32 {
+        self.num_free_mini_sectors
+    }
+
+    /// Returns the total number of directory entries that fit in the
+    /// directory sectors.
+    pub fn num_dir_entries(&self) -> u32 {
+        self.num_dir_entries
+    }
+
+    /// Returns the number of directory entries that are unallocated.
+    pub fn num_unallocated_dir_entries(&self) -> u32 {
+        self.num_unallocated_dir_entries
+    }
+
+    /// Returns statistics for every stream in the file, in preorder.
+    pub fn streams(&self) -> &[StreamStats] {
+        &self.streams
+    }
+
+    /// Returns statistics for every storage in the file (including the
+    /// root), in preorder.
+    pub fn storages(&self) -> &[StorageStats] {
+        &self.storages
+    }
+}
+
+//===========================================================================//
+
+/// Space-usage statistics for a single stream.
+#[derive(Clone, Debug)]
+pub struct StreamStats {
+    path: PathBuf,
+    len: u64,
+    allocated_len: u64,
+    is_mini: bool,
+    num_fragments: usize,
+}
+
+impl StreamStats {
+    /// Returns the path of the stream.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the length of the stream, in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the stream is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes of sectors (or mini sectors) allocated to
+    /// the stream.
+    pub fn allocated_len(&self) -> u64 {
+        self.allocated_len
+    }
+
+    /// Returns true if the stream is stored in the mini stream.
+    pub fn is_mini(&self) -> bool {
+        self.is_mini
+    }
+
+    /// Returns the number of physically contiguous runs that the stream's
+    /// data is split into (zero for an empty stream, one for a stream that
+    /// is not fragmented at all).
+    pub fn num_fragments(&self) -> usize {
+        self.num_fragments
+    }
+}
+
+//===========================================================================//
+
+/// Space-usage statistics for a storage and everything within it.
+#[derive(Clone, Debug)]
+pub struct StorageStats {
+    path: PathBuf,
+    total_len: u64,
+    allocated_len: u64,
+    num_streams: usize,
+}
+
+impl StorageStats {
+    /// Returns the path of the storage.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the total length, in bytes, of all streams within the storage
+    /// (recursively).
+    pub fn total_len(&self) -> u64 {
+        self.total_len
+    }
+
+    /// Returns the total number of bytes of sectors (or mini sectors)
+    /// allocated to streams within the storage (recursively).
+    pub fn allocated_len(&self) -> u64 {
+        self.allocated_len
+    }
+
+    /// Returns the number of streams within the storage (recursively).
+    pub fn num_streams(&self) -> usize {
+        self.num_streams
+    }
+}
+
+//===========================================================================//
+
+impl<F> MiniAllocator<F> {
+    /// Computes space-usage statistics for the whole compound file.
+    pub fn stats(&self) -> io::Result<Stats> {
+        let directory = self.directory();
+        let allocator = directory.allocator();
+        let version = self.version();
+        let sector_len = version.sector_len() as u64;
+        // Any sectors in the file beyond the end of the FAT are free.
+        let fat_len = allocator.fat().len() as u32;
+        let num_sectors = allocator.num_sectors().max(fat_len);
+        let num_free_sectors = allocator
+            .fat()
+            .iter()
+            .filter(|&&next| next == consts::FREE_SECTOR)
+            .count() as u32
+            + (num_sectors - fat_len);
+        let next_sector = |sector_id| self.next_sector(sector_id);
+        let num_minifat_sectors =
+            chain_sector_ids(self.minifat_start_sector(), next_sector)?.len()
+                as u32;
+        let num_dir_sectors =
+            chain_sector_ids(directory.dir_start_sector(), next_sector)?.len()
+                as u32;
+        let mini_stream_len = self.root_dir_entry().stream_len;
+        let num_mini_sectors =
+            mini_stream_len / consts::MINI_SECTOR_LEN as u64;
+        let num_used_mini_sectors = self
+            .minifat()
+            .iter()
+            .filter(|&&next| next != consts::FREE_SECTOR)
+            .count() as u64;
+        let num_dir_entries =
+            num_dir_sectors * version.dir_entries_per_sector() as u32;
+        let num_allocated_dir_entries = (0..directory.num_dir_entries())
+            .filter(|&stream_id| {
+                self.dir_entry(stream_id as u32).obj_type
+                    != ObjType::Unallocated
+            })
+            .count() as u32;
+
+        // Walk the directory tree in preorder, rolling up stream sizes into
+        // every ancestor storage.
+        let mut streams = Vec::<StreamStats>::new();
+        let mut storages = Vec::<StorageStats>::new();
+        let mut stack = vec![(consts::ROOT_STREAM_ID, Vec::<String>::new())];
+        let mut ancestors = Vec::<(usize, usize)>::new();
+        while let Some((stream_id, names)) = stack.pop() {
+            // Pop off any ancestors that this entry isn't nested within.
+            while let Some(&(_, depth)) = ancestors.last() {
+                if depth < names.len() {
+                    break;
+                }
+                ancestors.pop();
+            }
+            let path = internal::path::path_from_name_chain(&names);
+            let dir_entry = self.dir_entry(stream_id);
+            if dir_entry.obj_type == ObjType::Stream {
+                let extents = self.extents(stream_id)?;
+                let allocated_len = if extents.is_mini() {
+                    let mini_sector_len = consts::MINI_SECTOR_LEN as u64;
+                    dir_entry.stream_len.div_ceil(mini_sector_len)
+                        * mini_sector_len
+                } else {
+                    dir_entry.stream_len.div_ceil(sector_len) * sector_len
+                };
+                for &(index, _) in ancestors.iter() {
+                    let storage: &mut StorageStats = &mut storages[index];
+                    storage.total_len += dir_entry.stream_len;
+                    storage.allocated_len += allocated_len;
+                    storage.num_streams += 1;
+                }
+                streams.push(StreamStats {
+                    path,
+                    len: dir_entry.stream_len,
+                    allocated_len,
+                    is_mini: extents.is_mini(),
+                    num_fragments: extents.runs().len(),
+                });
+            } else {
+                ancestors.push((storages.len(), names.len()));
+                storages.push(StorageStats {
+                    path,
+                    total_len: 0,
+                    allocated_len: 0,
+                    num_streams: 0,
+                });
+                let children = self.child_ids(stream_id);
+                // Push in reverse, so that children are popped in order.
+                for &child in children.iter().rev() {
+                    let mut child_names = names.clone();
+                    child_names.push(self.dir_entry(child).name_string());
+                    stack.push((child, child_names));
+                }
+            }
+        }
+
+        Ok(Stats {
+            num_sectors,
+            num_free_sectors,
+            num_fat_sectors: allocator.difat().len() as u32,
+            num_difat_sectors: allocator.difat_sector_ids().len() as u32,
+            num_minifat_sectors,
+            num_dir_sectors,
+            mini_stream_len,
+            num_free_mini_sectors: num_mini_sectors
+                .saturating_sub(num_used_mini_sectors)
+                as u32,
+            num_dir_entries,
+            num_unallocated_dir_entries: num_dir_entries
+                .saturating_sub(num_allocated_dir_entries),
+            streams,
+            storages,
+        })
+    }
+}
+
+//===========================================================================//
@@ -1,4 +1,4 @@
-use crate::internal::{consts, MiniAllocator, ObjType, SectorInit, Timestamp};
+use crate::internal::{consts, MiniAllocator, ObjType, SectorInit};
 use std::cell::RefCell;
 use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
 use std::rc::{Rc, Weak};
@@ -40,9 +40,9 @@ impl<F> Stream<F> {
     }
 
     fn minialloc(&self) -> io::Result<Rc<RefCell<MiniAllocator<F>>>> {
-        self.minialloc.upgrade().ok_or_else(|| {
-            io::Error::new(io::ErrorKind::Other, "CompoundFile was dropped")
-        })
+        self.minialloc
+            .upgrade()
+            .ok_or_else(|| io::Error::other("CompoundFile was dropped"))
     }
 
     /// Returns the current length of the stream, in bytes.
@@ -79,11 +79,14 @@ impl<F: Read + Write + Seek> Stream<F> {
     /// unless the stream is truncated to before the current position, in which
     /// case the position becomes the new end of the stream.
     pub fn set_len(&mut self, size: u64) -> io::Result<()> {
+        self.minialloc()?.borrow().check_writable()?;
         if size != self.total_len {
             let new_position = self.current_position().min(size);
             self.flush_changes()?;
             let minialloc = self.minialloc()?;
-            resize_stream(&mut minialloc.borrow_mut(), self.stream_id, size)?;
+            let mut minialloc = minialloc.borrow_mut();
+            let result = resize_stream(&mut minialloc, self.stream_id, size);
+            minialloc.poison_on_error(result)?;
             self.total_len = size;
             self.buf_offset_from_start = new_position;
             self.buf_pos = 0;
@@ -212,6 +215,7 @@ impl<F: Read + Seek> Seek for Stream<F> {
 
 impl<F: Read + Write + Seek> Write for Stream<F> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.minialloc()?.borrow().check_writable()?;
         debug_assert!(self.buf_pos <= self.buffer.len());
         if self.buf_pos >= self.buffer.len() {
             self.flush_changes()?;
@@ -256,12 +260,15 @@ struct FlushBuffer;
 impl<F: Read + Write + Seek> Flusher<F> for FlushBuffer {
     fn flush_changes(&self, stream: &mut Stream<F>) -> io::Result<()> {
         let minialloc = stream.minialloc()?;
-        write_data_to_stream(
+        // The file may have been poisoned since this data was buffered.
+        minialloc.borrow().check_writable()?;
+        let result = write_data_to_stream(
             &mut minialloc.borrow_mut(),
             stream.stream_id,
             stream.buf_offset_from_start,
             &stream.buffer[..stream.buf_cap],
-        )?;
+        );
+        minialloc.borrow_mut().poison_on_error(result)?;
         debug_assert_eq!(
             minialloc.borrow().dir_entry(stream.stream_id).stream_len,
             stream.total_len
@@ -382,10 +389,12 @@ fn write_data_to_stream<F: Read + Write + Seek>(
         old_start_sector
     };
     // Update the directory entry for this stream.
+    let policy = minialloc.timestamp_policy();
     minialloc.with_dir_entry_mut(stream_id, |dir_entry| {
         dir_entry.start_sector = new_start_sector;
         dir_entry.stream_len = new_stream_len;
-        dir_entry.modified_time = Timestamp::now();
+        dir_entry.modified_time =
+            policy.modification_timestamp(dir_entry.modified_time);
     })
 }
 
@@ -478,10 +487,12 @@ fn resize_stream<F: Read + Write + Seek>(
         }
     };
     // Update the directory entry for this stream.
+    let policy = minialloc.timestamp_policy();
     minialloc.with_dir_entry_mut(stream_id, |dir_entry| {
         dir_entry.start_sector = new_start_sector;
         dir_entry.stream_len = new_stream_len;
-        dir_entry.modified_time = Timestamp::now();
+        dir_entry.modified_time =
+            policy.modification_timestamp(dir_entry.modified_time);
     })
 }
 
@@ -0,0 +1,203 @@
+use crate::internal::layout::Tree;
+use crate::internal::{ObjType, Timestamp, TimestampPolicy, Version};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use uuid::Uuid;
+
+//===========================================================================//
+
+enum Source<'a> {
+    Data(Vec<u8>),
+    Reader(Box<dyn Read + 'a>, Option<u64>),
+}
+
+//===========================================================================//
+
+/// Writes a complete compound file to a non-seekable sink in a single pass.
+///
+/// Unlike [`CompoundFile`](struct.CompoundFile.html), which mutates a
+/// compound file in place and therefore needs its underlying writer to be
+/// seekable, a `StreamingWriter` is first given the full tree of storages and
+/// streams, and then writes the whole file out sequentially.  Streams whose
+/// data comes from a reader with a known length are copied straight through
+/// without being buffered in memory.
+///
+/// # Example
+///
+/// ```
+/// use std::io::{Cursor, Read};
+///
+/// let mut writer = cfb::StreamingWriter::new();
+/// writer.add_storage("/foo").unwrap();
+/// writer.add_stream("/foo/bar", b"Hello, world!".to_vec()).unwrap();
+/// let data = writer.write_to(Vec::new()).unwrap();
+///
+/// let mut comp = cfb::CompoundFile::open_strict(Cursor::new(data)).unwrap();
+/// let mut text = String::new();
+/// comp.open_stream("/foo/bar").unwrap().read_to_string(&mut text).unwrap();
+/// assert_eq!(text, "Hello, world!");
+/// ```
+pub struct StreamingWriter<'a> {
+    version: Version,
+    timestamp_policy: TimestampPolicy,
+    tree: Tree,
+    sources: Vec<Option<Source<'a>>>,
+}
+
+impl<'a> StreamingWriter<'a> {
+    /// Creates a new, empty streaming writer for a version 4 compound file.
+    pub fn new() -> StreamingWriter<'a> {
+        StreamingWriter::with_version(Version::V4)
+    }
+
+    /// Creates a new, empty streaming writer for a compound file of the given
+    /// version.
+    pub fn with_version(version: Version) -> StreamingWriter<'a> {
+        StreamingWriter {
+            version,
+            timestamp_policy: TimestampPolicy::default(),
+            tree: Tree::new(),
+            sources: vec![None],
+        }
+    }
+
+    /// Returns the CFB format version that will be written.
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    /// Sets the policy for the timestamps written into directory entries.
+    /// With the default policy, `TimestampPolicy::Now`, each object gets the
+    /// time at which it was declared; any other policy gives every object the
+    /// same timestamp (with `Preserve` acting like `Zero`).
+    pub fn set_timestamp_policy(&mut self, policy: TimestampPolicy) {
+        self.timestamp_policy = policy;
+    }
+
+    /// Declares a new, empty storage object at the given path.  The parent
+    /// storage must already have been declared.
+    pub fn add_storage<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.insert(path.as_ref(), ObjType::Storage, None)
+    }
+
+    /// Declares a new stream object at the given path, with the given
+    /// contents.  The parent storage must already have been declared.
+    pub fn add_stream<P, D>(&mut self, path: P, data: D) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        D: Into<Vec<u8>>,
+    {
+        self.insert(
+            path.as_ref(),
+            ObjType::Stream,
+            Some(Source::Data(data.into())),
+        )
+    }
+
+    /// Declares a new stream object at the given path, whose contents will be
+    /// read from the given reader when the compound file is written.  The
+    /// parent storage must already have been declared.
+    ///
+    /// If `len` is given, exactly that many bytes will be copied from the
+    /// reader without buffering them, and writing will fail if the reader
+    /// runs out of data early.  If `len` is `None`, the reader will be read
+    /// to the end and buffered in memory at write time, since the stream
+    /// length must be known before any sectors can be laid out.
+    pub fn add_stream_from_reader<P, R>(
+        &mut self,
+        path: P,
+        len: Option<u64>,
+        reader: R,
+    ) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        R: Read + 'a,
+    {
+        let source = Source::Reader(Box::new(reader), len);
+        self.insert(path.as_ref(), ObjType::Stream, Some(source))
+    }
+
+    fn insert(
+        &mut self,
+        path: &Path,
+        obj_type: ObjType,
+        source: Option<Source<'a>>,
+    ) -> io::Result<()> {
+        let index = self.tree.insert(path, obj_type, Timestamp::now())?;
+        debug_assert_eq!(index, self.sources.len());
+        if let Some(Source::Data(ref data)) = source {
+            self.tree.dir_entry_mut(index).stream_len = data.len() as u64;
+        } else if let Some(Source::Reader(_, Some(len))) = source {
+            self.tree.dir_entry_mut(index).stream_len = len;
+        }
+        self.sources.push(source);
+        Ok(())
+    }
+
+    /// Sets the CLSID for the storage object at the given path.  (To set the
+    /// CLSID for the root storage object, pass `"/"` for the path.)
+    pub fn set_storage_clsid<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        clsid: Uuid,
+    ) -> io::Result<()> {
+        let index = self.tree.index_for_path(path.as_ref())?;
+        let dir_entry = self.tree.dir_entry_mut(index);
+        if dir_entry.obj_type == ObjType::Stream {
+            invalid_input!("Not a storage: {:?}", path.as_ref());
+        }
+        dir_entry.clsid = clsid;
+        Ok(())
+    }
+
+    /// Sets the user-defined bitflags for the object at the given path.
+    pub fn set_state_bits<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        bits: u32,
+    ) -> io::Result<()> {
+        let index = self.tree.index_for_path(path.as_ref())?;
+        self.tree.dir_entry_mut(index).state_bits = bits;
+        Ok(())
+    }
+
+    /// Writes out the complete compound file to the given writer, and
+    /// returns the writer.
+    pub fn write_to<W: Write>(mut self, mut writer: W) -> io::Result<W> {
+        // Any readers with an unknown length have to be buffered now, so that
+        // the size of every stream is known before laying out the sectors.
+        for index in 0..self.sources.len() {
+            if let Some(Source::Reader(ref mut reader, None)) =
+                self.sources[index]
+            {
+                let mut data = Vec::new();
+                reader.read_to_end(&mut data)?;
+                self.tree.dir_entry_mut(index).stream_len = data.len() as u64;
+                self.sources[index] = Some(Source::Data(data));
+            }
+        }
+        let layout = self.tree.layout(self.version, self.timestamp_policy)?;
+        let sources = &mut self.sources;
+        layout.write_to(&mut writer, |index, writer| {
+            match sources[index] {
+                Some(Source::Data(ref data)) => {
+                    writer.write_all(data)?;
+                    Ok(data.len() as u64)
+                }
+                Some(Source::Reader(ref mut reader, Some(len))) => {
+                    io::copy(&mut reader.take(len), writer)
+                }
+                _ => unreachable!(),
+            }
+        })?;
+        Ok(writer)
+    }
+}
+
+impl<'a> Default for StreamingWriter<'a> {
+    fn default() -> StreamingWriter<'a> {
+        StreamingWriter::new()
+    }
+}
+
+//===========================================================================//
@@ -5,8 +5,13 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 //===========================================================================//
 
 /// A CFB file timestamp.  This is represented as the number of 100-nanosecond
-/// intervals since January 1, 1601 UTC.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// intervals since January 1, 1601 UTC (i.e. a Windows `FILETIME`).
+///
+/// A timestamp of zero is used by the CFB format to mean that the timestamp
+/// is not set.
+#[derive(
+    Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd,
+)]
 pub struct Timestamp(u64);
 
 impl Timestamp {
@@ -27,20 +32,87 @@ impl Timestamp {
         Timestamp(timestamp_from_system_time(system_time))
     }
 
-    /// Returns the local system time that this timestamp represents.
-    pub fn to_system_time(self) -> SystemTime {
-        system_time_from_timestamp(self.0)
+    /// Returns a timestamp with the given raw 64-bit `FILETIME` value.
+    pub fn from_filetime(filetime: u64) -> Timestamp {
+        Timestamp(filetime)
     }
 
-    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Timestamp> {
+    /// Returns the raw 64-bit `FILETIME` value of this timestamp.
+    pub fn filetime(self) -> u64 {
+        self.0
+    }
+
+    /// Returns true if this is a zero (i.e. unset) timestamp.
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns the local system time that this timestamp represents, or
+    /// `None` if this is a zero (i.e. unset) timestamp.
+    pub fn to_system_time(self) -> Option<SystemTime> {
+        if self.is_zero() {
+            None
+        } else {
+            Some(system_time_from_timestamp(self.0))
+        }
+    }
+
+    pub(crate) fn read_from<R: Read>(reader: &mut R) -> io::Result<Timestamp> {
         Ok(Timestamp(reader.read_u64::<LittleEndian>()?))
     }
 
-    pub fn write_to<W: Write>(self, writer: &mut W) -> io::Result<()> {
+    pub(crate) fn write_to<W: Write>(self, writer: &mut W) -> io::Result<()> {
         writer.write_u64::<LittleEndian>(self.0)
     }
 }
 
+impl From<SystemTime> for Timestamp {
+    fn from(system_time: SystemTime) -> Timestamp {
+        Timestamp::from_system_time(system_time)
+    }
+}
+
+//===========================================================================//
+
+/// Determines which timestamps are written into directory entries when
+/// storage and stream objects are created or modified.
+///
+/// The default policy, `Now`, records the current system time, which means
+/// that generating the same compound file twice will not produce
+/// byte-identical output.  The other policies make the output reproducible.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TimestampPolicy {
+    /// Use the current system time.
+    #[default]
+    Now,
+    /// Always use the given time.
+    Fixed(SystemTime),
+    /// Always use a zero timestamp (which the CFB spec treats as "not
+    /// set").
+    Zero,
+    /// Leave existing timestamps unchanged when objects are modified, and
+    /// give newly-created objects zero timestamps.
+    Preserve,
+}
+
+impl TimestampPolicy {
+    /// Returns the timestamp to use for a newly-created object.
+    pub(crate) fn creation_timestamp(self) -> Timestamp {
+        self.modification_timestamp(Timestamp::zero())
+    }
+
+    /// Returns the timestamp to use for an object that is being modified and
+    /// that currently has the given timestamp.
+    pub(crate) fn modification_timestamp(self, old: Timestamp) -> Timestamp {
+        match self {
+            TimestampPolicy::Now => Timestamp::now(),
+            TimestampPolicy::Fixed(time) => Timestamp::from_system_time(time),
+            TimestampPolicy::Zero => Timestamp::zero(),
+            TimestampPolicy::Preserve => old,
+        }
+    }
+}
+
 //===========================================================================//
 
 /// The CFB timestamp value for the Unix epoch (Jan 1, 1970 UTC).
@@ -103,7 +175,7 @@ fn timestamp_delta_to_duration(delta: u64) -> Duration {
 mod tests {
     use super::{
         duration_to_timestamp_delta, system_time_from_timestamp,
-        timestamp_delta_to_duration, timestamp_from_system_time,
+        timestamp_delta_to_duration, timestamp_from_system_time, Timestamp,
         UNIX_EPOCH_TIMESTAMP,
     };
     use std::time::{Duration, UNIX_EPOCH};
@@ -167,6 +239,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn zero_timestamp_is_unset() {
+        assert!(Timestamp::zero().is_zero());
+        assert_eq!(Timestamp::zero().to_system_time(), None);
+        let timestamp = Timestamp::from_filetime(UNIX_EPOCH_TIMESTAMP);
+        assert!(!timestamp.is_zero());
+        assert_eq!(timestamp.to_system_time(), Some(UNIX_EPOCH));
+        assert_eq!(Timestamp::from(UNIX_EPOCH), timestamp);
+        assert_eq!(timestamp.filetime(), UNIX_EPOCH_TIMESTAMP);
+    }
+
     #[test]
     fn extreme_timestamps() {
         // If the system we're on can't represent these timestamps in a
@@ -0,0 +1,350 @@
+//! Uppercasing of individual UTF-16 code units, as used by MS-CFB to order
+//! and compare directory entry names.
+//!
+//! MS-CFB (section 2.6.4) specifies that names are compared by uppercasing
+//! each UTF-16 code unit independently using the Unicode *simple* case
+//! mappings; code units that have no simple uppercase mapping to another BMP
+//! code point (including all surrogates) are left unchanged.  In particular,
+//! this means that uppercasing never changes the length of a name (so e.g.
+//! `'\u{df}'` stays as is rather than becoming `"SS"`), and that there is no
+//! locale-specific behavior (so e.g. dotless `'\u{131}'` uppercases to `'I'`
+//! just like `'i'` does).
+//!
+//! The table below was generated from the simple uppercase mappings
+//! (`UnicodeData.txt` field 12) of Unicode 14.0, restricted to the BMP, by
+//! running:
+//!
+//! ```text
+//! curl -O https://www.unicode.org/Public/14.0.0/ucd/UnicodeData.txt
+//! python3 scripts/upcase_table.py UnicodeData.txt
+//! ```
+//!
+//! The table is pinned to a fixed Unicode version (rather than using
+//! `char::to_uppercase`, which follows whatever version the compiler was
+//! built with) because it determines the order of entries on disk, which
+//! must not change from one toolchain to the next.  MS-CFB doesn't name a
+//! version, so the choice is somewhat arbitrary; 14.0 is the version of the
+//! character database bundled with Python 3.11, which makes the table easy
+//! to cross-check with `unicodedata`.  Later versions (through 17.0) only
+//! add mappings for eight more BMP code units, such as U+019B and U+0264,
+//! so names using them sort by their raw code units here.
+
+use std::cmp::Ordering;
+
+//===========================================================================//
+
+/// Runs of code units with the same uppercase mapping, as `(start, count,
+/// stride, delta)`: the code units `start + i * stride` for `i` in
+/// `0..count` uppercase to themselves plus `delta` (wrapping).
+static UPPERCASE_RUNS: &[(u16, u16, u16, u16)] = &[
+    (0x0061, 26, 1, 0xFFE0),
+    (0x00B5, 1, 1, 0x02E7),
+    (0x00E0, 23, 1, 0xFFE0),
+    (0x00F8, 7, 1, 0xFFE0),
+    (0x00FF, 1, 1, 0x0079),
+    (0x0101, 24, 2, 0xFFFF),
+    (0x0131, 1, 1, 0xFF18),
+    (0x0133, 3, 2, 0xFFFF),
+    (0x013A, 8, 2, 0xFFFF),
+    (0x014B, 23, 2, 0xFFFF),
+    (0x017A, 3, 2, 0xFFFF),
+    (0x017F, 1, 1, 0xFED4),
+    (0x0180, 1, 1, 0x00C3),
+    (0x0183, 2, 2, 0xFFFF),
+    (0x0188, 1, 1, 0xFFFF),
+    (0x018C, 1, 1, 0xFFFF),
+    (0x0192, 1, 1, 0xFFFF),
+    (0x0195, 1, 1, 0x0061),
+    (0x0199, 1, 1, 0xFFFF),
+    (0x019A, 1, 1, 0x00A3),
+    (0x019E, 1, 1, 0x0082),
+    (0x01A1, 3, 2, 0xFFFF),
+    (0x01A8, 1, 1, 0xFFFF),
+    (0x01AD, 1, 1, 0xFFFF),
+    (0x01B0, 1, 1, 0xFFFF),
+    (0x01B4, 2, 2, 0xFFFF),
+    (0x01B9, 1, 1, 0xFFFF),
+    (0x01BD, 1, 1, 0xFFFF),
+    (0x01BF, 1, 1, 0x0038),
+    (0x01C5, 1, 1, 0xFFFF),
+    (0x01C6, 1, 1, 0xFFFE),
+    (0x01C8, 1, 1, 0xFFFF),
+    (0x01C9, 1, 1, 0xFFFE),
+    (0x01CB, 1, 1, 0xFFFF),
+    (0x01CC, 1, 1, 0xFFFE),
+    (0x01CE, 8, 2, 0xFFFF),
+    (0x01DD, 1, 1, 0xFFB1),
+    (0x01DF, 9, 2, 0xFFFF),
+    (0x01F2, 1, 1, 0xFFFF),
+    (0x01F3, 1, 1, 0xFFFE),
+    (0x01F5, 1, 1, 0xFFFF),
+    (0x01F9, 20, 2, 0xFFFF),
+    (0x0223, 9, 2, 0xFFFF),
+    (0x023C, 1, 1, 0xFFFF),
+    (0x023F, 2, 1, 0x2A3F),
+    (0x0242, 1, 1, 0xFFFF),
+    (0x0247, 5, 2, 0xFFFF),
+    (0x0250, 1, 1, 0x2A1F),
+    (0x0251, 1, 1, 0x2A1C),
+    (0x0252, 1, 1, 0x2A1E),
+    (0x0253, 1, 1, 0xFF2E),
+    (0x0254, 1, 1, 0xFF32),
+    (0x0256, 2, 1, 0xFF33),
+    (0x0259, 1, 1, 0xFF36),
+    (0x025B, 1, 1, 0xFF35),
+    (0x025C, 1, 1, 0xA54F),
+    (0x0260, 1, 1, 0xFF33),
+    (0x0261, 1, 1, 0xA54B),
+    (0x0263, 1, 1, 0xFF31),
+    (0x0265, 1, 1, 0xA528),
+    (0x0266, 1, 1, 0xA544),
+    (0x0268, 1, 1, 0xFF2F),
+    (0x0269, 1, 1, 0xFF2D),
+    (0x026A, 1, 1, 0xA544),
+    (0x026B, 1, 1, 0x29F7),
+    (0x026C, 1, 1, 0xA541),
+    (0x026F, 1, 1, 0xFF2D),
+    (0x0271, 1, 1, 0x29FD),
+    (0x0272, 1, 1, 0xFF2B),
+    (0x0275, 1, 1, 0xFF2A),
+    (0x027D, 1, 1, 0x29E7),
+    (0x0280, 1, 1, 0xFF26),
+    (0x0282, 1, 1, 0xA543),
+    (0x0283, 1, 1, 0xFF26),
+    (0x0287, 1, 1, 0xA52A),
+    (0x0288, 1, 1, 0xFF26),
+    (0x0289, 1, 1, 0xFFBB),
+    (0x028A, 2, 1, 0xFF27),
+    (0x028C, 1, 1, 0xFFB9),
+    (0x0292, 1, 1, 0xFF25),
+    (0x029D, 1, 1, 0xA515),
+    (0x029E, 1, 1, 0xA512),
+    (0x0345, 1, 1, 0x0054),
+    (0x0371, 2, 2, 0xFFFF),
+    (0x0377, 1, 1, 0xFFFF),
+    (0x037B, 3, 1, 0x0082),
+    (0x03AC, 1, 1, 0xFFDA),
+    (0x03AD, 3, 1, 0xFFDB),
+    (0x03B1, 17, 1, 0xFFE0),
+    (0x03C2, 1, 1, 0xFFE1),
+    (0x03C3, 9, 1, 0xFFE0),
+    (0x03CC, 1, 1, 0xFFC0),
+    (0x03CD, 2, 1, 0xFFC1),
+    (0x03D0, 1, 1, 0xFFC2),
+    (0x03D1, 1, 1, 0xFFC7),
+    (0x03D5, 1, 1, 0xFFD1),
+    (0x03D6, 1, 1, 0xFFCA),
+    (0x03D7, 1, 1, 0xFFF8),
+    (0x03D9, 12, 2, 0xFFFF),
+    (0x03F0, 1, 1, 0xFFAA),
+    (0x03F1, 1, 1, 0xFFB0),
+    (0x03F2, 1, 1, 0x0007),
+    (0x03F3, 1, 1, 0xFF8C),
+    (0x03F5, 1, 1, 0xFFA0),
+    (0x03F8, 1, 1, 0xFFFF),
+    (0x03FB, 1, 1, 0xFFFF),
+    (0x0430, 32, 1, 0xFFE0),
+    (0x0450, 16, 1, 0xFFB0),
+    (0x0461, 17, 2, 0xFFFF),
+    (0x048B, 27, 2, 0xFFFF),
+    (0x04C2, 7, 2, 0xFFFF),
+    (0x04CF, 1, 1, 0xFFF1),
+    (0x04D1, 48, 2, 0xFFFF),
+    (0x0561, 38, 1, 0xFFD0),
+    (0x10D0, 43, 1, 0x0BC0),
+    (0x10FD, 3, 1, 0x0BC0),
+    (0x13F8, 6, 1, 0xFFF8),
+    (0x1C80, 1, 1, 0xE792),
+    (0x1C81, 1, 1, 0xE793),
+    (0x1C82, 1, 1, 0xE79C),
+    (0x1C83, 2, 1, 0xE79E),
+    (0x1C85, 1, 1, 0xE79D),
+    (0x1C86, 1, 1, 0xE7A4),
+    (0x1C87, 1, 1, 0xE7DB),
+    (0x1C88, 1, 1, 0x89C2),
+    (0x1D79, 1, 1, 0x8A04),
+    (0x1D7D, 1, 1, 0x0EE6),
+    (0x1D8E, 1, 1, 0x8A38),
+    (0x1E01, 75, 2, 0xFFFF),
+    (0x1E9B, 1, 1, 0xFFC5),
+    (0x1EA1, 48, 2, 0xFFFF),
+    (0x1F00, 8, 1, 0x0008),
+    (0x1F10, 6, 1, 0x0008),
+    (0x1F20, 8, 1, 0x0008),
+    (0x1F30, 8, 1, 0x0008),
+    (0x1F40, 6, 1, 0x0008),
+    (0x1F51, 4, 2, 0x0008),
+    (0x1F60, 8, 1, 0x0008),
+    (0x1F70, 2, 1, 0x004A),
+    (0x1F72, 4, 1, 0x0056),
+    (0x1F76, 2, 1, 0x0064),
+    (0x1F78, 2, 1, 0x0080),
+    (0x1F7A, 2, 1, 0x0070),
+    (0x1F7C, 2, 1, 0x007E),
+    (0x1F80, 8, 1, 0x0008),
+    (0x1F90, 8, 1, 0x0008),
+    (0x1FA0, 8, 1, 0x0008),
+    (0x1FB0, 2, 1, 0x0008),
+    (0x1FB3, 1, 1, 0x0009),
+    (0x1FBE, 1, 1, 0xE3DB),
+    (0x1FC3, 1, 1, 0x0009),
+    (0x1FD0, 2, 1, 0x0008),
+    (0x1FE0, 2, 1, 0x0008),
+    (0x1FE5, 1, 1, 0x0007),
+    (0x1FF3, 1, 1, 0x0009),
+    (0x214E, 1, 1, 0xFFE4),
+    (0x2170, 16, 1, 0xFFF0),
+    (0x2184, 1, 1, 0xFFFF),
+    (0x24D0, 26, 1, 0xFFE6),
+    (0x2C30, 48, 1, 0xFFD0),
+    (0x2C61, 1, 1, 0xFFFF),
+    (0x2C65, 1, 1, 0xD5D5),
+    (0x2C66, 1, 1, 0xD5D8),
+    (0x2C68, 3, 2, 0xFFFF),
+    (0x2C73, 1, 1, 0xFFFF),
+    (0x2C76, 1, 1, 0xFFFF),
+    (0x2C81, 50, 2, 0xFFFF),
+    (0x2CEC, 2, 2, 0xFFFF),
+    (0x2CF3, 1, 1, 0xFFFF),
+    (0x2D00, 38, 1, 0xE3A0),
+    (0x2D27, 1, 1, 0xE3A0),
+    (0x2D2D, 1, 1, 0xE3A0),
+    (0xA641, 23, 2, 0xFFFF),
+    (0xA681, 14, 2, 0xFFFF),
+    (0xA723, 7, 2, 0xFFFF),
+    (0xA733, 31, 2, 0xFFFF),
+    (0xA77A, 2, 2, 0xFFFF),
+    (0xA77F, 5, 2, 0xFFFF),
+    (0xA78C, 1, 1, 0xFFFF),
+    (0xA791, 2, 2, 0xFFFF),
+    (0xA794, 1, 1, 0x0030),
+    (0xA797, 10, 2, 0xFFFF),
+    (0xA7B5, 8, 2, 0xFFFF),
+    (0xA7C8, 2, 2, 0xFFFF),
+    (0xA7D1, 1, 1, 0xFFFF),
+    (0xA7D7, 2, 2, 0xFFFF),
+    (0xA7F6, 1, 1, 0xFFFF),
+    (0xAB53, 1, 1, 0xFC60),
+    (0xAB70, 80, 1, 0x6830),
+    (0xFF41, 26, 1, 0xFFE0),
+];
+
+//===========================================================================//
+
+/// Returns the uppercase form of a single UTF-16 code unit.
+pub fn uppercase(code_unit: u16) -> u16 {
+    let index = match UPPERCASE_RUNS
+        .binary_search_by(|&(start, _, _, _)| start.cmp(&code_unit))
+    {
+        Ok(index) => index,
+        Err(0) => return code_unit,
+        Err(index) => index - 1,
+    };
+    let (start, count, stride, delta) = UPPERCASE_RUNS[index];
+    let offset = code_unit - start;
+    if offset % stride == 0 && offset / stride < count {
+        code_unit.wrapping_add(delta)
+    } else {
+        code_unit
+    }
+}
+
+/// Returns the uppercase form of a character, treating it as a sequence of
+/// UTF-16 code units.  Characters outside the BMP are never changed.
+pub fn uppercase_char(chr: char) -> char {
+    let code_point = chr as u32;
+    if code_point > 0xffff {
+        return chr;
+    }
+    char::from_u32(uppercase(code_point as u16) as u32).unwrap_or(chr)
+}
+
+/// Compares two UTF-16 names according to CFB ordering: shorter names come
+/// first, and names of the same length are compared code unit by code unit
+/// after uppercasing.
+pub fn compare_utf16<I, J>(name1: I, name2: J) -> Ordering
+where
+    I: Iterator<Item = u16> + Clone,
+    J: Iterator<Item = u16> + Clone,
+{
+    match name1.clone().count().cmp(&name2.clone().count()) {
+        Ordering::Equal => name1.map(uppercase).cmp(name2.map(uppercase)),
+        other => other,
+    }
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_utf16, uppercase, uppercase_char, UPPERCASE_RUNS};
+    use std::cmp::Ordering;
+
+    #[test]
+    fn runs_are_sorted_and_disjoint() {
+        for pair in UPPERCASE_RUNS.windows(2) {
+            let (start, count, stride, _) = pair[0];
+            let last = start as u32 + (count as u32 - 1) * stride as u32;
+            assert!(last < pair[1].0 as u32, "{:04x}", pair[1].0);
+        }
+    }
+
+    #[test]
+    fn number_of_mappings() {
+        let count = (0..=0xffffu16)
+            .filter(|&code_unit| uppercase(code_unit) != code_unit)
+            .count();
+        assert_eq!(count, 1190);
+    }
+
+    #[test]
+    fn ascii() {
+        for code_unit in 0..0x80u16 {
+            let expected = (code_unit as u8).to_ascii_uppercase() as u16;
+            assert_eq!(uppercase(code_unit), expected);
+        }
+    }
+
+    #[test]
+    fn simple_mappings() {
+        assert_eq!(uppercase_char('\u{e9}'), '\u{c9}');
+        assert_eq!(uppercase_char('\u{ff}'), '\u{178}');
+        // Sharp s has no single-character uppercase form.
+        assert_eq!(uppercase_char('\u{df}'), '\u{df}');
+        // Turkish dotless i and Latin long s uppercase as in the default
+        // (non-locale-specific) mappings.
+        assert_eq!(uppercase_char('\u{131}'), 'I');
+        assert_eq!(uppercase_char('\u{130}'), '\u{130}');
+        assert_eq!(uppercase_char('\u{17f}'), 'S');
+        // Alternating upper/lowercase runs.
+        assert_eq!(uppercase_char('\u{101}'), '\u{100}');
+        assert_eq!(uppercase_char('\u{100}'), '\u{100}');
+        assert_eq!(uppercase_char('\u{1c5}'), '\u{1c4}');
+        assert_eq!(uppercase_char('\u{1c6}'), '\u{1c4}');
+        // Greek, including final sigma and iota subscripts.
+        assert_eq!(uppercase_char('\u{3c2}'), '\u{3a3}');
+        assert_eq!(uppercase_char('\u{3c3}'), '\u{3a3}');
+        assert_eq!(uppercase_char('\u{3ac}'), '\u{386}');
+        assert_eq!(uppercase_char('\u{390}'), '\u{390}');
+        assert_eq!(uppercase_char('\u{1f80}'), '\u{1f88}');
+        assert_eq!(uppercase_char('\u{1fb3}'), '\u{1fbc}');
+        // Fullwidth forms.
+        assert_eq!(uppercase_char('\u{ff41}'), '\u{ff21}');
+        // Characters outside the BMP are never uppercased.
+        assert_eq!(uppercase_char('\u{10428}'), '\u{10428}');
+        assert_eq!(uppercase(0xd801), 0xd801);
+    }
+
+    #[test]
+    fn compare() {
+        let cmp = |a: &str, b: &str| {
+            compare_utf16(a.encode_utf16(), b.encode_utf16())
+        };
+        assert_eq!(cmp("\u{3c3}\u{3c2}", "\u{3a3}\u{3a3}"), Ordering::Equal);
+        assert_eq!(cmp("\u{131}", "i"), Ordering::Equal);
+        assert_eq!(cmp("\u{df}", "SS"), Ordering::Less);
+        assert_eq!(cmp("\u{df}", "S"), Ordering::Greater);
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,409 @@
+use crate::internal::{self, consts, Entry, MiniAllocator, ObjType};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+//===========================================================================//
+
+/// The order in which a [`Walk`](struct.Walk.html) visits entries.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum WalkOrder {
+    /// Each storage is visited before the entries within it.
+    #[default]
+    Preorder,
+    /// Each storage is visited after all the entries within it (so it is
+    /// safe to remove entries in the order they are visited).
+    Postorder,
+    /// All entries at one depth are visited before any entries at the next
+    /// depth.
+    BreadthFirst,
+}
+
+//===========================================================================//
+
+type EntryFilter<'a> = Box<dyn Fn(&Entry) -> bool + 'a>;
+
+/// Options controlling how a storage subtree is walked; see
+/// [`CompoundFile::walk_storage_with`](
+/// ../struct.CompoundFile.html#method.walk_storage_with).
+#[derive(Default)]
+pub struct WalkOptions<'a> {
+    order: WalkOrder,
+    max_depth: Option<usize>,
+    filter: Option<EntryFilter<'a>>,
+    pattern: Option<String>,
+}
+
+impl<'a> WalkOptions<'a> {
+    /// Returns the default options, which walk the entire subtree in
+    /// preorder.
+    pub fn new() -> WalkOptions<'a> {
+        WalkOptions::default()
+    }
+
+    /// Sets the order in which entries are visited.
+    pub fn order(mut self, order: WalkOrder) -> WalkOptions<'a> {
+        self.order = order;
+        self
+    }
+
+    /// Limits how deep into the subtree the walk descends.  The starting
+    /// entry is at depth zero, the entries directly within it at depth one,
+    /// and so on.
+    pub fn max_depth(mut self, max_depth: usize) -> WalkOptions<'a> {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Sets a predicate that is called for each entry before it is visited;
+    /// if it returns false, then neither that entry nor anything within it
+    /// will be visited.
+    pub fn filter_entry<P>(mut self, predicate: P) -> WalkOptions<'a>
+    where
+        P: Fn(&Entry) -> bool + 'a,
+    {
+        self.filter = Some(Box::new(predicate));
+        self
+    }
+
+    /// Restricts the walk to entries whose paths match the given glob
+    /// pattern.  Within a name, `*` matches any sequence of characters and
+    /// `?` matches any single character; a `**` component matches any number
+    /// of nested storages.  Patterns starting with `/` are matched against
+    /// the full path of each entry; other patterns are matched against the
+    /// path relative to the starting storage.  As with paths, matching is
+    /// case-insensitive.
+    pub fn pattern<S: Into<String>>(mut self, pattern: S) -> WalkOptions<'a> {
+        self.pattern = Some(pattern.into());
+        self
+    }
+}
+
+//===========================================================================//
+
+/// A compiled glob pattern, matched component by component as a
+/// nondeterministic automaton whose states are indices into `components`.
+struct Glob {
+    components: Vec<GlobComponent>,
+}
+
+enum GlobComponent {
+    AnyDepth,
+    Name(Vec<char>),
+}
+
+impl Glob {
+    fn parse(pattern: &str) -> io::Result<Glob> {
+        let mut components = Vec::new();
+        for component in pattern.split('/') {
+            if component.is_empty() || component == "." {
+                continue;
+            } else if component == ".." {
+                invalid_input!("Invalid pattern (must not contain \"..\")");
+            } else if component == "**" {
+                components.push(GlobComponent::AnyDepth);
+            } else if component.contains("**") {
+                invalid_input!(
+                    "Invalid pattern (\"**\" must be a whole component)"
+                );
+            } else {
+                components.push(GlobComponent::Name(uppercase(component)));
+            }
+        }
+        Ok(Glob { components })
+    }
+
+    fn initial_states(&self) -> Vec<usize> {
+        self.closure(vec![0])
+    }
+
+    /// Adds to `states` every state reachable without consuming a name.
+    fn closure(&self, mut states: Vec<usize>) -> Vec<usize> {
+        let mut index = 0;
+        while index < states.len() {
+            let state = states[index];
+            if let Some(GlobComponent::AnyDepth) = self.components.get(state) {
+                if !states.contains(&(state + 1)) {
+                    states.push(state + 1);
+                }
+            }
+            index += 1;
+        }
+        states
+    }
+
+    fn step(&self, states: &[usize], name: &str) -> Vec<usize> {
+        let name = uppercase(name);
+        let mut next = Vec::new();
+        for &state in states {
+            let target = match self.components.get(state) {
+                Some(GlobComponent::AnyDepth) => state,
+                Some(GlobComponent::Name(pattern)) => {
+                    if wildcard_match(pattern, &name) {
+                        state + 1
+                    } else {
+                        continue;
+                    }
+                }
+                None => continue,
+            };
+            if !next.contains(&target) {
+                next.push(target);
+            }
+        }
+        self.closure(next)
+    }
+
+    fn is_match(&self, states: &[usize]) -> bool {
+        states.contains(&self.components.len())
+    }
+}
+
+fn uppercase(name: &str) -> Vec<char> {
+    name.chars().map(internal::upcase::uppercase_char).collect()
+}
+
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if p < pattern.len()
+            && (pattern[p] == '?' || pattern[p] == name[n])
+        {
+            p += 1;
+            n += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            p = star_p + 1;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&chr| chr == '*')
+}
+
+//===========================================================================//
+
+struct WalkItem {
+    parent_path: PathBuf,
+    stream_id: u32,
+    depth: usize,
+    states: Vec<usize>,
+    expanded: bool,
+}
+
+/// An iterator over the entries in a storage subtree, created by
+/// [`CompoundFile::walk_storage_with`](
+/// ../struct.CompoundFile.html#method.walk_storage_with).
+pub struct Walk<'a, F: 'a> {
+    minialloc: &'a Rc<RefCell<MiniAllocator<F>>>,
+    order: WalkOrder,
+    max_depth: Option<usize>,
+    filter: Option<EntryFilter<'a>>,
+    glob: Option<Glob>,
+    queue: VecDeque<WalkItem>,
+}
+
+impl<'a, F> Walk<'a, F> {
+    pub(crate) fn new(
+        minialloc: &'a Rc<RefCell<MiniAllocator<F>>>,
+        names: &[&str],
+        stream_id: u32,
+        options: WalkOptions<'a>,
+    ) -> io::Result<Walk<'a, F>> {
+        let mut states = Vec::new();
+        let glob = match options.pattern {
+            Some(ref pattern) => {
+                let glob = Glob::parse(pattern)?;
+                states = glob.initial_states();
+                if pattern.starts_with('/') {
+                    for name in names.iter() {
+                        states = glob.step(&states, name);
+                    }
+                }
+                Some(glob)
+            }
+            None => None,
+        };
+        let parent_path = match names.split_last() {
+            Some((_, parent_names)) => {
+                internal::path::path_from_name_chain(parent_names)
+            }
+            None => internal::path::path_from_name_chain::<&str>(&[]),
+        };
+        let mut queue = VecDeque::new();
+        if glob.is_none() || !states.is_empty() {
+            queue.push_back(WalkItem {
+                parent_path,
+                stream_id,
+                depth: 0,
+                states,
+                expanded: false,
+            });
+        }
+        Ok(Walk {
+            minialloc,
+            order: options.order,
+            max_depth: options.max_depth,
+            filter: options.filter,
+            glob,
+            queue,
+        })
+    }
+
+    fn pop(&mut self) -> Option<WalkItem> {
+        match self.order {
+            WalkOrder::BreadthFirst => self.queue.pop_front(),
+            WalkOrder::Preorder | WalkOrder::Postorder => {
+                self.queue.pop_back()
+            }
+        }
+    }
+
+    /// Returns the items for the entries directly within the given storage,
+    /// in sorted order.  Subtrees that can't contain any matches for the
+    /// glob pattern are left out entirely.
+    fn children(&self, item: &WalkItem, path: &Path) -> Vec<WalkItem> {
+        if self.max_depth.is_some_and(|max_depth| item.depth >= max_depth) {
+            return Vec::new();
+        }
+        let minialloc = self.minialloc.borrow();
+        let mut children = Vec::new();
+        for child_id in minialloc.child_ids(item.stream_id) {
+            let states = match self.glob {
+                Some(ref glob) => {
+                    let name = minialloc.dir_entry(child_id).name_string();
+                    let states = glob.step(&item.states, &name);
+                    if states.is_empty() {
+                        continue;
+                    }
+                    states
+                }
+                None => Vec::new(),
+            };
+            children.push(WalkItem {
+                parent_path: path.to_path_buf(),
+                stream_id: child_id,
+                depth: item.depth + 1,
+                states,
+                expanded: false,
+            });
+        }
+        children
+    }
+}
+
+impl<'a, F> Iterator for Walk<'a, F> {
+    type Item = Entry;
+
+    fn next(&mut self) -> Option<Entry> {
+        while let Some(mut item) = self.pop() {
+            let (entry, is_storage) = {
+                let minialloc = self.minialloc.borrow();
+                let dir_entry = minialloc.dir_entry(item.stream_id);
+                let path = if dir_entry.obj_type == ObjType::Root {
+                    item.parent_path.clone()
+                } else {
+                    internal::path::join_name(
+                        &item.parent_path,
+                        &dir_entry.name_string(),
+                    )
+                };
+                let is_storage = dir_entry.obj_type != ObjType::Stream
+                    && dir_entry.child != consts::NO_STREAM;
+                (Entry::new(&minialloc, item.stream_id, path), is_storage)
+            };
+            if !item.expanded {
+                if let Some(ref filter) = self.filter {
+                    if !filter(&entry) {
+                        continue;
+                    }
+                }
+            }
+            let is_match = match self.glob {
+                Some(ref glob) => glob.is_match(&item.states),
+                None => true,
+            };
+            if is_storage && !item.expanded {
+                let mut children = self.children(&item, entry.path());
+                match self.order {
+                    WalkOrder::Preorder => {
+                        self.queue.extend(children.drain(..).rev());
+                    }
+                    WalkOrder::Postorder => {
+                        item.expanded = true;
+                        self.queue.push_back(item);
+                        self.queue.extend(children.drain(..).rev());
+                        continue;
+                    }
+                    WalkOrder::BreadthFirst => {
+                        self.queue.extend(children.drain(..));
+                    }
+                }
+            }
+            if is_match {
+                return Some(entry);
+            }
+        }
+        None
+    }
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{wildcard_match, Glob};
+
+    fn matches(pattern: &str, name: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+        wildcard_match(&pattern, &name)
+    }
+
+    fn glob_matches(pattern: &str, path: &[&str]) -> bool {
+        let glob = Glob::parse(pattern).unwrap();
+        let mut states = glob.initial_states();
+        for name in path {
+            states = glob.step(&states, name);
+        }
+        glob.is_match(&states)
+    }
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*", ""));
+        assert!(matches("*", "foo"));
+        assert!(matches("f*o", "fo"));
+        assert!(matches("f*o", "foooo"));
+        assert!(!matches("f*o", "foox"));
+        assert!(matches("?oo", "foo"));
+        assert!(!matches("?oo", "oo"));
+        assert!(matches("*.bin", "a.b.bin"));
+        assert!(matches("*a*b*", "xaybz"));
+        assert!(!matches("*a*b*", "xbya"));
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_matches("**", &[]));
+        assert!(glob_matches("**", &["a", "b"]));
+        assert!(glob_matches("**/*.BIN", &["x.bin"]));
+        assert!(glob_matches("**/*.bin", &["a", "b", "X.Bin"]));
+        assert!(!glob_matches("**/*.bin", &["a", "b"]));
+        assert!(glob_matches("/a/*/c", &["A", "b", "C"]));
+        assert!(!glob_matches("/a/*/c", &["a", "c"]));
+        assert!(glob_matches("a/**/c", &["a", "c"]));
+        assert!(glob_matches("a/**/c", &["a", "x", "y", "c"]));
+        assert!(Glob::parse("a/../b").is_err());
+        assert!(Glob::parse("a/b**").is_err());
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,340 @@
+//! Detection of what kind of document a compound file holds.
+//!
+//! Word documents, Excel workbooks, Windows Installer databases, Outlook
+//! messages, and many other formats are all compound files, and so all begin
+//! with the same magic number.  [`CompoundFile::detect_kind`](
+//! ../struct.CompoundFile.html#method.detect_kind) tells them apart by
+//! looking at the CLSID of the root storage, at the names of well-known
+//! streams, and at the user type recorded in the root `"\u{1}CompObj"`
+//! stream, in that order.  The [`KNOWN_CLSIDS`](constant.KNOWN_CLSIDS.html)
+//! table maps the CLSIDs of common applications to their names.
+//!
+//! # Example usage
+//!
+//! ```no_run
+//! use cfb::kind::DocumentKind;
+//!
+//! let mut comp = cfb::open("path/to/cfb/file").unwrap();
+//! match comp.detect_kind() {
+//!     DocumentKind::Word => println!("A Word document"),
+//!     DocumentKind::Unknown => {
+//!         let clsid = *comp.root_entry().clsid();
+//!         println!("Unknown ({:?})", cfb::kind::application_name(&clsid));
+//!     }
+//!     kind => println!("{}", kind),
+//! }
+//! ```
+
+use crate::internal::path::join_name;
+use crate::CompoundFile;
+use std::fmt;
+use std::io::{Read, Seek};
+use std::path::Path;
+use uuid::Uuid;
+
+//===========================================================================//
+
+/// The CLSID of a Word 97-2003 document.
+pub const CLSID_WORD_97: Uuid =
+    Uuid::from_u128(0x00020906_0000_0000_c000_000000000046);
+/// The CLSID of a Word 6.0/95 document.
+pub const CLSID_WORD_95: Uuid =
+    Uuid::from_u128(0x00020900_0000_0000_c000_000000000046);
+/// The CLSID of an Excel 97-2003 workbook.
+pub const CLSID_EXCEL_97: Uuid =
+    Uuid::from_u128(0x00020820_0000_0000_c000_000000000046);
+/// The CLSID of an Excel 97-2003 chart.
+pub const CLSID_EXCEL_97_CHART: Uuid =
+    Uuid::from_u128(0x00020821_0000_0000_c000_000000000046);
+/// The CLSID of an Excel 5.0/95 workbook.
+pub const CLSID_EXCEL_95: Uuid =
+    Uuid::from_u128(0x00020810_0000_0000_c000_000000000046);
+/// The CLSID of a PowerPoint 97-2003 presentation.
+pub const CLSID_POWERPOINT_97: Uuid =
+    Uuid::from_u128(0x64818d10_4f9b_11cf_86ea_00aa00b929e8);
+/// The CLSID of a PowerPoint 97-2003 slide.
+pub const CLSID_POWERPOINT_97_SLIDE: Uuid =
+    Uuid::from_u128(0x64818d11_4f9b_11cf_86ea_00aa00b929e8);
+/// The CLSID of a PowerPoint 95 presentation.
+pub const CLSID_POWERPOINT_95: Uuid =
+    Uuid::from_u128(0xea7bae70_fb3b_11cd_a903_00aa00510ea3);
+/// The CLSID of an Outlook message (`.msg` file).
+pub const CLSID_OUTLOOK_MESSAGE: Uuid =
+    Uuid::from_u128(0x00020d0b_0000_0000_c000_000000000046);
+/// The CLSID of a Windows Installer package (`.msi` or `.msm` file).
+pub const CLSID_INSTALLER_PACKAGE: Uuid =
+    Uuid::from_u128(0x000c1084_0000_0000_c000_000000000046);
+/// The CLSID of a Windows Installer patch (`.msp` file).
+pub const CLSID_INSTALLER_PATCH: Uuid =
+    Uuid::from_u128(0x000c1086_0000_0000_c000_000000000046);
+/// The CLSID of a Windows Installer transform (`.mst` file).
+pub const CLSID_INSTALLER_TRANSFORM: Uuid =
+    Uuid::from_u128(0x000c1082_0000_0000_c000_000000000046);
+/// The CLSID of an Equation Editor 3.0 object.
+pub const CLSID_EQUATION_3: Uuid =
+    Uuid::from_u128(0x0002ce02_0000_0000_c000_000000000046);
+/// The CLSID of an OLE "Package" object (an embedded file).
+pub const CLSID_PACKAGE: Uuid =
+    Uuid::from_u128(0x0003000c_0000_0000_c000_000000000046);
+
+/// Well-known CLSIDs, each with the name of the application or object type
+/// that it belongs to.
+pub const KNOWN_CLSIDS: &[(Uuid, &str)] = &[
+    (CLSID_WORD_97, "Microsoft Word 97-2003 Document"),
+    (CLSID_WORD_95, "Microsoft Word 6.0/95 Document"),
+    (CLSID_EXCEL_97, "Microsoft Excel 97-2003 Worksheet"),
+    (CLSID_EXCEL_97_CHART, "Microsoft Excel 97-2003 Chart"),
+    (CLSID_EXCEL_95, "Microsoft Excel 5.0/95 Worksheet"),
+    (CLSID_POWERPOINT_97, "Microsoft PowerPoint 97-2003 Presentation"),
+    (CLSID_POWERPOINT_97_SLIDE, "Microsoft PowerPoint 97-2003 Slide"),
+    (CLSID_POWERPOINT_95, "Microsoft PowerPoint 95 Presentation"),
+    (CLSID_OUTLOOK_MESSAGE, "Microsoft Outlook Message"),
+    (CLSID_INSTALLER_PACKAGE, "Windows Installer Package"),
+    (CLSID_INSTALLER_PATCH, "Windows Installer Patch"),
+    (CLSID_INSTALLER_TRANSFORM, "Windows Installer Transform"),
+    (CLSID_EQUATION_3, "Microsoft Equation 3.0"),
+    (CLSID_PACKAGE, "OLE Package"),
+];
+
+/// Returns the name of the application or object type that the given CLSID
+/// belongs to, if it is in the [`KNOWN_CLSIDS`](constant.KNOWN_CLSIDS.html)
+/// table.
+pub fn application_name(clsid: &Uuid) -> Option<&'static str> {
+    KNOWN_CLSIDS
+        .iter()
+        .find(|&&(known, _)| known == *clsid)
+        .map(|&(_, name)| name)
+}
+
+//===========================================================================//
+
+const WORD_STREAM: &str = "WordDocument";
+const EXCEL_STREAMS: &[&str] = &["Workbook", "Book"];
+const POWERPOINT_STREAM: &str = "PowerPoint Document";
+const VISIO_STREAM: &str = "VisioDocument";
+const ENCRYPTION_INFO_STREAM: &str = "EncryptionInfo";
+const ENCRYPTED_PACKAGE_STREAM: &str = "EncryptedPackage";
+const MSG_PROPERTIES_STREAM: &str = "__properties_version1.0";
+const THUMBS_CATALOG_STREAM: &str = "Catalog";
+const HWP_HEADER_STREAM: &str = "FileHeader";
+const HWP_SIGNATURE: &[u8] = b"HWP Document File";
+const VBA_PROJECT_STREAM: &str = "PROJECT";
+
+//===========================================================================//
+
+/// The kind of document that a compound file holds.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum DocumentKind {
+    /// A Word 97-2003 (or earlier) document (`.doc` or `.dot`).
+    Word,
+    /// An Excel 97-2003 (or earlier) workbook (`.xls` or `.xlt`).
+    Excel,
+    /// A PowerPoint 97-2003 (or earlier) presentation (`.ppt` or `.pot`).
+    PowerPoint,
+    /// A Visio 2003 (or earlier) drawing (`.vsd`).
+    Visio,
+    /// An Outlook message (`.msg`).
+    OutlookMessage,
+    /// A Windows Installer package or merge module (`.msi` or `.msm`).
+    InstallerPackage,
+    /// A Windows Installer patch (`.msp`).
+    InstallerPatch,
+    /// A Windows Installer transform (`.mst`).
+    InstallerTransform,
+    /// An Office Open XML document (e.g. `.docx`) encrypted with a password,
+    /// which the [`crypto`](../crypto/index.html) module can decrypt.
+    EncryptedOoxml,
+    /// A Windows Explorer thumbnail cache (`Thumbs.db`).
+    ThumbsDb,
+    /// A Hangul Word Processor 5.0 (or later) document (`.hwp`).
+    Hwp,
+    /// A SolidWorks 2014 (or earlier) part, assembly, or drawing.
+    SolidWorks,
+    /// A standalone VBA project, such as the `vbaProject.bin` part of an
+    /// Office Open XML document.
+    VbaProject,
+    /// A compound file of some other (or an unrecognized) kind.
+    Unknown,
+}
+
+impl DocumentKind {
+    /// Returns a short, human-readable name for this kind of document.
+    pub fn name(self) -> &'static str {
+        match self {
+            DocumentKind::Word => "Word document",
+            DocumentKind::Excel => "Excel workbook",
+            DocumentKind::PowerPoint => "PowerPoint presentation",
+            DocumentKind::Visio => "Visio drawing",
+            DocumentKind::OutlookMessage => "Outlook message",
+            DocumentKind::InstallerPackage => "Windows Installer package",
+            DocumentKind::InstallerPatch => "Windows Installer patch",
+            DocumentKind::InstallerTransform => "Windows Installer transform",
+            DocumentKind::EncryptedOoxml => "Encrypted Office Open XML",
+            DocumentKind::ThumbsDb => "Thumbnail cache",
+            DocumentKind::Hwp => "Hangul Word Processor document",
+            DocumentKind::SolidWorks => "SolidWorks document",
+            DocumentKind::VbaProject => "VBA project",
+            DocumentKind::Unknown => "Unknown",
+        }
+    }
+
+    /// Returns the kind of document whose root storage has the given
+    /// CLSID, if it is one that identifies a kind of document.
+    fn from_clsid(clsid: &Uuid) -> Option<DocumentKind> {
+        Some(match *clsid {
+            CLSID_WORD_97 | CLSID_WORD_95 => DocumentKind::Word,
+            CLSID_EXCEL_97 | CLSID_EXCEL_97_CHART | CLSID_EXCEL_95 => {
+                DocumentKind::Excel
+            }
+            CLSID_POWERPOINT_97
+            | CLSID_POWERPOINT_97_SLIDE
+            | CLSID_POWERPOINT_95 => DocumentKind::PowerPoint,
+            CLSID_OUTLOOK_MESSAGE => DocumentKind::OutlookMessage,
+            CLSID_INSTALLER_PACKAGE => DocumentKind::InstallerPackage,
+            CLSID_INSTALLER_PATCH => DocumentKind::InstallerPatch,
+            CLSID_INSTALLER_TRANSFORM => DocumentKind::InstallerTransform,
+            _ => return None,
+        })
+    }
+
+    /// Returns the kind of document that the user type or ProgID in a
+    /// `"\u{1}CompObj"` stream identifies, if any.
+    fn from_user_type(user_type: &str, prog_id: &str) -> Option<DocumentKind> {
+        let kinds = [
+            ("SolidWorks", "SldWorks.", DocumentKind::SolidWorks),
+            ("Microsoft Word", "Word.", DocumentKind::Word),
+            ("Microsoft Excel", "Excel.", DocumentKind::Excel),
+            ("Microsoft Office Excel", "Excel.", DocumentKind::Excel),
+            ("Microsoft PowerPoint", "PowerPoint.", DocumentKind::PowerPoint),
+            ("Microsoft Visio", "Visio.", DocumentKind::Visio),
+        ];
+        kinds
+            .iter()
+            .find(|&&(user_type_prefix, prog_id_prefix, _)| {
+                user_type.starts_with(user_type_prefix)
+                    || prog_id.starts_with(prog_id_prefix)
+            })
+            .map(|&(_, _, kind)| kind)
+    }
+}
+
+impl fmt::Display for DocumentKind {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.name())
+    }
+}
+
+//===========================================================================//
+
+pub(crate) fn detect<F: Read + Seek>(
+    comp: &mut CompoundFile<F>,
+) -> DocumentKind {
+    let root = Path::new("/");
+    let has_stream = |comp: &CompoundFile<F>, name: &str| {
+        comp.is_stream(join_name(root, name))
+    };
+
+    // An encrypted package keeps the CLSID (if any) of the compound file
+    // that it was made from, so check for one first.
+    if has_stream(comp, ENCRYPTION_INFO_STREAM)
+        && has_stream(comp, ENCRYPTED_PACKAGE_STREAM)
+    {
+        return DocumentKind::EncryptedOoxml;
+    }
+    if let Some(kind) = DocumentKind::from_clsid(comp.root_entry().clsid()) {
+        return kind;
+    }
+
+    if has_stream(comp, WORD_STREAM) {
+        return DocumentKind::Word;
+    }
+    if EXCEL_STREAMS.iter().any(|name| has_stream(comp, name)) {
+        return DocumentKind::Excel;
+    }
+    if has_stream(comp, POWERPOINT_STREAM) {
+        return DocumentKind::PowerPoint;
+    }
+    if has_stream(comp, VISIO_STREAM) {
+        return DocumentKind::Visio;
+    }
+    if has_stream(comp, MSG_PROPERTIES_STREAM) {
+        return DocumentKind::OutlookMessage;
+    }
+    if has_stream(comp, &crate::msi::encode_name("!_StringPool")) {
+        return DocumentKind::InstallerPackage;
+    }
+    if has_stream(comp, HWP_HEADER_STREAM) && has_hwp_signature(comp) {
+        return DocumentKind::Hwp;
+    }
+    if has_stream(comp, THUMBS_CATALOG_STREAM) {
+        return DocumentKind::ThumbsDb;
+    }
+    if has_stream(comp, VBA_PROJECT_STREAM)
+        && comp.is_storage(join_name(root, crate::vba::VBA_STORAGE))
+    {
+        return DocumentKind::VbaProject;
+    }
+
+    if comp.is_stream(join_name(root, crate::ole::COMP_OBJ_STREAM)) {
+        if let Ok(comp_obj) = comp.comp_obj(root) {
+            let prog_id = comp_obj.prog_id().unwrap_or("");
+            if let Some(kind) =
+                DocumentKind::from_user_type(comp_obj.user_type(), prog_id)
+            {
+                return kind;
+            }
+        }
+    }
+    DocumentKind::Unknown
+}
+
+fn has_hwp_signature<F: Read + Seek>(comp: &mut CompoundFile<F>) -> bool {
+    let mut signature = [0u8; 17];
+    match comp.open_stream(join_name(Path::new("/"), HWP_HEADER_STREAM)) {
+        Ok(mut stream) => {
+            stream.read_exact(&mut signature).is_ok()
+                && signature[..] == *HWP_SIGNATURE
+        }
+        Err(_) => false,
+    }
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{application_name, DocumentKind, CLSID_WORD_97, KNOWN_CLSIDS};
+    use std::collections::HashSet;
+    use uuid::Uuid;
+
+    #[test]
+    fn known_clsids() {
+        assert_eq!(
+            CLSID_WORD_97.to_string(),
+            "00020906-0000-0000-c000-000000000046"
+        );
+        assert_eq!(
+            application_name(&CLSID_WORD_97),
+            Some("Microsoft Word 97-2003 Document")
+        );
+        assert_eq!(application_name(&Uuid::nil()), None);
+        let clsids: HashSet<Uuid> =
+            KNOWN_CLSIDS.iter().map(|&(clsid, _)| clsid).collect();
+        assert_eq!(clsids.len(), KNOWN_CLSIDS.len());
+    }
+
+    #[test]
+    fn user_types() {
+        assert_eq!(
+            DocumentKind::from_user_type("Microsoft Word-Dokument", ""),
+            Some(DocumentKind::Word)
+        );
+        assert_eq!(
+            DocumentKind::from_user_type("", "SldWorks.Part"),
+            Some(DocumentKind::SolidWorks)
+        );
+        assert_eq!(DocumentKind::from_user_type("Paintbrush", ""), None);
+    }
+}
+
+//===========================================================================//
@@ -48,9 +48,14 @@
 use crate::internal::consts;
 use crate::internal::{
     Allocator, DirEntry, Directory, EntriesOrder, Header, MiniAllocator,
-    ObjType, SectorInit, Sectors, Timestamp, Validation,
+    ObjType, SectorInit, Sectors, Validation,
+};
+pub use crate::internal::{
+    CfbPath, CfbPathBuf, CompoundFileBuilder, Entries, Entry, EntryCursor,
+    EntryId, Extents, PathNames, Stats, StorageStats, Stream, StreamStats,
+    StreamingWriter, Timestamp, TimestampPolicy, Version, Walk, WalkOptions,
+    WalkOrder,
 };
-pub use crate::internal::{Entries, Entry, Stream, Version};
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use fnv::FnvHashSet;
 use std::cell::{Ref, RefCell, RefMut};
@@ -63,12 +68,24 @@ use uuid::Uuid;
 
 #[macro_use]
 mod internal;
+#[cfg(feature = "crypto")]
+pub mod crypto;
+pub mod kind;
+pub mod msg;
+pub mod msi;
+pub mod ole;
+pub mod propset;
+pub mod raw;
+pub mod vba;
 
 //===========================================================================//
 
 /// Opens an existing compound file at the given path in read-only mode.
+///
+/// Any attempt to modify the returned `CompoundFile` (or its streams) will
+/// fail with an error of kind `PermissionDenied`, without changing anything.
 pub fn open<P: AsRef<Path>>(path: P) -> io::Result<CompoundFile<fs::File>> {
-    CompoundFile::open(fs::File::open(path)?)
+    CompoundFile::open_read_only(fs::File::open(path)?)
 }
 
 /// Opens an existing compound file at the given path in read-write mode.
@@ -106,22 +123,61 @@ fn create_with_path(path: &Path) -> io::Result<CompoundFile<fs::File>> {
 /// [`Cursor`](https://doc.rust-lang.org/std/io/struct.Cursor.html)).
 pub struct CompoundFile<F> {
     minialloc: Rc<RefCell<MiniAllocator<F>>>,
+    // The header as originally read (or written) by this crate, for the
+    // fields that are never updated afterwards.
+    raw_header: raw::RawHeader,
 }
 
 impl<F> CompoundFile<F> {
-    fn minialloc(&self) -> Ref<MiniAllocator<F>> {
+    fn minialloc(&self) -> Ref<'_, MiniAllocator<F>> {
         self.minialloc.borrow()
     }
 
-    fn minialloc_mut(&mut self) -> RefMut<MiniAllocator<F>> {
+    fn minialloc_mut(&mut self) -> RefMut<'_, MiniAllocator<F>> {
         self.minialloc.borrow_mut()
     }
 
+    fn check_writable(&self) -> io::Result<()> {
+        self.minialloc().check_writable()
+    }
+
+    /// Returns true if this compound file was opened read-only (see
+    /// [`open_read_only`](#method.open_read_only)), in which case all
+    /// methods that would modify it return an error of kind
+    /// `PermissionDenied`.
+    pub fn is_read_only(&self) -> bool {
+        self.minialloc().is_read_only()
+    }
+
+    /// Returns true if an earlier attempt to modify this compound file failed
+    /// partway through (for example, because of an I/O error from the
+    /// underlying file), leaving it in a possibly-inconsistent state.  Once a
+    /// compound file is poisoned, all further attempts to modify or flush it
+    /// will fail immediately, so that the inconsistency isn't compounded.
+    pub fn is_poisoned(&self) -> bool {
+        self.minialloc().is_poisoned()
+    }
+
     /// Returns the CFB format version used for this compound file.
     pub fn version(&self) -> Version {
         self.minialloc().version()
     }
 
+    /// Returns the policy used for timestamps written into directory entries
+    /// when objects are created or modified.  The default is
+    /// `TimestampPolicy::Now`.
+    pub fn timestamp_policy(&self) -> TimestampPolicy {
+        self.minialloc().timestamp_policy()
+    }
+
+    /// Sets the policy used for timestamps written into directory entries
+    /// when objects are created or modified (including by `touch` and by
+    /// writes to or resizing of a `Stream`).  Existing timestamps are not
+    /// changed until the corresponding object is next modified.
+    pub fn set_timestamp_policy(&mut self, policy: TimestampPolicy) {
+        self.minialloc_mut().set_timestamp_policy(policy);
+    }
+
     fn stream_id_for_name_chain(&self, names: &[&str]) -> Option<u32> {
         self.minialloc().stream_id_for_name_chain(names)
     }
@@ -129,7 +185,11 @@ impl<F> CompoundFile<F> {
     /// Returns information about the root storage object.  This is equivalent
     /// to `self.entry("/").unwrap()` (but always succeeds).
     pub fn root_entry(&self) -> Entry {
-        Entry::new(self.minialloc().root_dir_entry(), PathBuf::from("/"))
+        Entry::new(
+            &self.minialloc(),
+            consts::ROOT_STREAM_ID,
+            PathBuf::from("/"),
+        )
     }
 
     /// Given a path within the compound file, get information about that
@@ -145,18 +205,154 @@ impl<F> CompoundFile<F> {
             Some(stream_id) => stream_id,
             None => not_found!("No such object: {:?}", path),
         };
-        Ok(Entry::new(self.minialloc().dir_entry(stream_id), path))
+        Ok(Entry::new(&self.minialloc(), stream_id, path))
+    }
+
+    /// Gets information about the stream or storage object with the given
+    /// name directly within the storage at the provided path.  The name is
+    /// given as raw UTF-16 code units, so unlike with
+    /// [`entry`](#method.entry), this can find objects whose names aren't
+    /// valid UTF-16 (for example, names containing unpaired surrogates).
+    pub fn entry_utf16<P: AsRef<Path>>(
+        &self,
+        parent: P,
+        name: &[u16],
+    ) -> io::Result<Entry> {
+        let (stream_id, path) = self.child_id_utf16(parent.as_ref(), name)?;
+        let stream_id = match stream_id {
+            Some(stream_id) => stream_id,
+            None => not_found!("No such object: {:?}", path),
+        };
+        Ok(Entry::new(&self.minialloc(), stream_id, path))
+    }
+
+    /// Looks up the object with the given raw UTF-16 name directly within
+    /// the storage at the given path.  Returns the object's stream ID (if it
+    /// exists) along with its path, for use in error messages.
+    fn child_id_utf16(
+        &self,
+        parent: &Path,
+        name: &[u16],
+    ) -> io::Result<(Option<u32>, PathBuf)> {
+        let (parent_id, parent_path) = self.storage_id_for_path(parent)?;
+        let path = internal::path::join_name(
+            &parent_path,
+            &String::from_utf16_lossy(name),
+        );
+        Ok((self.minialloc().child_id(parent_id, name), path))
+    }
+
+    /// Returns the stream ID of the storage at the given path, along with
+    /// its normalized path, or an error if there is no such storage.
+    fn storage_id_for_path(&self, path: &Path) -> io::Result<(u32, PathBuf)> {
+        let names = internal::path::name_chain_from_path(path)?;
+        let path = internal::path::path_from_name_chain(&names);
+        let stream_id = match self.stream_id_for_name_chain(&names) {
+            Some(stream_id) => stream_id,
+            None => not_found!("No such storage: {:?}", path),
+        };
+        if self.minialloc().dir_entry(stream_id).obj_type == ObjType::Stream {
+            invalid_input!("Not a storage: {:?}", path);
+        }
+        Ok((stream_id, path))
+    }
+
+    /// Returns the path of the object with the given ID, or an error if that
+    /// object has been removed (even if another object has since taken its
+    /// place in the directory).
+    fn path_for_id(&self, id: EntryId) -> io::Result<PathBuf> {
+        let minialloc = self.minialloc();
+        let stream_id = id.stream_id();
+        match minialloc.name_chain_for_stream_id(stream_id) {
+            Some(names) if minialloc.serial(stream_id) == id.serial() => {
+                Ok(internal::path::path_from_name_chain(&names))
+            }
+            _ => not_found!("No such object: {:?}", id),
+        }
+    }
+
+    /// Gets information about the stream or storage object with the given
+    /// ID.  Returns an error if that object has since been removed.
+    pub fn entry_by_id(&self, id: EntryId) -> io::Result<Entry> {
+        let path = self.path_for_id(id)?;
+        Ok(Entry::new(&self.minialloc(), id.stream_id(), path))
+    }
+
+    /// Returns a cursor for navigating the storage tree, starting at the
+    /// root storage.  Unlike iterating over entries, the cursor does not
+    /// borrow the compound file.
+    pub fn root_cursor(&self) -> EntryCursor<F> {
+        EntryCursor::new(&self.minialloc, consts::ROOT_STREAM_ID)
+    }
+
+    /// Returns a cursor for navigating the storage tree, starting at the
+    /// stream or storage object at the given path.
+    pub fn cursor<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> io::Result<EntryCursor<F>> {
+        self.cursor_with_path(path.as_ref())
+    }
+
+    fn cursor_with_path(&self, path: &Path) -> io::Result<EntryCursor<F>> {
+        let names = internal::path::name_chain_from_path(path)?;
+        let stream_id = match self.stream_id_for_name_chain(&names) {
+            Some(stream_id) => stream_id,
+            None => not_found!(
+                "No such object: {:?}",
+                internal::path::path_from_name_chain(&names)
+            ),
+        };
+        Ok(EntryCursor::new(&self.minialloc, stream_id))
+    }
+
+    /// Returns where the data for the stream at the given path physically
+    /// lives within the underlying file.  If called on the root storage,
+    /// returns the extents of the mini stream itself.
+    pub fn extents<P: AsRef<Path>>(&self, path: P) -> io::Result<Extents> {
+        self.extents_with_path(path.as_ref())
+    }
+
+    fn extents_with_path(&self, path: &Path) -> io::Result<Extents> {
+        let names = internal::path::name_chain_from_path(path)?;
+        let path = internal::path::path_from_name_chain(&names);
+        let stream_id = match self.stream_id_for_name_chain(&names) {
+            Some(stream_id) => stream_id,
+            None => not_found!("No such stream: {:?}", path),
+        };
+        let minialloc = self.minialloc();
+        if minialloc.dir_entry(stream_id).obj_type == ObjType::Storage {
+            invalid_input!("Not a stream: {:?}", path);
+        }
+        minialloc.extents(stream_id)
+    }
+
+    /// Computes space-usage statistics for the compound file: how many
+    /// sectors are used and free (and what they are used for), how
+    /// fragmented each stream is, and how much space each storage takes up
+    /// (including everything nested within it).
+    pub fn stats(&self) -> io::Result<Stats> {
+        self.minialloc().stats()
+    }
+
+    /// Returns a read-only snapshot of the low-level structures of this
+    /// compound file (header, DIFAT, FAT, MiniFAT, and directory entries).
+    /// The snapshot reflects the current in-memory state, including any
+    /// changes that haven't been flushed yet.  See the [`raw`](raw/index.html)
+    /// module for details.
+    pub fn raw(&self) -> io::Result<raw::RawView> {
+        raw::RawView::new(&self.minialloc(), &self.raw_header)
     }
 
     /// Returns an iterator over the entries within the root storage object.
     /// This is equivalent to `self.read_storage("/").unwrap()` (but always
     /// succeeds).
-    pub fn read_root_storage(&self) -> Entries<F> {
+    pub fn read_root_storage(&self) -> Entries<'_, F> {
         let start = self.minialloc().root_dir_entry().child;
         Entries::new(
             EntriesOrder::Nonrecursive,
             &self.minialloc,
-            internal::path::path_from_name_chain(&[]),
+            internal::path::path_from_name_chain::<&str>(&[]),
             start,
         )
     }
@@ -165,11 +361,14 @@ impl<F> CompoundFile<F> {
     pub fn read_storage<P: AsRef<Path>>(
         &self,
         path: P,
-    ) -> io::Result<Entries<F>> {
+    ) -> io::Result<Entries<'_, F>> {
         self.read_storage_with_path(path.as_ref())
     }
 
-    fn read_storage_with_path(&self, path: &Path) -> io::Result<Entries<F>> {
+    fn read_storage_with_path(
+        &self,
+        path: &Path,
+    ) -> io::Result<Entries<'_, F>> {
         let names = internal::path::name_chain_from_path(path)?;
         let path = internal::path::path_from_name_chain(&names);
         let stream_id = match self.stream_id_for_name_chain(&names) {
@@ -196,15 +395,38 @@ impl<F> CompoundFile<F> {
         ))
     }
 
+    /// Returns an iterator over the entries within the storage object with
+    /// the given ID.
+    pub fn read_storage_by_id(
+        &self,
+        id: EntryId,
+    ) -> io::Result<Entries<'_, F>> {
+        let path = self.path_for_id(id)?;
+        let start = {
+            let minialloc = self.minialloc();
+            let dir_entry = minialloc.dir_entry(id.stream_id());
+            if dir_entry.obj_type == ObjType::Stream {
+                invalid_input!("Not a storage: {:?}", path);
+            }
+            dir_entry.child
+        };
+        Ok(Entries::new(
+            EntriesOrder::Nonrecursive,
+            &self.minialloc,
+            path,
+            start,
+        ))
+    }
+
     /// Returns an iterator over all entries within the compound file, starting
     /// from and including the root entry.  The iterator walks the storage tree
     /// in a preorder traversal.  This is equivalent to
     /// `self.walk_storage("/").unwrap()` (but always succeeds).
-    pub fn walk(&self) -> Entries<F> {
+    pub fn walk(&self) -> Entries<'_, F> {
         Entries::new(
             EntriesOrder::Preorder,
             &self.minialloc,
-            internal::path::path_from_name_chain(&[]),
+            internal::path::path_from_name_chain::<&str>(&[]),
             consts::ROOT_STREAM_ID,
         )
     }
@@ -215,11 +437,14 @@ impl<F> CompoundFile<F> {
     pub fn walk_storage<P: AsRef<Path>>(
         &self,
         path: P,
-    ) -> io::Result<Entries<F>> {
+    ) -> io::Result<Entries<'_, F>> {
         self.walk_storage_with_path(path.as_ref())
     }
 
-    fn walk_storage_with_path(&self, path: &Path) -> io::Result<Entries<F>> {
+    fn walk_storage_with_path(
+        &self,
+        path: &Path,
+    ) -> io::Result<Entries<'_, F>> {
         let mut names = internal::path::name_chain_from_path(path)?;
         let stream_id = match self.stream_id_for_name_chain(&names) {
             Some(stream_id) => stream_id,
@@ -240,6 +465,27 @@ impl<F> CompoundFile<F> {
         ))
     }
 
+    /// Returns an iterator over entries under a storage subtree (including
+    /// the given path itself), with control over the traversal order, how
+    /// deep to descend, and which entries and subtrees to visit.
+    pub fn walk_storage_with<'a, P: AsRef<Path>>(
+        &'a self,
+        path: P,
+        options: WalkOptions<'a>,
+    ) -> io::Result<Walk<'a, F>> {
+        let names = internal::path::name_chain_from_path(path.as_ref())?;
+        let stream_id = match self.stream_id_for_name_chain(&names) {
+            Some(stream_id) => stream_id,
+            None => {
+                not_found!(
+                    "No such object: {:?}",
+                    internal::path::path_from_name_chain(&names)
+                );
+            }
+        };
+        Walk::new(&self.minialloc, &names, stream_id, options)
+    }
+
     /// Returns true if there is an existing stream or storage at the given
     /// path, or false if there is nothing at that path.
     pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
@@ -317,6 +563,37 @@ impl<F: Seek> CompoundFile<F> {
         }
         Ok(Stream::new(&self.minialloc, stream_id))
     }
+
+    /// Opens the existing stream with the given name directly within the
+    /// storage at the provided path.  The name is given as raw UTF-16 code
+    /// units, so unlike with [`open_stream`](#method.open_stream), this can
+    /// open streams whose names aren't valid UTF-16.
+    pub fn open_stream_utf16<P: AsRef<Path>>(
+        &mut self,
+        parent: P,
+        name: &[u16],
+    ) -> io::Result<Stream<F>> {
+        let (stream_id, path) = self.child_id_utf16(parent.as_ref(), name)?;
+        let stream_id = match stream_id {
+            Some(stream_id) => stream_id,
+            None => not_found!("No such stream: {:?}", path),
+        };
+        if self.minialloc().dir_entry(stream_id).obj_type != ObjType::Stream {
+            invalid_input!("Not a stream: {:?}", path);
+        }
+        Ok(Stream::new(&self.minialloc, stream_id))
+    }
+
+    /// Opens the existing stream with the given ID for reading and/or writing
+    /// (depending on what the underlying file supports).
+    pub fn open_stream_by_id(&mut self, id: EntryId) -> io::Result<Stream<F>> {
+        let path = self.path_for_id(id)?;
+        let stream_id = id.stream_id();
+        if self.minialloc().dir_entry(stream_id).obj_type != ObjType::Stream {
+            invalid_input!("Not a stream: {:?}", path);
+        }
+        Ok(Stream::new(&self.minialloc, stream_id))
+    }
 }
 
 impl<F: Read + Seek> CompoundFile<F> {
@@ -336,6 +613,17 @@ impl<F: Read + Seek> CompoundFile<F> {
         CompoundFile::open_internal(inner, Validation::Strict)
     }
 
+    /// Like `open()`, but the returned `CompoundFile` is read-only, even if
+    /// the underlying reader also supports the `Write` trait.  Any attempt to
+    /// modify the compound file (or its streams) will fail up front with an
+    /// error of kind `PermissionDenied`, before anything is changed either
+    /// in memory or in the underlying file.
+    pub fn open_read_only(inner: F) -> io::Result<CompoundFile<F>> {
+        let comp = CompoundFile::open_internal(inner, Validation::Permissive)?;
+        comp.minialloc.borrow_mut().set_read_only();
+        Ok(comp)
+    }
+
     fn open_internal(
         mut inner: F,
         validation: Validation,
@@ -348,6 +636,8 @@ impl<F: Read + Seek> CompoundFile<F> {
             );
         }
         inner.seek(SeekFrom::Start(0))?;
+        let raw_header = raw::RawHeader::read_from(&mut inner)?;
+        inner.seek(SeekFrom::Start(0))?;
 
         let header = Header::read_from(&mut inner)?;
         let sector_len = header.version.sector_len();
@@ -549,7 +839,153 @@ impl<F: Read + Seek> CompoundFile<F> {
             header.first_minifat_sector,
         )?;
 
-        Ok(CompoundFile { minialloc: Rc::new(RefCell::new(minialloc)) })
+        Ok(CompoundFile {
+            minialloc: Rc::new(RefCell::new(minialloc)),
+            raw_header,
+        })
+    }
+
+    /// Reads the entire contents of the stream at the given path.  This
+    /// doesn't preallocate based on the length recorded in the directory
+    /// entry, since a malformed file can claim a length far larger than the
+    /// data it actually holds.
+    pub(crate) fn read_stream_to_vec<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.open_stream(path)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Reads and parses the property set stream at the given path.  See the
+    /// [`propset`](propset/index.html) module for details.
+    pub fn property_set<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> io::Result<propset::PropertySet> {
+        propset::PropertySet::read(self.open_stream(path)?)
+    }
+
+    /// Reads and parses the `"\u{5}SummaryInformation"` stream (which holds
+    /// the document's title, author, creation time, and so on), returning
+    /// `None` if the compound file has no such stream.
+    pub fn summary_information(
+        &mut self,
+    ) -> io::Result<Option<propset::SummaryInformation>> {
+        if !self.is_stream(propset::SUMMARY_INFORMATION_STREAM) {
+            return Ok(None);
+        }
+        let set = self.property_set(propset::SUMMARY_INFORMATION_STREAM)?;
+        propset::SummaryInformation::from_property_set(set).map(Some)
+    }
+
+    /// Reads and parses the `"\u{5}DocumentSummaryInformation"` stream
+    /// (which holds the document's company, manager, user-defined
+    /// properties, and so on), returning `None` if the compound file has no
+    /// such stream.
+    pub fn document_summary_information(
+        &mut self,
+    ) -> io::Result<Option<propset::DocumentSummaryInformation>> {
+        if !self.is_stream(propset::DOC_SUMMARY_INFORMATION_STREAM) {
+            return Ok(None);
+        }
+        let set =
+            self.property_set(propset::DOC_SUMMARY_INFORMATION_STREAM)?;
+        propset::DocumentSummaryInformation::from_property_set(set).map(Some)
+    }
+
+    /// Reads and parses the `"\u{1}CompObj"` stream in the storage at the
+    /// provided path.  See the [`ole`](ole/index.html) module for details.
+    pub fn comp_obj<P: AsRef<Path>>(
+        &mut self,
+        storage: P,
+    ) -> io::Result<ole::CompObj> {
+        let path =
+            internal::path::join_name(storage.as_ref(), ole::COMP_OBJ_STREAM);
+        ole::CompObj::read(self.open_stream(path)?)
+    }
+
+    /// Reads and parses the `"\u{1}Ole"` stream in the storage at the
+    /// provided path.
+    pub fn ole_stream<P: AsRef<Path>>(
+        &mut self,
+        storage: P,
+    ) -> io::Result<ole::OleStream> {
+        let path =
+            internal::path::join_name(storage.as_ref(), ole::OLE_STREAM);
+        ole::OleStream::read(self.open_stream(path)?)
+    }
+
+    /// Reads and parses the `"\u{1}Ole10Native"` stream in the storage at
+    /// the provided path, which must hold a "Package" object (an embedded
+    /// file).
+    pub fn ole10_native<P: AsRef<Path>>(
+        &mut self,
+        storage: P,
+    ) -> io::Result<ole::Ole10Native> {
+        let path = internal::path::join_name(
+            storage.as_ref(),
+            ole::OLE10_NATIVE_STREAM,
+        );
+        ole::Ole10Native::read(self.open_stream(path)?)
+    }
+
+    /// Guesses what kind of document (Word document, Outlook message, and so
+    /// on) the compound file holds, from its root CLSID and the streams
+    /// within it.  See the [`kind`](kind/index.html) module for details.
+    pub fn detect_kind(&mut self) -> kind::DocumentKind {
+        kind::detect(self)
+    }
+
+    /// Reads an Outlook message from a `.msg` file, including its
+    /// recipients and attachments.  See the [`msg`](msg/index.html) module
+    /// for details.
+    pub fn outlook_message(&mut self) -> io::Result<msg::Message> {
+        msg::Message::read(self)
+    }
+
+    /// Reads the string pool and table schemas of a Windows Installer (MSI)
+    /// database.  Returns an error of kind `NotFound` if the compound file
+    /// has no `_StringPool` stream.  See the [`msi`](msi/index.html) module
+    /// for details.
+    pub fn msi_database(&mut self) -> io::Result<msi::Database> {
+        msi::Database::read(self)
+    }
+
+    /// Finds and reads the VBA project of a Word document, Excel workbook,
+    /// or `vbaProject.bin` file, including the source code of each module,
+    /// returning `None` if the compound file has no VBA project.  See the
+    /// [`vba`](vba/index.html) module for details.
+    pub fn vba_project(&mut self) -> io::Result<Option<vba::VbaProject>> {
+        for &root in vba::PROJECT_ROOTS {
+            let vba_storage =
+                internal::path::join_name(Path::new(root), vba::VBA_STORAGE);
+            let dir = internal::path::join_name(&vba_storage, vba::DIR_STREAM);
+            if self.is_stream(dir) {
+                return self.vba_project_at(root).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads the VBA project whose project root is the storage at the
+    /// provided path, including the source code of each module.
+    pub fn vba_project_at<P: AsRef<Path>>(
+        &mut self,
+        storage: P,
+    ) -> io::Result<vba::VbaProject> {
+        let vba_storage =
+            internal::path::join_name(storage.as_ref(), vba::VBA_STORAGE);
+        let dir = internal::path::join_name(&vba_storage, vba::DIR_STREAM);
+        let mut project = vba::VbaProject::read_dir(self.open_stream(dir)?)?;
+        let code_page = project.code_page();
+        for module in project.modules_mut() {
+            let path =
+                internal::path::join_name(&vba_storage, module.stream_name());
+            module.read_source(self.open_stream(path)?, code_page)?;
+        }
+        Ok(project)
     }
 }
 
@@ -579,7 +1015,11 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
                 consts::NUM_DIFAT_ENTRIES_IN_HEADER],
         };
         header.initial_difat_entries[0] = 0;
-        header.write_to(&mut inner)?;
+        let mut header_data = Vec::<u8>::with_capacity(consts::HEADER_LEN);
+        header.write_to(&mut header_data)?;
+        let raw_header =
+            raw::RawHeader::read_from(&mut header_data.as_slice())?;
+        inner.write_all(&header_data)?;
 
         // Pad the header with zeroes so it's the length of a sector.
         let sector_len = version.sector_len();
@@ -620,7 +1060,10 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
         let minialloc =
             MiniAllocator::new(directory, vec![], consts::END_OF_CHAIN)
                 .expect("minialloc");
-        Ok(CompoundFile { minialloc: Rc::new(RefCell::new(minialloc)) })
+        Ok(CompoundFile {
+            minialloc: Rc::new(RefCell::new(minialloc)),
+            raw_header,
+        })
     }
 
     /// Creates a new, empty storage object (i.e. "directory") at the provided
@@ -629,6 +1072,7 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
         &mut self,
         path: P,
     ) -> io::Result<()> {
+        self.check_writable()?;
         self.create_storage_with_path(path.as_ref())
     }
 
@@ -662,20 +1106,71 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
                 not_found!("Parent storage doesn't exist");
             }
         };
+        let name = internal::path::validate_name(name)?;
         self.minialloc_mut().insert_dir_entry(
             parent_id,
-            name,
+            &name,
             ObjType::Storage,
         )?;
         Ok(())
     }
 
+    /// Creates a new, empty storage object with the given name directly
+    /// within the storage at the provided path, which must already exist.
+    /// The name is given as raw UTF-16 code units, which need not be valid
+    /// UTF-16.
+    pub fn create_storage_utf16<P: AsRef<Path>>(
+        &mut self,
+        parent: P,
+        name: &[u16],
+    ) -> io::Result<()> {
+        self.check_writable()?;
+        self.create_with_utf16_name(parent.as_ref(), name, ObjType::Storage)?;
+        Ok(())
+    }
+
+    /// Creates a new directory entry of the given type with the given raw
+    /// UTF-16 name under the storage at the given path, and returns its
+    /// stream ID.  If `obj_type` is `Stream` and a stream with that name
+    /// already exists, returns that stream's ID instead.
+    fn create_with_utf16_name(
+        &mut self,
+        parent: &Path,
+        name: &[u16],
+        obj_type: ObjType,
+    ) -> io::Result<u32> {
+        let (parent_id, parent_path) = self.storage_id_for_path(parent)?;
+        let existing_id = self.minialloc().child_id(parent_id, name);
+        if let Some(stream_id) = existing_id {
+            let existing_type = self.minialloc().dir_entry(stream_id).obj_type;
+            if obj_type == ObjType::Stream && existing_type == ObjType::Stream
+            {
+                return Ok(stream_id);
+            }
+            already_exists!(
+                "Cannot create {} at {:?} because a {} already exists there",
+                if obj_type == ObjType::Stream { "stream" } else { "storage" },
+                internal::path::join_name(
+                    &parent_path,
+                    &String::from_utf16_lossy(name)
+                ),
+                if existing_type == ObjType::Stream {
+                    "stream"
+                } else {
+                    "storage"
+                }
+            );
+        }
+        self.minialloc_mut().insert_dir_entry(parent_id, name, obj_type)
+    }
+
     /// Recursively creates a storage and all of its parent storages if they
     /// are missing.
     pub fn create_storage_all<P: AsRef<Path>>(
         &mut self,
         path: P,
     ) -> io::Result<()> {
+        self.check_writable()?;
         self.create_storage_all_with_path(path.as_ref())
     }
 
@@ -698,16 +1193,44 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
         &mut self,
         path: P,
     ) -> io::Result<()> {
+        self.check_writable()?;
         self.remove_storage_with_path(path.as_ref())
     }
 
     fn remove_storage_with_path(&mut self, path: &Path) -> io::Result<()> {
-        let mut names = internal::path::name_chain_from_path(path)?;
+        let names = internal::path::name_chain_from_path(path)?;
         let stream_id = match self.stream_id_for_name_chain(&names) {
-            Some(parent_id) => parent_id,
+            Some(stream_id) => stream_id,
             None => not_found!("No such storage: {:?}", path),
         };
-        {
+        self.remove_storage_with_id(stream_id, path)
+    }
+
+    /// Removes the storage object with the given name directly within the
+    /// storage at the provided path.  The name is given as raw UTF-16 code
+    /// units, which need not be valid UTF-16.  The storage object must exist
+    /// and have no children.
+    pub fn remove_storage_utf16<P: AsRef<Path>>(
+        &mut self,
+        parent: P,
+        name: &[u16],
+    ) -> io::Result<()> {
+        self.check_writable()?;
+        let (stream_id, path) = self.child_id_utf16(parent.as_ref(), name)?;
+        match stream_id {
+            Some(stream_id) => self.remove_storage_with_id(stream_id, &path),
+            None => not_found!("No such storage: {:?}", path),
+        }
+    }
+
+    /// Removes the empty storage object with the given stream ID.  The path
+    /// is only used for error messages.
+    fn remove_storage_with_id(
+        &mut self,
+        stream_id: u32,
+        path: &Path,
+    ) -> io::Result<()> {
+        let (parent_id, name) = {
             let minialloc = self.minialloc();
             let dir_entry = minialloc.dir_entry(stream_id);
             if dir_entry.obj_type == ObjType::Root {
@@ -720,12 +1243,9 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
             if dir_entry.child != consts::NO_STREAM {
                 invalid_input!("Storage is not empty: {:?}", path);
             }
-        }
-        debug_assert!(!names.is_empty());
-        let name = names.pop().unwrap();
-        let parent_id = self.stream_id_for_name_chain(&names).unwrap();
-        self.minialloc_mut().remove_dir_entry(parent_id, name)?;
-        Ok(())
+            (minialloc.parent_id(stream_id), dir_entry.name.clone())
+        };
+        self.minialloc_mut().remove_dir_entry(parent_id, &name)
     }
 
     /// Recursively removes a storage and all of its children.  If called on
@@ -735,16 +1255,22 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
         &mut self,
         path: P,
     ) -> io::Result<()> {
+        self.check_writable()?;
         self.remove_storage_all_with_path(path.as_ref())
     }
 
     fn remove_storage_all_with_path(&mut self, path: &Path) -> io::Result<()> {
-        let mut stack = self.walk_storage(path)?.collect::<Vec<Entry>>();
-        while let Some(entry) = stack.pop() {
+        let options = WalkOptions::new().order(WalkOrder::Postorder);
+        let entries: Vec<Entry> =
+            self.walk_storage_with(path, options)?.collect();
+        // Remove each object by its stream ID rather than by its path, since
+        // the path is lossy for names that aren't valid UTF-16.
+        for entry in entries {
+            let stream_id = entry.id().stream_id();
             if entry.is_stream() {
-                self.remove_stream_with_path(entry.path())?;
+                self.remove_stream_with_id(stream_id, entry.path())?;
             } else if !entry.is_root() {
-                self.remove_storage_with_path(entry.path())?;
+                self.remove_storage_with_id(stream_id, entry.path())?;
             }
         }
         Ok(())
@@ -758,6 +1284,7 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
         path: P,
         clsid: Uuid,
     ) -> io::Result<()> {
+        self.check_writable()?;
         self.set_storage_clsid_with_path(path.as_ref(), clsid)
     }
 
@@ -793,6 +1320,7 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
         &mut self,
         path: P,
     ) -> io::Result<Stream<F>> {
+        self.check_writable()?;
         self.create_stream_with_path(path.as_ref(), true)
     }
 
@@ -803,6 +1331,7 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
         &mut self,
         path: P,
     ) -> io::Result<Stream<F>> {
+        self.check_writable()?;
         self.create_stream_with_path(path.as_ref(), false)
     }
 
@@ -843,28 +1372,146 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
                 not_found!("Parent storage doesn't exist");
             }
         };
+        let name = internal::path::validate_name(name)?;
         let new_stream_id = self.minialloc_mut().insert_dir_entry(
             parent_id,
-            name,
+            &name,
             ObjType::Stream,
         )?;
         Ok(Stream::new(&self.minialloc, new_stream_id))
     }
 
+    /// Creates and returns a new, empty stream object with the given name
+    /// directly within the storage at the provided path, which must already
+    /// exist.  The name is given as raw UTF-16 code units, which need not be
+    /// valid UTF-16.  If a stream with that name already exists, it will be
+    /// replaced by the new stream.
+    pub fn create_stream_utf16<P: AsRef<Path>>(
+        &mut self,
+        parent: P,
+        name: &[u16],
+    ) -> io::Result<Stream<F>> {
+        self.check_writable()?;
+        let stream_id = self.create_with_utf16_name(
+            parent.as_ref(),
+            name,
+            ObjType::Stream,
+        )?;
+        let mut stream = Stream::new(&self.minialloc, stream_id);
+        stream.set_len(0)?;
+        Ok(stream)
+    }
+
+    /// Writes a property set stream directly within the storage at the
+    /// provided path, which must already exist.  The stream is named after
+    /// the format ID of the property set's first section (see
+    /// [`propset::fmtid_to_stream_name`](propset/fn.fmtid_to_stream_name.html)),
+    /// and replaces any existing stream with that name.
+    pub fn write_property_set<P: AsRef<Path>>(
+        &mut self,
+        storage: P,
+        builder: &propset::PropertySetBuilder,
+    ) -> io::Result<()> {
+        self.check_writable()?;
+        let data = builder.build()?;
+        let name = builder.stream_name().unwrap();
+        let path = internal::path::join_name(storage.as_ref(), &name);
+        let mut stream = self.create_stream(path)?;
+        stream.write_all(&data)?;
+        stream.flush()
+    }
+
+    /// Writes a `"\u{1}CompObj"` stream within the storage at the provided
+    /// path, which must already exist, replacing any existing one.
+    pub fn write_comp_obj<P: AsRef<Path>>(
+        &mut self,
+        storage: P,
+        comp_obj: &ole::CompObj,
+    ) -> io::Result<()> {
+        let data = comp_obj.to_bytes();
+        self.write_system_stream(storage.as_ref(), ole::COMP_OBJ_STREAM, &data)
+    }
+
+    /// Writes a `"\u{1}Ole"` stream within the storage at the provided path,
+    /// which must already exist, replacing any existing one.
+    pub fn write_ole_stream<P: AsRef<Path>>(
+        &mut self,
+        storage: P,
+        ole_stream: &ole::OleStream,
+    ) -> io::Result<()> {
+        let data = ole_stream.to_bytes();
+        self.write_system_stream(storage.as_ref(), ole::OLE_STREAM, &data)
+    }
+
+    /// Writes a `"\u{1}Ole10Native"` stream within the storage at the
+    /// provided path, which must already exist, replacing any existing one.
+    pub fn write_ole10_native<P: AsRef<Path>>(
+        &mut self,
+        storage: P,
+        native: &ole::Ole10Native,
+    ) -> io::Result<()> {
+        let data = native.to_bytes();
+        self.write_system_stream(
+            storage.as_ref(),
+            ole::OLE10_NATIVE_STREAM,
+            &data,
+        )
+    }
+
+    fn write_system_stream(
+        &mut self,
+        storage: &Path,
+        name: &str,
+        data: &[u8],
+    ) -> io::Result<()> {
+        self.check_writable()?;
+        let path = internal::path::join_name(storage, name);
+        let mut stream = self.create_stream(path)?;
+        stream.write_all(data)?;
+        stream.flush()
+    }
+
     /// Removes the stream object at the provided path.
     pub fn remove_stream<P: AsRef<Path>>(
         &mut self,
         path: P,
     ) -> io::Result<()> {
+        self.check_writable()?;
         self.remove_stream_with_path(path.as_ref())
     }
 
     fn remove_stream_with_path(&mut self, path: &Path) -> io::Result<()> {
-        let mut names = internal::path::name_chain_from_path(path)?;
+        let names = internal::path::name_chain_from_path(path)?;
         let stream_id = match self.stream_id_for_name_chain(&names) {
-            Some(parent_id) => parent_id,
+            Some(stream_id) => stream_id,
             None => not_found!("No such stream: {:?}", path),
         };
+        self.remove_stream_with_id(stream_id, path)
+    }
+
+    /// Removes the stream object with the given name directly within the
+    /// storage at the provided path.  The name is given as raw UTF-16 code
+    /// units, which need not be valid UTF-16.
+    pub fn remove_stream_utf16<P: AsRef<Path>>(
+        &mut self,
+        parent: P,
+        name: &[u16],
+    ) -> io::Result<()> {
+        self.check_writable()?;
+        let (stream_id, path) = self.child_id_utf16(parent.as_ref(), name)?;
+        match stream_id {
+            Some(stream_id) => self.remove_stream_with_id(stream_id, &path),
+            None => not_found!("No such stream: {:?}", path),
+        }
+    }
+
+    /// Removes the stream object with the given stream ID.  The path is only
+    /// used for error messages.
+    fn remove_stream_with_id(
+        &mut self,
+        stream_id: u32,
+        path: &Path,
+    ) -> io::Result<()> {
         let (start_sector_id, is_in_mini_stream) = {
             let minialloc = self.minialloc();
             let dir_entry = minialloc.dir_entry(stream_id);
@@ -882,11 +1529,12 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
         } else {
             self.minialloc_mut().free_chain(start_sector_id)?;
         }
-        debug_assert!(!names.is_empty());
-        let name = names.pop().unwrap();
-        let parent_id = self.stream_id_for_name_chain(&names).unwrap();
-        self.minialloc_mut().remove_dir_entry(parent_id, name)?;
-        Ok(())
+        let (parent_id, name) = {
+            let minialloc = self.minialloc();
+            let name = minialloc.dir_entry(stream_id).name.clone();
+            (minialloc.parent_id(stream_id), name)
+        };
+        self.minialloc_mut().remove_dir_entry(parent_id, &name)
     }
 
     /// Sets the user-defined bitflags for the object at the provided path.
@@ -897,6 +1545,7 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
         path: P,
         bits: u32,
     ) -> io::Result<()> {
+        self.check_writable()?;
         self.set_state_bits_with_path(path.as_ref(), bits)
     }
 
@@ -918,9 +1567,11 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
         })
     }
 
-    /// Sets the modified time for the object at the given path to now.  Has no
+    /// Sets the modified time for the object at the given path to now (or
+    /// to whatever time the current `TimestampPolicy` dictates).  Has no
     /// effect when called on the root storage.
     pub fn touch<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.check_writable()?;
         self.touch_with_path(path.as_ref())
     }
 
@@ -937,13 +1588,79 @@ impl<F: Read + Write + Seek> CompoundFile<F> {
                 minialloc.dir_entry(stream_id).obj_type,
                 ObjType::Root
             );
+            let policy = minialloc.timestamp_policy();
             minialloc.with_dir_entry_mut(stream_id, |dir_entry| {
-                dir_entry.modified_time = Timestamp::now();
+                dir_entry.modified_time =
+                    policy.modification_timestamp(dir_entry.modified_time);
             })?;
         }
         Ok(())
     }
 
+    /// Sets the creation time for the object at the given path.  The time can
+    /// be given either as a `SystemTime` or as a raw `Timestamp` (use
+    /// `Timestamp::zero()` to mark the creation time as unset).  Unlike other
+    /// modifications, this ignores the current `TimestampPolicy`.
+    ///
+    /// The MS-CFB spec requires the root storage's creation time to be zero,
+    /// so calling this on the root storage is an error.
+    pub fn set_created<P, T>(&mut self, path: P, time: T) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        T: Into<Timestamp>,
+    {
+        self.check_writable()?;
+        self.set_created_with_path(path.as_ref(), time.into())
+    }
+
+    fn set_created_with_path(
+        &mut self,
+        path: &Path,
+        timestamp: Timestamp,
+    ) -> io::Result<()> {
+        let names = internal::path::name_chain_from_path(path)?;
+        let path = internal::path::path_from_name_chain(&names);
+        let stream_id = match self.stream_id_for_name_chain(&names) {
+            Some(stream_id) => stream_id,
+            None => not_found!("No such object: {:?}", path),
+        };
+        if stream_id == consts::ROOT_STREAM_ID {
+            invalid_input!("Cannot set the creation time of the root storage");
+        }
+        self.minialloc_mut().with_dir_entry_mut(stream_id, |dir_entry| {
+            dir_entry.creation_time = timestamp;
+        })
+    }
+
+    /// Sets the modified time for the object at the given path.  The time can
+    /// be given either as a `SystemTime` or as a raw `Timestamp` (use
+    /// `Timestamp::zero()` to mark the modified time as unset).  Unlike other
+    /// modifications, this ignores the current `TimestampPolicy`.
+    pub fn set_modified<P, T>(&mut self, path: P, time: T) -> io::Result<()>
+    where
+        P: AsRef<Path>,
+        T: Into<Timestamp>,
+    {
+        self.check_writable()?;
+        self.set_modified_with_path(path.as_ref(), time.into())
+    }
+
+    fn set_modified_with_path(
+        &mut self,
+        path: &Path,
+        timestamp: Timestamp,
+    ) -> io::Result<()> {
+        let names = internal::path::name_chain_from_path(path)?;
+        let path = internal::path::path_from_name_chain(&names);
+        let stream_id = match self.stream_id_for_name_chain(&names) {
+            Some(stream_id) => stream_id,
+            None => not_found!("No such object: {:?}", path),
+        };
+        self.minialloc_mut().with_dir_entry_mut(stream_id, |dir_entry| {
+            dir_entry.modified_time = timestamp;
+        })
+    }
+
     /// Flushes all changes to the underlying file.
     pub fn flush(&mut self) -> io::Result<()> {
         self.minialloc_mut().flush()
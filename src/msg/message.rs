@@ -0,0 +1,390 @@
+use crate::internal::path::join_name;
+use crate::msg::named::{NamedProperties, NamedProperty};
+use crate::msg::properties::{
+    property_tag, read_properties, substg_name, Properties, PT_OBJECT,
+};
+use crate::msg::rtf::decompress_rtf;
+use crate::msg::{
+    ATTACHMENT_PREFIX, PID_TAG_ATTACH_CONTENT_ID, PID_TAG_ATTACH_DATA,
+    PID_TAG_ATTACH_FILENAME, PID_TAG_ATTACH_LONG_FILENAME,
+    PID_TAG_ATTACH_METHOD, PID_TAG_ATTACH_MIME_TAG, PID_TAG_BODY,
+    PID_TAG_BODY_HTML, PID_TAG_CLIENT_SUBMIT_TIME, PID_TAG_DISPLAY_NAME,
+    PID_TAG_EMAIL_ADDRESS, PID_TAG_INTERNET_CODEPAGE, PID_TAG_MESSAGE_CLASS,
+    PID_TAG_MESSAGE_DELIVERY_TIME, PID_TAG_RECIPIENT_TYPE,
+    PID_TAG_RTF_COMPRESSED, PID_TAG_SENDER_EMAIL_ADDRESS, PID_TAG_SENDER_NAME,
+    PID_TAG_SENDER_SMTP_ADDRESS, PID_TAG_SMTP_ADDRESS, PID_TAG_SUBJECT,
+    RECIPIENT_PREFIX,
+};
+use crate::propset::codepage;
+use crate::propset::PropertyValue;
+use crate::CompoundFile;
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+//===========================================================================//
+
+// The `__properties_version1.0` stream starts with a header whose length
+// depends on what kind of object it belongs to.
+const TOP_LEVEL_HEADER_LEN: usize = 32;
+const EMBEDDED_HEADER_LEN: usize = 24;
+const SUBOBJECT_HEADER_LEN: usize = 8;
+
+/// Embedded messages nested more deeply than this are rejected, so that a
+/// malformed file can't cause unbounded recursion.
+const MAX_EMBEDDING_DEPTH: usize = 32;
+
+const ATTACH_EMBEDDED_MSG: i64 = 5;
+
+const MAPI_ORIG: i64 = 0;
+const MAPI_TO: i64 = 1;
+const MAPI_CC: i64 = 2;
+const MAPI_BCC: i64 = 3;
+
+//===========================================================================//
+
+/// Returns the string value of a property, if it has one.
+fn string(properties: &Properties, id: u16) -> Option<&str> {
+    properties.get(&id).and_then(PropertyValue::as_str)
+}
+
+/// Returns the binary value of a property, if it has one.
+fn binary(properties: &Properties, id: u16) -> Option<&[u8]> {
+    match properties.get(&id) {
+        Some(PropertyValue::Blob(data)) => Some(data),
+        _ => None,
+    }
+}
+
+/// Returns the names of the storages directly within the given storage
+/// whose names start with the given prefix, in order.
+fn sub_storages<F>(
+    comp: &CompoundFile<F>,
+    storage: &Path,
+    prefix: &str,
+) -> io::Result<Vec<PathBuf>> {
+    let mut paths: Vec<PathBuf> = comp
+        .read_storage(storage)?
+        .filter(|entry| entry.is_storage() && entry.name().starts_with(prefix))
+        .map(|entry| entry.path().to_path_buf())
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+//===========================================================================//
+
+/// An Outlook message, read from a `.msg` file or from an attachment that
+/// embeds one message within another.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    properties: Properties,
+    code_page: u16,
+    named: NamedProperties,
+    recipients: Vec<Recipient>,
+    attachments: Vec<Attachment>,
+}
+
+impl Message {
+    pub(crate) fn read<F: Read + Seek>(
+        comp: &mut CompoundFile<F>,
+    ) -> io::Result<Message> {
+        let root = Path::new("/");
+        let named = NamedProperties::read(comp, root)?;
+        Message::read_at(comp, root, TOP_LEVEL_HEADER_LEN, named, 0)
+    }
+
+    fn read_at<F: Read + Seek>(
+        comp: &mut CompoundFile<F>,
+        storage: &Path,
+        header_len: usize,
+        named: NamedProperties,
+        depth: usize,
+    ) -> io::Result<Message> {
+        if depth > MAX_EMBEDDING_DEPTH {
+            invalid_data!("Embedded messages are nested too deeply");
+        }
+        let (properties, code_page) =
+            read_properties(comp, storage, header_len, None)?;
+        let mut recipients = Vec::new();
+        for path in sub_storages(comp, storage, RECIPIENT_PREFIX)? {
+            let (properties, _) = read_properties(
+                comp,
+                &path,
+                SUBOBJECT_HEADER_LEN,
+                Some(code_page),
+            )?;
+            recipients.push(Recipient { properties });
+        }
+        let mut attachments = Vec::new();
+        for path in sub_storages(comp, storage, ATTACHMENT_PREFIX)? {
+            let (properties, _) = read_properties(
+                comp,
+                &path,
+                SUBOBJECT_HEADER_LEN,
+                Some(code_page),
+            )?;
+            let object = join_name(
+                &path,
+                &substg_name(property_tag(PID_TAG_ATTACH_DATA, PT_OBJECT)),
+            );
+            let embedded = if comp.is_storage(&object) {
+                let message = Message::read_at(
+                    comp,
+                    &object,
+                    EMBEDDED_HEADER_LEN,
+                    named.clone(),
+                    depth + 1,
+                )?;
+                Some(Box::new(message))
+            } else {
+                None
+            };
+            attachments.push(Attachment { properties, embedded });
+        }
+        Ok(Message { properties, code_page, named, recipients, attachments })
+    }
+
+    /// Returns the message's properties, keyed by property ID.  Properties
+    /// with IDs of 0x8000 and above are named properties; see
+    /// [`named_properties`](#method.named_properties).
+    pub fn properties(&self) -> &Properties {
+        &self.properties
+    }
+
+    /// Returns the value of the property with the given ID, if the message
+    /// has it.
+    pub fn property(&self, id: u16) -> Option<&PropertyValue> {
+        self.properties.get(&id)
+    }
+
+    /// Returns the mapping between named properties and the IDs that they
+    /// are stored under.
+    pub fn named_properties(&self) -> &NamedProperties {
+        &self.named
+    }
+
+    /// Returns the value of the given named property, if the message has
+    /// it.
+    pub fn named_property(
+        &self,
+        property: &NamedProperty,
+    ) -> Option<&PropertyValue> {
+        self.named.id(property).and_then(|id| self.property(id))
+    }
+
+    /// Returns the code page that non-Unicode strings in the message are
+    /// encoded in.
+    pub fn code_page(&self) -> u16 {
+        self.code_page
+    }
+
+    /// Returns the message class (e.g. `"IPM.Note"` for an email).
+    pub fn message_class(&self) -> Option<&str> {
+        string(&self.properties, PID_TAG_MESSAGE_CLASS)
+    }
+
+    /// Returns the subject of the message.
+    pub fn subject(&self) -> Option<&str> {
+        string(&self.properties, PID_TAG_SUBJECT)
+    }
+
+    /// Returns the display name of the sender.
+    pub fn sender_name(&self) -> Option<&str> {
+        string(&self.properties, PID_TAG_SENDER_NAME)
+    }
+
+    /// Returns the email address of the sender, preferring the SMTP address
+    /// (since the address may otherwise be an Exchange address).
+    pub fn sender_email(&self) -> Option<&str> {
+        string(&self.properties, PID_TAG_SENDER_SMTP_ADDRESS)
+            .or_else(|| string(&self.properties, PID_TAG_SENDER_EMAIL_ADDRESS))
+    }
+
+    /// Returns the time that the message was sent, or if that isn't
+    /// recorded, the time that it was delivered.
+    pub fn sent_time(&self) -> Option<SystemTime> {
+        [PID_TAG_CLIENT_SUBMIT_TIME, PID_TAG_MESSAGE_DELIVERY_TIME]
+            .iter()
+            .filter_map(|id| self.property(*id))
+            .find_map(PropertyValue::as_timestamp)
+            .and_then(|timestamp| timestamp.to_system_time())
+    }
+
+    /// Returns the recipients of the message.
+    pub fn recipients(&self) -> &[Recipient] {
+        &self.recipients
+    }
+
+    /// Returns the plain text body of the message.
+    pub fn body(&self) -> Option<&str> {
+        string(&self.properties, PID_TAG_BODY)
+    }
+
+    /// Returns the HTML body of the message.  This is usually stored as
+    /// binary data in the message's Internet code page, and is decoded from
+    /// that code page.
+    pub fn html_body(&self) -> Option<String> {
+        match self.property(PID_TAG_BODY_HTML)? {
+            PropertyValue::Blob(data) => {
+                let code_page = self
+                    .property(PID_TAG_INTERNET_CODEPAGE)
+                    .and_then(PropertyValue::as_i64)
+                    .map_or(self.code_page, |code_page| code_page as u16);
+                Some(codepage::decode(code_page, data))
+            }
+            value => value.as_str().map(str::to_string),
+        }
+    }
+
+    /// Returns the compressed RTF body of the message, as stored in the
+    /// `PidTagRtfCompressed` property.
+    pub fn rtf_compressed(&self) -> Option<&[u8]> {
+        binary(&self.properties, PID_TAG_RTF_COMPRESSED)
+    }
+
+    /// Returns the RTF body of the message, decompressed.  Returns an error
+    /// if the compressed body is malformed.
+    pub fn rtf_body(&self) -> io::Result<Option<Vec<u8>>> {
+        match self.rtf_compressed() {
+            Some(data) => {
+                let mut rtf = Vec::new();
+                decompress_rtf(data, &mut rtf)?;
+                Ok(Some(rtf))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the attachments of the message.
+    pub fn attachments(&self) -> &[Attachment] {
+        &self.attachments
+    }
+}
+
+//===========================================================================//
+
+/// The kind of a message recipient.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum RecipientKind {
+    /// The originator of the message.
+    Originator,
+    /// A primary (To) recipient.
+    To,
+    /// A carbon copy (Cc) recipient.
+    Cc,
+    /// A blind carbon copy (Bcc) recipient.
+    Bcc,
+    /// Some other kind of recipient; the value is the raw recipient type.
+    Other(i64),
+}
+
+/// A recipient of a message.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Recipient {
+    properties: Properties,
+}
+
+impl Recipient {
+    /// Returns the recipient's properties, keyed by property ID.
+    pub fn properties(&self) -> &Properties {
+        &self.properties
+    }
+
+    /// Returns the value of the property with the given ID, if the
+    /// recipient has it.
+    pub fn property(&self, id: u16) -> Option<&PropertyValue> {
+        self.properties.get(&id)
+    }
+
+    /// Returns the kind of recipient (To, Cc, or Bcc).
+    pub fn kind(&self) -> RecipientKind {
+        let kind = self
+            .property(PID_TAG_RECIPIENT_TYPE)
+            .and_then(PropertyValue::as_i64)
+            .unwrap_or(MAPI_TO);
+        // The high bits hold flags, such as whether the message has already
+        // been sent to this recipient.
+        match kind & 0x0fff_ffff {
+            MAPI_ORIG => RecipientKind::Originator,
+            MAPI_TO => RecipientKind::To,
+            MAPI_CC => RecipientKind::Cc,
+            MAPI_BCC => RecipientKind::Bcc,
+            _ => RecipientKind::Other(kind),
+        }
+    }
+
+    /// Returns the display name of the recipient.
+    pub fn display_name(&self) -> Option<&str> {
+        string(&self.properties, PID_TAG_DISPLAY_NAME)
+    }
+
+    /// Returns the email address of the recipient, preferring the SMTP
+    /// address.
+    pub fn email(&self) -> Option<&str> {
+        string(&self.properties, PID_TAG_SMTP_ADDRESS)
+            .or_else(|| string(&self.properties, PID_TAG_EMAIL_ADDRESS))
+    }
+}
+
+//===========================================================================//
+
+/// An attachment of a message: either a file, or another message embedded
+/// within this one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Attachment {
+    properties: Properties,
+    embedded: Option<Box<Message>>,
+}
+
+impl Attachment {
+    /// Returns the attachment's properties, keyed by property ID.
+    pub fn properties(&self) -> &Properties {
+        &self.properties
+    }
+
+    /// Returns the value of the property with the given ID, if the
+    /// attachment has it.
+    pub fn property(&self, id: u16) -> Option<&PropertyValue> {
+        self.properties.get(&id)
+    }
+
+    /// Returns the file name of the attachment, preferring the long file
+    /// name.
+    pub fn filename(&self) -> Option<&str> {
+        string(&self.properties, PID_TAG_ATTACH_LONG_FILENAME)
+            .or_else(|| string(&self.properties, PID_TAG_ATTACH_FILENAME))
+    }
+
+    /// Returns the MIME type of the attachment.
+    pub fn mime_type(&self) -> Option<&str> {
+        string(&self.properties, PID_TAG_ATTACH_MIME_TAG)
+    }
+
+    /// Returns the content ID of the attachment, by which the HTML body can
+    /// refer to it (e.g. for inline images).
+    pub fn content_id(&self) -> Option<&str> {
+        string(&self.properties, PID_TAG_ATTACH_CONTENT_ID)
+    }
+
+    /// Returns the contents of the attachment, if it is a file.
+    pub fn data(&self) -> Option<&[u8]> {
+        binary(&self.properties, PID_TAG_ATTACH_DATA)
+    }
+
+    /// Returns true if the attachment is an embedded message.
+    pub fn is_embedded_message(&self) -> bool {
+        self.embedded.is_some()
+            || self
+                .property(PID_TAG_ATTACH_METHOD)
+                .and_then(PropertyValue::as_i64)
+                == Some(ATTACH_EMBEDDED_MSG)
+    }
+
+    /// Returns the embedded message, if the attachment is one.
+    pub fn embedded_message(&self) -> Option<&Message> {
+        self.embedded.as_deref()
+    }
+}
+
+//===========================================================================//
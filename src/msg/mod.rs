@@ -0,0 +1,118 @@
+//! Reading of Outlook messages (`.msg` files), as described in [MS-OXMSG](
+//! https://learn.microsoft.com/en-us/openspecs/exchange_server_protocols/ms-oxmsg).
+//!
+//! A `.msg` file is a compound file holding a message's MAPI properties.
+//! Fixed-length properties (such as integers and times) are stored in the
+//! `__properties_version1.0` stream, and each variable-length property
+//! (such as a string) in a `__substg1.0_XXXXYYYY` stream of its own, named
+//! after the property's ID (`XXXX`) and type (`YYYY`).  Each recipient and
+//! attachment is a storage with properties of its own, and an attachment
+//! that is itself a message (such as a forwarded email) holds that message
+//! in a nested storage.  Property values are represented with the
+//! [`PropertyValue`](../propset/enum.PropertyValue.html) type from the
+//! [`propset`](../propset/index.html) module, since MAPI property types
+//! mostly correspond to property set types.
+//!
+//! Named properties (those with IDs of 0x8000 and above) are mapped to
+//! their names by the `__nameid_version1.0` storage; see
+//! [`NamedProperties`](struct.NamedProperties.html).
+//!
+//! The RTF body of a message is compressed as described in [MS-OXRTFCP](
+//! https://learn.microsoft.com/en-us/openspecs/exchange_server_protocols/ms-oxrtfcp),
+//! and is stored in the `__substg1.0_10090102` stream.  The compression is
+//! available on its own through the [`compress_rtf`](fn.compress_rtf.html)
+//! and [`decompress_rtf`](fn.decompress_rtf.html) functions, which can
+//! read from or write to that stream directly.
+//!
+//! # Example usage
+//!
+//! ```no_run
+//! let mut comp = cfb::open("path/to/message.msg").unwrap();
+//! let message = comp.outlook_message().unwrap();
+//! println!("From: {:?}", message.sender_email());
+//! println!("Subject: {:?}", message.subject());
+//! for recipient in message.recipients() {
+//!     println!("{:?}: {:?}", recipient.kind(), recipient.email());
+//! }
+//! for attachment in message.attachments() {
+//!     println!("Attachment: {:?}", attachment.filename());
+//! }
+//! ```
+//!
+//! ```no_run
+//! // Extract the RTF body of a message straight from its stream.
+//! let mut comp = cfb::open("path/to/message.msg").unwrap();
+//! let stream = comp.open_stream("/__substg1.0_10090102").unwrap();
+//! let mut output = std::fs::File::create("body.rtf").unwrap();
+//! cfb::msg::decompress_rtf(stream, &mut output).unwrap();
+//! ```
+
+pub use crate::msg::message::{Attachment, Message, Recipient, RecipientKind};
+pub use crate::msg::named::{
+    NamedProperties, NamedProperty, PropertyName, PS_MAPI, PS_PUBLIC_STRINGS,
+};
+pub use crate::msg::properties::Properties;
+pub use crate::msg::rtf::{compress_rtf, decompress_rtf};
+
+mod message;
+mod named;
+mod properties;
+mod rtf;
+
+//===========================================================================//
+
+/// The prefix of the names of the storages that hold a message's
+/// recipients; each is followed by an eight-digit hexadecimal index.
+pub const RECIPIENT_PREFIX: &str = "__recip_version1.0_#";
+/// The prefix of the names of the storages that hold a message's
+/// attachments; each is followed by an eight-digit hexadecimal index.
+pub const ATTACHMENT_PREFIX: &str = "__attach_version1.0_#";
+
+/// `PidTagMessageClass`: the type of the message (e.g. `"IPM.Note"`).
+pub const PID_TAG_MESSAGE_CLASS: u16 = 0x001a;
+/// `PidTagSubject`: the subject of the message.
+pub const PID_TAG_SUBJECT: u16 = 0x0037;
+/// `PidTagClientSubmitTime`: the time that the message was sent.
+pub const PID_TAG_CLIENT_SUBMIT_TIME: u16 = 0x0039;
+/// `PidTagRecipientType`: whether a recipient is a To, Cc, or Bcc
+/// recipient.
+pub const PID_TAG_RECIPIENT_TYPE: u16 = 0x0c15;
+/// `PidTagSenderName`: the display name of the sender.
+pub const PID_TAG_SENDER_NAME: u16 = 0x0c1a;
+/// `PidTagSenderEmailAddress`: the email address of the sender.
+pub const PID_TAG_SENDER_EMAIL_ADDRESS: u16 = 0x0c1f;
+/// `PidTagMessageDeliveryTime`: the time that the message was delivered.
+pub const PID_TAG_MESSAGE_DELIVERY_TIME: u16 = 0x0e06;
+/// `PidTagBody`: the plain text body of the message.
+pub const PID_TAG_BODY: u16 = 0x1000;
+/// `PidTagRtfCompressed`: the RTF body of the message, compressed.
+pub const PID_TAG_RTF_COMPRESSED: u16 = 0x1009;
+/// `PidTagBodyHtml`: the HTML body of the message.
+pub const PID_TAG_BODY_HTML: u16 = 0x1013;
+/// `PidTagDisplayName`: the display name of a recipient.
+pub const PID_TAG_DISPLAY_NAME: u16 = 0x3001;
+/// `PidTagEmailAddress`: the email address of a recipient.
+pub const PID_TAG_EMAIL_ADDRESS: u16 = 0x3003;
+/// `PidTagAttachDataBinary` or `PidTagAttachDataObject`: the contents of an
+/// attachment, or the embedded message.
+pub const PID_TAG_ATTACH_DATA: u16 = 0x3701;
+/// `PidTagAttachFilename`: the short (8.3) file name of an attachment.
+pub const PID_TAG_ATTACH_FILENAME: u16 = 0x3704;
+/// `PidTagAttachMethod`: how an attachment is stored.
+pub const PID_TAG_ATTACH_METHOD: u16 = 0x3705;
+/// `PidTagAttachLongFilename`: the full file name of an attachment.
+pub const PID_TAG_ATTACH_LONG_FILENAME: u16 = 0x3707;
+/// `PidTagAttachMimeTag`: the MIME type of an attachment.
+pub const PID_TAG_ATTACH_MIME_TAG: u16 = 0x370e;
+/// `PidTagAttachContentId`: the content ID of an attachment.
+pub const PID_TAG_ATTACH_CONTENT_ID: u16 = 0x3712;
+/// `PidTagSmtpAddress`: the SMTP address of a recipient.
+pub const PID_TAG_SMTP_ADDRESS: u16 = 0x39fe;
+/// `PidTagInternetCodepage`: the code page of the HTML body.
+pub const PID_TAG_INTERNET_CODEPAGE: u16 = 0x3fde;
+/// `PidTagMessageCodepage`: the code page of non-Unicode strings.
+pub const PID_TAG_MESSAGE_CODEPAGE: u16 = 0x3ffd;
+/// `PidTagSenderSmtpAddress`: the SMTP address of the sender.
+pub const PID_TAG_SENDER_SMTP_ADDRESS: u16 = 0x5d01;
+
+//===========================================================================//
@@ -0,0 +1,208 @@
+use crate::internal::path::join_name;
+use crate::CompoundFile;
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::BTreeMap;
+use std::io::{self, Read, Seek};
+use std::path::Path;
+use uuid::Uuid;
+
+//===========================================================================//
+
+/// The name of the storage, at the top level of a `.msg` file, that maps
+/// named properties to property IDs.
+pub(crate) const NAMEID_STORAGE: &str = "__nameid_version1.0";
+const GUID_STREAM: &str = "__substg1.0_00020102";
+const ENTRY_STREAM: &str = "__substg1.0_00030102";
+const STRING_STREAM: &str = "__substg1.0_00040102";
+
+/// The property set of named properties that MAPI itself defines; GUID
+/// index 1 in the entry stream.
+pub const PS_MAPI: Uuid =
+    Uuid::from_u128(0x00020328_0000_0000_c000_000000000046);
+/// The property set of named properties with string names that don't
+/// belong to any other property set; GUID index 2 in the entry stream.
+pub const PS_PUBLIC_STRINGS: Uuid =
+    Uuid::from_u128(0x00020329_0000_0000_c000_000000000046);
+
+/// Named properties are assigned property IDs starting from this one.
+const FIRST_NAMED_ID: u32 = 0x8000;
+const ENTRY_LEN: usize = 8;
+const GUID_LEN: usize = 16;
+
+//===========================================================================//
+
+/// The name of a named property, within its property set.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PropertyName {
+    /// A numeric name (a *long ID*, or LID).
+    Id(u32),
+    /// A string name.
+    String(String),
+}
+
+/// A named property: a property set GUID and a name within that set.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct NamedProperty {
+    guid: Uuid,
+    name: PropertyName,
+}
+
+impl NamedProperty {
+    /// Creates a named property with the given property set GUID and name.
+    pub fn new(guid: Uuid, name: PropertyName) -> NamedProperty {
+        NamedProperty { guid, name }
+    }
+
+    /// Returns the GUID of the property set that the name belongs to.
+    pub fn guid(&self) -> &Uuid {
+        &self.guid
+    }
+
+    /// Returns the name of the property within its property set.
+    pub fn name(&self) -> &PropertyName {
+        &self.name
+    }
+}
+
+//===========================================================================//
+
+/// The mapping between named properties and the property IDs (0x8000 and
+/// above) that they are stored under in a `.msg` file, read from the
+/// `__nameid_version1.0` storage.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct NamedProperties {
+    names: BTreeMap<u16, NamedProperty>,
+}
+
+impl NamedProperties {
+    pub(crate) fn read<F: Read + Seek>(
+        comp: &mut CompoundFile<F>,
+        storage: &Path,
+    ) -> io::Result<NamedProperties> {
+        let mut named = NamedProperties::default();
+        let storage = join_name(storage, NAMEID_STORAGE);
+        if !comp.is_storage(&storage) {
+            return Ok(named);
+        }
+        let mut read = |name: &str| -> io::Result<Vec<u8>> {
+            let path = join_name(&storage, name);
+            if comp.is_stream(&path) {
+                comp.read_stream_to_vec(&path)
+            } else {
+                Ok(Vec::new())
+            }
+        };
+        let guids = read(GUID_STREAM)?;
+        let entries = read(ENTRY_STREAM)?;
+        let strings = read(STRING_STREAM)?;
+        for entry in entries.chunks_exact(ENTRY_LEN) {
+            let name_or_offset = LittleEndian::read_u32(&entry[0..4]);
+            let index_and_kind = LittleEndian::read_u16(&entry[4..6]);
+            let property_index = LittleEndian::read_u16(&entry[6..8]);
+            let guid = match index_and_kind >> 1 {
+                1 => PS_MAPI,
+                2 => PS_PUBLIC_STRINGS,
+                index if index >= 3 => {
+                    let start = (index as usize - 3) * GUID_LEN;
+                    match guids.get(start..(start + GUID_LEN)) {
+                        Some(bytes) => {
+                            let mut array = [0u8; GUID_LEN];
+                            array.copy_from_slice(bytes);
+                            Uuid::from_bytes_le(array)
+                        }
+                        None => invalid_data!(
+                            "Named property GUID index {} is out of range",
+                            index
+                        ),
+                    }
+                }
+                index => {
+                    invalid_data!(
+                        "Invalid named property GUID index {}",
+                        index
+                    )
+                }
+            };
+            let name = if index_and_kind & 1 == 0 {
+                PropertyName::Id(name_or_offset)
+            } else {
+                PropertyName::String(read_name(&strings, name_or_offset)?)
+            };
+            let id = FIRST_NAMED_ID + u32::from(property_index);
+            if id > u32::from(u16::MAX) {
+                invalid_data!("Named property index {} is too large", id);
+            }
+            named.names.insert(id as u16, NamedProperty { guid, name });
+        }
+        Ok(named)
+    }
+
+    /// Returns the name of the property with the given ID, or `None` if
+    /// the ID isn't mapped to a name.
+    pub fn name(&self, id: u16) -> Option<&NamedProperty> {
+        self.names.get(&id)
+    }
+
+    /// Returns the ID that the given named property is stored under, or
+    /// `None` if the property isn't mapped to an ID.
+    pub fn id(&self, property: &NamedProperty) -> Option<u16> {
+        self.names
+            .iter()
+            .find(|&(_, named)| named == property)
+            .map(|(&id, _)| id)
+    }
+
+    /// Returns the number of named properties in the mapping.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns true if the mapping is empty.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Returns an iterator over the property IDs and their names, in order
+    /// of ID.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, &NamedProperty)> {
+        self.names.iter().map(|(&id, named)| (id, named))
+    }
+}
+
+/// Reads a string name from the string stream: a four-byte length followed
+/// by that many bytes of UTF-16.
+fn read_name(strings: &[u8], offset: u32) -> io::Result<String> {
+    let offset = offset as usize;
+    let len = match strings.get(offset..offset.saturating_add(4)) {
+        Some(bytes) => LittleEndian::read_u32(bytes) as usize,
+        None => invalid_data!("Named property string offset is out of range"),
+    };
+    let start = offset + 4;
+    match strings.get(start..start.saturating_add(len)) {
+        Some(bytes) => {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect();
+            Ok(String::from_utf16_lossy(&units))
+        }
+        None => invalid_data!("Named property string is out of range"),
+    }
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::read_name;
+
+    #[test]
+    fn string_names() {
+        let strings = b"\x04\0\0\0H\0i\0\x02\0\0\0";
+        assert_eq!(read_name(strings, 0).unwrap(), "Hi");
+        assert!(read_name(strings, 8).is_err());
+        assert!(read_name(strings, 100).is_err());
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,252 @@
+use crate::internal::path::join_name;
+use crate::internal::Timestamp;
+use crate::msg::{PID_TAG_INTERNET_CODEPAGE, PID_TAG_MESSAGE_CODEPAGE};
+use crate::propset::codepage;
+use crate::propset::PropertyValue;
+use crate::CompoundFile;
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io::{self, Read, Seek};
+use std::path::Path;
+use uuid::Uuid;
+
+//===========================================================================//
+
+/// The name of the stream that holds the fixed-length properties of a
+/// message, recipient, or attachment, and the sizes of its variable-length
+/// properties.
+pub(crate) const PROPERTIES_STREAM: &str = "__properties_version1.0";
+
+const PT_I2: u16 = 0x0002;
+const PT_LONG: u16 = 0x0003;
+const PT_FLOAT: u16 = 0x0004;
+const PT_DOUBLE: u16 = 0x0005;
+const PT_CURRENCY: u16 = 0x0006;
+const PT_APPTIME: u16 = 0x0007;
+const PT_ERROR: u16 = 0x000a;
+const PT_BOOLEAN: u16 = 0x000b;
+pub(crate) const PT_OBJECT: u16 = 0x000d;
+const PT_I8: u16 = 0x0014;
+const PT_STRING8: u16 = 0x001e;
+const PT_UNICODE: u16 = 0x001f;
+const PT_SYSTIME: u16 = 0x0040;
+const PT_CLSID: u16 = 0x0048;
+const PT_BINARY: u16 = 0x0102;
+const MV_FLAG: u16 = 0x1000;
+
+const PROPERTY_ENTRY_LEN: usize = 16;
+
+//===========================================================================//
+
+/// Returns the name of the stream (or, for object properties, the storage)
+/// that holds the value of a variable-length property with the given tag.
+pub(crate) fn substg_name(tag: u32) -> String {
+    format!("__substg1.0_{:08X}", tag)
+}
+
+/// Returns the tag of a property with the given ID and type.
+pub(crate) fn property_tag(id: u16, value_type: u16) -> u32 {
+    (u32::from(id) << 16) | u32::from(value_type)
+}
+
+//===========================================================================//
+
+/// The properties of a message, recipient, or attachment, keyed by property
+/// ID.
+pub type Properties = BTreeMap<u16, PropertyValue>;
+
+/// Reads the properties of the object stored in the given storage, whose
+/// `__properties_version1.0` stream begins with a header of the given
+/// length.  Strings that aren't Unicode are decoded in the given code page;
+/// if that is `None`, the code page is taken from the object's own
+/// `PidTagMessageCodepage` or `PidTagInternetCodepage` property.
+pub(crate) fn read_properties<F: Read + Seek>(
+    comp: &mut CompoundFile<F>,
+    storage: &Path,
+    header_len: usize,
+    code_page: Option<u16>,
+) -> io::Result<(Properties, u16)> {
+    let data =
+        comp.read_stream_to_vec(join_name(storage, PROPERTIES_STREAM))?;
+    if data.len() < header_len {
+        invalid_data!(
+            "{} stream of {:?} is too short",
+            PROPERTIES_STREAM,
+            storage
+        );
+    }
+    let mut properties = Properties::new();
+    let mut variable_tags = Vec::new();
+    for entry in data[header_len..].chunks_exact(PROPERTY_ENTRY_LEN) {
+        let tag = LittleEndian::read_u32(&entry[0..4]);
+        let id = (tag >> 16) as u16;
+        let value_type = tag as u16;
+        match fixed_value(value_type, &entry[8..]) {
+            Some(value) => {
+                properties.insert(id, value);
+            }
+            None => variable_tags.push(tag),
+        }
+    }
+    let code_page = match code_page {
+        Some(code_page) => code_page,
+        None => [PID_TAG_MESSAGE_CODEPAGE, PID_TAG_INTERNET_CODEPAGE]
+            .iter()
+            .filter_map(|id| properties.get(id).and_then(|v| v.as_i64()))
+            .find_map(|code_page| u16::try_from(code_page).ok())
+            .unwrap_or(codepage::CP_WINDOWS_1252),
+    };
+    for tag in variable_tags {
+        let id = (tag >> 16) as u16;
+        let value_type = tag as u16;
+        if value_type == PT_OBJECT {
+            continue;
+        }
+        let path = join_name(storage, &substg_name(tag));
+        if !comp.is_stream(&path) {
+            continue;
+        }
+        let data = comp.read_stream_to_vec(&path)?;
+        let value = if value_type & MV_FLAG != 0 {
+            read_multi_value(
+                comp,
+                &path,
+                value_type & !MV_FLAG,
+                &data,
+                code_page,
+            )?
+        } else {
+            variable_value(value_type, &data, code_page)
+        };
+        properties.insert(id, value);
+    }
+    Ok((properties, code_page))
+}
+
+/// Parses the value of a fixed-length property from the eight-byte value
+/// field of its entry in the `__properties_version1.0` stream, returning
+/// `None` if the type isn't a fixed-length one.
+fn fixed_value(value_type: u16, bytes: &[u8]) -> Option<PropertyValue> {
+    Some(match value_type {
+        PT_I2 => PropertyValue::I2(LittleEndian::read_i16(bytes)),
+        PT_LONG => PropertyValue::I4(LittleEndian::read_i32(bytes)),
+        PT_FLOAT => PropertyValue::R4(LittleEndian::read_f32(bytes)),
+        PT_DOUBLE => PropertyValue::R8(LittleEndian::read_f64(bytes)),
+        PT_CURRENCY => PropertyValue::Currency(LittleEndian::read_i64(bytes)),
+        PT_APPTIME => PropertyValue::Date(LittleEndian::read_f64(bytes)),
+        PT_ERROR => PropertyValue::Error(LittleEndian::read_u32(bytes)),
+        PT_BOOLEAN => PropertyValue::Bool(bytes[0] != 0),
+        PT_I8 => PropertyValue::I8(LittleEndian::read_i64(bytes)),
+        PT_SYSTIME => PropertyValue::FileTime(Timestamp::from_filetime(
+            LittleEndian::read_u64(bytes),
+        )),
+        _ => return None,
+    })
+}
+
+/// Returns the length of each value in a multiple-valued property of the
+/// given fixed-length type.
+fn fixed_len(value_type: u16) -> Option<usize> {
+    match value_type {
+        PT_I2 => Some(2),
+        PT_LONG | PT_FLOAT => Some(4),
+        PT_DOUBLE | PT_CURRENCY | PT_APPTIME | PT_I8 | PT_SYSTIME => Some(8),
+        PT_CLSID => Some(16),
+        _ => None,
+    }
+}
+
+/// Parses the value of a variable-length property from its stream.
+fn variable_value(
+    value_type: u16,
+    data: &[u8],
+    code_page: u16,
+) -> PropertyValue {
+    match value_type {
+        PT_UNICODE => PropertyValue::LpWStr(codepage::decode(
+            codepage::CP_WINUNICODE,
+            data,
+        )),
+        PT_STRING8 => PropertyValue::LpStr(codepage::decode(code_page, data)),
+        PT_BINARY => PropertyValue::Blob(data.to_vec()),
+        PT_CLSID if data.len() >= 16 => {
+            let mut bytes = [0u8; 16];
+            bytes.copy_from_slice(&data[..16]);
+            PropertyValue::ClsId(Uuid::from_bytes_le(bytes))
+        }
+        _ => PropertyValue::Unsupported(value_type),
+    }
+}
+
+/// Parses a multiple-valued property.  Fixed-length values are stored one
+/// after another in the property's stream; for variable-length values,
+/// that stream holds their lengths and each value has a stream of its own,
+/// named with a `-XXXXXXXX` suffix giving its index.
+fn read_multi_value<F: Read + Seek>(
+    comp: &mut CompoundFile<F>,
+    path: &Path,
+    value_type: u16,
+    data: &[u8],
+    code_page: u16,
+) -> io::Result<PropertyValue> {
+    let mut values = Vec::new();
+    if let Some(len) = fixed_len(value_type) {
+        for bytes in data.chunks_exact(len) {
+            values.push(match fixed_value(value_type, bytes) {
+                Some(value) => value,
+                None => variable_value(value_type, bytes, code_page),
+            });
+        }
+        return Ok(PropertyValue::Vector(values));
+    }
+    // Lengths are four bytes each for strings, and eight (a length and four
+    // reserved bytes) for binary values.
+    let length_len = if value_type == PT_BINARY { 8 } else { 4 };
+    let count = data.len() / length_len;
+    let name = path.to_string_lossy();
+    for index in 0..count {
+        let value_path = format!("{}-{:08X}", name, index);
+        if !comp.is_stream(&value_path) {
+            invalid_data!("Missing value {} of {:?}", index, path);
+        }
+        let value_data = comp.read_stream_to_vec(&value_path)?;
+        values.push(variable_value(value_type, &value_data, code_page));
+    }
+    Ok(PropertyValue::Vector(values))
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{fixed_value, substg_name, variable_value};
+    use crate::propset::PropertyValue;
+
+    #[test]
+    fn property_values() {
+        assert_eq!(substg_name(0x0037001f), "__substg1.0_0037001F");
+        let bytes = [0xfe, 0xff, 0xff, 0xff, 0, 0, 0, 0];
+        assert_eq!(fixed_value(0x0002, &bytes), Some(PropertyValue::I2(-2)));
+        assert_eq!(fixed_value(0x0003, &bytes), Some(PropertyValue::I4(-2)));
+        assert_eq!(
+            fixed_value(0x000b, &bytes),
+            Some(PropertyValue::Bool(true))
+        );
+        assert_eq!(fixed_value(0x001f, &bytes), None);
+        assert_eq!(
+            variable_value(0x001f, b"H\0i\0\0\0", 1252),
+            PropertyValue::LpWStr("Hi".to_string())
+        );
+        assert_eq!(
+            variable_value(0x001e, b"Caf\xe9\0", 1252),
+            PropertyValue::LpStr("Caf\u{e9}".to_string())
+        );
+        assert_eq!(
+            variable_value(0x00fb, b"", 1252),
+            PropertyValue::Unsupported(0x00fb)
+        );
+    }
+}
+
+//===========================================================================//
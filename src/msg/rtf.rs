@@ -0,0 +1,368 @@
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+use fnv::FnvHashMap;
+use std::io::{self, Read, Write};
+
+//===========================================================================//
+
+const HEADER_LEN: usize = 16;
+/// The length of the header fields that the `COMPSIZE` field counts.
+const COUNTED_HEADER_LEN: u32 = 12;
+const COMPRESSED: u32 = 0x7546_5a4c; // "LZFu"
+const UNCOMPRESSED: u32 = 0x414c_454d; // "MELA"
+
+const DICTIONARY_LEN: usize = 4096;
+const MIN_MATCH_LEN: usize = 2;
+const MAX_MATCH_LEN: usize = 17;
+
+/// The initial contents of the dictionary.
+const PREBUF: &[u8] = b"{\\rtf1\\ansi\\mac\\deff0\\deftab720{\\fonttbl;}\
+    {\\f0\\fnil \\froman \\fswiss \\fmodern \\fscript \\fdecor MS Sans \
+    SerifSymbolArialTimes New RomanCourier{\\colortbl\\red0\\green0\\blue0\
+    \r\n\\par \\pard\\plain\\f0\\fs20\\b\\i\\u\\tab\\tx";
+
+//===========================================================================//
+
+/// Decompresses an MS-OXRTFCP compressed RTF body (the value of the
+/// `PidTagRtfCompressed` property) from the reader, writing the RTF to the
+/// writer.  Returns the number of decompressed bytes written.
+///
+/// Both compressed (`LZFu`) and uncompressed (`MELA`) bodies are supported;
+/// the CRC of a compressed body is verified.
+pub fn decompress_rtf<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+) -> io::Result<u64> {
+    let comp_size = reader.read_u32::<LittleEndian>()?;
+    let raw_size = reader.read_u32::<LittleEndian>()?;
+    let comp_type = reader.read_u32::<LittleEndian>()?;
+    let crc = reader.read_u32::<LittleEndian>()?;
+    if comp_size < COUNTED_HEADER_LEN {
+        invalid_data!("Compressed RTF size {} is too small", comp_size);
+    }
+    let mut data = Vec::new();
+    reader
+        .take(u64::from(comp_size - COUNTED_HEADER_LEN))
+        .read_to_end(&mut data)?;
+    if data.len() < (comp_size - COUNTED_HEADER_LEN) as usize {
+        invalid_data!("Compressed RTF is truncated");
+    }
+    let output = match comp_type {
+        COMPRESSED => {
+            let actual = crc32(&data);
+            if actual != crc {
+                invalid_data!(
+                    "Compressed RTF CRC mismatch (expected 0x{:08x}, \
+                     found 0x{:08x})",
+                    crc,
+                    actual
+                );
+            }
+            decompress_body(&data)?
+        }
+        UNCOMPRESSED => data,
+        _ => invalid_data!("Invalid compressed RTF type 0x{:08x}", comp_type),
+    };
+    if output.len() != raw_size as usize {
+        invalid_data!(
+            "Compressed RTF decompresses to {} bytes instead of {}",
+            output.len(),
+            raw_size
+        );
+    }
+    writer.write_all(&output)?;
+    Ok(output.len() as u64)
+}
+
+fn decompress_body(mut data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut dictionary = [0u8; DICTIONARY_LEN];
+    dictionary[..PREBUF.len()].copy_from_slice(PREBUF);
+    let mut write_offset = PREBUF.len();
+    let mut output = Vec::new();
+    while let Some((&control, rest)) = data.split_first() {
+        data = rest;
+        for bit in 0..8 {
+            if data.is_empty() {
+                break;
+            }
+            if control & (1 << bit) == 0 {
+                output.push(data[0]);
+                dictionary[write_offset] = data[0];
+                write_offset = (write_offset + 1) % DICTIONARY_LEN;
+                data = &data[1..];
+                continue;
+            }
+            let reference = data.read_u16::<BigEndian>()?;
+            let mut read_offset = (reference >> 4) as usize;
+            if read_offset == write_offset {
+                return Ok(output);
+            }
+            let len = (reference & 0xf) as usize + MIN_MATCH_LEN;
+            for _ in 0..len {
+                let byte = dictionary[read_offset];
+                output.push(byte);
+                dictionary[write_offset] = byte;
+                read_offset = (read_offset + 1) % DICTIONARY_LEN;
+                write_offset = (write_offset + 1) % DICTIONARY_LEN;
+            }
+        }
+    }
+    Ok(output)
+}
+
+/// Computes the CRC used by MS-OXRTFCP: the usual CRC-32, but with an
+/// initial value of zero and no final inversion.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc =
+                if crc & 1 != 0 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+//===========================================================================//
+
+/// Compresses all of the RTF from the reader into an MS-OXRTFCP compressed
+/// (`LZFu`) body, suitable for storing in the `PidTagRtfCompressed`
+/// property, and writes it to the writer.  Returns the number of compressed
+/// bytes written.
+pub fn compress_rtf<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+) -> io::Result<u64> {
+    let mut input = Vec::new();
+    reader.read_to_end(&mut input)?;
+    if input.len() > u32::MAX as usize {
+        invalid_input!("RTF is too long to compress");
+    }
+    let body = compress_body(&input);
+    let comp_size = body.len() as u64 + u64::from(COUNTED_HEADER_LEN);
+    if comp_size > u64::from(u32::MAX) {
+        invalid_input!("RTF is too long to compress");
+    }
+    writer.write_u32::<LittleEndian>(comp_size as u32)?;
+    writer.write_u32::<LittleEndian>(input.len() as u32)?;
+    writer.write_u32::<LittleEndian>(COMPRESSED)?;
+    writer.write_u32::<LittleEndian>(crc32(&body))?;
+    writer.write_all(&body)?;
+    Ok((HEADER_LEN + body.len()) as u64)
+}
+
+fn compress_body(input: &[u8]) -> Vec<u8> {
+    // Rather than tracking the dictionary itself, the matcher works over
+    // the prebuffer followed by the input, of which the dictionary is always
+    // a sliding window.
+    let mut history = PREBUF.to_vec();
+    history.extend_from_slice(input);
+    let mut matcher = Matcher::new(&history);
+    for position in 0..PREBUF.len() {
+        matcher.insert(position);
+    }
+    let mut output = Vec::new();
+    let mut position = PREBUF.len();
+    loop {
+        let control_index = output.len();
+        output.push(0);
+        for bit in 0..8 {
+            if position >= history.len() {
+                // The end of the data is marked by a reference to the
+                // current write offset.
+                let offset = (position % DICTIONARY_LEN) as u16;
+                output.extend_from_slice(&(offset << 4).to_be_bytes());
+                output[control_index] |= 1 << bit;
+                return output;
+            }
+            let (start, len) = matcher.longest_match(position);
+            let len = if len >= MIN_MATCH_LEN {
+                let offset = (start % DICTIONARY_LEN) as u16;
+                let reference = (offset << 4) | (len - MIN_MATCH_LEN) as u16;
+                output.extend_from_slice(&reference.to_be_bytes());
+                output[control_index] |= 1 << bit;
+                len
+            } else {
+                output.push(history[position]);
+                1
+            };
+            for index in position..(position + len) {
+                matcher.insert(index);
+            }
+            position += len;
+        }
+    }
+}
+
+//===========================================================================//
+
+/// Finds earlier occurrences of data within the dictionary window, by
+/// chaining together positions that start with the same two bytes.
+struct Matcher<'a> {
+    history: &'a [u8],
+    latest: FnvHashMap<&'a [u8], usize>,
+    previous: Vec<Option<usize>>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(history: &'a [u8]) -> Matcher<'a> {
+        Matcher {
+            history,
+            latest: FnvHashMap::default(),
+            previous: vec![None; history.len()],
+        }
+    }
+
+    fn key(&self, position: usize) -> Option<&'a [u8]> {
+        self.history.get(position..(position + MIN_MATCH_LEN))
+    }
+
+    fn insert(&mut self, position: usize) {
+        if let Some(key) = self.key(position) {
+            self.previous[position] = self.latest.insert(key, position);
+        }
+    }
+
+    /// Returns the start and length of the longest match for the data at
+    /// `position` (which must not have been inserted yet) that lies within
+    /// the dictionary window.  Ties are broken in favour of the oldest
+    /// match, except that the search stops at the first match of the
+    /// maximum length.  A match may run on past `position`, just as a
+    /// reference may copy bytes that it has itself written to the
+    /// dictionary.
+    fn longest_match(&self, position: usize) -> (usize, usize) {
+        let max_len = MAX_MATCH_LEN.min(self.history.len() - position);
+        let window_start = position.saturating_sub(DICTIONARY_LEN - 1);
+        let mut best = (0, 0);
+        let mut candidate = match self.key(position) {
+            Some(key) => self.latest.get(key).copied(),
+            None => None,
+        };
+        while let Some(start) = candidate {
+            if start < window_start {
+                break;
+            }
+            let len = (0..max_len)
+                .take_while(|&index| {
+                    self.history[start + index]
+                        == self.history[position + index]
+                })
+                .count();
+            if len >= best.1 {
+                best = (start, len);
+                if len == max_len {
+                    break;
+                }
+            }
+            candidate = self.previous[start];
+        }
+        best
+    }
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{compress_rtf, crc32, decompress_rtf, PREBUF};
+
+    fn compress_bytes(data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        let len = compress_rtf(data, &mut output).unwrap();
+        assert_eq!(len, output.len() as u64);
+        output
+    }
+
+    fn decompress_bytes(data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        let len = decompress_rtf(data, &mut output).unwrap();
+        assert_eq!(len, output.len() as u64);
+        output
+    }
+
+    // The examples from section 3.1 of MS-OXRTFCP.
+    const EXAMPLES: &[(&[u8], &[u8])] = &[
+        (
+            b"{\\rtf1\\ansi\\ansicpg1252\\pard hello world}\r\n",
+            b"\x2d\x00\x00\x00\x2b\x00\x00\x00\x4c\x5a\x46\x75\xf1\xc5\xc7\xa7\
+              \x03\x00\x0a\x00\x72\x63\x70\x67\x31\x32\x35\x42\x32\x0a\xf3\x20\
+              \x68\x65\x6c\x09\x00\x20\x62\x77\x05\xb0\x6c\x64\x7d\x0a\x80\x0f\
+              \xa0",
+        ),
+        (
+            b"{\\rtf1 WXYZWXYZWXYZWXYZWXYZ}",
+            b"\x1a\x00\x00\x00\x1c\x00\x00\x00\x4c\x5a\x46\x75\xe2\xd4\x4b\x51\
+              \x41\x00\x04\x20\x57\x58\x59\x5a\x0d\x6e\x7d\x01\x0e\xb0",
+        ),
+    ];
+
+    #[test]
+    fn spec_examples() {
+        assert_eq!(PREBUF.len(), 207);
+        for &(decompressed, compressed) in EXAMPLES {
+            assert_eq!(decompress_bytes(compressed), decompressed);
+            assert_eq!(compress_bytes(decompressed), compressed);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut rtf = b"{\\rtf1\\ansi\\ansicpg1252\\deff0".to_vec();
+        for line in 0..1000 {
+            rtf.extend_from_slice(
+                format!("\\par Line {} of the message body\r\n", line)
+                    .as_bytes(),
+            );
+        }
+        rtf.push(b'}');
+        // Pseudo-random bytes, which don't compress.
+        let mut state: u32 = 1;
+        let noise: Vec<u8> = (0..8192)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        for data in &[&[][..], &rtf[..], &noise[..], &[b' '; 10000][..]] {
+            let compressed = compress_bytes(data);
+            assert_eq!(&decompress_bytes(&compressed), data);
+        }
+        assert!(compress_bytes(&rtf).len() < rtf.len() / 4);
+    }
+
+    #[test]
+    fn uncompressed() {
+        let data = b"\x0f\x00\x00\x00\x03\x00\x00\x00MELA\x00\x00\x00\x00{a}";
+        assert_eq!(decompress_bytes(data), b"{a}");
+    }
+
+    #[test]
+    fn invalid() {
+        let (_, compressed) = EXAMPLES[1];
+        let mut output = Vec::new();
+        // Truncated header or body.
+        for len in 0..compressed.len() {
+            assert!(decompress_rtf(&compressed[..len], &mut output).is_err());
+        }
+        // Bad CRC.
+        let mut data = compressed.to_vec();
+        data[20] ^= 1;
+        assert!(decompress_rtf(data.as_slice(), &mut output).is_err());
+        // Bad compression type.
+        let mut data = compressed.to_vec();
+        data[8] = b'X';
+        assert!(decompress_rtf(data.as_slice(), &mut output).is_err());
+        // Wrong raw size.
+        let mut data = compressed.to_vec();
+        data[4] += 1;
+        assert!(decompress_rtf(data.as_slice(), &mut output).is_err());
+    }
+
+    #[test]
+    fn crc() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(&EXAMPLES[1].1[16..]), 0x514b_d4e2);
+    }
+}
+
+//===========================================================================//
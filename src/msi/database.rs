@@ -0,0 +1,140 @@
+use crate::msi::name::encode_name;
+use crate::msi::strings::StringPool;
+use crate::msi::table::{Column, Row, Table, Value};
+use crate::CompoundFile;
+use std::io::{self, Read, Seek};
+
+//===========================================================================//
+
+const STRING_POOL_STREAM: &str = "!_StringPool";
+const STRING_DATA_STREAM: &str = "!_StringData";
+const TABLES_TABLE: &str = "_Tables";
+const COLUMNS_TABLE: &str = "_Columns";
+
+// The types of the columns of the `_Tables` and `_Columns` tables, which
+// aren't described anywhere in the database itself.
+const STRING_KEY_COLUMN: u16 = 0x2d40;
+const INT16_KEY_COLUMN: u16 = 0x2502;
+const STRING_COLUMN: u16 = 0x0d40;
+const INT16_COLUMN: u16 = 0x0502;
+
+//===========================================================================//
+
+/// The string pool and table schemas of an MSI database.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Database {
+    strings: StringPool,
+    tables: Vec<Table>,
+}
+
+impl Database {
+    pub(crate) fn read<F: Read + Seek>(
+        comp: &mut CompoundFile<F>,
+    ) -> io::Result<Database> {
+        if !comp.is_stream(format!("/{}", encode_name(STRING_POOL_STREAM))) {
+            not_found!("Compound file is not an MSI database");
+        }
+        let pool = read_stream(comp, STRING_POOL_STREAM)?;
+        let data = read_stream(comp, STRING_DATA_STREAM)?;
+        let mut database = Database {
+            strings: StringPool::from_bytes(&pool, &data)?,
+            tables: vec![
+                Table::new(
+                    TABLES_TABLE,
+                    vec![Column::new("Name", STRING_KEY_COLUMN)?],
+                ),
+                Table::new(
+                    COLUMNS_TABLE,
+                    vec![
+                        Column::new("Table", STRING_KEY_COLUMN)?,
+                        Column::new("Number", INT16_KEY_COLUMN)?,
+                        Column::new("Name", STRING_COLUMN)?,
+                        Column::new("Type", INT16_COLUMN)?,
+                    ],
+                ),
+            ],
+        };
+        let mut columns: Vec<(String, i32, String, i32)> = Vec::new();
+        for row in database.rows(comp, COLUMNS_TABLE)? {
+            use crate::msi::table::Value::{Int, Str};
+            match row.values() {
+                [Str(table), Int(number), Str(name), Int(bits)] => {
+                    columns.push((
+                        table.clone(),
+                        *number,
+                        name.clone(),
+                        *bits,
+                    ));
+                }
+                _ => invalid_data!("Malformed row in _Columns table"),
+            }
+        }
+        columns.sort_by_key(|&(_, number, _, _)| number);
+        for row in database.rows(comp, TABLES_TABLE)? {
+            let name = match row.values() {
+                [Value::Str(name)] => name.clone(),
+                _ => invalid_data!("Malformed row in _Tables table"),
+            };
+            let mut table_columns = Vec::new();
+            for (table, _, column, type_bits) in columns.iter() {
+                if *table == name {
+                    table_columns
+                        .push(Column::new(column, *type_bits as u16)?);
+                }
+            }
+            if table_columns.is_empty() {
+                invalid_data!("Table {:?} has no columns", name);
+            }
+            database.tables.push(Table::new(&name, table_columns));
+        }
+        Ok(database)
+    }
+
+    /// Returns the database's string pool.
+    pub fn string_pool(&self) -> &StringPool {
+        &self.strings
+    }
+
+    /// Returns the schemas of all the tables in the database, including the
+    /// `_Tables` and `_Columns` tables that describe the others.
+    pub fn tables(&self) -> &[Table] {
+        &self.tables
+    }
+
+    /// Returns the schema of the table with the given name, or `None` if
+    /// there is no such table.
+    pub fn table(&self, name: &str) -> Option<&Table> {
+        self.tables.iter().find(|table| table.name() == name)
+    }
+
+    /// Reads all the rows of the table with the given name from the
+    /// compound file that the database was read from.
+    pub fn rows<F: Read + Seek>(
+        &self,
+        comp: &mut CompoundFile<F>,
+        table: &str,
+    ) -> io::Result<Vec<Row>> {
+        let table = match self.table(table) {
+            Some(table) => table,
+            None => not_found!("No such table: {:?}", table),
+        };
+        let data = read_stream(comp, &table.stream_name())?;
+        table.read_rows(&data, &self.strings)
+    }
+}
+
+/// Reads the stream with the given (unencoded) name from the root storage,
+/// treating a missing stream as empty (as MSI does for empty tables).
+fn read_stream<F: Read + Seek>(
+    comp: &mut CompoundFile<F>,
+    name: &str,
+) -> io::Result<Vec<u8>> {
+    let path = format!("/{}", encode_name(name));
+    let mut data = Vec::new();
+    if comp.is_stream(&path) {
+        comp.open_stream(&path)?.read_to_end(&mut data)?;
+    }
+    Ok(data)
+}
+
+//===========================================================================//
@@ -0,0 +1,44 @@
+//! Reading of Windows Installer (MSI) databases.
+//!
+//! An MSI database (such as a `.msi`, `.msm`, or `.msp` file) is a compound
+//! file whose streams hold a relational database.  Most stream names are
+//! packed into the range U+3800 to U+4840, two characters to each UTF-16
+//! code unit, so they look like gibberish when listed directly;
+//! [`decode_name`](fn.decode_name.html) and
+//! [`encode_name`](fn.encode_name.html) convert between the stored names
+//! and readable ones, and [`decode_path`](fn.decode_path.html) does the same
+//! for whole paths (such as those of the entries returned by
+//! [`CompoundFile::walk`](../struct.CompoundFile.html#method.walk)).
+//!
+//! The strings used by every table are kept together in the `_StringPool`
+//! and `_StringData` streams, the `_Tables` table lists the tables in the
+//! database, and the `_Columns` table describes their columns.
+//! [`CompoundFile::msi_database`](
+//! ../struct.CompoundFile.html#method.msi_database) reads all of these, and
+//! the resulting [`Database`](struct.Database.html) can then read the rows
+//! of any table.
+//!
+//! # Example usage
+//!
+//! ```no_run
+//! let mut comp = cfb::open("path/to/installer.msi").unwrap();
+//! for entry in comp.walk() {
+//!     println!("{}", cfb::msi::decode_path(entry.path()).display());
+//! }
+//! let database = comp.msi_database().unwrap();
+//! for row in database.rows(&mut comp, "Property").unwrap() {
+//!     let name = row.get("Property").unwrap();
+//!     let value = row.get("Value").unwrap();
+//!     println!("{} = {}", name, value);
+//! }
+//! ```
+
+pub use crate::msi::database::Database;
+pub use crate::msi::name::{decode_name, decode_path, encode_name};
+pub use crate::msi::strings::StringPool;
+pub use crate::msi::table::{Column, ColumnType, Row, Table, Value};
+
+mod database;
+mod name;
+mod strings;
+mod table;
@@ -0,0 +1,154 @@
+use std::path::{Component, Path, PathBuf};
+
+//===========================================================================//
+
+/// The character that marks the stream of a database table; it is decoded
+/// as a leading `!`.
+const TABLE_MARKER: u32 = 0x4840;
+/// The start of the range of characters that each encode two characters of
+/// the name.
+const PAIR_BASE: u32 = 0x3800;
+/// The start of the range of characters that each encode one character of
+/// the name.
+const SINGLE_BASE: u32 = 0x4800;
+
+//===========================================================================//
+
+/// Decodes an MSI stream name (as stored in the compound file) into a
+/// readable name.  Characters from the packed range each decode to one or
+/// two characters from the set `0-9A-Za-z._`; the streams of database
+/// tables (which begin with a special marker character) are given a leading
+/// `!`, as in `"!_StringPool"`.  Any other characters are left unchanged,
+/// so names that aren't encoded (such as `"\u{5}SummaryInformation"`) are
+/// returned as is.
+pub fn decode_name(name: &str) -> String {
+    let mut decoded = String::with_capacity(name.len());
+    for chr in name.chars() {
+        let code = chr as u32;
+        if code == TABLE_MARKER {
+            decoded.push('!');
+        } else if (SINGLE_BASE..TABLE_MARKER).contains(&code) {
+            decoded.push(from_base64(code - SINGLE_BASE));
+        } else if (PAIR_BASE..SINGLE_BASE).contains(&code) {
+            let value = code - PAIR_BASE;
+            decoded.push(from_base64(value & 0x3f));
+            decoded.push(from_base64(value >> 6));
+        } else {
+            decoded.push(chr);
+        }
+    }
+    decoded
+}
+
+/// Encodes a readable name into an MSI stream name; this is the inverse of
+/// [`decode_name`](fn.decode_name.html).  A leading `!` marks the name as
+/// that of a database table.  Runs of characters from the set
+/// `0-9A-Za-z._` are packed two to a character; any other characters are
+/// left unchanged.
+pub fn encode_name(name: &str) -> String {
+    let mut encoded = String::with_capacity(name.len());
+    let mut chars = name.chars().peekable();
+    if name.starts_with('!') {
+        chars.next();
+        encoded.push(char_from(TABLE_MARKER));
+    }
+    while let Some(chr) = chars.next() {
+        let first = match to_base64(chr) {
+            Some(first) => first,
+            None => {
+                encoded.push(chr);
+                continue;
+            }
+        };
+        match chars.peek().and_then(|&next| to_base64(next)) {
+            Some(second) => {
+                chars.next();
+                encoded.push(char_from(PAIR_BASE + (second << 6) + first));
+            }
+            None => encoded.push(char_from(SINGLE_BASE + first)),
+        }
+    }
+    encoded
+}
+
+/// Decodes each name in a path with [`decode_name`](fn.decode_name.html),
+/// for displaying the paths of entries in an MSI database (for example,
+/// those returned by [`CompoundFile::walk`](
+/// ../struct.CompoundFile.html#method.walk)).
+pub fn decode_path(path: &Path) -> PathBuf {
+    let mut decoded = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => {
+                decoded.push(decode_name(&name.to_string_lossy()));
+            }
+            other => decoded.push(other.as_os_str()),
+        }
+    }
+    decoded
+}
+
+fn to_base64(chr: char) -> Option<u32> {
+    match chr {
+        '0'..='9' => Some(chr as u32 - '0' as u32),
+        'A'..='Z' => Some(chr as u32 - 'A' as u32 + 10),
+        'a'..='z' => Some(chr as u32 - 'a' as u32 + 36),
+        '.' => Some(62),
+        '_' => Some(63),
+        _ => None,
+    }
+}
+
+fn from_base64(value: u32) -> char {
+    match value {
+        0..=9 => char_from('0' as u32 + value),
+        10..=35 => char_from('A' as u32 + value - 10),
+        36..=61 => char_from('a' as u32 + value - 36),
+        62 => '.',
+        _ => '_',
+    }
+}
+
+fn char_from(code: u32) -> char {
+    char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_name, decode_path, encode_name};
+    use std::path::Path;
+
+    #[test]
+    fn string_pool_name() {
+        let encoded =
+            "\u{4840}\u{3f3f}\u{4577}\u{446c}\u{3e6a}\u{44b2}\u{482f}";
+        assert_eq!(decode_name(encoded), "!_StringPool");
+        assert_eq!(encode_name("!_StringPool"), encoded);
+    }
+
+    #[test]
+    fn round_trip() {
+        for &name in &[
+            "!_Tables",
+            "!Property",
+            "Binary.NewBinary1",
+            "a",
+            "ab",
+            "a-b c",
+            "\u{5}SummaryInformation",
+            "",
+        ] {
+            assert_eq!(decode_name(&encode_name(name)), name);
+        }
+        // Characters outside the packed set aren't encoded.
+        assert_eq!(encode_name("a-b"), "\u{4824}-\u{4825}");
+        assert_eq!(
+            decode_path(Path::new("/\u{4840}\u{3f3f}")),
+            Path::new("/!_S")
+        );
+    }
+}
+
+//===========================================================================//
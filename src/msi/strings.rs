@@ -0,0 +1,160 @@
+use crate::propset::codepage;
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::convert::TryFrom;
+use std::io;
+
+//===========================================================================//
+
+/// Set in the high word of the `_StringPool` header if string references in
+/// tables are three bytes long rather than two.
+const LONG_REFS_FLAG: u16 = 0x8000;
+
+//===========================================================================//
+
+/// The strings of an MSI database, read from the `_StringPool` and
+/// `_StringData` streams.  Tables refer to strings by their one-based index
+/// in the pool; index zero stands for a null value.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StringPool {
+    code_page: u32,
+    long_refs: bool,
+    strings: Vec<String>,
+}
+
+impl StringPool {
+    pub(crate) fn from_bytes(
+        mut pool: &[u8],
+        data: &[u8],
+    ) -> io::Result<StringPool> {
+        if pool.is_empty() {
+            return Ok(StringPool {
+                code_page: 0,
+                long_refs: false,
+                strings: Vec::new(),
+            });
+        }
+        let code_page_low = pool.read_u16::<LittleEndian>()?;
+        let code_page_high = pool.read_u16::<LittleEndian>()?;
+        let long_refs = code_page_high & LONG_REFS_FLAG != 0;
+        let code_page = u32::from(code_page_low)
+            | (u32::from(code_page_high & !LONG_REFS_FLAG) << 16);
+        // Strings are stored in the database's code page, where zero means
+        // the system's ANSI code page.
+        let decode_page = match u16::try_from(code_page) {
+            Ok(0) | Err(_) => codepage::CP_WINDOWS_1252,
+            Ok(code_page) => code_page,
+        };
+        let entries: Vec<(u16, u16)> = pool
+            .chunks_exact(4)
+            .map(|entry| {
+                (
+                    u16::from_le_bytes([entry[0], entry[1]]),
+                    u16::from_le_bytes([entry[2], entry[3]]),
+                )
+            })
+            .collect();
+        let mut strings = Vec::with_capacity(entries.len());
+        let mut offset = 0;
+        let mut index = 0;
+        while index < entries.len() {
+            let (len, ref_count) = entries[index];
+            index += 1;
+            let len = if len != 0 {
+                len as usize
+            } else if ref_count == 0 {
+                // An unused entry still takes up a string index.
+                strings.push(String::new());
+                continue;
+            } else {
+                // A string of 64k bytes or more is stored as an empty entry
+                // (holding the reference count) followed by an entry holding
+                // the length's high and low words.
+                match entries.get(index) {
+                    Some(&(low, high)) => {
+                        index += 1;
+                        (usize::from(high) << 16) | usize::from(low)
+                    }
+                    None => invalid_data!("String pool is truncated"),
+                }
+            };
+            if len > data.len() - offset {
+                invalid_data!(
+                    "String {} extends past the end of the string data",
+                    strings.len() + 1
+                );
+            }
+            let bytes = &data[offset..(offset + len)];
+            strings.push(codepage::decode(decode_page, bytes));
+            offset += len;
+        }
+        Ok(StringPool { code_page, long_refs, strings })
+    }
+
+    /// Returns the code page that the strings were encoded in (zero if
+    /// they are in the system's ANSI code page).
+    pub fn code_page(&self) -> u32 {
+        self.code_page
+    }
+
+    /// Returns the number of strings in the pool.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns true if the pool has no strings.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Returns the string with the given one-based index, or `None` if there
+    /// is no such string.
+    pub fn get(&self, index: u32) -> Option<&str> {
+        let index = (index as usize).checked_sub(1)?;
+        self.strings.get(index).map(String::as_str)
+    }
+
+    /// Returns the length, in bytes, of a string reference in a table.
+    pub(crate) fn ref_len(&self) -> usize {
+        if self.long_refs {
+            3
+        } else {
+            2
+        }
+    }
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::StringPool;
+
+    #[test]
+    fn read_pool() {
+        let mut pool = vec![0xe4, 0x04, 0x00, 0x00];
+        pool.extend_from_slice(&[3, 0, 1, 0]); // "Foo"
+        pool.extend_from_slice(&[0, 0, 0, 0]); // unused
+        pool.extend_from_slice(&[0, 0, 2, 0, 4, 0, 0, 0]); // "Caf\xe9"
+        let pool = StringPool::from_bytes(&pool, b"FooCaf\xe9").unwrap();
+        assert_eq!(pool.code_page(), 1252);
+        assert_eq!(pool.ref_len(), 2);
+        assert_eq!(pool.len(), 3);
+        assert_eq!(pool.get(0), None);
+        assert_eq!(pool.get(1), Some("Foo"));
+        assert_eq!(pool.get(2), Some(""));
+        assert_eq!(pool.get(3), Some("Caf\u{e9}"));
+        assert_eq!(pool.get(4), None);
+
+        let pool = StringPool::from_bytes(&[0, 0, 0, 0x80], b"").unwrap();
+        assert_eq!(pool.ref_len(), 3);
+        assert!(pool.is_empty());
+        assert!(
+            StringPool::from_bytes(&[0, 0, 0, 0, 4, 0, 1, 0], b"abc").is_err()
+        );
+        assert!(
+            StringPool::from_bytes(&[0, 0, 0, 0, 0, 0, 1, 0], b"").is_err()
+        );
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,357 @@
+use crate::msi::strings::StringPool;
+use std::fmt;
+use std::io;
+use std::rc::Rc;
+
+//===========================================================================//
+
+const COLUMN_WIDTH_MASK: u16 = 0x00ff;
+const COLUMN_VALID: u16 = 0x0100;
+const COLUMN_LOCALIZABLE: u16 = 0x0200;
+const COLUMN_STRING: u16 = 0x0800;
+const COLUMN_NULLABLE: u16 = 0x1000;
+const COLUMN_PRIMARY_KEY: u16 = 0x2000;
+
+//===========================================================================//
+
+/// The type of the values in a column of an MSI table.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ColumnType {
+    /// A 16-bit integer.
+    Int16,
+    /// A 32-bit integer.
+    Int32,
+    /// A string, stored as a reference into the string pool.
+    String,
+    /// Binary data, stored in a stream of its own (such as the `Data`
+    /// column of the `Binary` table).
+    Stream,
+}
+
+impl ColumnType {
+    /// Returns the number of bytes that each value in a column of this type
+    /// takes up in a table's stream.
+    fn value_len(self, string_ref_len: usize) -> usize {
+        match self {
+            ColumnType::Int16 | ColumnType::Stream => 2,
+            ColumnType::Int32 => 4,
+            ColumnType::String => string_ref_len,
+        }
+    }
+}
+
+//===========================================================================//
+
+/// A column of an MSI table, as described by the `_Columns` table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Column {
+    name: String,
+    type_bits: u16,
+    column_type: ColumnType,
+}
+
+impl Column {
+    pub(crate) fn new(name: &str, type_bits: u16) -> io::Result<Column> {
+        let width = type_bits & COLUMN_WIDTH_MASK;
+        let column_type =
+            if type_bits & !COLUMN_NULLABLE == COLUMN_STRING | COLUMN_VALID {
+                ColumnType::Stream
+            } else if type_bits & COLUMN_STRING != 0 {
+                ColumnType::String
+            } else if width <= 2 {
+                ColumnType::Int16
+            } else if width == 4 {
+                ColumnType::Int32
+            } else {
+                invalid_data!(
+                    "Column {:?} has invalid type 0x{:04x}",
+                    name,
+                    type_bits
+                );
+            };
+        Ok(Column { name: name.to_string(), type_bits, column_type })
+    }
+
+    /// Returns the name of the column.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the type of the column's values.
+    pub fn column_type(&self) -> ColumnType {
+        self.column_type
+    }
+
+    /// Returns the column's type as stored in the `_Columns` table,
+    /// including the flags and the maximum width of string values.
+    pub fn type_bits(&self) -> u16 {
+        self.type_bits
+    }
+
+    /// Returns true if the column is part of its table's primary key.
+    pub fn is_primary_key(&self) -> bool {
+        self.type_bits & COLUMN_PRIMARY_KEY != 0
+    }
+
+    /// Returns true if the column's values may be null.
+    pub fn is_nullable(&self) -> bool {
+        self.type_bits & COLUMN_NULLABLE != 0
+    }
+
+    /// Returns true if the column's values may be localized.
+    pub fn is_localizable(&self) -> bool {
+        self.type_bits & COLUMN_LOCALIZABLE != 0
+    }
+}
+
+//===========================================================================//
+
+/// The schema of an MSI table: its name and columns.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Table {
+    name: String,
+    columns: Rc<[Column]>,
+}
+
+impl Table {
+    pub(crate) fn new(name: &str, columns: Vec<Column>) -> Table {
+        Table { name: name.to_string(), columns: columns.into() }
+    }
+
+    /// Returns the name of the table (e.g. `"File"`).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the table's columns, in order.
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Returns the column with the given name, or `None` if there is no such
+    /// column.  As in MSI itself, column names are case-sensitive.
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.columns.iter().find(|column| column.name == name)
+    }
+
+    /// Returns the name of the stream (before encoding) that holds the
+    /// table's rows.
+    pub(crate) fn stream_name(&self) -> String {
+        format!("!{}", self.name)
+    }
+
+    /// Parses the rows of the table from its stream.  Values are stored
+    /// column by column: first the values of the first column for every
+    /// row, then those of the second column, and so on.
+    pub(crate) fn read_rows(
+        &self,
+        data: &[u8],
+        strings: &StringPool,
+    ) -> io::Result<Vec<Row>> {
+        let ref_len = strings.ref_len();
+        let row_len: usize = self
+            .columns
+            .iter()
+            .map(|column| column.column_type.value_len(ref_len))
+            .sum();
+        if row_len == 0 || data.len() % row_len != 0 {
+            invalid_data!(
+                "Length of {} bytes is invalid for table {:?}",
+                data.len(),
+                self.name
+            );
+        }
+        let num_rows = data.len() / row_len;
+        let mut rows: Vec<Vec<Value>> = (0..num_rows)
+            .map(|_| Vec::with_capacity(self.columns.len()))
+            .collect();
+        let mut offset = 0;
+        for column in self.columns.iter() {
+            let value_len = column.column_type.value_len(ref_len);
+            for values in rows.iter_mut() {
+                let bytes = &data[offset..(offset + value_len)];
+                let raw = bytes
+                    .iter()
+                    .rev()
+                    .fold(0u32, |raw, &byte| (raw << 8) | u32::from(byte));
+                values.push(self.parse_value(column, raw, strings)?);
+                offset += value_len;
+            }
+        }
+        Ok(rows
+            .into_iter()
+            .map(|mut values| {
+                self.name_streams(&mut values);
+                Row { columns: self.columns.clone(), values }
+            })
+            .collect())
+    }
+
+    fn parse_value(
+        &self,
+        column: &Column,
+        raw: u32,
+        strings: &StringPool,
+    ) -> io::Result<Value> {
+        if raw == 0 {
+            return Ok(Value::Null);
+        }
+        // Integers are stored with their sign bit flipped, so that zero can
+        // stand for null.
+        Ok(match column.column_type {
+            ColumnType::Int16 => Value::Int(i32::from((raw ^ 0x8000) as i16)),
+            ColumnType::Int32 => Value::Int((raw ^ 0x8000_0000) as i32),
+            ColumnType::String => match strings.get(raw) {
+                Some(string) => Value::Str(string.to_string()),
+                None => invalid_data!(
+                    "Column {:?} of table {:?} refers to nonexistent string \
+                     {}",
+                    column.name,
+                    self.name,
+                    raw
+                ),
+            },
+            ColumnType::Stream => Value::Stream(String::new()),
+        })
+    }
+
+    /// Fills in the stream names for the non-null stream values of a row.
+    /// Each is named after the table and the row's primary key, joined with
+    /// periods (e.g. `"Binary.NewBinary1"`).
+    fn name_streams(&self, values: &mut [Value]) {
+        if !values.iter().any(|value| matches!(value, Value::Stream(_))) {
+            return;
+        }
+        let mut name = self.name.clone();
+        for (column, value) in self.columns.iter().zip(values.iter()) {
+            if column.is_primary_key() {
+                name.push('.');
+                name.push_str(&value.to_string());
+            }
+        }
+        for value in values.iter_mut() {
+            if let Value::Stream(ref mut stream_name) = *value {
+                stream_name.clone_from(&name);
+            }
+        }
+    }
+}
+
+//===========================================================================//
+
+/// A value in a row of an MSI table.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum Value {
+    /// A null value.
+    Null,
+    /// An integer.
+    Int(i32),
+    /// A string.
+    Str(String),
+    /// Binary data, held in the stream with the given name.  The name is
+    /// not encoded; use [`encode_name`](fn.encode_name.html) to get the
+    /// name of the stream within the compound file.
+    Stream(String),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Value::Null => Ok(()),
+            Value::Int(value) => value.fmt(formatter),
+            Value::Str(ref string) => string.fmt(formatter),
+            Value::Stream(ref name) => name.fmt(formatter),
+        }
+    }
+}
+
+//===========================================================================//
+
+/// A row of an MSI table.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Row {
+    columns: Rc<[Column]>,
+    values: Vec<Value>,
+}
+
+impl Row {
+    /// Returns the row's values, in the same order as the table's columns.
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// Returns the value in the column with the given name, or `None` if
+    /// there is no such column.
+    pub fn get(&self, column: &str) -> Option<&Value> {
+        let index = self.columns.iter().position(|col| col.name == column)?;
+        self.values.get(index)
+    }
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{Column, ColumnType, Table, Value};
+    use crate::msi::strings::StringPool;
+
+    #[test]
+    fn column_types() {
+        let column = Column::new("Key", 0x2d48).unwrap();
+        assert_eq!(column.column_type(), ColumnType::String);
+        assert!(column.is_primary_key());
+        assert!(!column.is_nullable());
+        assert_eq!(
+            Column::new("A", 0x1502).unwrap().column_type(),
+            ColumnType::Int16
+        );
+        assert_eq!(
+            Column::new("A", 0x0104).unwrap().column_type(),
+            ColumnType::Int32
+        );
+        assert_eq!(
+            Column::new("A", 0x1900).unwrap().column_type(),
+            ColumnType::Stream
+        );
+        assert!(Column::new("A", 0x0103).is_err());
+    }
+
+    #[test]
+    fn read_rows() {
+        let strings = StringPool::from_bytes(
+            &[0, 0, 0, 0, 1, 0, 1, 0, 1, 0, 1, 0],
+            b"ab",
+        )
+        .unwrap();
+        let table = Table::new(
+            "Binary",
+            vec![
+                Column::new("Name", 0x2d48).unwrap(),
+                Column::new("Size", 0x1104).unwrap(),
+                Column::new("Data", 0x1900).unwrap(),
+            ],
+        );
+        let data = [
+            1, 0, 2, 0, // Name
+            5, 0, 0, 0x80, 0, 0, 0, 0, // Size
+            1, 0, 0, 0, // Data
+        ];
+        let rows = table.read_rows(&data, &strings).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            rows[0].values(),
+            &[
+                Value::Str("a".to_string()),
+                Value::Int(5),
+                Value::Stream("Binary.a".to_string())
+            ]
+        );
+        assert_eq!(rows[1].get("Size"), Some(&Value::Null));
+        assert_eq!(rows[1].get("Data"), Some(&Value::Null));
+        assert_eq!(rows[1].get("Missing"), None);
+        assert!(table.read_rows(&data[1..], &strings).is_err());
+        assert!(table.read_rows(&[3, 0, 0, 0, 0, 0, 0, 0], &strings).is_err());
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,297 @@
+use crate::ole::{
+    read_ansi_string, read_guid, read_unicode_string, write_ansi_string,
+    write_unicode_string,
+};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+use uuid::Uuid;
+
+//===========================================================================//
+
+const HEADER_RESERVED1: u32 = 0xfffe_0001;
+const HEADER_VERSION: u32 = 0x0000_0a03;
+const CLSID_MARKER: u32 = 0xffff_ffff;
+const UNICODE_MARKER: u32 = 0x71b2_39f4;
+const STANDARD_FORMAT_MARKERS: [u32; 2] = [0xffff_ffff, 0xffff_fffe];
+const MAX_PROG_ID_LEN: usize = 0x28;
+
+//===========================================================================//
+
+/// A clipboard format, as recorded in a [`CompObj`](struct.CompObj.html)
+/// stream.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ClipboardFormat {
+    /// A standard Windows clipboard format, such as `CF_METAFILEPICT` (3).
+    Standard(u32),
+    /// A registered clipboard format, identified by name (e.g.
+    /// `"Biff8"`).
+    Registered(String),
+}
+
+//===========================================================================//
+
+/// The contents of a `"\u{1}CompObj"` stream, which describes the type of an
+/// embedded or linked object.
+///
+/// Strings are stored in the stream both as ANSI (Windows-1252) and, in
+/// newer files, as UTF-16; when both are present, the UTF-16 versions are
+/// used.  When writing, both versions are written.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CompObj {
+    clsid: Uuid,
+    user_type: String,
+    clipboard_format: Option<ClipboardFormat>,
+    prog_id: Option<String>,
+}
+
+impl CompObj {
+    /// Creates CompObj data with the given CLSID and user-readable type name
+    /// (e.g. `"Microsoft Word Document"`), and no clipboard format or
+    /// ProgID.
+    pub fn new(clsid: Uuid, user_type: &str) -> CompObj {
+        CompObj {
+            clsid,
+            user_type: user_type.to_string(),
+            clipboard_format: None,
+            prog_id: None,
+        }
+    }
+
+    /// Reads and parses an entire CompObj stream.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<CompObj> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        CompObj::from_bytes(&data)
+    }
+
+    /// Parses CompObj data from the given bytes.
+    pub fn from_bytes(data: &[u8]) -> io::Result<CompObj> {
+        let mut reader = data;
+        let _reserved1 = reader.read_u32::<LittleEndian>()?;
+        let _version = reader.read_u32::<LittleEndian>()?;
+        let marker = reader.read_u32::<LittleEndian>()?;
+        let clsid = read_guid(&mut reader)?;
+        let clsid = if marker == CLSID_MARKER { clsid } else { Uuid::nil() };
+        let mut comp_obj = CompObj {
+            clsid,
+            user_type: read_ansi_string(&mut reader)?,
+            clipboard_format: read_clipboard_format(&mut reader, false)?,
+            prog_id: None,
+        };
+        // Everything after the clipboard format is optional, and older
+        // writers sometimes put garbage here, so stop at the first thing
+        // that doesn't look valid.
+        if reader.len() < 4 {
+            return Ok(comp_obj);
+        }
+        let len = (&reader[..4]).read_u32::<LittleEndian>()? as usize;
+        if len > MAX_PROG_ID_LEN || len > reader.len() - 4 {
+            return Ok(comp_obj);
+        }
+        comp_obj.prog_id = non_empty(read_ansi_string(&mut reader)?);
+        if reader.len() < 4
+            || reader.read_u32::<LittleEndian>()? != UNICODE_MARKER
+        {
+            return Ok(comp_obj);
+        }
+        let user_type = read_unicode_string(&mut reader)?;
+        let clipboard_format = read_clipboard_format(&mut reader, true)?;
+        let prog_id = non_empty(read_unicode_string(&mut reader)?);
+        if !user_type.is_empty() {
+            comp_obj.user_type = user_type;
+        }
+        if clipboard_format.is_some() {
+            comp_obj.clipboard_format = clipboard_format;
+        }
+        if prog_id.is_some() {
+            comp_obj.prog_id = prog_id;
+        }
+        Ok(comp_obj)
+    }
+
+    /// Returns the CLSID of the object (nil if the stream doesn't record
+    /// one).
+    pub fn clsid(&self) -> &Uuid {
+        &self.clsid
+    }
+
+    /// Sets the CLSID of the object.
+    pub fn set_clsid(&mut self, clsid: Uuid) {
+        self.clsid = clsid;
+    }
+
+    /// Returns the user-readable name of the object's type (e.g.
+    /// `"Microsoft Excel Worksheet"`).
+    pub fn user_type(&self) -> &str {
+        &self.user_type
+    }
+
+    /// Sets the user-readable name of the object's type.
+    pub fn set_user_type(&mut self, user_type: &str) {
+        self.user_type = user_type.to_string();
+    }
+
+    /// Returns the clipboard format of the object's data, if recorded.
+    pub fn clipboard_format(&self) -> Option<&ClipboardFormat> {
+        self.clipboard_format.as_ref()
+    }
+
+    /// Sets the clipboard format of the object's data.
+    pub fn set_clipboard_format(&mut self, format: Option<ClipboardFormat>) {
+        self.clipboard_format = format;
+    }
+
+    /// Returns the programmatic identifier of the object's type (e.g.
+    /// `"Excel.Sheet.8"`), if recorded.
+    pub fn prog_id(&self) -> Option<&str> {
+        self.prog_id.as_deref()
+    }
+
+    /// Sets the programmatic identifier of the object's type.  Returns an
+    /// error if the ProgID is longer than 39 characters.
+    pub fn set_prog_id(&mut self, prog_id: Option<&str>) -> io::Result<()> {
+        if let Some(prog_id) = prog_id {
+            if prog_id.is_empty() || prog_id.len() >= MAX_PROG_ID_LEN {
+                invalid_input!("Invalid ProgID {:?}", prog_id);
+            }
+        }
+        self.prog_id = prog_id.map(str::to_string);
+        Ok(())
+    }
+
+    /// Serializes the CompObj data.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        self.write(&mut data).unwrap();
+        data
+    }
+
+    /// Serializes the CompObj data to the given writer.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<LittleEndian>(HEADER_RESERVED1)?;
+        writer.write_u32::<LittleEndian>(HEADER_VERSION)?;
+        writer.write_u32::<LittleEndian>(CLSID_MARKER)?;
+        writer.write_all(&self.clsid.to_bytes_le())?;
+        write_ansi_string(&mut writer, &self.user_type)?;
+        self.write_clipboard_format(&mut writer, false)?;
+        match self.prog_id {
+            Some(ref prog_id) => write_ansi_string(&mut writer, prog_id)?,
+            None => writer.write_u32::<LittleEndian>(0)?,
+        }
+        writer.write_u32::<LittleEndian>(UNICODE_MARKER)?;
+        write_unicode_string(&mut writer, &self.user_type)?;
+        self.write_clipboard_format(&mut writer, true)?;
+        match self.prog_id {
+            Some(ref prog_id) => write_unicode_string(&mut writer, prog_id),
+            None => writer.write_u32::<LittleEndian>(0),
+        }
+    }
+
+    fn write_clipboard_format<W: Write>(
+        &self,
+        writer: &mut W,
+        unicode: bool,
+    ) -> io::Result<()> {
+        match self.clipboard_format {
+            None => writer.write_u32::<LittleEndian>(0),
+            Some(ClipboardFormat::Standard(format)) => {
+                writer
+                    .write_u32::<LittleEndian>(STANDARD_FORMAT_MARKERS[0])?;
+                writer.write_u32::<LittleEndian>(format)
+            }
+            Some(ClipboardFormat::Registered(ref name)) => {
+                if unicode {
+                    write_unicode_string(writer, name)
+                } else {
+                    write_ansi_string(writer, name)
+                }
+            }
+        }
+    }
+}
+
+fn read_clipboard_format(
+    reader: &mut &[u8],
+    unicode: bool,
+) -> io::Result<Option<ClipboardFormat>> {
+    let marker = (&reader[..]).read_u32::<LittleEndian>()?;
+    if marker == 0 {
+        *reader = &reader[4..];
+        return Ok(None);
+    }
+    if STANDARD_FORMAT_MARKERS.contains(&marker) {
+        *reader = &reader[4..];
+        let format = reader.read_u32::<LittleEndian>()?;
+        return Ok(Some(ClipboardFormat::Standard(format)));
+    }
+    let name = if unicode {
+        read_unicode_string(reader)?
+    } else {
+        read_ansi_string(reader)?
+    };
+    Ok(Some(ClipboardFormat::Registered(name)))
+}
+
+fn non_empty(string: String) -> Option<String> {
+    if string.is_empty() {
+        None
+    } else {
+        Some(string)
+    }
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{ClipboardFormat, CompObj};
+    use uuid::Uuid;
+
+    // A CompObj stream as written by Excel for an embedded worksheet,
+    // without the Unicode section.
+    const ANSI_ONLY: &[u8] = b"\x01\0\xfe\xff\x03\x0a\0\0\xff\xff\xff\xff\
+        \x20\x08\x02\0\0\0\0\0\xc0\0\0\0\0\0\0\x46\
+        \x1a\0\0\0Microsoft Excel Worksheet\0\
+        \x06\0\0\0Biff8\0\
+        \x0e\0\0\0Excel.Sheet.8\0";
+
+    #[test]
+    fn parse_ansi_only() {
+        let comp_obj = CompObj::from_bytes(ANSI_ONLY).unwrap();
+        assert_eq!(
+            comp_obj.clsid(),
+            &Uuid::from_u128(0x00020820_0000_0000_c000_000000000046)
+        );
+        assert_eq!(comp_obj.user_type(), "Microsoft Excel Worksheet");
+        assert_eq!(
+            comp_obj.clipboard_format(),
+            Some(&ClipboardFormat::Registered("Biff8".to_string()))
+        );
+        assert_eq!(comp_obj.prog_id(), Some("Excel.Sheet.8"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut comp_obj =
+            CompObj::new(Uuid::from_u128(1), "Caf\u{e9} \u{263a}");
+        comp_obj.set_clipboard_format(Some(ClipboardFormat::Standard(3)));
+        comp_obj.set_prog_id(Some("Foo.Bar.1")).unwrap();
+        let data = comp_obj.to_bytes();
+        assert_eq!(CompObj::from_bytes(&data).unwrap(), comp_obj);
+
+        comp_obj.set_prog_id(None).unwrap();
+        comp_obj.set_clipboard_format(None);
+        let data = comp_obj.to_bytes();
+        assert_eq!(CompObj::from_bytes(&data).unwrap(), comp_obj);
+        assert!(comp_obj.set_prog_id(Some(&"x".repeat(40))).is_err());
+    }
+
+    #[test]
+    fn truncated() {
+        for len in 0..(ANSI_ONLY.len() - 20) {
+            assert!(CompObj::from_bytes(&ANSI_ONLY[..len]).is_err());
+        }
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,190 @@
+//! Reading and writing of the OLE system streams found in storages that
+//! hold embedded or linked objects, as described in [MS-OLEDS](
+//! https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-oleds).
+//!
+//! Word, Excel, and other applications store each embedded object (e.g. in
+//! `/ObjectPool/_1234` or `/MBD0001A2B3`) as a storage containing some of
+//! these streams alongside the object's own data:
+//!
+//! * `"\u{1}CompObj"` ([`CompObj`](struct.CompObj.html)) records the
+//!   object's user-readable type name, clipboard format, and ProgID.
+//! * `"\u{1}Ole"` ([`OleStream`](struct.OleStream.html)) records whether the
+//!   object is embedded or linked, and for linked objects, the monikers
+//!   identifying the link source.
+//! * `"\u{1}Ole10Native"` ([`Ole10Native`](struct.Ole10Native.html)) holds
+//!   the native data of an OLE 1.0 object; for "Package" objects (files
+//!   dragged into a document), this is the original file name and contents.
+//!
+//! Each type can be read from and written to a storage of a
+//! [`CompoundFile`](../struct.CompoundFile.html) with methods such as
+//! [`CompoundFile::comp_obj`](../struct.CompoundFile.html#method.comp_obj)
+//! and [`CompoundFile::write_comp_obj`](
+//! ../struct.CompoundFile.html#method.write_comp_obj).
+//!
+//! # Example usage
+//!
+//! ```no_run
+//! let mut comp = cfb::open("path/to/cfb/file").unwrap();
+//! let package = comp.ole10_native("/ObjectPool/_1234").unwrap();
+//! println!("{} ({} bytes)", package.label(), package.data().len());
+//! ```
+
+use crate::propset::codepage;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+use uuid::Uuid;
+
+pub use crate::ole::compobj::{ClipboardFormat, CompObj};
+pub use crate::ole::native::Ole10Native;
+pub use crate::ole::olestream::{LinkInfo, Moniker, OleStream};
+
+mod compobj;
+mod native;
+mod olestream;
+
+//===========================================================================//
+
+/// The name of the stream holding an object's [`CompObj`](
+/// struct.CompObj.html) data.
+pub const COMP_OBJ_STREAM: &str = "\u{1}CompObj";
+/// The name of the stream holding an object's [`OleStream`](
+/// struct.OleStream.html) data.
+pub const OLE_STREAM: &str = "\u{1}Ole";
+/// The name of the stream holding an OLE 1.0 object's [`Ole10Native`](
+/// struct.Ole10Native.html) data.
+pub const OLE10_NATIVE_STREAM: &str = "\u{1}Ole10Native";
+
+//===========================================================================//
+
+/// Reads a `u32` length, checking that at least that many bytes (times
+/// `unit_len`) remain.
+fn read_len(reader: &mut &[u8], unit_len: usize) -> io::Result<usize> {
+    let len = reader.read_u32::<LittleEndian>()? as usize;
+    if len.saturating_mul(unit_len) > reader.len() {
+        invalid_data!(
+            "Length of {} is too large for the {} bytes remaining",
+            len,
+            reader.len()
+        );
+    }
+    Ok(len)
+}
+
+fn read_bytes(reader: &mut &[u8], len: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0u8; len];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_guid(reader: &mut &[u8]) -> io::Result<Uuid> {
+    let mut bytes = [0u8; 16];
+    reader.read_exact(&mut bytes)?;
+    Ok(Uuid::from_bytes_le(bytes))
+}
+
+/// Reads a `LengthPrefixedAnsiString` (whose length includes the
+/// terminating null character).
+fn read_ansi_string(reader: &mut &[u8]) -> io::Result<String> {
+    let len = read_len(reader, 1)?;
+    let bytes = read_bytes(reader, len)?;
+    Ok(codepage::decode(codepage::CP_WINDOWS_1252, &bytes))
+}
+
+/// Reads a `LengthPrefixedUnicodeString` (whose length is in UTF-16 code
+/// units and includes the terminating null character).
+fn read_unicode_string(reader: &mut &[u8]) -> io::Result<String> {
+    let len = read_len(reader, 2)?;
+    let bytes = read_bytes(reader, len * 2)?;
+    Ok(codepage::decode(codepage::CP_WINUNICODE, &bytes))
+}
+
+/// Reads a null-terminated ANSI string.
+fn read_ansi_cstring(reader: &mut &[u8]) -> io::Result<String> {
+    match reader.iter().position(|&byte| byte == 0) {
+        Some(len) => {
+            let string =
+                codepage::decode(codepage::CP_WINDOWS_1252, &reader[..len]);
+            *reader = &reader[(len + 1)..];
+            Ok(string)
+        }
+        None => invalid_data!("Unterminated string"),
+    }
+}
+
+/// Encodes a string as Windows-1252, replacing any characters that can't be
+/// represented with `'?'`.
+fn encode_ansi(string: &str) -> Vec<u8> {
+    string
+        .chars()
+        .flat_map(|chr| {
+            let mut buffer = [0u8; 4];
+            codepage::encode(
+                codepage::CP_WINDOWS_1252,
+                chr.encode_utf8(&mut buffer),
+            )
+            .unwrap_or_else(|| vec![b'?'])
+        })
+        .collect()
+}
+
+fn write_ansi_string<W: Write>(
+    writer: &mut W,
+    string: &str,
+) -> io::Result<()> {
+    let bytes = encode_ansi(string);
+    writer.write_u32::<LittleEndian>(bytes.len() as u32 + 1)?;
+    writer.write_all(&bytes)?;
+    writer.write_u8(0)
+}
+
+fn write_unicode_string<W: Write>(
+    writer: &mut W,
+    string: &str,
+) -> io::Result<()> {
+    let units: Vec<u16> = string.encode_utf16().collect();
+    writer.write_u32::<LittleEndian>(units.len() as u32 + 1)?;
+    for unit in units {
+        writer.write_u16::<LittleEndian>(unit)?;
+    }
+    writer.write_u16::<LittleEndian>(0)
+}
+
+fn write_ansi_cstring<W: Write>(
+    writer: &mut W,
+    string: &str,
+) -> io::Result<()> {
+    writer.write_all(&encode_ansi(string))?;
+    writer.write_u8(0)
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        encode_ansi, read_ansi_cstring, read_ansi_string, read_unicode_string,
+        write_ansi_string, write_unicode_string,
+    };
+
+    #[test]
+    fn strings() {
+        let mut data = Vec::new();
+        write_ansi_string(&mut data, "caf\u{e9} \u{65e5}").unwrap();
+        write_unicode_string(&mut data, "caf\u{e9} \u{65e5}").unwrap();
+        let mut reader = data.as_slice();
+        assert_eq!(read_ansi_string(&mut reader).unwrap(), "caf\u{e9} ?");
+        assert_eq!(
+            read_unicode_string(&mut reader).unwrap(),
+            "caf\u{e9} \u{65e5}"
+        );
+        assert!(reader.is_empty());
+        assert_eq!(encode_ansi("\u{20ac}"), vec![0x80]);
+
+        let mut reader = &b"abc\0def"[..];
+        assert_eq!(read_ansi_cstring(&mut reader).unwrap(), "abc");
+        assert!(read_ansi_cstring(&mut reader).is_err());
+        assert!(read_ansi_string(&mut &b"\xff\0\0\0abc"[..]).is_err());
+    }
+}
+
+//===========================================================================//
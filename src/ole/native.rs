@@ -0,0 +1,207 @@
+use crate::ole::{
+    read_ansi_cstring, read_bytes, read_len, write_ansi_cstring,
+};
+use crate::propset::codepage;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+
+//===========================================================================//
+
+const PACKAGE_SIGNATURE: u16 = 0x0002;
+const EMBEDDED_FILE_TYPE: u32 = 0x0003_0000;
+
+//===========================================================================//
+
+/// The contents of a `"\u{1}Ole10Native"` stream holding a "Package" object
+/// (a file embedded in a document by the Windows Object Packager, e.g. by
+/// dragging it in): the file's original name and path, and its contents.
+///
+/// Strings are stored both as ANSI (Windows-1252) and, in newer files, as
+/// UTF-16; when both are present, the UTF-16 versions are used.  When
+/// writing, both versions are written.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ole10Native {
+    label: String,
+    source_path: String,
+    temp_path: String,
+    data: Vec<u8>,
+}
+
+impl Ole10Native {
+    /// Creates a Package object for a file with the given label (usually
+    /// the file name), original path, and contents.
+    pub fn new(label: &str, source_path: &str, data: Vec<u8>) -> Ole10Native {
+        Ole10Native {
+            label: label.to_string(),
+            source_path: source_path.to_string(),
+            temp_path: source_path.to_string(),
+            data,
+        }
+    }
+
+    /// Reads and parses an entire `"\u{1}Ole10Native"` stream.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<Ole10Native> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ole10Native::from_bytes(&data)
+    }
+
+    /// Parses `"\u{1}Ole10Native"` stream data from the given bytes.
+    /// Returns an error if the data isn't in the Package format.
+    pub fn from_bytes(data: &[u8]) -> io::Result<Ole10Native> {
+        let mut reader = data;
+        let size = read_len(&mut reader, 1)?;
+        reader = &reader[..size];
+        if reader.read_u16::<LittleEndian>()? != PACKAGE_SIGNATURE {
+            invalid_data!("Ole10Native stream is not a Package object");
+        }
+        let mut label = read_ansi_cstring(&mut reader)?;
+        let mut source_path = read_ansi_cstring(&mut reader)?;
+        let _file_type = reader.read_u32::<LittleEndian>()?;
+        let len = read_len(&mut reader, 1)?;
+        let mut temp_path = codepage::decode(
+            codepage::CP_WINDOWS_1252,
+            &read_bytes(&mut reader, len)?,
+        );
+        let len = read_len(&mut reader, 1)?;
+        let data = read_bytes(&mut reader, len)?;
+        // Newer writers follow the data with UTF-16 copies of the strings.
+        if reader.len() >= 12 {
+            let mut unicode = || -> io::Result<String> {
+                let len = read_len(&mut reader, 2)?;
+                let bytes = read_bytes(&mut reader, len * 2)?;
+                Ok(codepage::decode(codepage::CP_WINUNICODE, &bytes))
+            };
+            if let (Ok(temp), Ok(name), Ok(source)) =
+                (unicode(), unicode(), unicode())
+            {
+                temp_path = temp;
+                label = name;
+                source_path = source;
+            }
+        }
+        Ok(Ole10Native { label, source_path, temp_path, data })
+    }
+
+    /// Returns the object's label, which is usually the original file name.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Returns the full path of the file when it was embedded.
+    pub fn source_path(&self) -> &str {
+        &self.source_path
+    }
+
+    /// Returns the path of the temporary copy of the file that was made
+    /// while embedding it (often the same as the source path).
+    pub fn temp_path(&self) -> &str {
+        &self.temp_path
+    }
+
+    /// Returns the original file name: the label if there is one, and
+    /// otherwise the last component of the source path.
+    pub fn file_name(&self) -> &str {
+        if !self.label.is_empty() {
+            return &self.label;
+        }
+        let path = &self.source_path;
+        match path.rfind(['\\', '/']) {
+            Some(index) => &path[(index + 1)..],
+            None => path,
+        }
+    }
+
+    /// Returns the contents of the embedded file.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Consumes this object, returning the contents of the embedded file.
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Serializes the `"\u{1}Ole10Native"` stream data.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        self.write_body(&mut body).unwrap();
+        let mut data = Vec::with_capacity(4 + body.len());
+        data.write_u32::<LittleEndian>(body.len() as u32).unwrap();
+        data.extend_from_slice(&body);
+        data
+    }
+
+    /// Serializes the `"\u{1}Ole10Native"` stream data to the given writer.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+
+    fn write_body(&self, body: &mut Vec<u8>) -> io::Result<()> {
+        body.write_u16::<LittleEndian>(PACKAGE_SIGNATURE)?;
+        write_ansi_cstring(body, &self.label)?;
+        write_ansi_cstring(body, &self.source_path)?;
+        body.write_u32::<LittleEndian>(EMBEDDED_FILE_TYPE)?;
+        let mut temp_path = Vec::new();
+        write_ansi_cstring(&mut temp_path, &self.temp_path)?;
+        body.write_u32::<LittleEndian>(temp_path.len() as u32)?;
+        body.extend_from_slice(&temp_path);
+        body.write_u32::<LittleEndian>(self.data.len() as u32)?;
+        body.extend_from_slice(&self.data);
+        for string in &[&self.temp_path, &self.label, &self.source_path] {
+            let units: Vec<u16> = string.encode_utf16().collect();
+            body.write_u32::<LittleEndian>(units.len() as u32)?;
+            for unit in units {
+                body.write_u16::<LittleEndian>(unit)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::Ole10Native;
+
+    // A Package object without the trailing UTF-16 strings.
+    const ANSI_ONLY: &[u8] =
+        b"\x3f\0\0\0\x02\0hello.txt\0C:\\tmp\\hello.txt\0\
+        \0\0\x03\0\x11\0\0\0C:\\tmp\\hello.txt\0\x05\0\0\0hello";
+
+    #[test]
+    fn parse_ansi_only() {
+        let package = Ole10Native::from_bytes(ANSI_ONLY).unwrap();
+        assert_eq!(package.label(), "hello.txt");
+        assert_eq!(package.source_path(), "C:\\tmp\\hello.txt");
+        assert_eq!(package.temp_path(), "C:\\tmp\\hello.txt");
+        assert_eq!(package.data(), b"hello");
+    }
+
+    #[test]
+    fn round_trip() {
+        let package = Ole10Native::new(
+            "r\u{e9}sum\u{e9} \u{65e5}.txt",
+            "D:\\docs\\r\u{e9}sum\u{e9} \u{65e5}.txt",
+            vec![1, 2, 3],
+        );
+        let data = package.to_bytes();
+        assert_eq!(Ole10Native::from_bytes(&data).unwrap(), package);
+        assert_eq!(package.file_name(), "r\u{e9}sum\u{e9} \u{65e5}.txt");
+        let unnamed = Ole10Native::new("", "D:\\docs/x.bin", vec![]);
+        assert_eq!(unnamed.file_name(), "x.bin");
+    }
+
+    #[test]
+    fn invalid() {
+        for len in 0..ANSI_ONLY.len() {
+            assert!(Ole10Native::from_bytes(&ANSI_ONLY[..len]).is_err());
+        }
+        let mut data = ANSI_ONLY.to_vec();
+        data[4] = 1;
+        assert!(Ole10Native::from_bytes(&data).is_err());
+    }
+}
+
+//===========================================================================//
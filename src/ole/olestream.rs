@@ -0,0 +1,439 @@
+use crate::internal::Timestamp;
+use crate::ole::{
+    read_ansi_string, read_bytes, read_guid, read_len, read_unicode_string,
+    write_ansi_string, write_unicode_string,
+};
+use crate::propset::codepage;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::io::{self, Read, Write};
+use uuid::Uuid;
+
+//===========================================================================//
+
+const OLE_STREAM_VERSION: u32 = 0x0200_0001;
+const FLAG_LINKED: u32 = 0x0000_0001;
+const CLSID_INDICATOR: i32 = -1;
+
+const CLSID_FILE_MONIKER: Uuid =
+    Uuid::from_u128(0x00000303_0000_0000_c000_000000000046);
+const CLSID_URL_MONIKER: Uuid =
+    Uuid::from_u128(0x79eac9e0_baf9_11ce_8c82_00aa004ba90b);
+const FILE_MONIKER_END_SERVER: u16 = 0xffff;
+const FILE_MONIKER_VERSION: u16 = 0xdead;
+const FILE_MONIKER_KEY_VALUE: u16 = 0x0003;
+
+//===========================================================================//
+
+/// A serialized moniker (a reference to a linked object's source), made up
+/// of the CLSID of the moniker class and that class's persisted data.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Moniker {
+    clsid: Uuid,
+    data: Vec<u8>,
+}
+
+impl Moniker {
+    /// Creates a moniker from a moniker class CLSID and its persisted data.
+    pub fn new(clsid: Uuid, data: Vec<u8>) -> Moniker {
+        Moniker { clsid, data }
+    }
+
+    /// Creates a file moniker for the given path.
+    pub fn file(path: &str) -> Moniker {
+        let mut data = Vec::new();
+        data.write_u16::<LittleEndian>(0).unwrap();
+        write_ansi_string(&mut data, path).unwrap();
+        data.write_u16::<LittleEndian>(FILE_MONIKER_END_SERVER).unwrap();
+        data.write_u16::<LittleEndian>(FILE_MONIKER_VERSION).unwrap();
+        data.extend_from_slice(&[0; 20]);
+        let units: Vec<u16> = path.encode_utf16().collect();
+        let unicode_len = units.len() * 2;
+        data.write_u32::<LittleEndian>(unicode_len as u32 + 6).unwrap();
+        data.write_u32::<LittleEndian>(unicode_len as u32).unwrap();
+        data.write_u16::<LittleEndian>(FILE_MONIKER_KEY_VALUE).unwrap();
+        for unit in units {
+            data.write_u16::<LittleEndian>(unit).unwrap();
+        }
+        Moniker { clsid: CLSID_FILE_MONIKER, data }
+    }
+
+    /// Creates a URL moniker for the given URL.
+    pub fn url(url: &str) -> Moniker {
+        let mut data = Vec::new();
+        let units: Vec<u16> = url.encode_utf16().collect();
+        data.write_u32::<LittleEndian>(units.len() as u32 * 2 + 2).unwrap();
+        for unit in units {
+            data.write_u16::<LittleEndian>(unit).unwrap();
+        }
+        data.write_u16::<LittleEndian>(0).unwrap();
+        Moniker { clsid: CLSID_URL_MONIKER, data }
+    }
+
+    /// Returns the CLSID of the moniker class.
+    pub fn clsid(&self) -> &Uuid {
+        &self.clsid
+    }
+
+    /// Returns the moniker's persisted data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// If this is a file moniker, returns the path it refers to.
+    pub fn file_path(&self) -> Option<String> {
+        if self.clsid != CLSID_FILE_MONIKER {
+            return None;
+        }
+        let mut reader = self.data.as_slice();
+        let num_anti = reader.read_u16::<LittleEndian>().ok()?;
+        let mut path = read_ansi_string(&mut reader).ok()?;
+        // The Unicode path, if any, follows some fixed fields.
+        if reader.len() >= 28 {
+            reader = &reader[24..];
+            let unicode_size = reader.read_u32::<LittleEndian>().ok()?;
+            if unicode_size > 0 {
+                let len = read_len(&mut reader, 1).ok()?;
+                let _key_value = reader.read_u16::<LittleEndian>().ok()?;
+                let bytes = read_bytes(&mut reader, len).ok()?;
+                path = codepage::decode(codepage::CP_WINUNICODE, &bytes);
+            }
+        }
+        Some("..\\".repeat(num_anti as usize) + &path)
+    }
+
+    /// If this is a URL moniker, returns the URL it refers to.
+    pub fn url_string(&self) -> Option<String> {
+        if self.clsid != CLSID_URL_MONIKER {
+            return None;
+        }
+        let mut reader = self.data.as_slice();
+        let len = read_len(&mut reader, 1).ok()?;
+        let units: Vec<u16> = reader[..len]
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .take_while(|&unit| unit != 0)
+            .collect();
+        Some(String::from_utf16_lossy(&units))
+    }
+
+    fn read_stream(reader: &mut &[u8]) -> io::Result<Option<Moniker>> {
+        let size = reader.read_u32::<LittleEndian>()? as usize;
+        if size == 0 {
+            return Ok(None);
+        }
+        if size < 20 || size - 4 > reader.len() {
+            invalid_data!("Invalid moniker stream size {}", size);
+        }
+        let clsid = read_guid(reader)?;
+        let data = read_bytes(reader, size - 20)?;
+        Ok(Some(Moniker { clsid, data }))
+    }
+
+    fn write_stream<W: Write>(
+        moniker: Option<&Moniker>,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        match moniker {
+            None => writer.write_u32::<LittleEndian>(0),
+            Some(moniker) => {
+                let size = 20 + moniker.data.len() as u32;
+                writer.write_u32::<LittleEndian>(size)?;
+                writer.write_all(&moniker.clsid.to_bytes_le())?;
+                writer.write_all(&moniker.data)
+            }
+        }
+    }
+}
+
+//===========================================================================//
+
+/// Information about the source of a linked object, as recorded in an
+/// [`OleStream`](struct.OleStream.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LinkInfo {
+    relative_source: Option<Moniker>,
+    absolute_source: Option<Moniker>,
+    clsid_indicator: i32,
+    clsid: Uuid,
+    display_name: String,
+    reserved2: u32,
+    local_update_time: Timestamp,
+    local_check_update_time: Timestamp,
+    remote_update_time: Timestamp,
+}
+
+impl LinkInfo {
+    /// Creates link information with the given source monikers (relative to
+    /// the containing document, and absolute), and with all other fields
+    /// zeroed.
+    pub fn new(
+        relative_source: Option<Moniker>,
+        absolute_source: Option<Moniker>,
+    ) -> LinkInfo {
+        LinkInfo {
+            relative_source,
+            absolute_source,
+            clsid_indicator: CLSID_INDICATOR,
+            clsid: Uuid::nil(),
+            display_name: String::new(),
+            reserved2: 0,
+            local_update_time: Timestamp::default(),
+            local_check_update_time: Timestamp::default(),
+            remote_update_time: Timestamp::default(),
+        }
+    }
+
+    /// Returns the moniker for the link source, relative to the containing
+    /// document, if present.
+    pub fn relative_source(&self) -> Option<&Moniker> {
+        self.relative_source.as_ref()
+    }
+
+    /// Returns the absolute moniker for the link source, if present.
+    pub fn absolute_source(&self) -> Option<&Moniker> {
+        self.absolute_source.as_ref()
+    }
+
+    /// Returns the CLSID of the link source's last known class (usually
+    /// nil).
+    pub fn clsid(&self) -> &Uuid {
+        &self.clsid
+    }
+
+    /// Returns the link's display name (usually empty).
+    pub fn display_name(&self) -> &str {
+        &self.display_name
+    }
+
+    /// Returns when the link was last updated locally.
+    pub fn local_update_time(&self) -> Timestamp {
+        self.local_update_time
+    }
+
+    /// Returns when the link source was last checked for updates.
+    pub fn local_check_update_time(&self) -> Timestamp {
+        self.local_check_update_time
+    }
+
+    /// Returns when the link source was last modified.
+    pub fn remote_update_time(&self) -> Timestamp {
+        self.remote_update_time
+    }
+
+    fn read(reader: &mut &[u8]) -> io::Result<LinkInfo> {
+        let relative_source = Moniker::read_stream(reader)?;
+        let absolute_source = Moniker::read_stream(reader)?;
+        let clsid_indicator = reader.read_i32::<LittleEndian>()?;
+        let clsid = read_guid(reader)?;
+        let display_name = read_unicode_string(reader)?;
+        let reserved2 = reader.read_u32::<LittleEndian>()?;
+        let mut times = [Timestamp::default(); 3];
+        for time in times.iter_mut() {
+            *time =
+                Timestamp::from_filetime(reader.read_u64::<LittleEndian>()?);
+        }
+        Ok(LinkInfo {
+            relative_source,
+            absolute_source,
+            clsid_indicator,
+            clsid,
+            display_name,
+            reserved2,
+            local_update_time: times[0],
+            local_check_update_time: times[1],
+            remote_update_time: times[2],
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        Moniker::write_stream(self.relative_source.as_ref(), writer)?;
+        Moniker::write_stream(self.absolute_source.as_ref(), writer)?;
+        writer.write_i32::<LittleEndian>(self.clsid_indicator)?;
+        writer.write_all(&self.clsid.to_bytes_le())?;
+        write_unicode_string(writer, &self.display_name)?;
+        writer.write_u32::<LittleEndian>(self.reserved2)?;
+        for time in &[
+            self.local_update_time,
+            self.local_check_update_time,
+            self.remote_update_time,
+        ] {
+            writer.write_u64::<LittleEndian>(time.filetime())?;
+        }
+        Ok(())
+    }
+}
+
+//===========================================================================//
+
+/// The contents of a `"\u{1}Ole"` stream, which records whether an object is
+/// embedded or linked, and where a linked object's source is.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OleStream {
+    flags: u32,
+    link_update_option: u32,
+    reserved_moniker: Option<Moniker>,
+    link: Option<LinkInfo>,
+}
+
+impl OleStream {
+    /// Creates an `"\u{1}Ole"` stream for an embedded object.
+    pub fn embedded() -> OleStream {
+        OleStream {
+            flags: 0,
+            link_update_option: 0,
+            reserved_moniker: None,
+            link: None,
+        }
+    }
+
+    /// Creates an `"\u{1}Ole"` stream for a linked object.
+    pub fn linked(link: LinkInfo) -> OleStream {
+        OleStream {
+            flags: FLAG_LINKED,
+            link_update_option: 0,
+            reserved_moniker: None,
+            link: Some(link),
+        }
+    }
+
+    /// Reads and parses an entire `"\u{1}Ole"` stream.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<OleStream> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        OleStream::from_bytes(&data)
+    }
+
+    /// Parses `"\u{1}Ole"` stream data from the given bytes.
+    pub fn from_bytes(data: &[u8]) -> io::Result<OleStream> {
+        let mut reader = data;
+        let version = reader.read_u32::<LittleEndian>()?;
+        if version != OLE_STREAM_VERSION {
+            invalid_data!("Invalid OLE stream version 0x{:08x}", version);
+        }
+        let flags = reader.read_u32::<LittleEndian>()?;
+        let link_update_option = reader.read_u32::<LittleEndian>()?;
+        let _reserved1 = reader.read_u32::<LittleEndian>()?;
+        let reserved_moniker = Moniker::read_stream(&mut reader)?;
+        let link = if flags & FLAG_LINKED != 0 {
+            Some(LinkInfo::read(&mut reader)?)
+        } else {
+            None
+        };
+        Ok(OleStream { flags, link_update_option, reserved_moniker, link })
+    }
+
+    /// Returns the raw flags field.
+    pub fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    /// Returns true if this is a linked object, rather than an embedded
+    /// one.
+    pub fn is_linked(&self) -> bool {
+        self.flags & FLAG_LINKED != 0
+    }
+
+    /// Returns how a linked object is updated: 1 for automatically, 3 for
+    /// manually (ignored for embedded objects).
+    pub fn link_update_option(&self) -> u32 {
+        self.link_update_option
+    }
+
+    /// Sets how a linked object is updated.
+    pub fn set_link_update_option(&mut self, option: u32) {
+        self.link_update_option = option;
+    }
+
+    /// Returns the reserved moniker, if present (in practice, some writers
+    /// store a moniker for the object here).
+    pub fn reserved_moniker(&self) -> Option<&Moniker> {
+        self.reserved_moniker.as_ref()
+    }
+
+    /// Returns information about a linked object's source (`None` for an
+    /// embedded object).
+    pub fn link(&self) -> Option<&LinkInfo> {
+        self.link.as_ref()
+    }
+
+    /// Serializes the `"\u{1}Ole"` stream data.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        self.write(&mut data).unwrap();
+        data
+    }
+
+    /// Serializes the `"\u{1}Ole"` stream data to the given writer.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_u32::<LittleEndian>(OLE_STREAM_VERSION)?;
+        writer.write_u32::<LittleEndian>(self.flags)?;
+        writer.write_u32::<LittleEndian>(self.link_update_option)?;
+        writer.write_u32::<LittleEndian>(0)?;
+        Moniker::write_stream(self.reserved_moniker.as_ref(), &mut writer)?;
+        if let Some(ref link) = self.link {
+            link.write(&mut writer)?;
+        }
+        Ok(())
+    }
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{LinkInfo, Moniker, OleStream};
+    use crate::internal::Timestamp;
+
+    #[test]
+    fn embedded() {
+        let data = b"\x01\0\0\x02\x08\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+        let stream = OleStream::from_bytes(data).unwrap();
+        assert!(!stream.is_linked());
+        assert_eq!(stream.flags(), 8);
+        assert!(stream.link().is_none());
+        assert_eq!(stream.to_bytes(), data.to_vec());
+        let data = OleStream::embedded().to_bytes();
+        assert_eq!(data.len(), 20);
+        assert!(!OleStream::from_bytes(&data).unwrap().is_linked());
+    }
+
+    #[test]
+    fn linked() {
+        let link = LinkInfo::new(
+            Some(Moniker::file("data\\book.xlsx")),
+            Some(Moniker::url("https://example.com/book.xlsx")),
+        );
+        let mut stream = OleStream::linked(link);
+        stream.set_link_update_option(1);
+        let data = stream.to_bytes();
+        let parsed = OleStream::from_bytes(&data).unwrap();
+        assert_eq!(parsed, stream);
+        assert!(parsed.is_linked());
+        let link = parsed.link().unwrap();
+        assert_eq!(
+            link.relative_source().unwrap().file_path(),
+            Some("data\\book.xlsx".to_string())
+        );
+        assert_eq!(
+            link.absolute_source().unwrap().url_string(),
+            Some("https://example.com/book.xlsx".to_string())
+        );
+        assert_eq!(link.absolute_source().unwrap().file_path(), None);
+        assert_eq!(link.remote_update_time(), Timestamp::default());
+        for len in 0..data.len() {
+            assert!(OleStream::from_bytes(&data[..len]).is_err());
+        }
+    }
+
+    #[test]
+    fn file_moniker_with_anti_count() {
+        let mut moniker = Moniker::file("\u{65e5}.doc");
+        moniker.data[0] = 2;
+        assert_eq!(
+            moniker.file_path(),
+            Some("..\\..\\\u{65e5}.doc".to_string())
+        );
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,362 @@
+use crate::propset::value::{encode_string, pad, write_typed_value};
+use crate::propset::{
+    codepage, fmtid_to_stream_name, PropertySet, PropertyValue, Section,
+    BYTE_ORDER_MARK, FMTID_DOC_SUMMARY_INFORMATION,
+    FMTID_USER_DEFINED_PROPERTIES, MAX_NUM_SECTIONS, PID_CODEPAGE,
+    PID_DICTIONARY,
+};
+use std::io::{self, Write};
+use uuid::Uuid;
+
+//===========================================================================//
+
+/// The default system identifier: Win32, OS version 6.0.
+const DEFAULT_SYSTEM_IDENTIFIER: u32 = 0x0002_0006;
+const PROPERTY_SET_HEADER_LEN: usize = 28;
+const SECTION_HEADER_LEN: usize = 20;
+
+//===========================================================================//
+
+/// Builds a property set stream from one or two sections.
+///
+/// The resulting stream can be parsed with
+/// [`PropertySet::from_bytes`](struct.PropertySet.html#method.from_bytes),
+/// and written to a compound file with
+/// [`CompoundFile::write_property_set`](
+/// ../struct.CompoundFile.html#method.write_property_set), which names the
+/// stream after the format ID of the first section.
+///
+/// # Example
+///
+/// ```
+/// use cfb::propset::{
+///     PropertySetBuilder, PropertyValue, FMTID_SUMMARY_INFORMATION,
+///     PIDSI_AUTHOR, PIDSI_TITLE,
+/// };
+///
+/// let mut builder = PropertySetBuilder::new();
+/// let section = builder.section_mut(FMTID_SUMMARY_INFORMATION).unwrap();
+/// section
+///     .set_property(PIDSI_TITLE, PropertyValue::LpStr("Report".to_string()))
+///     .unwrap();
+/// section
+///     .set_property(PIDSI_AUTHOR, PropertyValue::LpStr("Jane".to_string()))
+///     .unwrap();
+///
+/// let mut comp = cfb::CompoundFile::create(std::io::Cursor::new(Vec::new()))
+///     .unwrap();
+/// comp.write_property_set("/", &builder).unwrap();
+/// let summary = comp.summary_information().unwrap().unwrap();
+/// assert_eq!(summary.title(), Some("Report"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct PropertySetBuilder {
+    system_identifier: u32,
+    clsid: Uuid,
+    sections: Vec<Section>,
+}
+
+impl PropertySetBuilder {
+    /// Creates a new builder with no sections.
+    pub fn new() -> PropertySetBuilder {
+        PropertySetBuilder {
+            system_identifier: DEFAULT_SYSTEM_IDENTIFIER,
+            clsid: Uuid::nil(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Creates a builder with the same header fields and sections as an
+    /// existing property set, so that it can be modified and written back.
+    pub fn from_property_set(set: PropertySet) -> PropertySetBuilder {
+        PropertySetBuilder {
+            system_identifier: set.system_identifier(),
+            clsid: *set.clsid(),
+            sections: set.into_sections(),
+        }
+    }
+
+    /// Sets the system identifier to write in the property set header.
+    pub fn set_system_identifier(&mut self, system_identifier: u32) {
+        self.system_identifier = system_identifier;
+    }
+
+    /// Sets the application-specific CLSID to write in the property set
+    /// header.
+    pub fn set_clsid(&mut self, clsid: Uuid) {
+        self.clsid = clsid;
+    }
+
+    /// Returns the sections that will be written, in order.
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// Returns the section with the given format ID, adding a new, empty
+    /// section at the end if there isn't one yet.  Returns an error if a new
+    /// section is needed but the builder already has two sections (the most
+    /// a property set can hold).
+    pub fn section_mut(&mut self, fmtid: Uuid) -> io::Result<&mut Section> {
+        let index =
+            match self.sections.iter().position(|s| *s.fmtid() == fmtid) {
+                Some(index) => index,
+                None => {
+                    if self.sections.len() >= MAX_NUM_SECTIONS {
+                        invalid_input!(
+                            "A property set cannot have more than {} sections",
+                            MAX_NUM_SECTIONS
+                        );
+                    }
+                    self.sections.push(Section::new(fmtid));
+                    self.sections.len() - 1
+                }
+            };
+        Ok(&mut self.sections[index])
+    }
+
+    /// Removes the section with the given format ID, returning it, if it
+    /// was present.
+    pub fn remove_section(&mut self, fmtid: &Uuid) -> Option<Section> {
+        let index = self.sections.iter().position(|s| s.fmtid() == fmtid)?;
+        Some(self.sections.remove(index))
+    }
+
+    /// Returns the name of the stream that this property set belongs in,
+    /// based on the format ID of its first section, or `None` if there are
+    /// no sections.
+    pub fn stream_name(&self) -> Option<String> {
+        self.sections
+            .first()
+            .map(|section| fmtid_to_stream_name(section.fmtid()))
+    }
+
+    /// Serializes the property set.  Returns an error if there are no
+    /// sections, if there is a user-defined properties section that doesn't
+    /// follow a document summary information section, or if any property
+    /// value can't be written (for example, a string that can't be
+    /// represented in its section's code page).
+    pub fn build(&self) -> io::Result<Vec<u8>> {
+        if self.sections.is_empty() {
+            invalid_input!("A property set must have at least one section");
+        }
+        // MS-OLEPS only allows the user-defined properties section as the
+        // second section of the DocumentSummaryInformation property set.
+        if self
+            .sections
+            .iter()
+            .any(|section| *section.fmtid() == FMTID_USER_DEFINED_PROPERTIES)
+            && *self.sections[0].fmtid() != FMTID_DOC_SUMMARY_INFORMATION
+        {
+            invalid_input!(
+                "The user-defined properties section must follow a document \
+                 summary information section"
+            );
+        }
+        let mut sections = Vec::with_capacity(self.sections.len());
+        for section in self.sections.iter() {
+            sections.push(write_section(section)?);
+        }
+        let version: u16 =
+            if self.sections.iter().any(requires_version_1) { 1 } else { 0 };
+        let mut data = Vec::new();
+        data.extend_from_slice(&BYTE_ORDER_MARK.to_le_bytes());
+        data.extend_from_slice(&version.to_le_bytes());
+        data.extend_from_slice(&self.system_identifier.to_le_bytes());
+        data.extend_from_slice(&self.clsid.to_bytes_le());
+        data.extend_from_slice(&(sections.len() as u32).to_le_bytes());
+        let mut offset =
+            PROPERTY_SET_HEADER_LEN + SECTION_HEADER_LEN * sections.len();
+        for (section, bytes) in self.sections.iter().zip(sections.iter()) {
+            data.extend_from_slice(&section.fmtid().to_bytes_le());
+            data.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += bytes.len();
+        }
+        for bytes in sections {
+            data.extend_from_slice(&bytes);
+        }
+        Ok(data)
+    }
+
+    /// Serializes the property set to the given writer.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.build()?)
+    }
+}
+
+impl Default for PropertySetBuilder {
+    fn default() -> PropertySetBuilder {
+        PropertySetBuilder::new()
+    }
+}
+
+//===========================================================================//
+
+fn requires_version_1(section: &Section) -> bool {
+    section.is_case_sensitive()
+        || section.properties().values().any(PropertyValue::requires_version_1)
+}
+
+fn write_section(section: &Section) -> io::Result<Vec<u8>> {
+    let code_page = section.code_page();
+    let mut values: Vec<(u32, Vec<u8>)> = Vec::new();
+    if !section.dictionary().is_empty() {
+        values.push((PID_DICTIONARY, write_dictionary(section, code_page)?));
+    }
+    if section.property(PID_CODEPAGE).is_none() {
+        let mut bytes = Vec::new();
+        let value = PropertyValue::I2(code_page as i16);
+        write_typed_value(&mut bytes, &value, code_page)?;
+        values.push((PID_CODEPAGE, bytes));
+    }
+    for (&id, value) in section.properties().iter() {
+        let mut bytes = Vec::new();
+        write_typed_value(&mut bytes, value, code_page)?;
+        values.push((id, bytes));
+    }
+    values.sort_by_key(|&(id, _)| id);
+
+    // Section header: size, property count, then (ID, offset) pairs.
+    let mut offset = 8 + 8 * values.len();
+    let size = offset + values.iter().map(|(_, v)| v.len()).sum::<usize>();
+    let mut data = Vec::with_capacity(size);
+    data.extend_from_slice(&(size as u32).to_le_bytes());
+    data.extend_from_slice(&(values.len() as u32).to_le_bytes());
+    for (id, bytes) in values.iter() {
+        data.extend_from_slice(&id.to_le_bytes());
+        data.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += bytes.len();
+    }
+    for (_, bytes) in values {
+        data.extend_from_slice(&bytes);
+    }
+    debug_assert_eq!(data.len(), size);
+    Ok(data)
+}
+
+fn write_dictionary(section: &Section, code_page: u16) -> io::Result<Vec<u8>> {
+    let dictionary = section.dictionary();
+    let mut data = Vec::new();
+    data.extend_from_slice(&(dictionary.len() as u32).to_le_bytes());
+    for (&id, name) in dictionary.iter() {
+        let bytes = encode_string(code_page, name)?;
+        data.extend_from_slice(&id.to_le_bytes());
+        if code_page == codepage::CP_WINUNICODE {
+            data.extend_from_slice(&(bytes.len() as u32 / 2).to_le_bytes());
+            data.extend_from_slice(&bytes);
+            pad(&mut data);
+        } else {
+            data.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            data.extend_from_slice(&bytes);
+        }
+    }
+    pad(&mut data);
+    Ok(data)
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::PropertySetBuilder;
+    use crate::propset::{
+        PropertySet, PropertyValue, FMTID_DOC_SUMMARY_INFORMATION,
+        FMTID_USER_DEFINED_PROPERTIES,
+    };
+    use uuid::Uuid;
+
+    #[test]
+    fn round_trip_sections() {
+        let mut builder = PropertySetBuilder::new();
+        builder.set_clsid(Uuid::from_u128(7));
+        let section =
+            builder.section_mut(FMTID_DOC_SUMMARY_INFORMATION).unwrap();
+        section
+            .set_property(15, PropertyValue::LpStr("Acme".to_string()))
+            .unwrap();
+        let section =
+            builder.section_mut(FMTID_USER_DEFINED_PROPERTIES).unwrap();
+        section.set_code_page(1200);
+        section.set_locale(0x409);
+        let id = section
+            .set_named_property("Client", PropertyValue::I4(5))
+            .unwrap();
+        assert_eq!(id, 2);
+        let id = section
+            .set_named_property("Odd", PropertyValue::LpStr("x".to_string()))
+            .unwrap();
+        assert_eq!(id, 3);
+        assert_eq!(
+            section
+                .set_named_property("client", PropertyValue::I4(6))
+                .unwrap(),
+            2
+        );
+        assert!(builder.section_mut(Uuid::from_u128(1)).is_err());
+
+        let data = builder.build().unwrap();
+        assert_eq!(data.len() % 4, 0);
+        let set = PropertySet::from_bytes(&data).unwrap();
+        assert_eq!(set.version(), 0);
+        assert_eq!(set.clsid(), &Uuid::from_u128(7));
+        let sections = set.sections();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].code_page(), 1252);
+        assert_eq!(
+            sections[0].property(15),
+            Some(&PropertyValue::LpStr("Acme".to_string()))
+        );
+        assert_eq!(sections[1].code_page(), 1200);
+        assert_eq!(sections[1].locale(), Some(0x409));
+        assert_eq!(sections[1].property_name(3), Some("Odd"));
+        assert_eq!(
+            sections[1].property_by_name("CLIENT"),
+            Some(&PropertyValue::I4(6))
+        );
+
+        let rebuilt = PropertySetBuilder::from_property_set(set).build();
+        assert_eq!(rebuilt.unwrap(), data);
+    }
+
+    #[test]
+    fn version_1_features() {
+        let mut builder = PropertySetBuilder::new();
+        let section = builder.section_mut(Uuid::from_u128(1)).unwrap();
+        section.set_property(2, PropertyValue::I4(1)).unwrap();
+        let data = builder.build().unwrap();
+        assert_eq!(PropertySet::from_bytes(&data).unwrap().version(), 0);
+
+        let section = builder.section_mut(Uuid::from_u128(1)).unwrap();
+        section.set_property(3, PropertyValue::UI8(1)).unwrap();
+        let data = builder.build().unwrap();
+        assert_eq!(PropertySet::from_bytes(&data).unwrap().version(), 1);
+
+        let section = builder.section_mut(Uuid::from_u128(1)).unwrap();
+        section.remove_property(3);
+        section.set_case_sensitive(true);
+        let data = builder.build().unwrap();
+        let set = PropertySet::from_bytes(&data).unwrap();
+        assert_eq!(set.version(), 1);
+        assert!(set.sections()[0].is_case_sensitive());
+    }
+
+    #[test]
+    fn invalid_properties() {
+        assert!(PropertySetBuilder::new().build().is_err());
+        let mut builder = PropertySetBuilder::new();
+        let section = builder.section_mut(Uuid::from_u128(1)).unwrap();
+        assert!(section.set_property(0, PropertyValue::I4(1)).is_err());
+        assert!(section.set_property(1, PropertyValue::I4(1)).is_err());
+        assert!(section.set_property_name(1, "CodePage").is_err());
+        section.set_property_name(2, "Name").unwrap();
+        assert!(section.set_property_name(3, "NAME").is_err());
+        section
+            .set_property(2, PropertyValue::LpStr("\u{65e5}".to_string()))
+            .unwrap();
+        assert!(builder.build().is_err());
+        let section = builder.section_mut(Uuid::from_u128(1)).unwrap();
+        section.set_code_page(65001);
+        assert!(builder.build().is_ok());
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,161 @@
+//! Code page identifiers and string decoding for property sets.
+//!
+//! Strings in a property set section are encoded in the code page given by
+//! that section's `PID_CODEPAGE` property.  This crate decodes the common
+//! Unicode and Western European code pages exactly; see
+//! [`is_supported`](fn.is_supported.html).
+
+//===========================================================================//
+
+/// The code page identifier for UTF-16LE, which MS-OLEPS calls
+/// `CP_WINUNICODE`.
+pub const CP_WINUNICODE: u16 = 1200;
+
+/// The code page identifier for Windows-1252 (Western European), the most
+/// common code page for property sets that aren't Unicode.
+pub const CP_WINDOWS_1252: u16 = 1252;
+
+/// The code page identifier for ISO-8859-1 (Latin-1).
+pub const CP_LATIN_1: u16 = 28591;
+
+/// The code page identifier for US-ASCII.
+pub const CP_US_ASCII: u16 = 20127;
+
+/// The code page identifier for UTF-8.
+pub const CP_UTF8: u16 = 65001;
+
+/// The characters for bytes 0x80 through 0x9F in Windows-1252.  Bytes that
+/// are undefined in Windows-1252 map to the C1 control character of the same
+/// value, as Windows itself does.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}',
+    '\u{2020}', '\u{2021}', '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}',
+    '\u{152}', '\u{8d}', '\u{17d}', '\u{8f}', '\u{90}', '\u{2018}',
+    '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}',
+    '\u{17e}', '\u{178}',
+];
+
+//===========================================================================//
+
+/// Returns true if strings in the given code page can be decoded exactly.
+/// For other code pages, only ASCII characters are decoded, and all other
+/// bytes are replaced with U+FFFD.
+pub fn is_supported(code_page: u16) -> bool {
+    matches!(
+        code_page,
+        CP_WINUNICODE | CP_WINDOWS_1252 | CP_LATIN_1 | CP_US_ASCII | CP_UTF8
+    )
+}
+
+/// Decodes a string from the given code page, dropping any trailing null
+/// characters.
+pub fn decode(code_page: u16, bytes: &[u8]) -> String {
+    let string = match code_page {
+        CP_WINUNICODE => {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        CP_UTF8 => String::from_utf8_lossy(bytes).into_owned(),
+        CP_WINDOWS_1252 => bytes
+            .iter()
+            .map(|&byte| match byte {
+                0x80..=0x9f => WINDOWS_1252_HIGH[(byte - 0x80) as usize],
+                _ => byte as char,
+            })
+            .collect(),
+        CP_LATIN_1 => bytes.iter().map(|&byte| byte as char).collect(),
+        _ => bytes
+            .iter()
+            .map(|&byte| if byte < 0x80 { byte as char } else { '\u{fffd}' })
+            .collect(),
+    };
+    string.trim_end_matches('\0').to_string()
+}
+
+/// Encodes a string in the given code page (without a terminating null
+/// character), returning `None` if the code page isn't supported or can't
+/// represent every character in the string.
+pub fn encode(code_page: u16, string: &str) -> Option<Vec<u8>> {
+    match code_page {
+        CP_WINUNICODE => Some(
+            string
+                .encode_utf16()
+                .flat_map(|unit| unit.to_le_bytes())
+                .collect(),
+        ),
+        CP_UTF8 => Some(string.as_bytes().to_vec()),
+        CP_WINDOWS_1252 => string
+            .chars()
+            .map(|chr| match chr as u32 {
+                0x00..=0x7f | 0xa0..=0xff => Some(chr as u8),
+                _ => WINDOWS_1252_HIGH
+                    .iter()
+                    .position(|&high| high == chr)
+                    .map(|index| 0x80 + index as u8),
+            })
+            .collect(),
+        CP_LATIN_1 => string
+            .chars()
+            .map(
+                |chr| {
+                    if (chr as u32) < 0x100 {
+                        Some(chr as u8)
+                    } else {
+                        None
+                    }
+                },
+            )
+            .collect(),
+        CP_US_ASCII => {
+            if string.is_ascii() {
+                Some(string.as_bytes().to_vec())
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        decode, encode, is_supported, CP_US_ASCII, CP_WINDOWS_1252,
+        CP_WINUNICODE,
+    };
+
+    #[test]
+    fn decode_strings() {
+        assert_eq!(
+            decode(CP_WINDOWS_1252, b"caf\xe9 \x80\x99\0\0"),
+            "café €™"
+        );
+        assert_eq!(decode(CP_WINUNICODE, b"h\0i\0\xac\x20\0\0"), "hi€");
+        assert_eq!(decode(65001, "caf\u{e9}\0".as_bytes()), "café");
+        assert_eq!(decode(28591, b"\x80\xff"), "\u{80}\u{ff}");
+        assert_eq!(decode(932, b"ab\x82\xa0"), "ab\u{fffd}\u{fffd}");
+        assert!(!is_supported(932));
+    }
+
+    #[test]
+    fn encode_strings() {
+        for &string in &["café €™", "\u{81}", ""] {
+            let bytes = encode(CP_WINDOWS_1252, string).unwrap();
+            assert_eq!(decode(CP_WINDOWS_1252, &bytes), string);
+        }
+        assert_eq!(encode(CP_WINDOWS_1252, "\u{80}"), None);
+        assert_eq!(encode(CP_WINDOWS_1252, "日本"), None);
+        assert_eq!(encode(CP_WINUNICODE, "h€"), Some(b"h\0\xac\x20".to_vec()));
+        assert_eq!(encode(28591, "\u{ff}"), Some(vec![0xff]));
+        assert_eq!(encode(CP_US_ASCII, "é"), None);
+        assert_eq!(encode(932, "a"), None);
+    }
+}
+
+//===========================================================================//
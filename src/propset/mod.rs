@@ -0,0 +1,543 @@
+//! Reading and writing of OLE property sets, as described in [MS-OLEPS](
+//! https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-oleps).
+//!
+//! Many compound files carry metadata in *property set streams*, such as
+//! `"\u{5}SummaryInformation"` (title, author, creation time, and so on) and
+//! `"\u{5}DocumentSummaryInformation"` (company, manager, user-defined
+//! properties, and so on).  A [`PropertySet`](struct.PropertySet.html) holds
+//! the parsed contents of one such stream, which consists of one or two
+//! [`Section`](struct.Section.html)s, each identified by a format ID (FMTID)
+//! and mapping property IDs to typed [`PropertyValue`](
+//! enum.PropertyValue.html)s.
+//!
+//! For the two most common streams, [`SummaryInformation`](
+//! struct.SummaryInformation.html) and [`DocumentSummaryInformation`](
+//! struct.DocumentSummaryInformation.html) provide typed accessors for the
+//! well-known properties; these can be obtained directly from a compound file
+//! with [`CompoundFile::summary_information`](
+//! ../struct.CompoundFile.html#method.summary_information) and
+//! [`CompoundFile::document_summary_information`](
+//! ../struct.CompoundFile.html#method.document_summary_information).
+//!
+//! To write a property set, fill in the sections of a
+//! [`PropertySetBuilder`](struct.PropertySetBuilder.html) and pass it to
+//! [`CompoundFile::write_property_set`](
+//! ../struct.CompoundFile.html#method.write_property_set), which stores it in
+//! a stream named after its format ID (see
+//! [`fmtid_to_stream_name`](fn.fmtid_to_stream_name.html)).
+//!
+//! # Example usage
+//!
+//! ```no_run
+//! let mut comp = cfb::open("path/to/cfb/file").unwrap();
+//! if let Some(summary) = comp.summary_information().unwrap() {
+//!     println!("Title: {:?}", summary.title());
+//!     println!("Author: {:?}", summary.author());
+//!     println!("Created: {:?}", summary.created());
+//! }
+//! ```
+
+use crate::propset::value::{read_typed_value, Reader, VT_I2};
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use uuid::Uuid;
+
+pub use crate::propset::builder::PropertySetBuilder;
+pub use crate::propset::name::{fmtid_to_stream_name, stream_name_to_fmtid};
+pub use crate::propset::summary::{
+    DocumentSummaryInformation, SummaryInformation,
+    DOC_SUMMARY_INFORMATION_STREAM, FMTID_DOC_SUMMARY_INFORMATION,
+    FMTID_SUMMARY_INFORMATION, FMTID_USER_DEFINED_PROPERTIES,
+    PIDDSI_BYTECOUNT, PIDDSI_CATEGORY, PIDDSI_COMPANY, PIDDSI_DOCPARTS,
+    PIDDSI_HEADINGPAIR, PIDDSI_HIDDENCOUNT, PIDDSI_LINECOUNT,
+    PIDDSI_LINKSDIRTY, PIDDSI_MANAGER, PIDDSI_MMCLIPCOUNT, PIDDSI_NOTECOUNT,
+    PIDDSI_PARCOUNT, PIDDSI_PRESFORMAT, PIDDSI_SCALE, PIDDSI_SLIDECOUNT,
+    PIDSI_APPNAME, PIDSI_AUTHOR, PIDSI_CHARCOUNT, PIDSI_COMMENTS,
+    PIDSI_CREATE_DTM, PIDSI_DOC_SECURITY, PIDSI_EDITTIME, PIDSI_KEYWORDS,
+    PIDSI_LASTAUTHOR, PIDSI_LASTPRINTED, PIDSI_LASTSAVE_DTM, PIDSI_PAGECOUNT,
+    PIDSI_REVNUMBER, PIDSI_SUBJECT, PIDSI_TEMPLATE, PIDSI_THUMBNAIL,
+    PIDSI_TITLE, PIDSI_WORDCOUNT, SUMMARY_INFORMATION_STREAM,
+};
+pub use crate::propset::value::{ClipboardData, PropertyValue};
+
+mod builder;
+pub mod codepage;
+mod name;
+mod summary;
+mod value;
+
+//===========================================================================//
+
+/// The property ID of a section's dictionary, which maps other property IDs
+/// to names.
+pub const PID_DICTIONARY: u32 = 0x0000_0000;
+/// The property ID of a section's code page, which determines how strings in
+/// that section are encoded.
+pub const PID_CODEPAGE: u32 = 0x0000_0001;
+/// The property ID of a section's locale identifier.
+pub const PID_LOCALE: u32 = 0x8000_0000;
+/// The property ID of a section's behavior flags (which determine whether
+/// property names in the dictionary are case-sensitive).
+pub const PID_BEHAVIOR: u32 = 0x8000_0003;
+
+const BYTE_ORDER_MARK: u16 = 0xfffe;
+const MAX_NUM_SECTIONS: usize = 2;
+
+macro_rules! malformed {
+    ($e:expr) => { invalid_data!("Malformed property set ({})", $e) };
+    ($fmt:expr, $($arg:tt)+) => {
+        invalid_data!("Malformed property set ({})", format!($fmt, $($arg)+))
+    };
+}
+
+//===========================================================================//
+
+/// The parsed contents of a property set stream.
+#[derive(Clone, Debug)]
+pub struct PropertySet {
+    version: u16,
+    system_identifier: u32,
+    clsid: Uuid,
+    sections: Vec<Section>,
+}
+
+impl PropertySet {
+    /// Reads and parses an entire property set stream.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<PropertySet> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        PropertySet::from_bytes(&data)
+    }
+
+    /// Parses a property set stream from the given bytes.
+    pub fn from_bytes(data: &[u8]) -> io::Result<PropertySet> {
+        let mut reader = Reader::new(data, 0, 0);
+        let byte_order = reader.u16()?;
+        if byte_order != BYTE_ORDER_MARK {
+            malformed!("invalid byte order mark 0x{:04x}", byte_order);
+        }
+        let version = reader.u16()?;
+        if version > 1 {
+            malformed!("unsupported version {}", version);
+        }
+        let system_identifier = reader.u32()?;
+        let clsid = reader.guid()?;
+        let num_sections = reader.u32()? as usize;
+        if num_sections == 0 || num_sections > MAX_NUM_SECTIONS {
+            malformed!("invalid number of sections {}", num_sections);
+        }
+        let mut headers = Vec::with_capacity(num_sections);
+        for _ in 0..num_sections {
+            let fmtid = reader.guid()?;
+            let offset = reader.u32()? as usize;
+            headers.push((fmtid, offset));
+        }
+        let mut sections = Vec::with_capacity(num_sections);
+        for (fmtid, offset) in headers {
+            sections.push(Section::parse(data, fmtid, offset)?);
+        }
+        Ok(PropertySet { version, system_identifier, clsid, sections })
+    }
+
+    /// Returns the property set format version (0 or 1).
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Returns the system identifier, which records the operating system
+    /// (and its version) that wrote the property set.
+    pub fn system_identifier(&self) -> u32 {
+        self.system_identifier
+    }
+
+    /// Returns the application-specific CLSID stored in the property set
+    /// header (often nil).
+    pub fn clsid(&self) -> &Uuid {
+        &self.clsid
+    }
+
+    /// Returns the sections of this property set, in the order they appear
+    /// in the stream.
+    pub fn sections(&self) -> &[Section] {
+        &self.sections
+    }
+
+    /// Returns the section with the given format ID, if any.
+    pub fn section(&self, fmtid: &Uuid) -> Option<&Section> {
+        self.sections.iter().find(|section| section.fmtid == *fmtid)
+    }
+
+    pub(crate) fn into_sections(self) -> Vec<Section> {
+        self.sections
+    }
+}
+
+//===========================================================================//
+
+/// One section of a property set, holding a set of properties that are all
+/// identified by the same format ID.
+#[derive(Clone, Debug)]
+pub struct Section {
+    fmtid: Uuid,
+    properties: BTreeMap<u32, PropertyValue>,
+    dictionary: BTreeMap<u32, String>,
+}
+
+impl Section {
+    /// Creates a new, empty section with the given format ID.  Until a code
+    /// page is set, strings in the section will be encoded in Windows-1252.
+    pub fn new(fmtid: Uuid) -> Section {
+        Section {
+            fmtid,
+            properties: BTreeMap::new(),
+            dictionary: BTreeMap::new(),
+        }
+    }
+
+    fn parse(data: &[u8], fmtid: Uuid, offset: usize) -> io::Result<Section> {
+        if offset >= data.len() {
+            malformed!("section offset {} is past end of stream", offset);
+        }
+        let mut reader = Reader::new(data, offset, offset);
+        let size = reader.u32()? as usize;
+        if size > data.len() - offset {
+            malformed!("section size {} is past end of stream", size);
+        }
+        let data = &data[..(offset + size)];
+        let mut reader = Reader::new(data, offset, offset + 4);
+        let num_properties = reader.count(8)?;
+        let mut offsets = Vec::with_capacity(num_properties);
+        for _ in 0..num_properties {
+            let id = reader.u32()?;
+            let property_offset = reader.u32()? as usize;
+            if property_offset >= size {
+                malformed!(
+                    "property 0x{:x} offset {} is past end of section",
+                    id,
+                    property_offset
+                );
+            }
+            offsets.push((id, offset + property_offset));
+        }
+
+        // The code page determines how all other strings in the section are
+        // decoded, so it must be read first.
+        let mut code_page = codepage::CP_WINDOWS_1252;
+        if let Some(&(_, pos)) =
+            offsets.iter().find(|&&(id, _)| id == PID_CODEPAGE)
+        {
+            let mut reader = Reader::new(data, offset, pos);
+            if reader.u16()? != VT_I2 {
+                malformed!("code page property is not VT_I2");
+            }
+            reader.u16()?;
+            code_page = reader.u16()?;
+        }
+
+        let mut properties = BTreeMap::new();
+        let mut dictionary = BTreeMap::new();
+        for (id, pos) in offsets {
+            let mut reader = Reader::new(data, offset, pos);
+            if id == PID_DICTIONARY {
+                dictionary = parse_dictionary(&mut reader, code_page)?;
+            } else {
+                let value = read_typed_value(&mut reader, code_page)?;
+                properties.insert(id, value);
+            }
+        }
+        Ok(Section { fmtid, properties, dictionary })
+    }
+
+    /// Returns the format ID that identifies this section.
+    pub fn fmtid(&self) -> &Uuid {
+        &self.fmtid
+    }
+
+    /// Returns the code page used for strings in this section (1252 if the
+    /// section doesn't specify one).
+    pub fn code_page(&self) -> u16 {
+        match self.properties.get(&PID_CODEPAGE) {
+            Some(&PropertyValue::I2(code_page)) => code_page as u16,
+            _ => codepage::CP_WINDOWS_1252,
+        }
+    }
+
+    /// Returns the locale identifier for this section, if it specifies one.
+    pub fn locale(&self) -> Option<u32> {
+        match self.properties.get(&PID_LOCALE) {
+            Some(&PropertyValue::UI4(locale)) => Some(locale),
+            _ => None,
+        }
+    }
+
+    /// Returns true if property names in this section's dictionary are
+    /// case-sensitive.
+    pub fn is_case_sensitive(&self) -> bool {
+        matches!(
+            self.properties.get(&PID_BEHAVIOR),
+            Some(&PropertyValue::UI4(behavior)) if behavior & 1 != 0
+        )
+    }
+
+    /// Returns the value of the property with the given ID, if present.
+    pub fn property(&self, id: u32) -> Option<&PropertyValue> {
+        self.properties.get(&id)
+    }
+
+    /// Returns all the properties in this section, keyed by property ID.
+    /// This includes the code page, locale and behavior properties, if
+    /// present, but not the dictionary.
+    pub fn properties(&self) -> &BTreeMap<u32, PropertyValue> {
+        &self.properties
+    }
+
+    /// Returns this section's dictionary, which maps property IDs to names.
+    /// This is empty if the section has no dictionary.
+    pub fn dictionary(&self) -> &BTreeMap<u32, String> {
+        &self.dictionary
+    }
+
+    /// Returns the name of the property with the given ID, if the dictionary
+    /// names it.
+    pub fn property_name(&self, id: u32) -> Option<&str> {
+        self.dictionary.get(&id).map(String::as_str)
+    }
+
+    /// Returns the value of the property with the given name (as recorded
+    /// in the dictionary), if present.  Unless the section is
+    /// case-sensitive, names are compared case-insensitively.
+    pub fn property_by_name(&self, name: &str) -> Option<&PropertyValue> {
+        self.properties.get(&self.id_for_name(name)?)
+    }
+
+    fn id_for_name(&self, name: &str) -> Option<u32> {
+        let case_sensitive = self.is_case_sensitive();
+        self.dictionary
+            .iter()
+            .find(|&(_, entry)| {
+                if case_sensitive {
+                    entry == name
+                } else {
+                    entry.to_lowercase() == name.to_lowercase()
+                }
+            })
+            .map(|(&id, _)| id)
+    }
+
+    /// Sets the code page used to encode strings in this section (see the
+    /// [`codepage`](codepage/index.html) module).
+    pub fn set_code_page(&mut self, code_page: u16) {
+        self.properties
+            .insert(PID_CODEPAGE, PropertyValue::I2(code_page as i16));
+    }
+
+    /// Sets the locale identifier for this section.
+    pub fn set_locale(&mut self, locale: u32) {
+        self.properties.insert(PID_LOCALE, PropertyValue::UI4(locale));
+    }
+
+    /// Sets whether property names in this section's dictionary are
+    /// case-sensitive.  Writing a case-sensitive section requires property
+    /// set format version 1, which older readers may not support.
+    pub fn set_case_sensitive(&mut self, case_sensitive: bool) {
+        if case_sensitive {
+            self.properties.insert(PID_BEHAVIOR, PropertyValue::UI4(1));
+        } else {
+            self.properties.remove(&PID_BEHAVIOR);
+        }
+    }
+
+    /// Sets the value of the property with the given ID, returning the
+    /// previous value, if any.  Returns an error if the ID is that of the
+    /// dictionary, or if it is that of the code page, locale, or behavior
+    /// property and the value has the wrong type.
+    pub fn set_property(
+        &mut self,
+        id: u32,
+        value: PropertyValue,
+    ) -> io::Result<Option<PropertyValue>> {
+        match (id, &value) {
+            (PID_DICTIONARY, _) => {
+                invalid_input!("Cannot set the dictionary as a property")
+            }
+            (PID_CODEPAGE, &PropertyValue::I2(_)) => {}
+            (PID_CODEPAGE, _) => {
+                invalid_input!("Code page property must be an I2")
+            }
+            (PID_LOCALE, &PropertyValue::UI4(_))
+            | (PID_BEHAVIOR, &PropertyValue::UI4(_)) => {}
+            (PID_LOCALE, _) | (PID_BEHAVIOR, _) => {
+                invalid_input!("Property 0x{:x} must be a UI4", id)
+            }
+            _ => {}
+        }
+        Ok(self.properties.insert(id, value))
+    }
+
+    /// Removes the property with the given ID (and its name, if any),
+    /// returning its value, if it was present.
+    pub fn remove_property(&mut self, id: u32) -> Option<PropertyValue> {
+        self.dictionary.remove(&id);
+        self.properties.remove(&id)
+    }
+
+    /// Sets the name of the property with the given ID in this section's
+    /// dictionary.
+    pub fn set_property_name(
+        &mut self,
+        id: u32,
+        name: &str,
+    ) -> io::Result<()> {
+        if id == PID_DICTIONARY || id == PID_CODEPAGE || id >= PID_LOCALE {
+            invalid_input!("Cannot name reserved property 0x{:x}", id);
+        }
+        if name.is_empty() || name.contains('\0') {
+            invalid_input!("Invalid property name {:?}", name);
+        }
+        if let Some(other) = self.id_for_name(name) {
+            if other != id {
+                already_exists!("Property name {:?} is already in use", name);
+            }
+        }
+        self.dictionary.insert(id, name.to_string());
+        Ok(())
+    }
+
+    /// Sets the value of the property with the given name, as is done for
+    /// user-defined properties.  If no property has that name yet, a new
+    /// property ID is allocated and named in the dictionary.  Returns the
+    /// property ID.
+    pub fn set_named_property(
+        &mut self,
+        name: &str,
+        value: PropertyValue,
+    ) -> io::Result<u32> {
+        let id = match self.id_for_name(name) {
+            Some(id) => id,
+            None => {
+                let last_id = self
+                    .properties
+                    .keys()
+                    .chain(self.dictionary.keys())
+                    .filter(|&&id| id < PID_LOCALE)
+                    .max()
+                    .cloned()
+                    .unwrap_or(PID_CODEPAGE);
+                let id = last_id.max(PID_CODEPAGE) + 1;
+                if id >= PID_LOCALE {
+                    invalid_input!("No property IDs left in section");
+                }
+                self.set_property_name(id, name)?;
+                id
+            }
+        };
+        self.set_property(id, value)?;
+        Ok(id)
+    }
+}
+
+fn parse_dictionary(
+    reader: &mut Reader,
+    code_page: u16,
+) -> io::Result<BTreeMap<u32, String>> {
+    let num_entries = reader.count(8)?;
+    let mut dictionary = BTreeMap::new();
+    for _ in 0..num_entries {
+        let id = reader.u32()?;
+        let len = reader.count(1)?;
+        let name = if code_page == codepage::CP_WINUNICODE {
+            let bytes = reader.bytes(len.saturating_mul(2))?;
+            reader.align();
+            codepage::decode(code_page, bytes)
+        } else {
+            codepage::decode(code_page, reader.bytes(len)?)
+        };
+        dictionary.insert(id, name);
+    }
+    Ok(dictionary)
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{PropertySet, PropertyValue, PID_CODEPAGE};
+    use uuid::Uuid;
+
+    const FMTID: Uuid =
+        Uuid::from_u128(0x0123_4567_89ab_cdef_0123_4567_89ab_cdef);
+
+    fn make_stream(section: &[u8]) -> Vec<u8> {
+        let mut data = vec![0xfe, 0xff, 0, 0, 2, 1, 0, 0];
+        data.extend_from_slice(&[0; 16]);
+        data.extend_from_slice(&1u32.to_le_bytes());
+        let (d1, d2, d3, d4) = FMTID.as_fields();
+        data.extend_from_slice(&d1.to_le_bytes());
+        data.extend_from_slice(&d2.to_le_bytes());
+        data.extend_from_slice(&d3.to_le_bytes());
+        data.extend_from_slice(d4);
+        data.extend_from_slice(&48u32.to_le_bytes());
+        data.extend_from_slice(section);
+        data
+    }
+
+    #[test]
+    fn unicode_dictionary() {
+        // Dictionary: one entry, "ab" (with NUL) in UTF-16, padded to 4.
+        let body = b"\x01\0\0\0\x03\0\0\0\x03\0\0\0a\0b\0\0\0\0\0";
+        let code_page = b"\x02\0\0\0\xb0\x04\0\0";
+        let value = b"\x03\0\0\0\x2a\0\0\0";
+        let header_len = 8 + 3 * 8;
+        let dict_offset = header_len as u32;
+        let cp_offset = dict_offset + body.len() as u32;
+        let value_offset = cp_offset + code_page.len() as u32;
+        let size = value_offset + value.len() as u32;
+        let mut section = Vec::new();
+        section.extend_from_slice(&size.to_le_bytes());
+        section.extend_from_slice(&3u32.to_le_bytes());
+        for &(id, offset) in
+            &[(0u32, dict_offset), (1, cp_offset), (3, value_offset)]
+        {
+            section.extend_from_slice(&id.to_le_bytes());
+            section.extend_from_slice(&offset.to_le_bytes());
+        }
+        section.extend_from_slice(body);
+        section.extend_from_slice(code_page);
+        section.extend_from_slice(value);
+
+        let set = PropertySet::from_bytes(&make_stream(&section)).unwrap();
+        assert_eq!(set.version(), 0);
+        assert_eq!(set.system_identifier(), 0x102);
+        assert_eq!(set.sections().len(), 1);
+        let section = set.section(&FMTID).unwrap();
+        assert_eq!(section.code_page(), 1200);
+        assert_eq!(
+            section.property(PID_CODEPAGE),
+            Some(&PropertyValue::I2(1200))
+        );
+        assert_eq!(section.property_name(3), Some("ab"));
+        assert_eq!(
+            section.property_by_name("AB"),
+            Some(&PropertyValue::I4(42))
+        );
+        assert!(!section.is_case_sensitive());
+        assert_eq!(section.locale(), None);
+    }
+
+    #[test]
+    fn malformed_headers_are_errors() {
+        assert!(PropertySet::from_bytes(b"").is_err());
+        let mut data = make_stream(b"\x08\0\0\0\0\0\0\0");
+        assert!(PropertySet::from_bytes(&data).is_ok());
+        data[0] = 0xff;
+        assert!(PropertySet::from_bytes(&data).is_err());
+        let mut data = make_stream(b"\x80\0\0\0\0\0\0\0");
+        assert!(PropertySet::from_bytes(&data).is_err());
+        data.truncate(40);
+        assert!(PropertySet::from_bytes(&data).is_err());
+        let data = make_stream(b"\x10\0\0\0\x01\0\0\0\x02\0\0\0\x40\0\0\0");
+        assert!(PropertySet::from_bytes(&data).is_err());
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,133 @@
+use crate::propset::{
+    FMTID_DOC_SUMMARY_INFORMATION, FMTID_SUMMARY_INFORMATION,
+    FMTID_USER_DEFINED_PROPERTIES,
+};
+use uuid::Uuid;
+
+//===========================================================================//
+
+const SUMMARY_INFORMATION_NAME: &str = "\u{5}SummaryInformation";
+const DOC_SUMMARY_INFORMATION_NAME: &str = "\u{5}DocumentSummaryInformation";
+
+/// The characters used to encode each five bits of an FMTID.
+const FMTID_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz012345";
+const BITS_PER_CHAR: usize = 5;
+const NUM_FMTID_CHARS: usize = 128usize.div_ceil(BITS_PER_CHAR);
+
+//===========================================================================//
+
+/// Returns the name of the stream that holds the property set with the
+/// given format ID, as specified by MS-OLEPS: the well-known summary
+/// information property sets have fixed names, and any other FMTID is
+/// encoded as `'\u{5}'` followed by 26 characters from the alphabet `a-z0-5`
+/// (with characters that start on a byte boundary upper-cased).
+pub fn fmtid_to_stream_name(fmtid: &Uuid) -> String {
+    if *fmtid == FMTID_SUMMARY_INFORMATION {
+        return SUMMARY_INFORMATION_NAME.to_string();
+    }
+    if *fmtid == FMTID_DOC_SUMMARY_INFORMATION
+        || *fmtid == FMTID_USER_DEFINED_PROPERTIES
+    {
+        return DOC_SUMMARY_INFORMATION_NAME.to_string();
+    }
+    let bits = u128::from_le_bytes(fmtid.to_bytes_le());
+    let mut name = String::with_capacity(1 + NUM_FMTID_CHARS);
+    name.push('\u{5}');
+    for index in 0..NUM_FMTID_CHARS {
+        let shift = index * BITS_PER_CHAR;
+        let chr = FMTID_ALPHABET[((bits >> shift) & 0x1f) as usize] as char;
+        name.push(if shift % 8 == 0 { chr.to_ascii_uppercase() } else { chr });
+    }
+    name
+}
+
+/// Returns the format ID of the property set held in a stream with the
+/// given name, or `None` if the name isn't a valid property set stream name
+/// (see [`fmtid_to_stream_name`](fn.fmtid_to_stream_name.html)).  Names are
+/// compared case-insensitively.
+pub fn stream_name_to_fmtid(name: &str) -> Option<Uuid> {
+    if name.eq_ignore_ascii_case(SUMMARY_INFORMATION_NAME) {
+        return Some(FMTID_SUMMARY_INFORMATION);
+    }
+    if name.eq_ignore_ascii_case(DOC_SUMMARY_INFORMATION_NAME) {
+        return Some(FMTID_DOC_SUMMARY_INFORMATION);
+    }
+    let chars = name.strip_prefix('\u{5}')?.as_bytes();
+    if chars.len() != NUM_FMTID_CHARS {
+        return None;
+    }
+    let mut bits: u128 = 0;
+    for (index, &chr) in chars.iter().enumerate() {
+        let chr = chr.to_ascii_lowercase();
+        let value = FMTID_ALPHABET.iter().position(|&c| c == chr)? as u128;
+        let shift = index * BITS_PER_CHAR;
+        // The last character holds only the top three bits; the rest must
+        // be zero.
+        if shift + BITS_PER_CHAR > 128 && (value >> (128 - shift)) != 0 {
+            return None;
+        }
+        bits |= value << shift;
+    }
+    Some(Uuid::from_bytes_le(bits.to_le_bytes()))
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{fmtid_to_stream_name, stream_name_to_fmtid};
+    use crate::propset::{
+        FMTID_DOC_SUMMARY_INFORMATION, FMTID_SUMMARY_INFORMATION,
+        FMTID_USER_DEFINED_PROPERTIES,
+    };
+    use uuid::Uuid;
+
+    #[test]
+    fn well_known_names() {
+        assert_eq!(
+            fmtid_to_stream_name(&FMTID_SUMMARY_INFORMATION),
+            "\u{5}SummaryInformation"
+        );
+        assert_eq!(
+            fmtid_to_stream_name(&FMTID_USER_DEFINED_PROPERTIES),
+            "\u{5}DocumentSummaryInformation"
+        );
+        assert_eq!(
+            stream_name_to_fmtid("\u{5}documentsummaryinformation"),
+            Some(FMTID_DOC_SUMMARY_INFORMATION)
+        );
+    }
+
+    #[test]
+    fn encoded_names() {
+        // The FMTID of the Windows Shell "Storage" property set.
+        let fmtid = Uuid::from_u128(0xb725f130_47ef_101a_a5f1_02608c9eebac);
+        let name = fmtid_to_stream_name(&fmtid);
+        assert_eq!(name, "\u{5}Qj2ls143Hsgarsg4Cayyipo3Mf");
+        assert_eq!(stream_name_to_fmtid(&name), Some(fmtid));
+        assert_eq!(stream_name_to_fmtid(&name.to_lowercase()), Some(fmtid));
+        for value in &[0u128, u128::MAX, 0x0123_4567_89ab_cdef << 17] {
+            let fmtid = Uuid::from_u128(*value);
+            let name = fmtid_to_stream_name(&fmtid);
+            assert_eq!(name.len(), 27);
+            assert_eq!(stream_name_to_fmtid(&name), Some(fmtid));
+        }
+    }
+
+    #[test]
+    fn invalid_names() {
+        assert_eq!(stream_name_to_fmtid("SummaryInformation"), None);
+        assert_eq!(stream_name_to_fmtid("\u{5}abc"), None);
+        assert_eq!(
+            stream_name_to_fmtid("\u{5}aaaaaaaaaaaaaaaaaaaaaaaaa9"),
+            None
+        );
+        // The last character may only use its low three bits.
+        assert_eq!(
+            stream_name_to_fmtid("\u{5}aaaaaaaaaaaaaaaaaaaaaaaaai"),
+            None
+        );
+    }
+}
+
+//===========================================================================//
@@ -0,0 +1,392 @@
+use crate::propset::{ClipboardData, PropertySet, PropertyValue, Section};
+use std::io;
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+//===========================================================================//
+
+/// The path of the stream that conventionally holds the summary information
+/// property set.
+pub const SUMMARY_INFORMATION_STREAM: &str = "/\u{5}SummaryInformation";
+/// The path of the stream that conventionally holds the document summary
+/// information property set.
+pub const DOC_SUMMARY_INFORMATION_STREAM: &str =
+    "/\u{5}DocumentSummaryInformation";
+
+/// The format ID of the `"\u{5}SummaryInformation"` property set.
+pub const FMTID_SUMMARY_INFORMATION: Uuid =
+    Uuid::from_u128(0xf29f85e0_4ff9_1068_ab91_08002b27b3d9);
+/// The format ID of the first section of the
+/// `"\u{5}DocumentSummaryInformation"` property set.
+pub const FMTID_DOC_SUMMARY_INFORMATION: Uuid =
+    Uuid::from_u128(0xd5cdd502_2e9c_101b_9397_08002b2cf9ae);
+/// The format ID of the second (user-defined properties) section of the
+/// `"\u{5}DocumentSummaryInformation"` property set.
+pub const FMTID_USER_DEFINED_PROPERTIES: Uuid =
+    Uuid::from_u128(0xd5cdd505_2e9c_101b_9397_08002b2cf9ae);
+
+/// The title of the document (`VT_LPSTR`).
+pub const PIDSI_TITLE: u32 = 0x02;
+/// The subject of the document (`VT_LPSTR`).
+pub const PIDSI_SUBJECT: u32 = 0x03;
+/// The author of the document (`VT_LPSTR`).
+pub const PIDSI_AUTHOR: u32 = 0x04;
+/// Keywords for the document (`VT_LPSTR`).
+pub const PIDSI_KEYWORDS: u32 = 0x05;
+/// Comments on the document (`VT_LPSTR`).
+pub const PIDSI_COMMENTS: u32 = 0x06;
+/// The template the document was created from (`VT_LPSTR`).
+pub const PIDSI_TEMPLATE: u32 = 0x07;
+/// The user who last saved the document (`VT_LPSTR`).
+pub const PIDSI_LASTAUTHOR: u32 = 0x08;
+/// The revision number of the document (`VT_LPSTR`).
+pub const PIDSI_REVNUMBER: u32 = 0x09;
+/// The total time spent editing the document (`VT_FILETIME`, as a duration).
+pub const PIDSI_EDITTIME: u32 = 0x0a;
+/// When the document was last printed (`VT_FILETIME`).
+pub const PIDSI_LASTPRINTED: u32 = 0x0b;
+/// When the document was created (`VT_FILETIME`).
+pub const PIDSI_CREATE_DTM: u32 = 0x0c;
+/// When the document was last saved (`VT_FILETIME`).
+pub const PIDSI_LASTSAVE_DTM: u32 = 0x0d;
+/// The number of pages in the document (`VT_I4`).
+pub const PIDSI_PAGECOUNT: u32 = 0x0e;
+/// The number of words in the document (`VT_I4`).
+pub const PIDSI_WORDCOUNT: u32 = 0x0f;
+/// The number of characters in the document (`VT_I4`).
+pub const PIDSI_CHARCOUNT: u32 = 0x10;
+/// A thumbnail image of the document (`VT_CF`).
+pub const PIDSI_THUMBNAIL: u32 = 0x11;
+/// The name of the application that created the document (`VT_LPSTR`).
+pub const PIDSI_APPNAME: u32 = 0x12;
+/// Security flags for the document (`VT_I4`).
+pub const PIDSI_DOC_SECURITY: u32 = 0x13;
+
+/// The category of the document (`VT_LPSTR`).
+pub const PIDDSI_CATEGORY: u32 = 0x02;
+/// The target format of a presentation (`VT_LPSTR`).
+pub const PIDDSI_PRESFORMAT: u32 = 0x03;
+/// The size of the document in bytes (`VT_I4`).
+pub const PIDDSI_BYTECOUNT: u32 = 0x04;
+/// The number of lines in the document (`VT_I4`).
+pub const PIDDSI_LINECOUNT: u32 = 0x05;
+/// The number of paragraphs in the document (`VT_I4`).
+pub const PIDDSI_PARCOUNT: u32 = 0x06;
+/// The number of slides in a presentation (`VT_I4`).
+pub const PIDDSI_SLIDECOUNT: u32 = 0x07;
+/// The number of slides with notes in a presentation (`VT_I4`).
+pub const PIDDSI_NOTECOUNT: u32 = 0x08;
+/// The number of hidden slides in a presentation (`VT_I4`).
+pub const PIDDSI_HIDDENCOUNT: u32 = 0x09;
+/// The number of multimedia clips in a presentation (`VT_I4`).
+pub const PIDDSI_MMCLIPCOUNT: u32 = 0x0a;
+/// Whether the thumbnail is cropped rather than scaled (`VT_BOOL`).
+pub const PIDDSI_SCALE: u32 = 0x0b;
+/// Headings and part counts for the document's parts
+/// (`VT_VECTOR | VT_VARIANT`).
+pub const PIDDSI_HEADINGPAIR: u32 = 0x0c;
+/// The titles of the document's parts (`VT_VECTOR | VT_LPSTR`).
+pub const PIDDSI_DOCPARTS: u32 = 0x0d;
+/// The manager of the document's author (`VT_LPSTR`).
+pub const PIDDSI_MANAGER: u32 = 0x0e;
+/// The company the document was written for (`VT_LPSTR`).
+pub const PIDDSI_COMPANY: u32 = 0x0f;
+/// Whether the document's links are out of date (`VT_BOOL`).
+pub const PIDDSI_LINKSDIRTY: u32 = 0x10;
+
+const FILETIME_TICKS_PER_SECOND: u64 = 10_000_000;
+
+//===========================================================================//
+
+/// Typed access to the properties of a `"\u{5}SummaryInformation"` property
+/// set.
+#[derive(Clone, Debug)]
+pub struct SummaryInformation {
+    section: Section,
+}
+
+impl SummaryInformation {
+    /// Extracts the summary information section from a property set,
+    /// returning an error if the property set has no such section.
+    pub fn from_property_set(
+        set: PropertySet,
+    ) -> io::Result<SummaryInformation> {
+        match take_section(set, &FMTID_SUMMARY_INFORMATION) {
+            Some(section) => Ok(SummaryInformation { section }),
+            None => invalid_data!("Not a SummaryInformation property set"),
+        }
+    }
+
+    /// Returns the underlying property set section, for access to
+    /// properties that don't have a dedicated accessor.
+    pub fn section(&self) -> &Section {
+        &self.section
+    }
+
+    /// Returns the title of the document.
+    pub fn title(&self) -> Option<&str> {
+        self.string(PIDSI_TITLE)
+    }
+
+    /// Returns the subject of the document.
+    pub fn subject(&self) -> Option<&str> {
+        self.string(PIDSI_SUBJECT)
+    }
+
+    /// Returns the author of the document.
+    pub fn author(&self) -> Option<&str> {
+        self.string(PIDSI_AUTHOR)
+    }
+
+    /// Returns the keywords for the document.
+    pub fn keywords(&self) -> Option<&str> {
+        self.string(PIDSI_KEYWORDS)
+    }
+
+    /// Returns the comments on the document.
+    pub fn comments(&self) -> Option<&str> {
+        self.string(PIDSI_COMMENTS)
+    }
+
+    /// Returns the name of the template the document was created from.
+    pub fn template(&self) -> Option<&str> {
+        self.string(PIDSI_TEMPLATE)
+    }
+
+    /// Returns the name of the user who last saved the document.
+    pub fn last_author(&self) -> Option<&str> {
+        self.string(PIDSI_LASTAUTHOR)
+    }
+
+    /// Returns the revision number of the document.
+    pub fn revision_number(&self) -> Option<&str> {
+        self.string(PIDSI_REVNUMBER)
+    }
+
+    /// Returns the name of the application that created the document.
+    pub fn application_name(&self) -> Option<&str> {
+        self.string(PIDSI_APPNAME)
+    }
+
+    /// Returns the total time spent editing the document.
+    pub fn edit_time(&self) -> Option<Duration> {
+        let ticks = self.section.property(PIDSI_EDITTIME)?.as_timestamp()?;
+        let ticks = ticks.filetime();
+        Some(Duration::new(
+            ticks / FILETIME_TICKS_PER_SECOND,
+            ((ticks % FILETIME_TICKS_PER_SECOND) * 100) as u32,
+        ))
+    }
+
+    /// Returns when the document was last printed.
+    pub fn last_printed(&self) -> Option<SystemTime> {
+        self.time(PIDSI_LASTPRINTED)
+    }
+
+    /// Returns when the document was created.
+    pub fn created(&self) -> Option<SystemTime> {
+        self.time(PIDSI_CREATE_DTM)
+    }
+
+    /// Returns when the document was last saved.
+    pub fn last_saved(&self) -> Option<SystemTime> {
+        self.time(PIDSI_LASTSAVE_DTM)
+    }
+
+    /// Returns the number of pages in the document.
+    pub fn page_count(&self) -> Option<i32> {
+        self.int(PIDSI_PAGECOUNT)
+    }
+
+    /// Returns the number of words in the document.
+    pub fn word_count(&self) -> Option<i32> {
+        self.int(PIDSI_WORDCOUNT)
+    }
+
+    /// Returns the number of characters in the document.
+    pub fn char_count(&self) -> Option<i32> {
+        self.int(PIDSI_CHARCOUNT)
+    }
+
+    /// Returns the document's security flags.
+    pub fn security(&self) -> Option<i32> {
+        self.int(PIDSI_DOC_SECURITY)
+    }
+
+    /// Returns the document's thumbnail image.
+    pub fn thumbnail(&self) -> Option<&ClipboardData> {
+        match self.section.property(PIDSI_THUMBNAIL)? {
+            PropertyValue::ClipboardData(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    fn string(&self, id: u32) -> Option<&str> {
+        self.section.property(id)?.as_str()
+    }
+
+    fn int(&self, id: u32) -> Option<i32> {
+        int_property(&self.section, id)
+    }
+
+    fn time(&self, id: u32) -> Option<SystemTime> {
+        self.section.property(id)?.as_timestamp()?.to_system_time()
+    }
+}
+
+//===========================================================================//
+
+/// Typed access to the properties of a
+/// `"\u{5}DocumentSummaryInformation"` property set, including its optional
+/// section of user-defined (custom) properties.
+#[derive(Clone, Debug)]
+pub struct DocumentSummaryInformation {
+    section: Section,
+    user_defined: Option<Section>,
+}
+
+impl DocumentSummaryInformation {
+    /// Extracts the document summary information sections from a property
+    /// set, returning an error if the property set has no such sections.
+    pub fn from_property_set(
+        set: PropertySet,
+    ) -> io::Result<DocumentSummaryInformation> {
+        let mut section = None;
+        let mut user_defined = None;
+        for candidate in set.into_sections() {
+            if *candidate.fmtid() == FMTID_DOC_SUMMARY_INFORMATION {
+                section = Some(candidate);
+            } else if *candidate.fmtid() == FMTID_USER_DEFINED_PROPERTIES {
+                user_defined = Some(candidate);
+            }
+        }
+        if section.is_none() && user_defined.is_none() {
+            invalid_data!("Not a DocumentSummaryInformation property set");
+        }
+        let section = section
+            .unwrap_or_else(|| Section::new(FMTID_DOC_SUMMARY_INFORMATION));
+        Ok(DocumentSummaryInformation { section, user_defined })
+    }
+
+    /// Returns the underlying property set section (not including
+    /// user-defined properties), for access to properties that don't have a
+    /// dedicated accessor.
+    pub fn section(&self) -> &Section {
+        &self.section
+    }
+
+    /// Returns the section of user-defined properties, if present.  The
+    /// names of these properties are stored in the section's dictionary.
+    pub fn user_defined_properties(&self) -> Option<&Section> {
+        self.user_defined.as_ref()
+    }
+
+    /// Returns the value of the user-defined property with the given name,
+    /// if present.
+    pub fn custom_property(&self, name: &str) -> Option<&PropertyValue> {
+        self.user_defined.as_ref()?.property_by_name(name)
+    }
+
+    /// Returns the category of the document.
+    pub fn category(&self) -> Option<&str> {
+        self.string(PIDDSI_CATEGORY)
+    }
+
+    /// Returns the target format of a presentation (e.g. "On-screen Show").
+    pub fn presentation_format(&self) -> Option<&str> {
+        self.string(PIDDSI_PRESFORMAT)
+    }
+
+    /// Returns the name of the manager of the document's author.
+    pub fn manager(&self) -> Option<&str> {
+        self.string(PIDDSI_MANAGER)
+    }
+
+    /// Returns the name of the company the document was written for.
+    pub fn company(&self) -> Option<&str> {
+        self.string(PIDDSI_COMPANY)
+    }
+
+    /// Returns the size of the document in bytes.
+    pub fn byte_count(&self) -> Option<i32> {
+        int_property(&self.section, PIDDSI_BYTECOUNT)
+    }
+
+    /// Returns the number of lines in the document.
+    pub fn line_count(&self) -> Option<i32> {
+        int_property(&self.section, PIDDSI_LINECOUNT)
+    }
+
+    /// Returns the number of paragraphs in the document.
+    pub fn paragraph_count(&self) -> Option<i32> {
+        int_property(&self.section, PIDDSI_PARCOUNT)
+    }
+
+    /// Returns the number of slides in a presentation.
+    pub fn slide_count(&self) -> Option<i32> {
+        int_property(&self.section, PIDDSI_SLIDECOUNT)
+    }
+
+    /// Returns the number of slides with notes in a presentation.
+    pub fn note_count(&self) -> Option<i32> {
+        int_property(&self.section, PIDDSI_NOTECOUNT)
+    }
+
+    /// Returns the number of hidden slides in a presentation.
+    pub fn hidden_count(&self) -> Option<i32> {
+        int_property(&self.section, PIDDSI_HIDDENCOUNT)
+    }
+
+    /// Returns the number of multimedia clips in a presentation.
+    pub fn multimedia_clip_count(&self) -> Option<i32> {
+        int_property(&self.section, PIDDSI_MMCLIPCOUNT)
+    }
+
+    /// Returns true if the thumbnail should be cropped rather than scaled.
+    pub fn scale_crop(&self) -> Option<bool> {
+        self.section.property(PIDDSI_SCALE)?.as_bool()
+    }
+
+    /// Returns true if the document's links are out of date.
+    pub fn links_dirty(&self) -> Option<bool> {
+        self.section.property(PIDDSI_LINKSDIRTY)?.as_bool()
+    }
+
+    /// Returns the document's heading pairs: each heading (e.g.
+    /// "Worksheets") together with the number of document parts listed
+    /// under it in [`titles_of_parts`](#method.titles_of_parts).
+    pub fn heading_pairs(&self) -> Option<Vec<(&str, i32)>> {
+        let values = self.section.property(PIDDSI_HEADINGPAIR)?.as_slice()?;
+        values
+            .chunks_exact(2)
+            .map(|pair| Some((pair[0].as_str()?, pair[1].as_i64()? as i32)))
+            .collect()
+    }
+
+    /// Returns the titles of the document's parts (e.g. the names of the
+    /// worksheets in a spreadsheet).
+    pub fn titles_of_parts(&self) -> Option<Vec<&str>> {
+        let values = self.section.property(PIDDSI_DOCPARTS)?.as_slice()?;
+        values.iter().map(PropertyValue::as_str).collect()
+    }
+
+    fn string(&self, id: u32) -> Option<&str> {
+        self.section.property(id)?.as_str()
+    }
+}
+
+//===========================================================================//
+
+fn take_section(set: PropertySet, fmtid: &Uuid) -> Option<Section> {
+    set.into_sections().into_iter().find(|section| section.fmtid() == fmtid)
+}
+
+fn int_property(section: &Section, id: u32) -> Option<i32> {
+    match *section.property(id)? {
+        PropertyValue::I2(value) => Some(value as i32),
+        PropertyValue::I4(value) | PropertyValue::Int(value) => Some(value),
+        _ => None,
+    }
+}
+
+//===========================================================================//
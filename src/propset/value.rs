@@ -0,0 +1,755 @@
+use crate::internal::Timestamp;
+use crate::propset::codepage;
+use std::io;
+use uuid::Uuid;
+
+//===========================================================================//
+
+pub(super) const VT_EMPTY: u16 = 0x0000;
+pub(super) const VT_NULL: u16 = 0x0001;
+pub(super) const VT_I2: u16 = 0x0002;
+pub(super) const VT_I4: u16 = 0x0003;
+pub(super) const VT_R4: u16 = 0x0004;
+pub(super) const VT_R8: u16 = 0x0005;
+pub(super) const VT_CY: u16 = 0x0006;
+pub(super) const VT_DATE: u16 = 0x0007;
+pub(super) const VT_BSTR: u16 = 0x0008;
+pub(super) const VT_ERROR: u16 = 0x000a;
+pub(super) const VT_BOOL: u16 = 0x000b;
+pub(super) const VT_VARIANT: u16 = 0x000c;
+pub(super) const VT_I1: u16 = 0x0010;
+pub(super) const VT_UI1: u16 = 0x0011;
+pub(super) const VT_UI2: u16 = 0x0012;
+pub(super) const VT_UI4: u16 = 0x0013;
+pub(super) const VT_I8: u16 = 0x0014;
+pub(super) const VT_UI8: u16 = 0x0015;
+pub(super) const VT_INT: u16 = 0x0016;
+pub(super) const VT_UINT: u16 = 0x0017;
+pub(super) const VT_LPSTR: u16 = 0x001e;
+pub(super) const VT_LPWSTR: u16 = 0x001f;
+pub(super) const VT_FILETIME: u16 = 0x0040;
+pub(super) const VT_BLOB: u16 = 0x0041;
+pub(super) const VT_CF: u16 = 0x0047;
+pub(super) const VT_CLSID: u16 = 0x0048;
+pub(super) const VT_VECTOR: u16 = 0x1000;
+
+//===========================================================================//
+
+/// The value of a single property in a property set.
+///
+/// Each variant corresponds to one of the MS-OLEPS property types (for
+/// example, `LpStr` is `VT_LPSTR`).  Strings are decoded according to the
+/// code page of the section they appear in.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyValue {
+    /// `VT_EMPTY`: no value.
+    Empty,
+    /// `VT_NULL`: a null value.
+    Null,
+    /// `VT_I1`: a signed 8-bit integer.
+    I1(i8),
+    /// `VT_UI1`: an unsigned 8-bit integer.
+    UI1(u8),
+    /// `VT_I2`: a signed 16-bit integer.
+    I2(i16),
+    /// `VT_UI2`: an unsigned 16-bit integer.
+    UI2(u16),
+    /// `VT_I4`: a signed 32-bit integer.
+    I4(i32),
+    /// `VT_UI4`: an unsigned 32-bit integer.
+    UI4(u32),
+    /// `VT_I8`: a signed 64-bit integer.
+    I8(i64),
+    /// `VT_UI8`: an unsigned 64-bit integer.
+    UI8(u64),
+    /// `VT_INT`: a signed 32-bit integer.
+    Int(i32),
+    /// `VT_UINT`: an unsigned 32-bit integer.
+    UInt(u32),
+    /// `VT_R4`: a 32-bit floating-point number.
+    R4(f32),
+    /// `VT_R8`: a 64-bit floating-point number.
+    R8(f64),
+    /// `VT_CY`: a currency amount, in units of 1/10000.
+    Currency(i64),
+    /// `VT_DATE`: an OLE automation date (days since December 30, 1899).
+    Date(f64),
+    /// `VT_BSTR`: a string in the section's code page.
+    BStr(String),
+    /// `VT_ERROR`: an `HRESULT` error code.
+    Error(u32),
+    /// `VT_BOOL`: a boolean.
+    Bool(bool),
+    /// `VT_LPSTR`: a string in the section's code page.
+    LpStr(String),
+    /// `VT_LPWSTR`: a UTF-16 string.
+    LpWStr(String),
+    /// `VT_FILETIME`: a timestamp (or, for some properties, such as the
+    /// total editing time, a duration).
+    FileTime(Timestamp),
+    /// `VT_BLOB`: an arbitrary sequence of bytes.
+    Blob(Vec<u8>),
+    /// `VT_CF`: clipboard data, such as a document thumbnail.
+    ClipboardData(ClipboardData),
+    /// `VT_CLSID`: a GUID.
+    ClsId(Uuid),
+    /// `VT_VECTOR` combined with any type other than `VT_VARIANT`: a list of
+    /// values that are all of the same type.
+    Vector(Vec<PropertyValue>),
+    /// `VT_VECTOR | VT_VARIANT`: a list of values, each of which can have a
+    /// different type.
+    VariantVector(Vec<PropertyValue>),
+    /// A property of a type that this crate doesn't support (such as
+    /// `VT_ARRAY` or `VT_STREAM`); the value is the raw type identifier.
+    Unsupported(u16),
+}
+
+impl PropertyValue {
+    /// Returns the string contained in this value, if it is a `BStr`,
+    /// `LpStr`, or `LpWStr`.
+    pub fn as_str(&self) -> Option<&str> {
+        match *self {
+            PropertyValue::BStr(ref string)
+            | PropertyValue::LpStr(ref string)
+            | PropertyValue::LpWStr(ref string) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// Returns the integer contained in this value, if it is any of the
+    /// integer types.
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            PropertyValue::I1(value) => Some(value as i64),
+            PropertyValue::UI1(value) => Some(value as i64),
+            PropertyValue::I2(value) => Some(value as i64),
+            PropertyValue::UI2(value) => Some(value as i64),
+            PropertyValue::I4(value) | PropertyValue::Int(value) => {
+                Some(value as i64)
+            }
+            PropertyValue::UI4(value) | PropertyValue::UInt(value) => {
+                Some(value as i64)
+            }
+            PropertyValue::I8(value) => Some(value),
+            PropertyValue::UI8(value) => Some(value as i64),
+            _ => None,
+        }
+    }
+
+    /// Returns the boolean contained in this value, if it is a `Bool`.
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            PropertyValue::Bool(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the timestamp contained in this value, if it is a `FileTime`.
+    pub fn as_timestamp(&self) -> Option<Timestamp> {
+        match *self {
+            PropertyValue::FileTime(timestamp) => Some(timestamp),
+            _ => None,
+        }
+    }
+
+    /// Returns the list of values contained in this value, if it is a
+    /// `Vector` or `VariantVector`.
+    pub fn as_slice(&self) -> Option<&[PropertyValue]> {
+        match *self {
+            PropertyValue::Vector(ref values)
+            | PropertyValue::VariantVector(ref values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+//===========================================================================//
+
+/// Clipboard data (a `VT_CF` property value), such as a document thumbnail.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ClipboardData {
+    format: i32,
+    data: Vec<u8>,
+}
+
+impl ClipboardData {
+    /// Creates clipboard data with the given format tag and data.  (The
+    /// format data, such as the clipboard format identifier, is included at
+    /// the start of `data`.)
+    pub fn new(format: i32, data: Vec<u8>) -> ClipboardData {
+        ClipboardData { format, data }
+    }
+
+    /// Returns the format tag: -1 for a Windows clipboard format, -2 for a
+    /// Macintosh clipboard format, -3 for a format identified by a GUID, a
+    /// positive length for a format identified by name, or 0 for none.
+    pub fn format(&self) -> i32 {
+        self.format
+    }
+
+    /// Returns the format data (if any) followed by the clipboard data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+//===========================================================================//
+
+macro_rules! malformed {
+    ($e:expr) => { invalid_data!("Malformed property set ({})", $e) };
+    ($fmt:expr, $($arg:tt)+) => {
+        invalid_data!("Malformed property set ({})", format!($fmt, $($arg)+))
+    };
+}
+
+/// A cursor over the bytes of a property set stream, where alignment is
+/// relative to the start of the current section.
+pub(super) struct Reader<'a> {
+    data: &'a [u8],
+    base: usize,
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8], base: usize, pos: usize) -> Reader<'a> {
+        Reader { data, base, pos }
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.pos)
+    }
+
+    pub fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if len > self.remaining() {
+            malformed!(
+                "{} bytes needed at offset {}, but only {} remain",
+                len,
+                self.pos,
+                self.remaining()
+            );
+        }
+        let bytes = &self.data[self.pos..(self.pos + len)];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn u16(&mut self) -> io::Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    pub fn u32(&mut self) -> io::Result<u32> {
+        let mut array = [0u8; 4];
+        array.copy_from_slice(self.bytes(4)?);
+        Ok(u32::from_le_bytes(array))
+    }
+
+    pub fn u64(&mut self) -> io::Result<u64> {
+        let mut array = [0u8; 8];
+        array.copy_from_slice(self.bytes(8)?);
+        Ok(u64::from_le_bytes(array))
+    }
+
+    pub fn guid(&mut self) -> io::Result<Uuid> {
+        let d1 = self.u32()?;
+        let d2 = self.u16()?;
+        let d3 = self.u16()?;
+        let mut d4 = [0u8; 8];
+        d4.copy_from_slice(self.bytes(8)?);
+        Ok(Uuid::from_fields(d1, d2, d3, &d4))
+    }
+
+    /// Skips padding up to the next multiple of four bytes from the start
+    /// of the section.
+    pub fn align(&mut self) {
+        let offset = self.pos - self.base;
+        self.pos += (4 - offset % 4) % 4;
+    }
+
+    /// Reads a length-prefixed count, checking that it's plausible given the
+    /// number of bytes remaining (each item taking at least `min_item_len`
+    /// bytes).
+    pub fn count(&mut self, min_item_len: usize) -> io::Result<usize> {
+        let count = self.u32()? as usize;
+        if count.saturating_mul(min_item_len) > self.remaining() {
+            malformed!("count of {} is too large", count);
+        }
+        Ok(count)
+    }
+}
+
+//===========================================================================//
+
+/// Reads a `TypedPropertyValue` (a type identifier followed by a value).
+pub(super) fn read_typed_value(
+    reader: &mut Reader,
+    code_page: u16,
+) -> io::Result<PropertyValue> {
+    let value_type = reader.u16()?;
+    let _padding = reader.u16()?;
+    if value_type & VT_VECTOR != 0 {
+        let element_type = value_type & !VT_VECTOR;
+        let count = reader.count(1)?;
+        let mut values = Vec::with_capacity(count);
+        for _ in 0..count {
+            values.push(if element_type == VT_VARIANT {
+                // Vectors can't be nested, so there's no need to recurse any
+                // deeper than this (and a malicious file can't make us).
+                let value_type = reader.u16()?;
+                let _padding = reader.u16()?;
+                if value_type & VT_VECTOR != 0 {
+                    malformed!("vectors cannot be nested");
+                }
+                let value = read_value(reader, value_type, code_page)?;
+                reader.align();
+                value
+            } else {
+                read_value(reader, element_type, code_page)?
+            });
+        }
+        reader.align();
+        return Ok(if element_type == VT_VARIANT {
+            PropertyValue::VariantVector(values)
+        } else {
+            PropertyValue::Vector(values)
+        });
+    }
+    let value = read_value(reader, value_type, code_page)?;
+    reader.align();
+    Ok(value)
+}
+
+/// Reads a single (non-vector) value of the given type, without any
+/// trailing padding (except for variable-length values, which are always
+/// padded).
+fn read_value(
+    reader: &mut Reader,
+    value_type: u16,
+    code_page: u16,
+) -> io::Result<PropertyValue> {
+    Ok(match value_type {
+        VT_EMPTY => PropertyValue::Empty,
+        VT_NULL => PropertyValue::Null,
+        VT_I1 => PropertyValue::I1(reader.u8()? as i8),
+        VT_UI1 => PropertyValue::UI1(reader.u8()?),
+        VT_I2 => PropertyValue::I2(reader.u16()? as i16),
+        VT_UI2 => PropertyValue::UI2(reader.u16()?),
+        VT_I4 => PropertyValue::I4(reader.u32()? as i32),
+        VT_UI4 => PropertyValue::UI4(reader.u32()?),
+        VT_INT => PropertyValue::Int(reader.u32()? as i32),
+        VT_UINT => PropertyValue::UInt(reader.u32()?),
+        VT_I8 => PropertyValue::I8(reader.u64()? as i64),
+        VT_UI8 => PropertyValue::UI8(reader.u64()?),
+        VT_R4 => PropertyValue::R4(f32::from_bits(reader.u32()?)),
+        VT_R8 => PropertyValue::R8(f64::from_bits(reader.u64()?)),
+        VT_CY => PropertyValue::Currency(reader.u64()? as i64),
+        VT_DATE => PropertyValue::Date(f64::from_bits(reader.u64()?)),
+        VT_ERROR => PropertyValue::Error(reader.u32()?),
+        VT_BOOL => PropertyValue::Bool(reader.u16()? != 0),
+        VT_BSTR => {
+            PropertyValue::BStr(read_code_page_string(reader, code_page)?)
+        }
+        VT_LPSTR => {
+            PropertyValue::LpStr(read_code_page_string(reader, code_page)?)
+        }
+        VT_LPWSTR => {
+            let len = reader.count(2)?;
+            let bytes = reader.bytes(len * 2)?;
+            reader.align();
+            PropertyValue::LpWStr(codepage::decode(
+                codepage::CP_WINUNICODE,
+                bytes,
+            ))
+        }
+        VT_FILETIME => {
+            let low = reader.u32()? as u64;
+            let high = reader.u32()? as u64;
+            PropertyValue::FileTime(Timestamp::from_filetime(
+                (high << 32) | low,
+            ))
+        }
+        VT_BLOB => {
+            let len = reader.count(1)?;
+            let bytes = reader.bytes(len)?.to_vec();
+            reader.align();
+            PropertyValue::Blob(bytes)
+        }
+        VT_CF => {
+            let len = reader.count(1)?;
+            if len < 4 {
+                malformed!("clipboard data size of {} is too small", len);
+            }
+            let format = reader.u32()? as i32;
+            let data = reader.bytes(len - 4)?.to_vec();
+            reader.align();
+            PropertyValue::ClipboardData(ClipboardData { format, data })
+        }
+        VT_CLSID => PropertyValue::ClsId(reader.guid()?),
+        _ => PropertyValue::Unsupported(value_type),
+    })
+}
+
+fn read_code_page_string(
+    reader: &mut Reader,
+    code_page: u16,
+) -> io::Result<String> {
+    let len = reader.count(1)?;
+    let bytes = reader.bytes(len)?;
+    reader.align();
+    Ok(codepage::decode(code_page, bytes))
+}
+
+//===========================================================================//
+
+impl PropertyValue {
+    /// Returns the type identifier for this value, or an error if it can't
+    /// be written.
+    fn value_type(&self) -> io::Result<u16> {
+        Ok(match *self {
+            PropertyValue::Empty => VT_EMPTY,
+            PropertyValue::Null => VT_NULL,
+            PropertyValue::I1(_) => VT_I1,
+            PropertyValue::UI1(_) => VT_UI1,
+            PropertyValue::I2(_) => VT_I2,
+            PropertyValue::UI2(_) => VT_UI2,
+            PropertyValue::I4(_) => VT_I4,
+            PropertyValue::UI4(_) => VT_UI4,
+            PropertyValue::I8(_) => VT_I8,
+            PropertyValue::UI8(_) => VT_UI8,
+            PropertyValue::Int(_) => VT_INT,
+            PropertyValue::UInt(_) => VT_UINT,
+            PropertyValue::R4(_) => VT_R4,
+            PropertyValue::R8(_) => VT_R8,
+            PropertyValue::Currency(_) => VT_CY,
+            PropertyValue::Date(_) => VT_DATE,
+            PropertyValue::BStr(_) => VT_BSTR,
+            PropertyValue::Error(_) => VT_ERROR,
+            PropertyValue::Bool(_) => VT_BOOL,
+            PropertyValue::LpStr(_) => VT_LPSTR,
+            PropertyValue::LpWStr(_) => VT_LPWSTR,
+            PropertyValue::FileTime(_) => VT_FILETIME,
+            PropertyValue::Blob(_) => VT_BLOB,
+            PropertyValue::ClipboardData(_) => VT_CF,
+            PropertyValue::ClsId(_) => VT_CLSID,
+            PropertyValue::Vector(ref values) => {
+                let element_type = match values.first() {
+                    Some(value) => value.value_type()?,
+                    None => VT_VARIANT,
+                };
+                match element_type {
+                    VT_EMPTY | VT_NULL | VT_INT | VT_UINT | VT_BLOB => {
+                        invalid_input!(
+                            "Type 0x{:x} is not allowed in a vector",
+                            element_type
+                        );
+                    }
+                    _ if element_type & VT_VECTOR != 0 => {
+                        invalid_input!("Vectors cannot be nested");
+                    }
+                    _ => {}
+                }
+                for value in values.iter() {
+                    if value.value_type()? != element_type {
+                        invalid_input!(
+                            "Vector elements must all have the same type"
+                        );
+                    }
+                }
+                VT_VECTOR | element_type
+            }
+            PropertyValue::VariantVector(ref values) => {
+                for value in values.iter() {
+                    if value.value_type()? & VT_VECTOR != 0 {
+                        invalid_input!("Vectors cannot be nested");
+                    }
+                }
+                VT_VECTOR | VT_VARIANT
+            }
+            PropertyValue::Unsupported(value_type) => {
+                invalid_input!(
+                    "Cannot write property of type 0x{:x}",
+                    value_type
+                )
+            }
+        })
+    }
+
+    /// Returns true if writing this value requires property set format
+    /// version 1.
+    pub(super) fn requires_version_1(&self) -> bool {
+        match *self {
+            PropertyValue::I1(_)
+            | PropertyValue::I8(_)
+            | PropertyValue::UI8(_)
+            | PropertyValue::Int(_)
+            | PropertyValue::UInt(_) => true,
+            PropertyValue::Vector(ref values)
+            | PropertyValue::VariantVector(ref values) => {
+                values.iter().any(PropertyValue::requires_version_1)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Appends a `TypedPropertyValue` to the given section data, followed by
+/// padding to a multiple of four bytes.
+pub(super) fn write_typed_value(
+    out: &mut Vec<u8>,
+    value: &PropertyValue,
+    code_page: u16,
+) -> io::Result<()> {
+    let value_type = value.value_type()?;
+    out.extend_from_slice(&value_type.to_le_bytes());
+    out.extend_from_slice(&[0, 0]);
+    match *value {
+        PropertyValue::Vector(ref values) => {
+            out.extend_from_slice(&(values.len() as u32).to_le_bytes());
+            for value in values.iter() {
+                write_value(out, value, code_page)?;
+            }
+        }
+        PropertyValue::VariantVector(ref values) => {
+            out.extend_from_slice(&(values.len() as u32).to_le_bytes());
+            for value in values.iter() {
+                write_typed_value(out, value, code_page)?;
+            }
+        }
+        _ => write_value(out, value, code_page)?,
+    }
+    pad(out);
+    Ok(())
+}
+
+/// Appends a single (non-vector) value, without its type identifier.
+fn write_value(
+    out: &mut Vec<u8>,
+    value: &PropertyValue,
+    code_page: u16,
+) -> io::Result<()> {
+    match *value {
+        PropertyValue::Empty | PropertyValue::Null => {}
+        PropertyValue::I1(value) => out.push(value as u8),
+        PropertyValue::UI1(value) => out.push(value),
+        PropertyValue::I2(value) => {
+            out.extend_from_slice(&value.to_le_bytes())
+        }
+        PropertyValue::UI2(value) => {
+            out.extend_from_slice(&value.to_le_bytes())
+        }
+        PropertyValue::I4(value) | PropertyValue::Int(value) => {
+            out.extend_from_slice(&value.to_le_bytes())
+        }
+        PropertyValue::UI4(value)
+        | PropertyValue::UInt(value)
+        | PropertyValue::Error(value) => {
+            out.extend_from_slice(&value.to_le_bytes())
+        }
+        PropertyValue::I8(value) | PropertyValue::Currency(value) => {
+            out.extend_from_slice(&value.to_le_bytes())
+        }
+        PropertyValue::UI8(value) => {
+            out.extend_from_slice(&value.to_le_bytes())
+        }
+        PropertyValue::R4(value) => {
+            out.extend_from_slice(&value.to_bits().to_le_bytes())
+        }
+        PropertyValue::R8(value) | PropertyValue::Date(value) => {
+            out.extend_from_slice(&value.to_bits().to_le_bytes())
+        }
+        PropertyValue::Bool(value) => {
+            let value: u16 = if value { 0xffff } else { 0 };
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+        PropertyValue::BStr(ref string) | PropertyValue::LpStr(ref string) => {
+            let bytes = encode_string(code_page, string)?;
+            out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            out.extend_from_slice(&bytes);
+            pad(out);
+        }
+        PropertyValue::LpWStr(ref string) => {
+            let bytes = encode_string(codepage::CP_WINUNICODE, string)?;
+            out.extend_from_slice(&(bytes.len() as u32 / 2).to_le_bytes());
+            out.extend_from_slice(&bytes);
+            pad(out);
+        }
+        PropertyValue::FileTime(timestamp) => {
+            out.extend_from_slice(&timestamp.filetime().to_le_bytes())
+        }
+        PropertyValue::Blob(ref data) => {
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(data);
+            pad(out);
+        }
+        PropertyValue::ClipboardData(ref clipboard) => {
+            let len = clipboard.data.len() as u32 + 4;
+            out.extend_from_slice(&len.to_le_bytes());
+            out.extend_from_slice(&clipboard.format.to_le_bytes());
+            out.extend_from_slice(&clipboard.data);
+            pad(out);
+        }
+        PropertyValue::ClsId(ref clsid) => {
+            out.extend_from_slice(&clsid.to_bytes_le())
+        }
+        PropertyValue::Vector(_)
+        | PropertyValue::VariantVector(_)
+        | PropertyValue::Unsupported(_) => unreachable!(),
+    }
+    Ok(())
+}
+
+/// Encodes a string in the given code page, including a terminating null
+/// character.
+pub(super) fn encode_string(
+    code_page: u16,
+    string: &str,
+) -> io::Result<Vec<u8>> {
+    let mut bytes = match codepage::encode(code_page, string) {
+        Some(bytes) => bytes,
+        None => invalid_input!(
+            "Cannot encode {:?} in code page {}",
+            string,
+            code_page
+        ),
+    };
+    bytes.push(0);
+    if code_page == codepage::CP_WINUNICODE {
+        bytes.push(0);
+    }
+    Ok(bytes)
+}
+
+/// Appends zero bytes up to the next multiple of four.
+pub(super) fn pad(out: &mut Vec<u8>) {
+    while out.len() % 4 != 0 {
+        out.push(0);
+    }
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{read_typed_value, write_typed_value, PropertyValue, Reader};
+    use crate::internal::Timestamp;
+
+    fn read(data: &[u8]) -> PropertyValue {
+        let mut reader = Reader::new(data, 0, 0);
+        let value = read_typed_value(&mut reader, 1252).unwrap();
+        assert_eq!(reader.remaining(), 0);
+        value
+    }
+
+    #[test]
+    fn scalars() {
+        assert_eq!(read(b"\x02\0\0\0\xfe\xff\0\0"), PropertyValue::I2(-2));
+        assert_eq!(read(b"\x03\0\0\0\x01\x02\0\0"), PropertyValue::I4(0x201));
+        assert_eq!(read(b"\x0b\0\0\0\xff\xff\0\0"), PropertyValue::Bool(true));
+        assert_eq!(
+            read(b"\x40\0\0\0\x01\0\0\0\x02\0\0\0"),
+            PropertyValue::FileTime(Timestamp::from_filetime(0x200000001))
+        );
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            read(b"\x1e\0\0\0\x04\0\0\0ab\x80\0"),
+            PropertyValue::LpStr("ab€".to_string())
+        );
+        assert_eq!(
+            read(b"\x1f\0\0\0\x02\0\0\0x\0\0\0"),
+            PropertyValue::LpWStr("x".to_string())
+        );
+    }
+
+    #[test]
+    fn vectors() {
+        assert_eq!(
+            read(b"\x02\x10\0\0\x03\0\0\0\x01\0\x02\0\x03\0\0\0"),
+            PropertyValue::Vector(vec![
+                PropertyValue::I2(1),
+                PropertyValue::I2(2),
+                PropertyValue::I2(3),
+            ])
+        );
+        assert_eq!(
+            read(
+                b"\x0c\x10\0\0\x02\0\0\0\x1e\0\0\0\x02\0\0\0a\0\0\0\
+                   \x03\0\0\0\x07\0\0\0"
+            ),
+            PropertyValue::VariantVector(vec![
+                PropertyValue::LpStr("a".to_string()),
+                PropertyValue::I4(7),
+            ])
+        );
+    }
+
+    #[test]
+    fn round_trip_values() {
+        let values = vec![
+            PropertyValue::Empty,
+            PropertyValue::I1(-3),
+            PropertyValue::UI2(7),
+            PropertyValue::UI8(u64::MAX),
+            PropertyValue::R8(1.5),
+            PropertyValue::Currency(-12345),
+            PropertyValue::Error(0x8000_4005),
+            PropertyValue::LpStr("caf\u{e9}".to_string()),
+            PropertyValue::LpWStr("\u{65e5}\u{672c}".to_string()),
+            PropertyValue::Blob(vec![1, 2, 3, 4, 5]),
+            PropertyValue::ClsId(uuid::Uuid::from_u128(0x1234)),
+            PropertyValue::Vector(vec![
+                PropertyValue::UI1(1),
+                PropertyValue::UI1(2),
+                PropertyValue::UI1(3),
+            ]),
+            PropertyValue::Vector(vec![
+                PropertyValue::BStr("a".to_string()),
+                PropertyValue::BStr("bcd".to_string()),
+            ]),
+            PropertyValue::VariantVector(vec![
+                PropertyValue::Bool(true),
+                PropertyValue::LpStr("xyz".to_string()),
+            ]),
+        ];
+        for value in values {
+            let mut data = Vec::new();
+            write_typed_value(&mut data, &value, 1252).unwrap();
+            assert_eq!(data.len() % 4, 0);
+            assert_eq!(read(&data), value);
+        }
+    }
+
+    #[test]
+    fn unwritable_values_are_errors() {
+        let values = vec![
+            PropertyValue::Unsupported(0x2003),
+            PropertyValue::LpStr("\u{65e5}".to_string()),
+            PropertyValue::Vector(vec![
+                PropertyValue::I2(1),
+                PropertyValue::I4(2),
+            ]),
+            PropertyValue::Vector(vec![PropertyValue::Blob(vec![])]),
+            PropertyValue::VariantVector(vec![PropertyValue::Vector(vec![])]),
+        ];
+        for value in values {
+            let mut data = Vec::new();
+            assert!(write_typed_value(&mut data, &value, 1252).is_err());
+        }
+    }
+
+    #[test]
+    fn truncated_values_are_errors() {
+        let mut reader = Reader::new(b"\x1e\0\0\0\x10\0\0\0ab", 0, 0);
+        assert!(read_typed_value(&mut reader, 1252).is_err());
+        let mut reader = Reader::new(b"\x02\x10\0\0\xff\xff\xff\xff", 0, 0);
+        assert!(read_typed_value(&mut reader, 1252).is_err());
+    }
+}
+
+//===========================================================================//
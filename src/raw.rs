@@ -0,0 +1,381 @@
+//! Read-only access to the low-level structures of a compound file.
+//!
+//! Most users of this crate should never need this module; it exists for
+//! tools that want to inspect the physical structure of a compound file
+//! (e.g. to draw a sector map or to debug interoperability problems) without
+//! reimplementing the MS-CFB parsing logic.  A [`RawView`](struct.RawView.html)
+//! is obtained from
+//! [`CompoundFile::raw`](../struct.CompoundFile.html#method.raw), and is a
+//! snapshot of the compound file's current in-memory state: it includes
+//! changes that haven't yet been flushed to the underlying file, and it does
+//! not change if the compound file is later modified.
+
+use crate::internal::{
+    chain_sector_ids, consts, DirEntry, MiniAllocator, Timestamp,
+};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{self, Read};
+use uuid::Uuid;
+
+pub use crate::internal::{Color, ObjType};
+
+//===========================================================================//
+
+/// The fields of a compound file header.
+///
+/// Fields that this crate keeps up to date as the file changes (such as the
+/// FAT and MiniFAT locations and the initial DIFAT entries) reflect the
+/// current in-memory state; all other fields are exactly as stored in the
+/// file when it was opened.
+#[derive(Clone)]
+pub struct RawHeader {
+    magic: [u8; 8],
+    clsid: [u8; 16],
+    minor_version: u16,
+    major_version: u16,
+    byte_order_mark: u16,
+    sector_shift: u16,
+    mini_sector_shift: u16,
+    num_dir_sectors: u32,
+    num_fat_sectors: u32,
+    first_dir_sector: u32,
+    transaction_signature: u32,
+    mini_stream_cutoff: u32,
+    first_minifat_sector: u32,
+    num_minifat_sectors: u32,
+    first_difat_sector: u32,
+    num_difat_sectors: u32,
+    initial_difat: [u32; consts::NUM_DIFAT_ENTRIES_IN_HEADER],
+}
+
+impl RawHeader {
+    pub(crate) fn read_from<R: Read>(reader: &mut R) -> io::Result<RawHeader> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        let mut clsid = [0u8; 16];
+        reader.read_exact(&mut clsid)?;
+        let minor_version = reader.read_u16::<LittleEndian>()?;
+        let major_version = reader.read_u16::<LittleEndian>()?;
+        let byte_order_mark = reader.read_u16::<LittleEndian>()?;
+        let sector_shift = reader.read_u16::<LittleEndian>()?;
+        let mini_sector_shift = reader.read_u16::<LittleEndian>()?;
+        reader.read_exact(&mut [0u8; 6])?; // reserved field
+        let num_dir_sectors = reader.read_u32::<LittleEndian>()?;
+        let num_fat_sectors = reader.read_u32::<LittleEndian>()?;
+        let first_dir_sector = reader.read_u32::<LittleEndian>()?;
+        let transaction_signature = reader.read_u32::<LittleEndian>()?;
+        let mini_stream_cutoff = reader.read_u32::<LittleEndian>()?;
+        let first_minifat_sector = reader.read_u32::<LittleEndian>()?;
+        let num_minifat_sectors = reader.read_u32::<LittleEndian>()?;
+        let first_difat_sector = reader.read_u32::<LittleEndian>()?;
+        let num_difat_sectors = reader.read_u32::<LittleEndian>()?;
+        let mut initial_difat = [0u32; consts::NUM_DIFAT_ENTRIES_IN_HEADER];
+        for entry in initial_difat.iter_mut() {
+            *entry = reader.read_u32::<LittleEndian>()?;
+        }
+        Ok(RawHeader {
+            magic,
+            clsid,
+            minor_version,
+            major_version,
+            byte_order_mark,
+            sector_shift,
+            mini_sector_shift,
+            num_dir_sectors,
+            num_fat_sectors,
+            first_dir_sector,
+            transaction_signature,
+            mini_stream_cutoff,
+            first_minifat_sector,
+            num_minifat_sectors,
+            first_difat_sector,
+            num_difat_sectors,
+            initial_difat,
+        })
+    }
+
+    /// Returns the header signature (which should always be `D0 CF 11 E0 A1
+    /// B1 1A E1`).
+    pub fn magic(&self) -> &[u8; 8] {
+        &self.magic
+    }
+
+    /// Returns the header CLSID field (which the spec says must be all
+    /// zeros).
+    pub fn clsid(&self) -> &[u8; 16] {
+        &self.clsid
+    }
+
+    /// Returns the minor version number (which should be `0x003E`).
+    pub fn minor_version(&self) -> u16 {
+        self.minor_version
+    }
+
+    /// Returns the major version number (3 or 4).
+    pub fn major_version(&self) -> u16 {
+        self.major_version
+    }
+
+    /// Returns the byte order mark (which should be `0xFFFE`).
+    pub fn byte_order_mark(&self) -> u16 {
+        self.byte_order_mark
+    }
+
+    /// Returns the sector shift, i.e. the base-2 logarithm of the sector
+    /// length (9 for version 3, or 12 for version 4).
+    pub fn sector_shift(&self) -> u16 {
+        self.sector_shift
+    }
+
+    /// Returns the mini sector shift (which should be 6).
+    pub fn mini_sector_shift(&self) -> u16 {
+        self.mini_sector_shift
+    }
+
+    /// Returns the number of directory sectors field (which is always zero
+    /// for version 3 files that follow the spec).
+    pub fn num_dir_sectors(&self) -> u32 {
+        self.num_dir_sectors
+    }
+
+    /// Returns the number of FAT sectors.
+    pub fn num_fat_sectors(&self) -> u32 {
+        self.num_fat_sectors
+    }
+
+    /// Returns the starting sector of the directory chain.
+    pub fn first_dir_sector(&self) -> u32 {
+        self.first_dir_sector
+    }
+
+    /// Returns the transaction signature number (which is unused by this
+    /// crate, and usually zero).
+    pub fn transaction_signature(&self) -> u32 {
+        self.transaction_signature
+    }
+
+    /// Returns the mini stream cutoff size (which should be 4096).
+    pub fn mini_stream_cutoff(&self) -> u32 {
+        self.mini_stream_cutoff
+    }
+
+    /// Returns the starting sector of the MiniFAT chain.
+    pub fn first_minifat_sector(&self) -> u32 {
+        self.first_minifat_sector
+    }
+
+    /// Returns the number of MiniFAT sectors.
+    pub fn num_minifat_sectors(&self) -> u32 {
+        self.num_minifat_sectors
+    }
+
+    /// Returns the starting sector of the DIFAT chain.
+    pub fn first_difat_sector(&self) -> u32 {
+        self.first_difat_sector
+    }
+
+    /// Returns the number of DIFAT sectors.
+    pub fn num_difat_sectors(&self) -> u32 {
+        self.num_difat_sectors
+    }
+
+    /// Returns the first 109 DIFAT entries, which are stored in the header.
+    pub fn initial_difat(&self) -> &[u32] {
+        &self.initial_difat
+    }
+}
+
+//===========================================================================//
+
+/// A single directory entry, as stored in the directory.
+#[derive(Clone)]
+pub struct RawDirEntry {
+    name: String,
+    dir_entry: DirEntry,
+}
+
+impl RawDirEntry {
+    fn new(dir_entry: DirEntry) -> RawDirEntry {
+        RawDirEntry { name: dir_entry.name_string(), dir_entry }
+    }
+
+    /// Returns the name of the entry (empty for unallocated entries), with
+    /// any invalid UTF-16 replaced by U+FFFD.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the entry as raw UTF-16 code units.
+    pub fn name_utf16(&self) -> &[u16] {
+        &self.dir_entry.name
+    }
+
+    /// Returns the object type of the entry.
+    pub fn obj_type(&self) -> ObjType {
+        self.dir_entry.obj_type
+    }
+
+    /// Returns the red-black tree color of the entry.
+    pub fn color(&self) -> Color {
+        self.dir_entry.color
+    }
+
+    /// Returns the stream ID of the left sibling of the entry, or
+    /// `0xFFFFFFFF` (`NOSTREAM`) if there is none.
+    pub fn left_sibling(&self) -> u32 {
+        self.dir_entry.left_sibling
+    }
+
+    /// Returns the stream ID of the right sibling of the entry, or
+    /// `0xFFFFFFFF` (`NOSTREAM`) if there is none.
+    pub fn right_sibling(&self) -> u32 {
+        self.dir_entry.right_sibling
+    }
+
+    /// Returns the stream ID of the root of the entry's child tree, or
+    /// `0xFFFFFFFF` (`NOSTREAM`) if there is none.
+    pub fn child(&self) -> u32 {
+        self.dir_entry.child
+    }
+
+    /// Returns the CLSID of the entry.
+    pub fn clsid(&self) -> &Uuid {
+        &self.dir_entry.clsid
+    }
+
+    /// Returns the user-defined state bits of the entry.
+    pub fn state_bits(&self) -> u32 {
+        self.dir_entry.state_bits
+    }
+
+    /// Returns the creation time of the entry.
+    pub fn creation_time(&self) -> Timestamp {
+        self.dir_entry.creation_time
+    }
+
+    /// Returns the modified time of the entry.
+    pub fn modified_time(&self) -> Timestamp {
+        self.dir_entry.modified_time
+    }
+
+    /// Returns the starting sector of the entry's stream (a mini sector
+    /// index for streams that live in the mini stream).
+    pub fn start_sector(&self) -> u32 {
+        self.dir_entry.start_sector
+    }
+
+    /// Returns the length of the entry's stream, in bytes.
+    pub fn stream_len(&self) -> u64 {
+        self.dir_entry.stream_len
+    }
+}
+
+//===========================================================================//
+
+/// A read-only snapshot of the low-level structures of a compound file.
+#[derive(Clone)]
+pub struct RawView {
+    header: RawHeader,
+    difat_sectors: Vec<u32>,
+    difat: Vec<u32>,
+    fat: Vec<u32>,
+    minifat: Vec<u32>,
+    dir_entries: Vec<RawDirEntry>,
+}
+
+impl RawView {
+    pub(crate) fn new<F>(
+        minialloc: &MiniAllocator<F>,
+        initial_header: &RawHeader,
+    ) -> io::Result<RawView> {
+        let directory = minialloc.directory();
+        let allocator = directory.allocator();
+        // The directory may be tracking fewer entries than will fit in its
+        // sectors; the rest of the sectors are filled with unallocated
+        // entries, so include those too.
+        let dir_entries_per_sector =
+            minialloc.version().dir_entries_per_sector();
+        let num_dir_entries = directory.num_dir_entries();
+        let mut dir_entries: Vec<RawDirEntry> = (0..num_dir_entries)
+            .map(|stream_id| {
+                RawDirEntry::new(directory.dir_entry(stream_id as u32).clone())
+            })
+            .collect();
+        while dir_entries.len() % dir_entries_per_sector != 0 {
+            dir_entries.push(RawDirEntry::new(DirEntry::unallocated()));
+        }
+        let difat = allocator.difat();
+        let difat_sector_ids = allocator.difat_sector_ids();
+        let next_sector = |sector_id| minialloc.next_sector(sector_id);
+        let num_minifat_sectors =
+            chain_sector_ids(minialloc.minifat_start_sector(), next_sector)?
+                .len() as u32;
+        let mut initial_difat =
+            [consts::FREE_SECTOR; consts::NUM_DIFAT_ENTRIES_IN_HEADER];
+        for (entry, &sector_id) in initial_difat.iter_mut().zip(difat) {
+            *entry = sector_id;
+        }
+        let header = RawHeader {
+            num_fat_sectors: difat.len() as u32,
+            first_dir_sector: directory.dir_start_sector(),
+            first_minifat_sector: minialloc.minifat_start_sector(),
+            num_minifat_sectors,
+            first_difat_sector: difat_sector_ids
+                .first()
+                .copied()
+                .unwrap_or(consts::END_OF_CHAIN),
+            num_difat_sectors: difat_sector_ids.len() as u32,
+            initial_difat,
+            ..initial_header.clone()
+        };
+        Ok(RawView {
+            header,
+            difat_sectors: difat_sector_ids.to_vec(),
+            difat: difat.to_vec(),
+            fat: allocator.fat().to_vec(),
+            minifat: minialloc.minifat().to_vec(),
+            dir_entries,
+        })
+    }
+
+    /// Returns the file header.
+    pub fn header(&self) -> &RawHeader {
+        &self.header
+    }
+
+    /// Returns the sector IDs of the DIFAT sectors, in chain order (not
+    /// including the part of the DIFAT stored in the header).
+    pub fn difat_sectors(&self) -> &[u32] {
+        &self.difat_sectors
+    }
+
+    /// Returns the full DIFAT, i.e. the sector IDs of all FAT sectors in
+    /// order (including those listed in the header).
+    pub fn difat(&self) -> &[u32] {
+        &self.difat
+    }
+
+    /// Returns the full FAT, with one entry for every sector in the file.
+    pub fn fat(&self) -> &[u32] {
+        &self.fat
+    }
+
+    /// Returns the full MiniFAT, with one entry for every mini sector in the
+    /// mini stream.
+    pub fn minifat(&self) -> &[u32] {
+        &self.minifat
+    }
+
+    /// Returns all directory entries (including unallocated ones), indexed
+    /// by stream ID.
+    pub fn dir_entries(&self) -> &[RawDirEntry] {
+        &self.dir_entries
+    }
+
+    /// Returns the directory entry with the given stream ID, if any.
+    pub fn dir_entry(&self, stream_id: u32) -> Option<&RawDirEntry> {
+        self.dir_entries.get(stream_id as usize)
+    }
+}
+
+//===========================================================================//
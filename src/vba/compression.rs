@@ -0,0 +1,356 @@
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use fnv::FnvHashMap;
+use std::io::{self, Read, Write};
+
+//===========================================================================//
+
+const CONTAINER_SIGNATURE: u8 = 0x01;
+const CHUNK_SIGNATURE: u16 = 0b011;
+const CHUNK_SIZE_MASK: u16 = 0x0fff;
+const CHUNK_COMPRESSED_FLAG: u16 = 0x8000;
+const MAX_CHUNK_DATA_LEN: usize = 4096;
+const MIN_MATCH_LEN: usize = 3;
+
+//===========================================================================//
+
+/// Decompresses an entire MS-OVBA `CompressedContainer` from the reader,
+/// writing the decompressed data to the writer.  Returns the number of
+/// decompressed bytes written.
+///
+/// This is the run-length encoding used for the `dir` stream and the source
+/// code of each module in a VBA project.
+pub fn decompress<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+) -> io::Result<u64> {
+    if reader.read_u8()? != CONTAINER_SIGNATURE {
+        invalid_data!("Invalid VBA compressed container signature");
+    }
+    let mut total: u64 = 0;
+    let mut chunk = Vec::with_capacity(MAX_CHUNK_DATA_LEN);
+    let mut output = Vec::with_capacity(MAX_CHUNK_DATA_LEN);
+    loop {
+        let low_byte = match reader.read_u8() {
+            Ok(byte) => byte,
+            Err(ref error) if error.kind() == io::ErrorKind::UnexpectedEof => {
+                break;
+            }
+            Err(error) => return Err(error),
+        };
+        let header = u16::from_le_bytes([low_byte, reader.read_u8()?]);
+        if (header >> 12) & 0b111 != CHUNK_SIGNATURE {
+            invalid_data!(
+                "Invalid VBA compressed chunk header 0x{:04x}",
+                header
+            );
+        }
+        let data_len = (header & CHUNK_SIZE_MASK) as usize + 1;
+        chunk.resize(data_len, 0);
+        reader.read_exact(&mut chunk)?;
+        output.clear();
+        if header & CHUNK_COMPRESSED_FLAG == 0 {
+            output.extend_from_slice(&chunk);
+        } else {
+            decompress_chunk(&chunk, &mut output)?;
+        }
+        writer.write_all(&output)?;
+        total += output.len() as u64;
+    }
+    Ok(total)
+}
+
+fn decompress_chunk(mut chunk: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+    while let Some((&flags, rest)) = chunk.split_first() {
+        chunk = rest;
+        for bit in 0..8 {
+            if chunk.is_empty() {
+                break;
+            }
+            if flags & (1 << bit) == 0 {
+                output.push(chunk[0]);
+                chunk = &chunk[1..];
+                continue;
+            }
+            let token = chunk.read_u16::<LittleEndian>()?;
+            let (length_mask, offset_shift) = copy_token_help(output.len());
+            let length = (token & length_mask) as usize + MIN_MATCH_LEN;
+            let offset = (token >> offset_shift) as usize + 1;
+            if offset > output.len() {
+                invalid_data!(
+                    "VBA copy token offset {} is out of range at {}",
+                    offset,
+                    output.len()
+                );
+            }
+            if output.len() + length > MAX_CHUNK_DATA_LEN {
+                invalid_data!("VBA compressed chunk decompresses too large");
+            }
+            let start = output.len() - offset;
+            for index in start..(start + length) {
+                let byte = output[index];
+                output.push(byte);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns the length mask and offset shift for a copy token at the given
+/// position within the decompressed chunk (the `CopyToken Help` procedure
+/// from MS-OVBA).
+fn copy_token_help(position: usize) -> (u16, u16) {
+    let mut bit_count: u16 = 4;
+    while (1usize << bit_count) < position {
+        bit_count += 1;
+    }
+    (0xffff >> bit_count, 16 - bit_count)
+}
+
+//===========================================================================//
+
+/// Compresses all of the data from the reader into an MS-OVBA
+/// `CompressedContainer`, writing it to the writer.  Returns the number of
+/// compressed bytes written.
+///
+/// As the specification requires, a final chunk that can't be compressed is
+/// stored raw and padded out to 4096 bytes with zeros, so decompressing the
+/// result can yield trailing zero bytes that weren't in the original data.
+pub fn compress<R: Read, W: Write>(
+    mut reader: R,
+    mut writer: W,
+) -> io::Result<u64> {
+    writer.write_u8(CONTAINER_SIGNATURE)?;
+    let mut total: u64 = 1;
+    let mut input = vec![0u8; MAX_CHUNK_DATA_LEN];
+    let mut compressed = Vec::with_capacity(MAX_CHUNK_DATA_LEN + 2);
+    loop {
+        let mut len = 0;
+        while len < input.len() {
+            match reader.read(&mut input[len..]) {
+                Ok(0) => break,
+                Ok(count) => len += count,
+                Err(ref error)
+                    if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+        if len == 0 {
+            break;
+        }
+        compressed.clear();
+        compress_chunk(&input[..len], &mut compressed);
+        if compressed.len() <= MAX_CHUNK_DATA_LEN {
+            // The size field holds the chunk size (including the two-byte
+            // header) minus three.
+            let header = CHUNK_COMPRESSED_FLAG
+                | (CHUNK_SIGNATURE << 12)
+                | (compressed.len() - 1) as u16;
+            writer.write_u16::<LittleEndian>(header)?;
+            writer.write_all(&compressed)?;
+            total += 2 + compressed.len() as u64;
+        } else {
+            input[len..].iter_mut().for_each(|byte| *byte = 0);
+            let header = (CHUNK_SIGNATURE << 12) | CHUNK_SIZE_MASK;
+            writer.write_u16::<LittleEndian>(header)?;
+            writer.write_all(&input)?;
+            total += 2 + input.len() as u64;
+        }
+        if len < MAX_CHUNK_DATA_LEN {
+            break;
+        }
+    }
+    Ok(total)
+}
+
+fn compress_chunk(input: &[u8], output: &mut Vec<u8>) {
+    let mut matcher = Matcher::new(input);
+    let mut position = 0;
+    while position < input.len() {
+        let flags_index = output.len();
+        output.push(0);
+        for bit in 0..8 {
+            if position >= input.len() {
+                break;
+            }
+            let (length_mask, offset_shift) = copy_token_help(position);
+            let max_len = (length_mask as usize + MIN_MATCH_LEN)
+                .min(input.len() - position);
+            let (offset, len) = matcher.longest_match(position, max_len);
+            let len = if len >= MIN_MATCH_LEN {
+                let token = (((offset - 1) as u16) << offset_shift)
+                    | (len - MIN_MATCH_LEN) as u16;
+                output.write_u16::<LittleEndian>(token).unwrap();
+                output[flags_index] |= 1 << bit;
+                len
+            } else {
+                output.push(input[position]);
+                1
+            };
+            for index in position..(position + len) {
+                matcher.insert(index);
+            }
+            position += len;
+        }
+    }
+}
+
+//===========================================================================//
+
+/// Finds earlier occurrences of data within a chunk being compressed, by
+/// chaining together positions that start with the same three bytes.
+struct Matcher<'a> {
+    input: &'a [u8],
+    latest: FnvHashMap<&'a [u8], usize>,
+    previous: Vec<Option<usize>>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(input: &'a [u8]) -> Matcher<'a> {
+        Matcher {
+            input,
+            latest: FnvHashMap::default(),
+            previous: vec![None; input.len()],
+        }
+    }
+
+    fn key(&self, position: usize) -> Option<&'a [u8]> {
+        self.input.get(position..(position + MIN_MATCH_LEN))
+    }
+
+    fn insert(&mut self, position: usize) {
+        if let Some(key) = self.key(position) {
+            self.previous[position] = self.latest.insert(key, position);
+        }
+    }
+
+    /// Returns the offset and length of the longest match for the data at
+    /// `position` (which must not have been inserted yet), preferring the
+    /// nearest one when there are ties, as the MS-OVBA `Matching` procedure
+    /// does.
+    fn longest_match(
+        &self,
+        position: usize,
+        max_len: usize,
+    ) -> (usize, usize) {
+        let mut best = (0, 0);
+        let mut candidate = match self.key(position) {
+            Some(key) if max_len >= MIN_MATCH_LEN => {
+                self.latest.get(key).copied()
+            }
+            _ => None,
+        };
+        while let Some(start) = candidate {
+            let len = (0..max_len)
+                .take_while(|&index| {
+                    self.input[start + index] == self.input[position + index]
+                })
+                .count();
+            if len > best.1 {
+                best = (position - start, len);
+                if len == max_len {
+                    break;
+                }
+            }
+            candidate = self.previous[start];
+        }
+        best
+    }
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{compress, decompress};
+
+    fn compress_bytes(data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        let len = compress(data, &mut output).unwrap();
+        assert_eq!(len, output.len() as u64);
+        output
+    }
+
+    fn decompress_bytes(data: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        let len = decompress(data, &mut output).unwrap();
+        assert_eq!(len, output.len() as u64);
+        output
+    }
+
+    // The examples from section 3.2 of MS-OVBA.
+    const EXAMPLES: &[(&[u8], &[u8])] = &[
+        (
+            b"abcdefghijklmnopqrstuv.",
+            b"\x01\x19\xb0\x00abcdefgh\x00ijklmnop\x00qrstuv.",
+        ),
+        (
+            b"#aaabcdefaaaaghijaaaaaklaaamnopqaaaaaaaaaaaarstuvwxyzaaa",
+            b"\x01\x2f\xb0\x00#aaabcde\x82f\x00\x70aghij\x01\x38\x08akl\
+              \x00\x30mnop\x06q\x02\x70\x04\x10rstuv\x10wxyz\x00\x3c",
+        ),
+        (&[b'a'; 73], b"\x01\x03\xb0\x02a\x45\x00"),
+    ];
+
+    #[test]
+    fn spec_examples() {
+        for &(decompressed, compressed) in EXAMPLES {
+            assert_eq!(decompress_bytes(compressed), decompressed);
+            let recompressed = compress_bytes(decompressed);
+            assert_eq!(recompressed.len(), compressed.len());
+            assert_eq!(decompress_bytes(&recompressed), decompressed);
+        }
+        // The second example picks different (but equally long) matches in
+        // a few places, so only the others are reproduced byte-for-byte.
+        assert_eq!(compress_bytes(EXAMPLES[0].0), EXAMPLES[0].1);
+        assert_eq!(compress_bytes(EXAMPLES[2].0), EXAMPLES[2].1);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut text = Vec::new();
+        for line in 0..500 {
+            text.extend_from_slice(
+                format!("Debug.Print \"Line {}\" & vbCrLf\r\n", line)
+                    .as_bytes(),
+            );
+        }
+        // Pseudo-random bytes, which don't compress.
+        let mut state: u32 = 1;
+        let noise: Vec<u8> = (0..8192)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as u8
+            })
+            .collect();
+        for data in &[&[][..], &text[..], &text[..4096], &noise[..]] {
+            let compressed = compress_bytes(data);
+            assert_eq!(&decompress_bytes(&compressed), data);
+        }
+        assert!(compress_bytes(&text).len() < text.len() / 4);
+        // A short incompressible final chunk is padded out to 4096 bytes.
+        let compressed = compress_bytes(&noise[..(4096 + 4000)]);
+        let decompressed = decompress_bytes(&compressed);
+        assert_eq!(decompressed.len(), 8192);
+        assert_eq!(&decompressed[..8096], &noise[..8096]);
+    }
+
+    #[test]
+    fn invalid() {
+        let (_, compressed) = EXAMPLES[1];
+        let mut output = Vec::new();
+        assert!(decompress(&b""[..], &mut output).is_err());
+        assert!(decompress(&b"\x02"[..], &mut output).is_err());
+        // Bad chunk signature.
+        assert!(decompress(&b"\x01\x19\xa0\x00abc"[..], &mut output).is_err());
+        // Truncated chunk.
+        for len in 2..compressed.len() {
+            assert!(decompress(&compressed[..len], &mut output).is_err());
+        }
+        // Copy token pointing before the start of the chunk.
+        assert!(
+            decompress(&b"\x01\x03\xb0\x01\x45\x00"[..], &mut output).is_err()
+        );
+    }
+}
+
+//===========================================================================//
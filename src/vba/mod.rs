@@ -0,0 +1,53 @@
+//! Extraction of VBA macro source code, as described in [MS-OVBA](
+//! https://learn.microsoft.com/en-us/openspecs/office_file_formats/ms-ovba).
+//!
+//! A VBA project is stored as a storage (the *project root*) containing a
+//! `VBA` storage, within which the `dir` stream lists the project's modules
+//! and one stream per module holds that module's source code.  Both the
+//! `dir` stream and the source code are compressed with the MS-OVBA
+//! run-length encoding, which is available on its own through the
+//! [`compress`](fn.compress.html) and [`decompress`](fn.decompress.html)
+//! functions.
+//!
+//! Word documents keep their project root at `/Macros`, Excel workbooks at
+//! `/_VBA_PROJECT_CUR`, and the `vbaProject.bin` part of an Office Open XML
+//! file is itself a compound file whose root storage is the project root;
+//! [`CompoundFile::vba_project`](
+//! ../struct.CompoundFile.html#method.vba_project) looks in each of these
+//! places.  PowerPoint documents store their project as a compressed
+//! compound file embedded within the `PowerPoint Document` stream, so once
+//! it has been extracted from there, it can be opened as a compound file of
+//! its own.
+//!
+//! # Example usage
+//!
+//! ```no_run
+//! let mut comp = cfb::open("path/to/cfb/file").unwrap();
+//! if let Some(project) = comp.vba_project().unwrap() {
+//!     for module in project.modules() {
+//!         println!("' {}\n{}", module.name(), module.source());
+//!     }
+//! }
+//! ```
+
+pub use crate::vba::compression::{compress, decompress};
+pub use crate::vba::project::{ModuleKind, VbaModule, VbaProject};
+
+mod compression;
+mod project;
+
+//===========================================================================//
+
+/// The name of the storage, within a project root, that holds the `dir`
+/// stream and the module streams.
+pub const VBA_STORAGE: &str = "VBA";
+/// The name of the stream, within the `VBA` storage, that describes the
+/// project and its modules.
+pub const DIR_STREAM: &str = "dir";
+
+/// The paths at which Word and Excel documents (and `vbaProject.bin` files)
+/// keep their VBA project roots.
+pub(crate) const PROJECT_ROOTS: &[&str] =
+    &["/Macros", "/_VBA_PROJECT_CUR", "/"];
+
+//===========================================================================//
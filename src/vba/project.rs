@@ -0,0 +1,416 @@
+use crate::propset::codepage;
+use crate::vba::decompress;
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{self, Read};
+
+//===========================================================================//
+
+const PROJECTCODEPAGE: u16 = 0x0003;
+const PROJECTNAME: u16 = 0x0004;
+const PROJECTDOCSTRING: u16 = 0x0005;
+const PROJECTVERSION: u16 = 0x0009;
+const PROJECTMODULES: u16 = 0x000f;
+const DIR_TERMINATOR: u16 = 0x0010;
+const REFERENCENAME: u16 = 0x0016;
+const REFERENCENAME_UNICODE: u16 = 0x003e;
+const MODULENAME: u16 = 0x0019;
+const MODULESTREAMNAME: u16 = 0x001a;
+const MODULEDOCSTRING: u16 = 0x001c;
+const MODULETYPE_PROCEDURAL: u16 = 0x0021;
+const MODULETYPE_DOCUMENT: u16 = 0x0022;
+const MODULEREADONLY: u16 = 0x0025;
+const MODULEPRIVATE: u16 = 0x0028;
+const MODULE_TERMINATOR: u16 = 0x002b;
+const MODULEOFFSET: u16 = 0x0031;
+const MODULESTREAMNAME_UNICODE: u16 = 0x0032;
+const PROJECTDOCSTRING_UNICODE: u16 = 0x0040;
+const MODULENAME_UNICODE: u16 = 0x0047;
+const MODULEDOCSTRING_UNICODE: u16 = 0x0048;
+
+// The PROJECTVERSION record's size field is always 4, but it is followed by
+// six bytes of data (a major and minor version number).
+const PROJECTVERSION_DATA_LEN: usize = 6;
+
+//===========================================================================//
+
+/// The kind of a VBA module.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum ModuleKind {
+    /// A procedural (standard) module.
+    Procedural,
+    /// A document module, class module, or designer module (e.g.
+    /// `ThisDocument` or `Sheet1`).
+    Document,
+}
+
+//===========================================================================//
+
+/// A VBA module: its name and properties from the `dir` stream, and its
+/// decompressed source code.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VbaModule {
+    name: String,
+    stream_name: String,
+    doc_string: String,
+    offset: u32,
+    kind: ModuleKind,
+    read_only: bool,
+    private: bool,
+    source_bytes: Vec<u8>,
+    source: String,
+}
+
+impl VbaModule {
+    fn new(name: String) -> VbaModule {
+        VbaModule {
+            stream_name: name.clone(),
+            name,
+            doc_string: String::new(),
+            offset: 0,
+            kind: ModuleKind::Procedural,
+            read_only: false,
+            private: false,
+            source_bytes: Vec::new(),
+            source: String::new(),
+        }
+    }
+
+    /// Returns the name of the module (e.g. `"ThisDocument"`).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the name of the stream within the `VBA` storage that holds
+    /// the module's source code.
+    pub fn stream_name(&self) -> &str {
+        &self.stream_name
+    }
+
+    /// Returns the module's description (empty if it has none).
+    pub fn doc_string(&self) -> &str {
+        &self.doc_string
+    }
+
+    /// Returns the offset within the module's stream at which its
+    /// compressed source code begins (the `MODULEOFFSET` record); the bytes
+    /// before it are a cache of compiled code.
+    pub fn offset(&self) -> u32 {
+        self.offset
+    }
+
+    /// Returns the kind of the module.
+    pub fn kind(&self) -> ModuleKind {
+        self.kind
+    }
+
+    /// Returns true if the module is marked read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns true if the module is marked private.
+    pub fn is_private(&self) -> bool {
+        self.private
+    }
+
+    /// Returns the module's decompressed source code, decoded from the
+    /// project's code page.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the module's decompressed source code as raw bytes in the
+    /// project's code page.
+    pub fn source_bytes(&self) -> &[u8] {
+        &self.source_bytes
+    }
+
+    /// Reads the module's stream, decompressing the source code that begins
+    /// at the module's offset.
+    pub(crate) fn read_source<R: Read>(
+        &mut self,
+        mut reader: R,
+        code_page: u16,
+    ) -> io::Result<()> {
+        let skipped = io::copy(
+            &mut (&mut reader).take(self.offset as u64),
+            &mut io::sink(),
+        )?;
+        if skipped < self.offset as u64 {
+            invalid_data!(
+                "Offset {} of VBA module {:?} is past the end of its stream",
+                self.offset,
+                self.name
+            );
+        }
+        let mut source = Vec::new();
+        decompress(reader, &mut source)?;
+        self.source = codepage::decode(code_page, &source);
+        self.source_bytes = source;
+        Ok(())
+    }
+}
+
+//===========================================================================//
+
+/// A VBA project, as described by the `dir` stream in its `VBA` storage.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VbaProject {
+    name: String,
+    doc_string: String,
+    code_page: u16,
+    references: Vec<String>,
+    modules: Vec<VbaModule>,
+}
+
+impl VbaProject {
+    /// Reads and parses a compressed `dir` stream.  The modules of the
+    /// returned project have empty source code, since that is stored in
+    /// separate streams; use [`CompoundFile::vba_project_at`](
+    /// ../struct.CompoundFile.html#method.vba_project_at) to read a project
+    /// along with its source code.
+    pub fn read_dir<R: Read>(reader: R) -> io::Result<VbaProject> {
+        let mut data = Vec::new();
+        decompress(reader, &mut data)?;
+        VbaProject::from_dir_bytes(&data)
+    }
+
+    /// Parses the records of an already-decompressed `dir` stream.
+    pub fn from_dir_bytes(data: &[u8]) -> io::Result<VbaProject> {
+        let mut project = VbaProject {
+            name: String::new(),
+            doc_string: String::new(),
+            code_page: codepage::CP_WINDOWS_1252,
+            references: Vec::new(),
+            modules: Vec::new(),
+        };
+        let mut module: Option<VbaModule> = None;
+        let mut reader = data;
+        loop {
+            if reader.is_empty() {
+                invalid_data!("VBA dir stream has no terminator");
+            }
+            let id = reader.read_u16::<LittleEndian>()?;
+            let mut len = reader.read_u32::<LittleEndian>()? as usize;
+            if id == PROJECTVERSION {
+                len = PROJECTVERSION_DATA_LEN;
+            }
+            if len > reader.len() {
+                invalid_data!(
+                    "VBA dir record 0x{:04x} has length {}, but only {} \
+                     bytes remain",
+                    id,
+                    len,
+                    reader.len()
+                );
+            }
+            let (record, rest) = reader.split_at(len);
+            reader = rest;
+            let code_page = project.code_page;
+            let ansi = || codepage::decode(code_page, record);
+            let unicode = || codepage::decode(codepage::CP_WINUNICODE, record);
+            if let Some(ref mut module) = module {
+                match id {
+                    MODULENAME_UNICODE => module.name = unicode(),
+                    MODULESTREAMNAME => module.stream_name = ansi(),
+                    MODULESTREAMNAME_UNICODE => module.stream_name = unicode(),
+                    MODULEDOCSTRING => module.doc_string = ansi(),
+                    MODULEDOCSTRING_UNICODE => module.doc_string = unicode(),
+                    MODULEOFFSET => module.offset = read_u32(record)?,
+                    MODULETYPE_PROCEDURAL => {
+                        module.kind = ModuleKind::Procedural
+                    }
+                    MODULETYPE_DOCUMENT => module.kind = ModuleKind::Document,
+                    MODULEREADONLY => module.read_only = true,
+                    MODULEPRIVATE => module.private = true,
+                    _ => {}
+                }
+            }
+            match id {
+                PROJECTCODEPAGE => project.code_page = read_u16(record)?,
+                PROJECTNAME => project.name = ansi(),
+                PROJECTDOCSTRING => project.doc_string = ansi(),
+                PROJECTDOCSTRING_UNICODE => project.doc_string = unicode(),
+                REFERENCENAME => project.references.push(ansi()),
+                REFERENCENAME_UNICODE => {
+                    if let Some(name) = project.references.last_mut() {
+                        *name = unicode();
+                    }
+                }
+                PROJECTMODULES => {
+                    project.modules.reserve(read_u16(record)? as usize);
+                }
+                MODULENAME => {
+                    if module.is_some() {
+                        invalid_data!("Unterminated VBA module record");
+                    }
+                    module = Some(VbaModule::new(ansi()));
+                }
+                MODULE_TERMINATOR => match module.take() {
+                    Some(module) => project.modules.push(module),
+                    None => invalid_data!("Unexpected VBA module terminator"),
+                },
+                DIR_TERMINATOR => {
+                    if module.is_some() {
+                        invalid_data!("Unterminated VBA module record");
+                    }
+                    return Ok(project);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the name of the project (e.g. `"VBAProject"`).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the project's description (empty if it has none).
+    pub fn doc_string(&self) -> &str {
+        &self.doc_string
+    }
+
+    /// Returns the code page used for the project's strings and source
+    /// code.
+    pub fn code_page(&self) -> u16 {
+        self.code_page
+    }
+
+    /// Returns the names of the libraries and projects that this project
+    /// references (e.g. `"stdole"` or `"Office"`).
+    pub fn references(&self) -> &[String] {
+        &self.references
+    }
+
+    /// Returns the project's modules, in the order they are listed in the
+    /// `dir` stream.
+    pub fn modules(&self) -> &[VbaModule] {
+        &self.modules
+    }
+
+    /// Returns the module with the given name (compared
+    /// case-insensitively, as VBA does), if any.
+    pub fn module(&self, name: &str) -> Option<&VbaModule> {
+        self.modules
+            .iter()
+            .find(|module| module.name.eq_ignore_ascii_case(name))
+    }
+
+    pub(crate) fn modules_mut(&mut self) -> &mut [VbaModule] {
+        &mut self.modules
+    }
+}
+
+fn read_u16(mut record: &[u8]) -> io::Result<u16> {
+    record.read_u16::<LittleEndian>()
+}
+
+fn read_u32(mut record: &[u8]) -> io::Result<u32> {
+    record.read_u32::<LittleEndian>()
+}
+
+//===========================================================================//
+
+#[cfg(test)]
+mod tests {
+    use super::{ModuleKind, VbaProject};
+
+    fn record(data: &mut Vec<u8>, id: u16, body: &[u8]) {
+        data.extend_from_slice(&id.to_le_bytes());
+        data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        data.extend_from_slice(body);
+    }
+
+    fn utf16(string: &str) -> Vec<u8> {
+        string.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn dir_stream() -> Vec<u8> {
+        let mut data = Vec::new();
+        record(&mut data, 0x0001, &1u32.to_le_bytes());
+        record(&mut data, 0x0002, &0x409u32.to_le_bytes());
+        record(&mut data, 0x0014, &0x409u32.to_le_bytes());
+        record(&mut data, 0x0003, &1252u16.to_le_bytes());
+        record(&mut data, 0x0004, b"VBAProject");
+        record(&mut data, 0x0005, b"Caf\xe9");
+        record(&mut data, 0x0040, &utf16("Caf\u{e9} \u{263a}"));
+        record(&mut data, 0x0006, b"");
+        record(&mut data, 0x003d, b"");
+        record(&mut data, 0x0007, &0u32.to_le_bytes());
+        record(&mut data, 0x0008, &0u32.to_le_bytes());
+        // PROJECTVERSION has six bytes of data despite its size field.
+        record(&mut data, 0x0009, &0x1234u32.to_le_bytes());
+        data.extend_from_slice(&0x10u16.to_le_bytes());
+        record(&mut data, 0x000c, b"");
+        record(&mut data, 0x003c, b"");
+        record(&mut data, 0x0016, b"stdole");
+        record(&mut data, 0x003e, &utf16("stdole"));
+        record(&mut data, 0x000d, b"\x04\0\0\0*\\G{}\0\0\0\0\0\0");
+        record(&mut data, 0x000f, &2u16.to_le_bytes());
+        record(&mut data, 0x0013, &0xffffu16.to_le_bytes());
+
+        record(&mut data, 0x0019, b"Module1");
+        record(&mut data, 0x0047, &utf16("Module1"));
+        record(&mut data, 0x001a, b"Module1");
+        record(&mut data, 0x0032, &utf16("Module1"));
+        record(&mut data, 0x001c, b"");
+        record(&mut data, 0x0048, &utf16(""));
+        record(&mut data, 0x0031, &0x1a3u32.to_le_bytes());
+        record(&mut data, 0x001e, &0u32.to_le_bytes());
+        record(&mut data, 0x002c, &0xffffu16.to_le_bytes());
+        record(&mut data, 0x0021, b"");
+        record(&mut data, 0x002b, b"");
+
+        record(&mut data, 0x0019, b"ThisDocument");
+        record(&mut data, 0x001a, b"ThisDoc");
+        record(&mut data, 0x0031, &0u32.to_le_bytes());
+        record(&mut data, 0x0022, b"");
+        record(&mut data, 0x0025, b"");
+        record(&mut data, 0x0028, b"");
+        record(&mut data, 0x002b, b"");
+        record(&mut data, 0x0010, b"");
+        data
+    }
+
+    #[test]
+    fn parse_dir() {
+        let project = VbaProject::from_dir_bytes(&dir_stream()).unwrap();
+        assert_eq!(project.name(), "VBAProject");
+        assert_eq!(project.doc_string(), "Caf\u{e9} \u{263a}");
+        assert_eq!(project.code_page(), 1252);
+        assert_eq!(project.references(), &["stdole".to_string()]);
+        assert_eq!(project.modules().len(), 2);
+
+        let module = &project.modules()[0];
+        assert_eq!(module.name(), "Module1");
+        assert_eq!(module.stream_name(), "Module1");
+        assert_eq!(module.offset(), 0x1a3);
+        assert_eq!(module.kind(), ModuleKind::Procedural);
+        assert!(!module.is_read_only());
+        assert!(!module.is_private());
+
+        let module = project.module("thisdocument").unwrap();
+        assert_eq!(module.stream_name(), "ThisDoc");
+        assert_eq!(module.offset(), 0);
+        assert_eq!(module.kind(), ModuleKind::Document);
+        assert!(module.is_read_only());
+        assert!(module.is_private());
+        assert!(project.module("Module2").is_none());
+    }
+
+    #[test]
+    fn invalid_dir() {
+        let data = dir_stream();
+        for len in 0..data.len() {
+            assert!(VbaProject::from_dir_bytes(&data[..len]).is_err());
+        }
+        // A module without a terminator.
+        let start = data.windows(7).position(|w| w == b"Module1").unwrap();
+        let mut data = data[..(start + 7)].to_vec();
+        data.extend_from_slice(b"\x10\0\0\0\0\0");
+        assert!(VbaProject::from_dir_bytes(&data).is_err());
+    }
+}
+
+//===========================================================================//
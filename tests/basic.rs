@@ -1,3 +1,7 @@
+// These tests predate the lints below; in particular, they exercise `Seek`
+// with `SeekFrom::Current(0)` on purpose.
+#![allow(clippy::needless_range_loop, clippy::seek_from_current)]
+
 use cfb::{CompoundFile, Entry, Version};
 use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
 use std::path::Path;
@@ -122,6 +126,33 @@ fn create_directory_tree() {
     assert!(read_storage_to_vec(&comp, "/foo/bar").is_empty());
 }
 
+#[test]
+fn non_ascii_names() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).expect("create");
+    // Names are uppercased one UTF-16 code unit at a time, so sharp s stays
+    // as it is (sorting after ASCII letters), rather than becoming "SS".
+    comp.create_stream("/\u{df}").unwrap();
+    comp.create_stream("/T").unwrap();
+    comp.create_stream("/\u{3c3}\u{3c2}").unwrap();
+    comp.create_stream("/\u{1fb3}").unwrap();
+    comp.create_stream("/\u{131}").unwrap();
+    assert!(comp.create_new_stream("/\u{3a3}\u{3a3}").is_err());
+    assert!(comp.create_new_stream("/\u{1fbc}").is_err());
+    assert!(comp.create_new_stream("/i").is_err());
+    assert!(comp.is_stream("/\u{3a3}\u{3c3}"));
+
+    let cursor = comp.into_inner();
+    let comp = CompoundFile::open_strict(cursor).expect("open");
+    assert_eq!(
+        read_root_storage_to_vec(&comp),
+        vec!["\u{131}", "T", "\u{df}", "\u{1fb3}", "\u{3c3}\u{3c2}"]
+    );
+    assert!(comp.is_stream("/I"));
+    assert!(comp.is_stream("/t"));
+    assert!(!comp.is_stream("/SS"));
+}
+
 #[test]
 fn walk_directory_tree() {
     let cursor = Cursor::new(Vec::new());
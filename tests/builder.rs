@@ -0,0 +1,118 @@
+use cfb::{CompoundFile, CompoundFileBuilder, Version};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+//===========================================================================//
+
+fn create_data(len: usize) -> Vec<u8> {
+    let mut data = Vec::<u8>::new();
+    let mut number = 0u32;
+    while data.len() < len {
+        data.extend_from_slice(number.to_string().as_bytes());
+        number += 1;
+    }
+    data.truncate(len);
+    data
+}
+
+fn read_stream<F: Read + Seek>(
+    comp: &mut CompoundFile<F>,
+    path: &str,
+) -> Vec<u8> {
+    let mut stream = comp.open_stream(path).unwrap();
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).unwrap();
+    data
+}
+
+//===========================================================================//
+
+#[test]
+fn build_round_trip() {
+    for &version in &[Version::V3, Version::V4] {
+        let mut builder = CompoundFileBuilder::with_version(version);
+        builder.create_storage("/foo").unwrap();
+        builder.create_stream("/foo/small", create_data(1000)).unwrap();
+        builder.create_stream("/foo/large", create_data(9000)).unwrap();
+        builder.create_stream("/empty", Vec::new()).unwrap();
+        builder.append_to_stream("/empty", b"now it's not").unwrap();
+        builder.set_state_bits("/foo", 42).unwrap();
+        let data = builder.build(Vec::new()).unwrap();
+
+        let mut comp = CompoundFile::open_strict(Cursor::new(data)).unwrap();
+        assert_eq!(comp.version(), version);
+        assert_eq!(read_stream(&mut comp, "/foo/small"), create_data(1000));
+        assert_eq!(read_stream(&mut comp, "/foo/large"), create_data(9000));
+        assert_eq!(read_stream(&mut comp, "/empty"), b"now it's not");
+        assert_eq!(comp.entry("/foo").unwrap().state_bits(), 42);
+    }
+}
+
+#[test]
+fn build_is_repeatable() {
+    let mut builder = CompoundFileBuilder::new();
+    builder.create_stream("/foo", create_data(5000)).unwrap();
+    let data1 = builder.build(Vec::new()).unwrap();
+    let data2 = builder.build(Vec::new()).unwrap();
+    assert_eq!(data1, data2);
+}
+
+#[test]
+fn append_to_storage() {
+    let mut builder = CompoundFileBuilder::new();
+    builder.create_storage("/foo").unwrap();
+    let error = builder.append_to_stream("/foo", b"data").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    let error = builder.append_to_stream("/bar", b"data").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn smaller_than_incremental() {
+    // Build the same tree both incrementally and with the builder, writing
+    // the streams a bit at a time in the incremental case so that their
+    // chains end up interleaved.
+    let paths: Vec<String> = (0..20).map(|i| format!("/{}", i)).collect();
+    let mut comp = CompoundFile::create_with_version(
+        Version::V3,
+        Cursor::new(Vec::new()),
+    )
+    .unwrap();
+    let mut builder = CompoundFileBuilder::with_version(Version::V3);
+    for path in paths.iter() {
+        comp.create_stream(path).unwrap();
+        builder.create_stream(path, Vec::new()).unwrap();
+    }
+    for chunk in 0..10 {
+        for path in paths.iter() {
+            let data = create_data(1000 * (chunk + 1));
+            let data = &data[(1000 * chunk)..];
+            let mut stream = comp.open_stream(path).unwrap();
+            stream.seek(SeekFrom::End(0)).unwrap();
+            stream.write_all(data).unwrap();
+            builder.append_to_stream(path, data).unwrap();
+        }
+    }
+    let incremental = comp.into_inner().into_inner();
+    let built = builder.build(Vec::new()).unwrap();
+    assert!(built.len() <= incremental.len());
+
+    let mut comp = CompoundFile::open_strict(Cursor::new(built)).unwrap();
+    for path in paths.iter() {
+        assert_eq!(read_stream(&mut comp, path), create_data(10000));
+    }
+    let names: Vec<String> = comp
+        .read_root_storage()
+        .map(|entry| entry.name().to_string())
+        .collect();
+    let mut expected = paths.clone();
+    expected.sort_by_key(|path| (path.len(), path.clone()));
+    let expected: Vec<String> = expected
+        .iter()
+        .map(|path| Path::new(path).file_name().unwrap())
+        .map(|name| name.to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, expected);
+}
+
+//===========================================================================//
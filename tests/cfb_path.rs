@@ -0,0 +1,89 @@
+use cfb::{CfbPath, CfbPathBuf, CompoundFile};
+use std::io::{Cursor, Read, Write};
+
+//===========================================================================//
+
+#[test]
+fn cfb_paths_work_with_compound_file_apis() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    let storage = CfbPathBuf::parse("/ObjectPool/_1234").unwrap();
+    comp.create_storage_all(&storage).unwrap();
+    let stream = storage.join("\u{1}Ole").unwrap();
+    comp.create_stream(&stream).unwrap().write_all(b"ole").unwrap();
+    let summary = CfbPathBuf::parse("/\\x05SummaryInformation").unwrap();
+    comp.create_stream(&summary).unwrap();
+
+    assert!(comp.is_storage(CfbPath::new("/ObjectPool").unwrap()));
+    let mut data = Vec::new();
+    let path = CfbPathBuf::parse("ObjectPool/_1234/\\x01Ole").unwrap();
+    comp.open_stream(&path).unwrap().read_to_end(&mut data).unwrap();
+    assert_eq!(data, b"ole");
+
+    let displayed: Vec<String> = comp
+        .walk()
+        .map(|entry| entry.cfb_path().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        displayed,
+        vec![
+            "/",
+            "/ObjectPool",
+            "/ObjectPool/_1234",
+            "/ObjectPool/_1234/\\x01Ole",
+            "/\\x05SummaryInformation",
+        ]
+    );
+    for entry in comp.walk() {
+        let displayed = entry.cfb_path().unwrap().to_string();
+        let reparsed = CfbPathBuf::parse(&displayed).unwrap();
+        assert_eq!(Some(&*reparsed), entry.cfb_path());
+    }
+}
+
+#[test]
+fn paths_are_platform_independent() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_storage("/foo").unwrap();
+    comp.create_stream("/foo/bar").unwrap();
+    // Backslash is never treated as a separator, on any platform.
+    assert!(!comp.exists("\\foo\\bar"));
+    assert!(comp.create_stream("/foo\\bar").is_err());
+    // Entry paths always use forward slashes.
+    let entry = comp.entry("/foo/bar").unwrap();
+    assert_eq!(entry.path().to_str(), Some("/foo/bar"));
+    assert_eq!(entry.cfb_path().unwrap().parent().unwrap().as_str(), "/foo");
+    // Unlike std paths, CfbPaths never contain "..".
+    assert!(CfbPathBuf::parse("/foo/../foo/bar").is_err());
+}
+
+#[test]
+fn inexact_names_have_no_cfb_path() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    let dot_dot: Vec<u16> = "..".encode_utf16().collect();
+    comp.create_storage_utf16("/", &dot_dot).unwrap();
+    comp.create_stream_utf16("/", &[0x2e]).unwrap();
+    comp.create_storage_utf16("/", &[0x41, 0xd800]).unwrap();
+    comp.create_storage("/ok").unwrap();
+
+    let paths: Vec<(Vec<u16>, Option<String>)> = comp
+        .read_root_storage()
+        .map(|entry| {
+            let cfb_path = entry.cfb_path().map(|path| path.to_string());
+            (entry.name_utf16().to_vec(), cfb_path)
+        })
+        .collect();
+    assert_eq!(
+        paths,
+        vec![
+            (vec![0x2e], None),
+            (dot_dot, None),
+            (vec![0x41, 0xd800], None),
+            ("ok".encode_utf16().collect(), Some("/ok".to_string())),
+        ]
+    );
+}
+
+//===========================================================================//
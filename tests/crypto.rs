@@ -0,0 +1,240 @@
+#![cfg(feature = "crypto")]
+
+use cfb::crypto::{self, decrypt_package, encrypt_package, EncryptionOptions};
+use cfb::CompoundFile;
+use std::io::{self, Cursor, Read, Write};
+
+//===========================================================================//
+
+// These streams were generated offline by an independent implementation of
+// MS-OFFCRYPTO (in Python, using the `cryptography` package), encrypting
+// PLAINTEXT with PASSWORD.  The Agile vector uses AES-256 and SHA-512, with
+// a spin count of 1000 rather than Office's usual 100000 to keep the test
+// fast; the Standard vector uses AES-128 and SHA-1.
+const PASSWORD: &str = "Password1234_";
+const STANDARD_INFO: &[u8] =
+    include_bytes!("crypto/standard_EncryptionInfo.bin");
+const STANDARD_PACKAGE: &[u8] =
+    include_bytes!("crypto/standard_EncryptedPackage.bin");
+const AGILE_INFO: &[u8] = include_bytes!("crypto/agile_EncryptionInfo.bin");
+const AGILE_PACKAGE: &[u8] =
+    include_bytes!("crypto/agile_EncryptedPackage.bin");
+
+fn plaintext() -> Vec<u8> {
+    (0..5000).map(|index| (index % 251) as u8).collect()
+}
+
+fn make_file(info: &[u8], package: &[u8]) -> CompoundFile<Cursor<Vec<u8>>> {
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    comp.create_stream(crypto::ENCRYPTION_INFO_STREAM)
+        .unwrap()
+        .write_all(info)
+        .unwrap();
+    comp.create_stream(crypto::ENCRYPTED_PACKAGE_STREAM)
+        .unwrap()
+        .write_all(package)
+        .unwrap();
+    comp
+}
+
+/// Builds a `"\u{6}Primary"` stream for a transform with the given ID.
+fn primary_stream(transform_id: &str) -> Vec<u8> {
+    let mut id: Vec<u8> =
+        transform_id.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let id_len = id.len() as u32;
+    id.resize(id.len().div_ceil(4) * 4, 0);
+    let mut data = Vec::new();
+    data.extend_from_slice(&(12 + id.len() as u32).to_le_bytes());
+    data.extend_from_slice(&1u32.to_le_bytes());
+    data.extend_from_slice(&id_len.to_le_bytes());
+    data.extend_from_slice(&id);
+    data
+}
+
+//===========================================================================//
+
+#[test]
+fn decrypt_standard() {
+    let mut comp = make_file(STANDARD_INFO, STANDARD_PACKAGE);
+    assert!(crypto::is_encrypted_package(&comp));
+    assert_eq!(decrypt_package(&mut comp, PASSWORD).unwrap(), plaintext());
+    let error = decrypt_package(&mut comp, "password1234_").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+}
+
+#[test]
+fn decrypt_agile() {
+    let mut comp = make_file(AGILE_INFO, AGILE_PACKAGE);
+    assert_eq!(decrypt_package(&mut comp, PASSWORD).unwrap(), plaintext());
+    let error = decrypt_package(&mut comp, "").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+}
+
+#[test]
+fn decrypt_agile_with_certificate_key_encryptor() {
+    // A document that can also be opened with a certificate lists another
+    // key encryptor, which may come before the password one.
+    let certificate = "<keyEncryptor \
+         uri=\"http://schemas.microsoft.com/office/2006/keyEncryptor/\
+         certificate\"><c:encryptedKey encryptedKeyValue=\"AAAA\" \
+         X509Certificate=\"AAAA\" certVerifier=\"AAAA\"/></keyEncryptor>";
+    let info = String::from_utf8_lossy(&AGILE_INFO[8..]).replace(
+        "<keyEncryptors>",
+        &format!("<keyEncryptors>{}", certificate),
+    );
+    assert!(info.contains(certificate));
+    let mut data = AGILE_INFO[..8].to_vec();
+    data.extend_from_slice(info.as_bytes());
+    let mut comp = make_file(&data, AGILE_PACKAGE);
+    assert_eq!(decrypt_package(&mut comp, PASSWORD).unwrap(), plaintext());
+
+    // Without a password key encryptor, the package can't be decrypted.
+    let info = info.replace("/password\"", "/other\"");
+    let mut data = AGILE_INFO[..8].to_vec();
+    data.extend_from_slice(info.as_bytes());
+    let mut comp = make_file(&data, AGILE_PACKAGE);
+    let error = decrypt_package(&mut comp, PASSWORD).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn agile_integrity_check() {
+    let mut package = AGILE_PACKAGE.to_vec();
+    package[100] ^= 1;
+    let mut comp = make_file(AGILE_INFO, &package);
+    let error = decrypt_package(&mut comp, PASSWORD).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn data_space_transforms() {
+    let mut comp = make_file(AGILE_INFO, AGILE_PACKAGE);
+    let storage = "/\u{6}DataSpaces/TransformInfo/StrongEncryptionTransform";
+    comp.create_storage_all(storage).unwrap();
+    comp.create_stream(format!("{}/\u{6}Primary", storage))
+        .unwrap()
+        .write_all(&primary_stream("{FF9A3F03-56EF-4613-BDD5-5A41C1D07246}"))
+        .unwrap();
+    assert_eq!(decrypt_package(&mut comp, PASSWORD).unwrap(), plaintext());
+
+    // A rights-managed document uses a different transform.
+    let storage = "/\u{6}DataSpaces/TransformInfo/DRMEncryptedTransform";
+    comp.create_storage(storage).unwrap();
+    comp.create_stream(format!("{}/\u{6}Primary", storage))
+        .unwrap()
+        .write_all(&primary_stream("{C73DFACD-061F-43B0-8B64-0C620D2A8B50}"))
+        .unwrap();
+    let error = decrypt_package(&mut comp, PASSWORD).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn encrypt_round_trip() {
+    for &key_bits in &[128, 192, 256] {
+        let mut options = EncryptionOptions::new();
+        options.set_spin_count(100);
+        options.set_key_bits(key_bits).unwrap();
+        for &len in &[0, 15, 4096, 5000] {
+            let package = &plaintext()[..len];
+            let mut comp =
+                encrypt_package(package, PASSWORD, &options).unwrap();
+            assert!(crypto::is_encrypted_package(&comp));
+            assert_eq!(decrypt_package(&mut comp, PASSWORD).unwrap(), package);
+            let error = decrypt_package(&mut comp, "wrong").unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+        }
+    }
+}
+
+#[test]
+fn encrypted_file_layout() {
+    let mut options = EncryptionOptions::default();
+    assert_eq!(options.spin_count(), 100_000);
+    assert_eq!(options.key_bits(), 256);
+    let error = options.set_key_bits(64).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    options.set_spin_count(10);
+
+    let comp = encrypt_package(&plaintext(), PASSWORD, &options).unwrap();
+    let data = comp.into_inner().into_inner();
+    let mut comp = CompoundFile::open(Cursor::new(data)).unwrap();
+    for path in &[
+        "/\u{6}DataSpaces/Version",
+        "/\u{6}DataSpaces/DataSpaceMap",
+        "/\u{6}DataSpaces/DataSpaceInfo/StrongEncryptionDataSpace",
+        "/\u{6}DataSpaces/TransformInfo/StrongEncryptionTransform/\u{6}Primary",
+    ] {
+        assert!(comp.is_stream(path), "missing {:?}", path);
+    }
+    let mut info = Vec::new();
+    comp.open_stream(crypto::ENCRYPTION_INFO_STREAM)
+        .unwrap()
+        .read_to_end(&mut info)
+        .unwrap();
+    assert_eq!(&info[..8], &[4, 0, 4, 0, 0x40, 0, 0, 0]);
+    let xml = String::from_utf8(info[8..].to_vec()).unwrap();
+    assert!(xml.contains("spinCount=\"10\""));
+    assert!(xml.contains("<dataIntegrity "));
+    assert_eq!(decrypt_package(&mut comp, PASSWORD).unwrap(), plaintext());
+}
+
+#[test]
+fn malformed_encryption_info() {
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    assert!(!crypto::is_encrypted_package(&comp));
+    let error = decrypt_package(&mut comp, PASSWORD).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+    for len in 0..STANDARD_INFO.len() {
+        let mut comp = make_file(&STANDARD_INFO[..len], STANDARD_PACKAGE);
+        assert!(decrypt_package(&mut comp, PASSWORD).is_err());
+    }
+    // RC4 encryption (version 1.1) isn't supported.
+    let mut comp = make_file(b"\x01\0\x01\0\0\0\0\0", STANDARD_PACKAGE);
+    let error = decrypt_package(&mut comp, PASSWORD).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    // Nor is any cipher other than AES.
+    let info = String::from_utf8_lossy(&AGILE_INFO[8..])
+        .replace("\"AES\"", "\"DES\"");
+    let mut data = AGILE_INFO[..8].to_vec();
+    data.extend_from_slice(info.as_bytes());
+    let mut comp = make_file(&data, AGILE_PACKAGE);
+    let error = decrypt_package(&mut comp, PASSWORD).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    // Spin counts above the spec's limit of ten million are rejected rather
+    // than hashed (or truncated to 32 bits).
+    for spin_count in ["10000001", "4294967296", "99999999999999999999"] {
+        let info = String::from_utf8_lossy(&AGILE_INFO[8..]).replace(
+            "spinCount=\"1000\"",
+            &format!("spinCount=\"{}\"", spin_count),
+        );
+        assert!(info.contains(spin_count));
+        let mut data = AGILE_INFO[..8].to_vec();
+        data.extend_from_slice(info.as_bytes());
+        let mut comp = make_file(&data, AGILE_PACKAGE);
+        let error = decrypt_package(&mut comp, PASSWORD).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{}", spin_count);
+    }
+}
+
+#[test]
+fn oversized_stream_len_is_an_error() {
+    let comp = make_file(AGILE_INFO, AGILE_PACKAGE);
+    let raw = comp.raw().unwrap();
+    let stream_id = raw
+        .dir_entries()
+        .iter()
+        .position(|entry| entry.name() == "EncryptionInfo")
+        .unwrap();
+    let first_dir_sector = raw.header().first_dir_sector() as usize;
+    let mut data = comp.into_inner().into_inner();
+    // Claim that the EncryptionInfo stream is 4 EiB long; reading it should
+    // fail once the data runs out, rather than trying to allocate that much
+    // memory up front.
+    let offset = 4096 * (1 + first_dir_sector) + 128 * stream_id + 120;
+    data[offset..(offset + 8)].copy_from_slice(&(1u64 << 62).to_le_bytes());
+    let mut comp = CompoundFile::open(Cursor::new(data)).unwrap();
+    assert!(decrypt_package(&mut comp, PASSWORD).is_err());
+}
+
+//===========================================================================//
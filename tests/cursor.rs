@@ -0,0 +1,122 @@
+use cfb::{CompoundFile, EntryCursor};
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+//===========================================================================//
+
+fn make_file() -> CompoundFile<Cursor<Vec<u8>>> {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_storage("/foo").unwrap();
+    comp.create_storage("/foo/baz").unwrap();
+    comp.create_stream("/foo/bar").unwrap().write_all(b"bar").unwrap();
+    comp.create_stream("/foo/baz/quux").unwrap().write_all(b"quux").unwrap();
+    comp.create_stream("/top").unwrap().write_all(b"top").unwrap();
+    comp
+}
+
+fn paths(cursors: &[EntryCursor<Cursor<Vec<u8>>>]) -> Vec<PathBuf> {
+    cursors
+        .iter()
+        .map(|cursor| cursor.entry().unwrap().path().to_path_buf())
+        .collect()
+}
+
+//===========================================================================//
+
+#[test]
+fn navigate_tree() {
+    let comp = make_file();
+    let root = comp.root_cursor();
+    assert!(root.entry().unwrap().is_root());
+    assert!(root.parent().unwrap().is_none());
+    let children = root.children().unwrap();
+    assert_eq!(paths(&children), vec![Path::new("/foo"), Path::new("/top")]);
+
+    let baz = root.child("FOO").unwrap().child("baz").unwrap();
+    assert_eq!(baz.entry().unwrap().path(), Path::new("/foo/baz"));
+    let quux = baz.child("quux").unwrap();
+    assert_eq!(quux.id(), comp.entry("/foo/baz/quux").unwrap().id());
+    let parent = quux.parent().unwrap().unwrap();
+    assert_eq!(parent.entry().unwrap().path(), Path::new("/foo/baz"));
+    let grandparent = parent.parent().unwrap().unwrap();
+    assert_eq!(grandparent.id(), comp.entry("/foo").unwrap().id());
+
+    let error = baz.child("nope").err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    let error = quux.children().err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    let error = quux.child("x").err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    let error = baz.open_stream().err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn open_streams_while_walking() {
+    let mut comp = make_file();
+    let mut stack = vec![comp.root_cursor()];
+    let mut contents = Vec::new();
+    while let Some(cursor) = stack.pop() {
+        if cursor.entry().unwrap().is_stream() {
+            let mut data = Vec::new();
+            cursor.open_stream().unwrap().read_to_end(&mut data).unwrap();
+            // The compound file isn't borrowed, so we can modify it too.
+            let path = cursor.entry().unwrap().path().to_path_buf();
+            comp.open_stream(&path).unwrap().write_all(b"!").unwrap();
+            contents.push(data);
+        } else {
+            stack.extend(cursor.children().unwrap());
+        }
+    }
+    contents.sort();
+    assert_eq!(
+        contents,
+        vec![b"bar".to_vec(), b"quux".to_vec(), b"top".to_vec()]
+    );
+    let mut data = Vec::new();
+    comp.open_stream("/top").unwrap().read_to_end(&mut data).unwrap();
+    assert_eq!(data, b"!op");
+}
+
+#[test]
+fn mutation_during_traversal() {
+    let mut comp = make_file();
+    let foo = comp.cursor("/foo").unwrap();
+    let children = foo.children().unwrap();
+    assert_eq!(children.len(), 2);
+
+    // The snapshot of children is unaffected by creating a new entry.
+    comp.create_stream("/foo/new").unwrap();
+    assert_eq!(children.len(), 2);
+    assert_eq!(foo.children().unwrap().len(), 3);
+
+    // Cursors for removed objects return errors, even if a new object is
+    // created at the same path (possibly reusing the same slot).
+    let bar = foo.child("bar").unwrap();
+    comp.remove_stream("/foo/bar").unwrap();
+    let error = bar.entry().err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    comp.create_stream("/foo/bar").unwrap();
+    let error = bar.open_stream().err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    assert!(foo.child("bar").unwrap().open_stream().is_ok());
+
+    // Removing an entire storage invalidates cursors within it.
+    let quux = comp.cursor("/foo/baz/quux").unwrap();
+    comp.remove_storage_all("/foo/baz").unwrap();
+    let error = quux.parent().err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    assert_eq!(paths(&foo.children().unwrap()).len(), 2);
+}
+
+#[test]
+fn cursor_outliving_file() {
+    let comp = make_file();
+    let cursor = comp.cursor("/top").unwrap();
+    drop(comp);
+    assert!(cursor.entry().is_err());
+    assert!(cursor.open_stream().is_err());
+}
+
+//===========================================================================//
@@ -0,0 +1,147 @@
+use cfb::CompoundFile;
+use std::io::{self, Cursor, Read, Write};
+use std::path::Path;
+
+//===========================================================================//
+
+fn read_stream_by_id(
+    comp: &mut CompoundFile<Cursor<Vec<u8>>>,
+    id: cfb::EntryId,
+) -> Vec<u8> {
+    let mut data = Vec::new();
+    comp.open_stream_by_id(id).unwrap().read_to_end(&mut data).unwrap();
+    data
+}
+
+//===========================================================================//
+
+#[test]
+fn ids_survive_removal_of_other_entries() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    let names: Vec<String> = (0..20).map(|i| format!("s{:02}", i)).collect();
+    for (index, name) in names.iter().enumerate() {
+        let path = format!("/{}", name);
+        let mut stream = comp.create_stream(&path).unwrap();
+        stream.write_all(&[index as u8; 10]).unwrap();
+    }
+    let ids: Vec<cfb::EntryId> = names
+        .iter()
+        .map(|name| comp.entry(format!("/{}", name)).unwrap().id())
+        .collect();
+    // Remove every third entry; some of these will be interior nodes of the
+    // sibling tree with two children.
+    for (index, name) in names.iter().enumerate() {
+        if index % 3 == 0 {
+            comp.remove_stream(format!("/{}", name)).unwrap();
+        }
+    }
+    for (index, name) in names.iter().enumerate() {
+        if index % 3 == 0 {
+            let error = comp.entry_by_id(ids[index]).err().unwrap();
+            assert_eq!(error.kind(), io::ErrorKind::NotFound);
+        } else {
+            let entry = comp.entry_by_id(ids[index]).unwrap();
+            assert_eq!(entry.path(), Path::new(&format!("/{}", name)));
+            assert_eq!(entry.id(), ids[index]);
+            assert_eq!(
+                read_stream_by_id(&mut comp, ids[index]),
+                vec![index as u8; 10]
+            );
+        }
+    }
+    let comp = CompoundFile::open_strict(comp.into_inner()).unwrap();
+    assert_eq!(comp.read_root_storage().count(), 13);
+}
+
+#[test]
+fn open_stream_survives_removal_of_sibling() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    for name in ["/b", "/a", "/d", "/c", "/e"].iter() {
+        comp.create_stream(name).unwrap().write_all(name.as_bytes()).unwrap();
+    }
+    let mut stream = comp.open_stream("/c").unwrap();
+    comp.remove_stream("/d").unwrap();
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).unwrap();
+    assert_eq!(data, b"/c");
+    let names: Vec<String> = comp
+        .read_root_storage()
+        .map(|entry| entry.name().to_string())
+        .collect();
+    assert_eq!(names, vec!["a", "b", "c", "e"]);
+}
+
+#[test]
+fn ids_after_reopening() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_storage("/foo").unwrap();
+    comp.create_stream("/foo/bar").unwrap().write_all(b"bar").unwrap();
+    comp.create_stream("/baz").unwrap().write_all(b"baz").unwrap();
+    let cursor = comp.into_inner();
+    let mut comp = CompoundFile::open(cursor).unwrap();
+
+    let foo_id = comp.entry("/foo").unwrap().id();
+    let entry = comp.entry_by_id(foo_id).unwrap();
+    assert!(entry.is_storage());
+    assert_eq!(entry.path(), Path::new("/foo"));
+    let children: Vec<cfb::EntryId> =
+        comp.read_storage_by_id(foo_id).unwrap().map(|e| e.id()).collect();
+    assert_eq!(children.len(), 1);
+    assert_eq!(
+        comp.entry_by_id(children[0]).unwrap().path(),
+        Path::new("/foo/bar")
+    );
+    assert_eq!(read_stream_by_id(&mut comp, children[0]), b"bar");
+
+    let root_id = comp.root_entry().id();
+    assert_eq!(comp.entry_by_id(root_id).unwrap().path(), Path::new("/"));
+    assert_eq!(comp.read_storage_by_id(root_id).unwrap().count(), 2);
+    for entry in comp.walk() {
+        assert_eq!(comp.entry_by_id(entry.id()).unwrap().path(), entry.path());
+    }
+}
+
+#[test]
+fn by_id_type_errors() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_storage("/foo").unwrap();
+    comp.create_stream("/bar").unwrap();
+    let foo_id = comp.entry("/foo").unwrap().id();
+    let bar_id = comp.entry("/bar").unwrap().id();
+    let error = comp.open_stream_by_id(foo_id).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    let error = comp.read_storage_by_id(bar_id).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    comp.remove_storage("/foo").unwrap();
+    let error = comp.open_stream_by_id(foo_id).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn stale_id_does_not_alias_new_object() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_stream("/old").unwrap().write_all(b"old").unwrap();
+    let old_id = comp.entry("/old").unwrap().id();
+    let old_cursor_id = comp.cursor("/old").unwrap().id();
+    assert_eq!(old_cursor_id, old_id);
+    comp.remove_stream("/old").unwrap();
+    // The new stream takes over the directory entry freed by the old one.
+    comp.create_stream("/new").unwrap().write_all(b"new").unwrap();
+    let new_id = comp.entry("/new").unwrap().id();
+    assert_ne!(new_id, old_id);
+    assert_eq!(read_stream_by_id(&mut comp, new_id), b"new");
+
+    let error = comp.entry_by_id(old_id).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    let error = comp.open_stream_by_id(old_id).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    let error = comp.read_storage_by_id(old_id).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+}
+
+//===========================================================================//
@@ -0,0 +1,134 @@
+use cfb::{CompoundFile, CompoundFileBuilder, Version};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+
+//===========================================================================//
+
+fn create_data(len: usize, seed: u8) -> Vec<u8> {
+    (0..len).map(|index| (index as u8).wrapping_mul(31) ^ seed).collect()
+}
+
+/// Reads a stream's data directly out of the raw file bytes, using its
+/// extents.
+fn read_via_extents(
+    comp: &CompoundFile<Cursor<Vec<u8>>>,
+    raw: &[u8],
+    path: &str,
+) -> Vec<u8> {
+    let extents = comp.extents(path).unwrap();
+    let mut data = Vec::new();
+    for &(offset, len) in extents.runs() {
+        data.extend_from_slice(&raw[offset as usize..(offset + len) as usize]);
+    }
+    data
+}
+
+//===========================================================================//
+
+#[test]
+fn fragmented_streams() {
+    for &version in &[Version::V3, Version::V4] {
+        let cursor = Cursor::new(Vec::new());
+        let mut comp =
+            CompoundFile::create_with_version(version, cursor).unwrap();
+        comp.create_storage("/foo").unwrap();
+        let paths = ["/a", "/foo/b", "/c", "/foo/small"];
+        for path in paths.iter() {
+            comp.create_stream(path).unwrap();
+        }
+        // Write to the big streams in interleaved chunks, so that their
+        // chains get fragmented.
+        for chunk in 0..5 {
+            for (seed, path) in paths[..3].iter().enumerate() {
+                let data = create_data(9096, seed as u8);
+                let start = if chunk == 0 { 0 } else { 4096 + 1000 * chunk };
+                let end = 4096 + 1000 * (chunk + 1);
+                let mut stream = comp.open_stream(path).unwrap();
+                stream.seek(SeekFrom::End(0)).unwrap();
+                stream.write_all(&data[start..end]).unwrap();
+            }
+        }
+        comp.open_stream("/foo/small")
+            .unwrap()
+            .write_all(&create_data(300, 9))
+            .unwrap();
+        comp.flush().unwrap();
+
+        let raw = comp.into_inner().into_inner();
+        let comp = CompoundFile::open(Cursor::new(raw.clone())).unwrap();
+        for (seed, path) in paths[..3].iter().enumerate() {
+            let extents = comp.extents(path).unwrap();
+            assert!(!extents.is_mini());
+            assert!(extents.runs().len() > 1);
+            assert_eq!(
+                read_via_extents(&comp, &raw, path),
+                create_data(9096, seed as u8)
+            );
+        }
+        let extents = comp.extents("/foo/small").unwrap();
+        assert!(extents.is_mini());
+        assert_eq!(extents.start_sector(), 0);
+        assert_eq!(
+            read_via_extents(&comp, &raw, "/foo/small"),
+            create_data(300, 9)
+        );
+    }
+}
+
+#[test]
+fn contiguous_streams() {
+    let mut builder = CompoundFileBuilder::with_version(Version::V3);
+    builder.create_stream("/big", create_data(10000, 1)).unwrap();
+    builder.create_stream("/small", create_data(1000, 2)).unwrap();
+    builder.create_stream("/empty", Vec::new()).unwrap();
+    let raw = builder.build(Vec::new()).unwrap();
+    let comp = CompoundFile::open(Cursor::new(raw.clone())).unwrap();
+
+    let extents = comp.extents("/big").unwrap();
+    assert_eq!(extents.runs().len(), 1);
+    assert_eq!(extents.runs()[0].1, 10000);
+    assert_eq!(read_via_extents(&comp, &raw, "/big"), create_data(10000, 1));
+
+    let extents = comp.extents("/small").unwrap();
+    assert!(extents.is_mini());
+    assert_eq!(extents.runs().len(), 1);
+    assert_eq!(read_via_extents(&comp, &raw, "/small"), create_data(1000, 2));
+
+    let extents = comp.extents("/empty").unwrap();
+    assert_eq!(extents.start_sector(), 0xfffffffe);
+    assert!(extents.runs().is_empty());
+    let stream_id = extents.stream_id();
+    assert_ne!(stream_id, 0);
+    assert_ne!(stream_id, comp.extents("/big").unwrap().stream_id());
+
+    // The root entry's extents describe the mini stream.
+    let extents = comp.extents("/").unwrap();
+    assert_eq!(extents.stream_id(), 0);
+    assert!(!extents.is_mini());
+    assert_eq!(extents.runs().iter().map(|run| run.1).sum::<u64>(), 1024);
+}
+
+#[test]
+fn extents_of_storage() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_storage("/foo").unwrap();
+    let error = comp.extents("/foo").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    let error = comp.extents("/bar").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn extents_match_stream_reads() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_stream("/foo").unwrap().write_all(&[7; 4096]).unwrap();
+    comp.flush().unwrap();
+    let raw = comp.into_inner().into_inner();
+    let mut comp = CompoundFile::open(Cursor::new(raw.clone())).unwrap();
+    let mut data = Vec::new();
+    comp.open_stream("/foo").unwrap().read_to_end(&mut data).unwrap();
+    assert_eq!(read_via_extents(&comp, &raw, "/foo"), data);
+}
+
+//===========================================================================//
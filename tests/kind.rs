@@ -0,0 +1,133 @@
+use cfb::kind::{self, DocumentKind};
+use cfb::ole::CompObj;
+use cfb::CompoundFile;
+use std::io::{Cursor, Write};
+use uuid::Uuid;
+
+//===========================================================================//
+
+/// Creates a compound file with the given (empty, unless specified)
+/// streams, and returns what kind of document it is detected as.
+fn detect(clsid: Option<Uuid>, streams: &[(&str, &[u8])]) -> DocumentKind {
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    if let Some(clsid) = clsid {
+        comp.set_storage_clsid("/", clsid).unwrap();
+    }
+    for &(path, data) in streams {
+        comp.create_stream(path).unwrap().write_all(data).unwrap();
+    }
+    comp.detect_kind()
+}
+
+//===========================================================================//
+
+#[test]
+fn detect_by_clsid() {
+    assert_eq!(detect(Some(kind::CLSID_WORD_97), &[]), DocumentKind::Word);
+    assert_eq!(detect(Some(kind::CLSID_EXCEL_95), &[]), DocumentKind::Excel);
+    assert_eq!(
+        detect(Some(kind::CLSID_POWERPOINT_97), &[]),
+        DocumentKind::PowerPoint
+    );
+    assert_eq!(
+        detect(Some(kind::CLSID_INSTALLER_PATCH), &[]),
+        DocumentKind::InstallerPatch
+    );
+    assert_eq!(
+        detect(Some(kind::CLSID_INSTALLER_TRANSFORM), &[]),
+        DocumentKind::InstallerTransform
+    );
+    // CLSIDs that don't identify a kind of document are ignored.
+    assert_eq!(detect(Some(kind::CLSID_PACKAGE), &[]), DocumentKind::Unknown);
+    assert_eq!(detect(None, &[]), DocumentKind::Unknown);
+}
+
+#[test]
+fn detect_by_streams() {
+    let cases: &[(&str, DocumentKind)] = &[
+        ("/WordDocument", DocumentKind::Word),
+        ("/Workbook", DocumentKind::Excel),
+        ("/Book", DocumentKind::Excel),
+        ("/PowerPoint Document", DocumentKind::PowerPoint),
+        ("/VisioDocument", DocumentKind::Visio),
+        ("/__properties_version1.0", DocumentKind::OutlookMessage),
+        ("/Catalog", DocumentKind::ThumbsDb),
+    ];
+    for &(path, expected) in cases {
+        assert_eq!(detect(None, &[(path, b"")]), expected, "{}", path);
+    }
+    let string_pool = format!("/{}", cfb::msi::encode_name("!_StringPool"));
+    assert_eq!(
+        detect(None, &[(&string_pool, b"")]),
+        DocumentKind::InstallerPackage
+    );
+}
+
+#[test]
+fn detect_encrypted_package() {
+    let streams: &[(&str, &[u8])] =
+        &[("/EncryptionInfo", b"info"), ("/EncryptedPackage", b"data")];
+    assert_eq!(detect(None, streams), DocumentKind::EncryptedOoxml);
+    // The root CLSID doesn't take precedence over the encryption streams.
+    assert_eq!(
+        detect(Some(kind::CLSID_WORD_97), streams),
+        DocumentKind::EncryptedOoxml
+    );
+    assert_eq!(
+        detect(None, &[("/EncryptionInfo", b"info")]),
+        DocumentKind::Unknown
+    );
+}
+
+#[test]
+fn detect_hwp() {
+    let mut header = b"HWP Document File".to_vec();
+    header.resize(256, 0);
+    assert_eq!(detect(None, &[("/FileHeader", &header)]), DocumentKind::Hwp);
+    // A `FileHeader` stream without the signature isn't enough.
+    assert_eq!(
+        detect(None, &[("/FileHeader", b"HWP")]),
+        DocumentKind::Unknown
+    );
+}
+
+#[test]
+fn detect_vba_project() {
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    comp.create_stream("/PROJECT").unwrap();
+    assert_eq!(comp.detect_kind(), DocumentKind::Unknown);
+    comp.create_storage("/VBA").unwrap();
+    assert_eq!(comp.detect_kind(), DocumentKind::VbaProject);
+}
+
+#[test]
+fn detect_by_comp_obj() {
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    let mut comp_obj = CompObj::new(Uuid::nil(), "SolidWorks Part");
+    comp.write_comp_obj("/", &comp_obj).unwrap();
+    assert_eq!(comp.detect_kind(), DocumentKind::SolidWorks);
+
+    comp_obj.set_user_type("Unnamed");
+    comp_obj.set_prog_id(Some("Visio.Drawing.11")).unwrap();
+    comp.write_comp_obj("/", &comp_obj).unwrap();
+    assert_eq!(comp.detect_kind(), DocumentKind::Visio);
+
+    comp_obj.set_prog_id(None).unwrap();
+    comp.write_comp_obj("/", &comp_obj).unwrap();
+    assert_eq!(comp.detect_kind(), DocumentKind::Unknown);
+
+    // A malformed `CompObj` stream is ignored.
+    comp.create_stream("/\u{1}CompObj").unwrap().write_all(b"?").unwrap();
+    assert_eq!(comp.detect_kind(), DocumentKind::Unknown);
+}
+
+#[test]
+fn kind_names() {
+    assert_eq!(DocumentKind::OutlookMessage.to_string(), "Outlook message");
+    assert_eq!(
+        kind::application_name(&kind::CLSID_OUTLOOK_MESSAGE),
+        Some("Microsoft Outlook Message")
+    );
+}
+
+//===========================================================================//
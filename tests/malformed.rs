@@ -1,3 +1,7 @@
+// These tests predate the lints below; the offset arithmetic spells out each
+// directory entry index on purpose.
+#![allow(clippy::identity_op, clippy::needless_borrows_for_generic_args)]
+
 use byteorder::{LittleEndian, WriteBytesExt};
 use cfb::CompoundFile;
 use std::{
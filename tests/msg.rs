@@ -0,0 +1,367 @@
+use cfb::msg::{self, NamedProperty, PropertyName, RecipientKind, PS_MAPI};
+use cfb::propset::PropertyValue;
+use cfb::CompoundFile;
+use std::io::{self, Cursor, Seek, SeekFrom, Write};
+use std::time::{Duration, UNIX_EPOCH};
+
+//===========================================================================//
+
+/// A property to store in a test message.
+enum Prop {
+    Long(u16, i32),
+    Time(u16, u64),
+    Unicode(u16, &'static str),
+    Binary(u16, &'static [u8]),
+    MultiUnicode(u16, &'static [&'static str]),
+    Object(u16),
+}
+
+fn utf16(string: &str) -> Vec<u8> {
+    let mut data: Vec<u8> =
+        string.encode_utf16().flat_map(|unit| unit.to_le_bytes()).collect();
+    data.extend_from_slice(&[0, 0]);
+    data
+}
+
+fn write_stream(
+    comp: &mut CompoundFile<Cursor<Vec<u8>>>,
+    path: &str,
+    data: &[u8],
+) {
+    comp.create_stream(path).unwrap().write_all(data).unwrap();
+}
+
+/// Writes the `__properties_version1.0` stream and `__substg1.0_*` streams
+/// of an object stored in the given storage.
+fn write_properties(
+    comp: &mut CompoundFile<Cursor<Vec<u8>>>,
+    storage: &str,
+    header: &[u8],
+    props: &[Prop],
+) {
+    let mut data = header.to_vec();
+    for prop in props {
+        let (tag, value): (u32, [u8; 8]) = match *prop {
+            Prop::Long(id, value) => {
+                let mut bytes = [0u8; 8];
+                bytes[..4].copy_from_slice(&value.to_le_bytes());
+                ((u32::from(id) << 16) | 0x0003, bytes)
+            }
+            Prop::Time(id, filetime) => {
+                ((u32::from(id) << 16) | 0x0040, filetime.to_le_bytes())
+            }
+            Prop::Unicode(id, string) => {
+                let tag = (u32::from(id) << 16) | 0x001f;
+                let value = utf16(string);
+                write_stream(
+                    comp,
+                    &format!("{}/__substg1.0_{:08X}", storage, tag),
+                    &value,
+                );
+                (tag, (value.len() as u64).to_le_bytes())
+            }
+            Prop::Binary(id, value) => {
+                let tag = (u32::from(id) << 16) | 0x0102;
+                write_stream(
+                    comp,
+                    &format!("{}/__substg1.0_{:08X}", storage, tag),
+                    value,
+                );
+                (tag, (value.len() as u64).to_le_bytes())
+            }
+            Prop::MultiUnicode(id, strings) => {
+                let tag = (u32::from(id) << 16) | 0x101f;
+                let path = format!("{}/__substg1.0_{:08X}", storage, tag);
+                let mut lengths = Vec::new();
+                for (index, string) in strings.iter().enumerate() {
+                    let value = utf16(string);
+                    lengths.extend_from_slice(
+                        &(value.len() as u32).to_le_bytes(),
+                    );
+                    write_stream(
+                        comp,
+                        &format!("{}-{:08X}", path, index),
+                        &value,
+                    );
+                }
+                write_stream(comp, &path, &lengths);
+                (tag, (lengths.len() as u64).to_le_bytes())
+            }
+            Prop::Object(id) => {
+                ((u32::from(id) << 16) | 0x000d, u64::MAX.to_le_bytes())
+            }
+        };
+        data.extend_from_slice(&tag.to_le_bytes());
+        data.extend_from_slice(&0x6u32.to_le_bytes());
+        data.extend_from_slice(&value);
+    }
+    write_stream(comp, &format!("{}/__properties_version1.0", storage), &data);
+}
+
+fn write_nameid(comp: &mut CompoundFile<Cursor<Vec<u8>>>) {
+    comp.create_storage("/__nameid_version1.0").unwrap();
+    let mut entries = Vec::new();
+    // Property 0x8000: LID 0x8501 in PS_MAPI.
+    entries.extend_from_slice(&0x8501u32.to_le_bytes());
+    entries.extend_from_slice(&(1u16 << 1).to_le_bytes());
+    entries.extend_from_slice(&0u16.to_le_bytes());
+    // Property 0x8001: string name "Keywords" in PS_PUBLIC_STRINGS.
+    entries.extend_from_slice(&0u32.to_le_bytes());
+    entries.extend_from_slice(&((2u16 << 1) | 1).to_le_bytes());
+    entries.extend_from_slice(&1u16.to_le_bytes());
+    let mut strings = Vec::new();
+    let name: Vec<u8> =
+        "Keywords".encode_utf16().flat_map(|u| u.to_le_bytes()).collect();
+    strings.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    strings.extend_from_slice(&name);
+    write_stream(comp, "/__nameid_version1.0/__substg1.0_00020102", &[]);
+    write_stream(comp, "/__nameid_version1.0/__substg1.0_00030102", &entries);
+    write_stream(comp, "/__nameid_version1.0/__substg1.0_00040102", &strings);
+}
+
+/// Rewrites the directory entries of all streams with the given name to
+/// claim a length of 4 EiB.
+fn with_huge_stream_len(
+    comp: CompoundFile<Cursor<Vec<u8>>>,
+    name: &str,
+) -> CompoundFile<Cursor<Vec<u8>>> {
+    let raw = comp.raw().unwrap();
+    // Each 4096-byte directory sector holds 32 entries.
+    let mut dir_sectors = vec![raw.header().first_dir_sector()];
+    while dir_sectors.len() * 32 < raw.dir_entries().len() {
+        dir_sectors.push(raw.fat()[*dir_sectors.last().unwrap() as usize]);
+    }
+    let offsets: Vec<usize> = (0..raw.dir_entries().len())
+        .filter(|&stream_id| raw.dir_entries()[stream_id].name() == name)
+        .map(|stream_id| {
+            let sector = dir_sectors[stream_id / 32] as usize;
+            4096 * (1 + sector) + 128 * (stream_id % 32) + 120
+        })
+        .collect();
+    assert!(!offsets.is_empty());
+    let mut data = comp.into_inner().into_inner();
+    for offset in offsets {
+        data[offset..(offset + 8)]
+            .copy_from_slice(&(1u64 << 62).to_le_bytes());
+    }
+    CompoundFile::open(Cursor::new(data)).unwrap()
+}
+
+/// 2021-01-01T00:00:00Z as a FILETIME.
+const SENT_FILETIME: u64 = 132_539_328_000_000_000;
+
+fn make_message() -> CompoundFile<Cursor<Vec<u8>>> {
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    write_nameid(&mut comp);
+    write_properties(
+        &mut comp,
+        "",
+        &[0u8; 32],
+        &[
+            Prop::Unicode(msg::PID_TAG_MESSAGE_CLASS, "IPM.Note"),
+            Prop::Unicode(msg::PID_TAG_SUBJECT, "Quarterly report"),
+            Prop::Unicode(msg::PID_TAG_SENDER_NAME, "Alice"),
+            Prop::Unicode(msg::PID_TAG_SENDER_EMAIL_ADDRESS, "/o=Example"),
+            Prop::Unicode(
+                msg::PID_TAG_SENDER_SMTP_ADDRESS,
+                "alice@example.com",
+            ),
+            Prop::Time(msg::PID_TAG_CLIENT_SUBMIT_TIME, SENT_FILETIME),
+            Prop::Unicode(msg::PID_TAG_BODY, "See attached."),
+            Prop::Binary(msg::PID_TAG_BODY_HTML, b"<p>See attached.</p>"),
+            Prop::Long(msg::PID_TAG_INTERNET_CODEPAGE, 65001),
+            Prop::Long(0x8000, 42),
+            Prop::MultiUnicode(0x8001, &["finance", "q3"]),
+        ],
+    );
+
+    let recipients = [("Bob", "bob@example.com", 1), ("Carol", "carol@x", 2)];
+    for (index, &(name, email, kind)) in recipients.iter().enumerate() {
+        let storage = format!("/__recip_version1.0_#{:08X}", index);
+        comp.create_storage(&storage).unwrap();
+        write_properties(
+            &mut comp,
+            &storage,
+            &[0u8; 8],
+            &[
+                Prop::Unicode(msg::PID_TAG_DISPLAY_NAME, name),
+                Prop::Unicode(msg::PID_TAG_SMTP_ADDRESS, email),
+                Prop::Long(msg::PID_TAG_RECIPIENT_TYPE, kind),
+            ],
+        );
+    }
+
+    comp.create_storage("/__attach_version1.0_#00000000").unwrap();
+    write_properties(
+        &mut comp,
+        "/__attach_version1.0_#00000000",
+        &[0u8; 8],
+        &[
+            Prop::Long(msg::PID_TAG_ATTACH_METHOD, 1),
+            Prop::Unicode(msg::PID_TAG_ATTACH_FILENAME, "REPORT~1.CSV"),
+            Prop::Unicode(msg::PID_TAG_ATTACH_LONG_FILENAME, "report.csv"),
+            Prop::Unicode(msg::PID_TAG_ATTACH_MIME_TAG, "text/csv"),
+            Prop::Binary(msg::PID_TAG_ATTACH_DATA, b"a,b\n1,2\n"),
+        ],
+    );
+
+    comp.create_storage("/__attach_version1.0_#00000001").unwrap();
+    write_properties(
+        &mut comp,
+        "/__attach_version1.0_#00000001",
+        &[0u8; 8],
+        &[
+            Prop::Long(msg::PID_TAG_ATTACH_METHOD, 5),
+            Prop::Object(msg::PID_TAG_ATTACH_DATA),
+        ],
+    );
+    let embedded = "/__attach_version1.0_#00000001/__substg1.0_3701000D";
+    comp.create_storage(embedded).unwrap();
+    write_properties(
+        &mut comp,
+        embedded,
+        &[0u8; 24],
+        &[
+            Prop::Unicode(msg::PID_TAG_SUBJECT, "Original message"),
+            Prop::Unicode(msg::PID_TAG_BODY, "Forwarded."),
+        ],
+    );
+    comp
+}
+
+//===========================================================================//
+
+#[test]
+fn read_message() {
+    let mut comp = make_message();
+    let message = comp.outlook_message().unwrap();
+    assert_eq!(message.message_class(), Some("IPM.Note"));
+    assert_eq!(message.subject(), Some("Quarterly report"));
+    assert_eq!(message.sender_name(), Some("Alice"));
+    assert_eq!(message.sender_email(), Some("alice@example.com"));
+    assert_eq!(
+        message.sent_time(),
+        Some(UNIX_EPOCH + Duration::from_secs(1_609_459_200))
+    );
+    assert_eq!(message.body(), Some("See attached."));
+    assert_eq!(message.html_body().as_deref(), Some("<p>See attached.</p>"));
+    assert_eq!(message.rtf_compressed(), None);
+
+    let recipients: Vec<(RecipientKind, Option<&str>, Option<&str>)> = message
+        .recipients()
+        .iter()
+        .map(|r| (r.kind(), r.display_name(), r.email()))
+        .collect();
+    assert_eq!(
+        recipients,
+        vec![
+            (RecipientKind::To, Some("Bob"), Some("bob@example.com")),
+            (RecipientKind::Cc, Some("Carol"), Some("carol@x")),
+        ]
+    );
+}
+
+#[test]
+fn attachments() {
+    let mut comp = make_message();
+    let message = comp.outlook_message().unwrap();
+    let attachments = message.attachments();
+    assert_eq!(attachments.len(), 2);
+
+    assert_eq!(attachments[0].filename(), Some("report.csv"));
+    assert_eq!(attachments[0].mime_type(), Some("text/csv"));
+    assert_eq!(attachments[0].data(), Some(&b"a,b\n1,2\n"[..]));
+    assert!(!attachments[0].is_embedded_message());
+    assert!(attachments[0].embedded_message().is_none());
+
+    assert!(attachments[1].is_embedded_message());
+    assert_eq!(attachments[1].data(), None);
+    let embedded = attachments[1].embedded_message().unwrap();
+    assert_eq!(embedded.subject(), Some("Original message"));
+    assert_eq!(embedded.body(), Some("Forwarded."));
+    assert!(embedded.recipients().is_empty());
+}
+
+#[test]
+fn named_properties() {
+    let mut comp = make_message();
+    let message = comp.outlook_message().unwrap();
+    let named = message.named_properties();
+    assert_eq!(named.len(), 2);
+    let lid = NamedProperty::new(PS_MAPI, PropertyName::Id(0x8501));
+    assert_eq!(named.name(0x8000), Some(&lid));
+    assert_eq!(message.named_property(&lid), Some(&PropertyValue::I4(42)));
+    let keywords = NamedProperty::new(
+        msg::PS_PUBLIC_STRINGS,
+        PropertyName::String("Keywords".to_string()),
+    );
+    assert_eq!(named.id(&keywords), Some(0x8001));
+    assert_eq!(
+        message.named_property(&keywords),
+        Some(&PropertyValue::Vector(vec![
+            PropertyValue::LpWStr("finance".to_string()),
+            PropertyValue::LpWStr("q3".to_string()),
+        ]))
+    );
+}
+
+#[test]
+fn rtf_body() {
+    let rtf = b"{\\rtf1\\ansi\\ansicpg1252\\pard See attached.\\par}\r\n";
+    let mut comp = make_message();
+    let stream = comp.create_stream("/__substg1.0_10090102").unwrap();
+    let len = msg::compress_rtf(&rtf[..], stream).unwrap();
+    let mut entry = Vec::new();
+    entry.extend_from_slice(&0x1009_0102u32.to_le_bytes());
+    entry.extend_from_slice(&0x6u32.to_le_bytes());
+    entry.extend_from_slice(&len.to_le_bytes());
+    {
+        let mut stream = comp.open_stream("/__properties_version1.0").unwrap();
+        stream.seek(SeekFrom::End(0)).unwrap();
+        stream.write_all(&entry).unwrap();
+    }
+
+    let message = comp.outlook_message().unwrap();
+    assert_eq!(message.rtf_compressed().unwrap().len() as u64, len);
+    assert_eq!(message.rtf_body().unwrap().as_deref(), Some(&rtf[..]));
+
+    let mut decompressed = Vec::new();
+    let stream = comp.open_stream("/__substg1.0_10090102").unwrap();
+    msg::decompress_rtf(stream, &mut decompressed).unwrap();
+    assert_eq!(decompressed, rtf);
+
+    // A corrupted body fails its CRC check.
+    {
+        let mut stream = comp.open_stream("/__substg1.0_10090102").unwrap();
+        stream.seek(SeekFrom::Start(20)).unwrap();
+        stream.write_all(b"?").unwrap();
+    }
+    let message = comp.outlook_message().unwrap();
+    let error = message.rtf_body().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn not_a_message() {
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    let error = comp.outlook_message().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+    // A properties stream shorter than its header is malformed.
+    let mut comp = make_message();
+    comp.open_stream("/__properties_version1.0").unwrap().set_len(16).unwrap();
+    let error = comp.outlook_message().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn oversized_stream_len_is_an_error() {
+    // Reading should fail once the data runs out, rather than trying to
+    // allocate the claimed length up front.
+    for name in ["__properties_version1.0", "__substg1.0_00030102"] {
+        let mut comp = with_huge_stream_len(make_message(), name);
+        assert!(comp.outlook_message().is_err(), "{}", name);
+    }
+}
+
+//===========================================================================//
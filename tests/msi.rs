@@ -0,0 +1,219 @@
+use cfb::msi::{self, ColumnType, Value};
+use cfb::CompoundFile;
+use std::io::{self, Cursor, Read, Write};
+
+//===========================================================================//
+
+/// A value to store in a test table: a string, a 16-bit integer, or a
+/// reference to a stream.
+enum Cell {
+    Str(&'static str),
+    Short(i16),
+    Stream(bool),
+}
+
+/// Builds the `_StringPool` and `_StringData` streams of a test database.
+#[derive(Default)]
+struct Strings {
+    strings: Vec<&'static str>,
+}
+
+impl Strings {
+    fn index(&mut self, string: &'static str) -> u16 {
+        let position = match self.strings.iter().position(|&s| s == string) {
+            Some(position) => position,
+            None => {
+                self.strings.push(string);
+                self.strings.len() - 1
+            }
+        };
+        position as u16 + 1
+    }
+
+    fn write(&self, comp: &mut CompoundFile<Cursor<Vec<u8>>>) {
+        let mut pool = vec![0xe4, 0x04, 0, 0];
+        let mut data = Vec::new();
+        for string in self.strings.iter() {
+            pool.extend_from_slice(&(string.len() as u16).to_le_bytes());
+            pool.extend_from_slice(&1u16.to_le_bytes());
+            data.extend_from_slice(string.as_bytes());
+        }
+        write_stream(comp, "!_StringPool", &pool);
+        write_stream(comp, "!_StringData", &data);
+    }
+}
+
+fn write_stream(
+    comp: &mut CompoundFile<Cursor<Vec<u8>>>,
+    name: &str,
+    data: &[u8],
+) {
+    let path = format!("/{}", msi::encode_name(name));
+    comp.create_stream(path).unwrap().write_all(data).unwrap();
+}
+
+/// Writes a table's rows column by column, as MSI stores them.
+fn write_table(
+    comp: &mut CompoundFile<Cursor<Vec<u8>>>,
+    strings: &mut Strings,
+    name: &str,
+    rows: &[Vec<Cell>],
+) {
+    let mut data = Vec::new();
+    for column in 0..rows[0].len() {
+        for row in rows {
+            let value = match row[column] {
+                Cell::Str(string) => strings.index(string),
+                Cell::Short(value) => (value as u16) ^ 0x8000,
+                Cell::Stream(present) => present as u16,
+            };
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    write_stream(comp, &format!("!{}", name), &data);
+}
+
+fn make_database() -> CompoundFile<Cursor<Vec<u8>>> {
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    let mut strings = Strings::default();
+    write_table(
+        &mut comp,
+        &mut strings,
+        "_Tables",
+        &[vec![Cell::Str("Property")], vec![Cell::Str("Binary")]],
+    );
+    let columns = [
+        ("Property", 1, "Property", 0x2d48),
+        ("Property", 2, "Value", 0x0f00),
+        ("Binary", 2, "Data", 0x0900),
+        ("Binary", 1, "Name", 0x2d48),
+    ];
+    let rows: Vec<Vec<Cell>> = columns
+        .iter()
+        .map(|&(table, number, name, type_bits)| {
+            vec![
+                Cell::Str(table),
+                Cell::Short(number),
+                Cell::Str(name),
+                Cell::Short(type_bits as i16),
+            ]
+        })
+        .collect();
+    write_table(&mut comp, &mut strings, "_Columns", &rows);
+    write_table(
+        &mut comp,
+        &mut strings,
+        "Property",
+        &[
+            vec![Cell::Str("ProductName"), Cell::Str("Test Product")],
+            vec![Cell::Str("ProductVersion"), Cell::Str("1.2.3")],
+        ],
+    );
+    write_table(
+        &mut comp,
+        &mut strings,
+        "Binary",
+        &[vec![Cell::Str("Icon.ico"), Cell::Stream(true)]],
+    );
+    write_stream(&mut comp, "Binary.Icon.ico", b"icon data");
+    strings.write(&mut comp);
+    comp
+}
+
+//===========================================================================//
+
+#[test]
+fn decoded_names() {
+    let comp = make_database();
+    let mut names: Vec<String> = comp
+        .walk()
+        .map(|entry| msi::decode_path(entry.path()).display().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        vec![
+            "/",
+            "/!Binary",
+            "/!Property",
+            "/!_Columns",
+            "/!_StringData",
+            "/!_StringPool",
+            "/!_Tables",
+            "/Binary.Icon.ico",
+        ]
+    );
+}
+
+#[test]
+fn read_tables() {
+    let mut comp = make_database();
+    let database = comp.msi_database().unwrap();
+    assert_eq!(database.string_pool().code_page(), 1252);
+    let names: Vec<&str> =
+        database.tables().iter().map(|table| table.name()).collect();
+    assert_eq!(names, vec!["_Tables", "_Columns", "Property", "Binary"]);
+
+    let table = database.table("Binary").unwrap();
+    let columns: Vec<&str> =
+        table.columns().iter().map(|column| column.name()).collect();
+    assert_eq!(columns, vec!["Name", "Data"]);
+    assert!(table.column("Name").unwrap().is_primary_key());
+    assert_eq!(
+        table.column("Data").unwrap().column_type(),
+        ColumnType::Stream
+    );
+
+    let rows = database.rows(&mut comp, "Property").unwrap();
+    let properties: Vec<(String, String)> = rows
+        .iter()
+        .map(|row| {
+            (
+                row.get("Property").unwrap().to_string(),
+                row.get("Value").unwrap().to_string(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        properties,
+        vec![
+            ("ProductName".to_string(), "Test Product".to_string()),
+            ("ProductVersion".to_string(), "1.2.3".to_string()),
+        ]
+    );
+
+    let rows = database.rows(&mut comp, "Binary").unwrap();
+    let stream_name = match rows[0].get("Data") {
+        Some(Value::Stream(name)) => name.clone(),
+        other => panic!("unexpected value {:?}", other),
+    };
+    assert_eq!(stream_name, "Binary.Icon.ico");
+    let mut data = Vec::new();
+    comp.open_stream(format!("/{}", msi::encode_name(&stream_name)))
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(data, b"icon data");
+
+    let error = database.rows(&mut comp, "File").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn not_a_database() {
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    let error = comp.msi_database().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+    // A table whose stream doesn't divide evenly into rows is malformed.
+    let mut comp = make_database();
+    comp.open_stream(format!("/{}", msi::encode_name("!Property")))
+        .unwrap()
+        .set_len(7)
+        .unwrap();
+    let database = comp.msi_database().unwrap();
+    let error = database.rows(&mut comp, "Property").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+//===========================================================================//
@@ -0,0 +1,110 @@
+use cfb::ole::{
+    ClipboardFormat, CompObj, LinkInfo, Moniker, Ole10Native, OleStream,
+};
+use cfb::CompoundFile;
+use std::io::{self, Cursor, Write};
+use uuid::Uuid;
+
+//===========================================================================//
+
+const CLSID_PACKAGE: Uuid =
+    Uuid::from_u128(0x0003000c_0000_0000_c000_000000000046);
+
+fn make_file() -> CompoundFile<Cursor<Vec<u8>>> {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_storage_all("/ObjectPool/_1234").unwrap();
+    comp.create_storage("/ObjectPool/_5678").unwrap();
+    comp
+}
+
+//===========================================================================//
+
+#[test]
+fn embedded_package() {
+    let mut comp = make_file();
+    let storage = "/ObjectPool/_1234";
+    let mut comp_obj = CompObj::new(CLSID_PACKAGE, "Package");
+    comp_obj.set_clipboard_format(Some(ClipboardFormat::Registered(
+        "Package".to_string(),
+    )));
+    comp_obj.set_prog_id(Some("Package")).unwrap();
+    comp.write_comp_obj(storage, &comp_obj).unwrap();
+    comp.write_ole_stream(storage, &OleStream::embedded()).unwrap();
+    let package = Ole10Native::new(
+        "notes.txt",
+        "C:\\Users\\me\\notes.txt",
+        b"hi".to_vec(),
+    );
+    comp.write_ole10_native(storage, &package).unwrap();
+
+    let data = comp.into_inner().into_inner();
+    let mut comp = CompoundFile::open(Cursor::new(data)).unwrap();
+    assert!(comp.is_stream("/ObjectPool/_1234/\u{1}CompObj"));
+    assert!(comp.is_stream("/ObjectPool/_1234/\u{1}Ole"));
+    assert!(comp.is_stream("/ObjectPool/_1234/\u{1}Ole10Native"));
+
+    let comp_obj = comp.comp_obj(storage).unwrap();
+    assert_eq!(comp_obj.clsid(), &CLSID_PACKAGE);
+    assert_eq!(comp_obj.user_type(), "Package");
+    assert_eq!(comp_obj.prog_id(), Some("Package"));
+    assert!(!comp.ole_stream(storage).unwrap().is_linked());
+    let package = comp.ole10_native(storage).unwrap();
+    assert_eq!(package.file_name(), "notes.txt");
+    assert_eq!(package.source_path(), "C:\\Users\\me\\notes.txt");
+    assert_eq!(package.into_data(), b"hi");
+}
+
+#[test]
+fn linked_object() {
+    let mut comp = make_file();
+    let storage = "/ObjectPool/_5678";
+    let link = LinkInfo::new(
+        Some(Moniker::file("..\\data\\book.xls")),
+        Some(Moniker::file("C:\\data\\book.xls")),
+    );
+    let mut ole_stream = OleStream::linked(link);
+    ole_stream.set_link_update_option(1);
+    comp.write_ole_stream(storage, &ole_stream).unwrap();
+    // Writing again replaces the existing stream.
+    comp.write_ole_stream(storage, &ole_stream).unwrap();
+
+    let parsed = comp.ole_stream(storage).unwrap();
+    assert_eq!(parsed, ole_stream);
+    assert!(parsed.is_linked());
+    assert_eq!(parsed.link_update_option(), 1);
+    let link = parsed.link().unwrap();
+    assert_eq!(
+        link.absolute_source().and_then(Moniker::file_path),
+        Some("C:\\data\\book.xls".to_string())
+    );
+    assert_eq!(
+        link.relative_source().and_then(Moniker::file_path),
+        Some("..\\data\\book.xls".to_string())
+    );
+}
+
+#[test]
+fn missing_or_malformed_streams() {
+    let mut comp = make_file();
+    let storage = "/ObjectPool/_1234";
+    let error = comp.comp_obj(storage).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    assert!(comp
+        .write_comp_obj("/nope", &CompObj::new(Uuid::nil(), ""))
+        .is_err());
+
+    comp.create_stream("/ObjectPool/_1234/\u{1}Ole10Native")
+        .unwrap()
+        .write_all(b"\x08\0\0\0\x07\0\0\0raw!")
+        .unwrap();
+    let error = comp.ole10_native(storage).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    comp.create_stream("/ObjectPool/_1234/\u{1}Ole")
+        .unwrap()
+        .write_all(b"\x01\0\0\x02")
+        .unwrap();
+    assert!(comp.ole_stream(storage).is_err());
+}
+
+//===========================================================================//
@@ -0,0 +1,156 @@
+use cfb::CompoundFile;
+use std::cell::Cell;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::rc::Rc;
+
+//===========================================================================//
+
+/// A file that starts failing all writes after a given number of them have
+/// succeeded.
+struct FaultyFile {
+    inner: Cursor<Vec<u8>>,
+    writes_left: Rc<Cell<usize>>,
+}
+
+impl Read for FaultyFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Seek for FaultyFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl Write for FaultyFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.writes_left.get() {
+            0 => Err(io::Error::other("injected fault")),
+            n => {
+                self.writes_left.set(n - 1);
+                self.inner.write(buf)
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn make_file() -> Vec<u8> {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_storage("/foo").unwrap();
+    comp.create_stream("/foo/bar").unwrap().write_all(&[1; 100]).unwrap();
+    comp.create_stream("/big").unwrap().write_all(&[2; 5000]).unwrap();
+    comp.into_inner().into_inner()
+}
+
+fn open_faulty(
+    writes_left: usize,
+) -> (CompoundFile<FaultyFile>, Rc<Cell<usize>>) {
+    let writes_left = Rc::new(Cell::new(writes_left));
+    let file = FaultyFile {
+        inner: Cursor::new(make_file()),
+        writes_left: writes_left.clone(),
+    };
+    (CompoundFile::open(file).unwrap(), writes_left)
+}
+
+fn mutate(comp: &mut CompoundFile<FaultyFile>) -> io::Result<()> {
+    comp.create_storage("/new")?;
+    let mut stream = comp.create_stream("/new/data")?;
+    stream.write_all(&[3; 10000])?;
+    stream.flush()?;
+    let mut stream = comp.open_stream("/foo/bar")?;
+    stream.seek(SeekFrom::End(0))?;
+    stream.write_all(&[4; 5000])?;
+    stream.flush()?;
+    comp.open_stream("/big")?.set_len(10)?;
+    comp.remove_stream("/new/data")?;
+    comp.set_state_bits("/foo", 7)?;
+    comp.flush()
+}
+
+//===========================================================================//
+
+#[test]
+fn failed_writes_poison_the_file() {
+    let mut num_failures = 0;
+    for limit in 0.. {
+        let (mut comp, writes_left) = open_faulty(limit);
+        let error = match mutate(&mut comp) {
+            Ok(()) => {
+                assert!(!comp.is_poisoned());
+                break;
+            }
+            Err(error) => error,
+        };
+        num_failures += 1;
+        assert_eq!(error.to_string(), "injected fault");
+        assert!(comp.is_poisoned());
+
+        // Even once the underlying file works again, all further
+        // modifications fail fast, without touching the file.
+        writes_left.set(1000);
+        assert!(comp.create_stream("/other").is_err());
+        assert!(comp.remove_storage_all("/foo").is_err());
+        assert!(comp.touch("/").is_err());
+        assert!(comp.flush().is_err());
+        let mut stream = comp.open_stream("/big").unwrap();
+        assert!(stream.write(b"x").is_err());
+        assert!(stream.set_len(0).is_err());
+        assert_eq!(writes_left.get(), 1000);
+        // Reading is still allowed.
+        assert!(comp.entry("/foo").is_ok());
+    }
+    assert!(num_failures > 10);
+}
+
+#[test]
+fn invalid_arguments_do_not_poison() {
+    let (mut comp, _writes_left) = open_faulty(0);
+    let error = comp.create_stream("/nope/x").err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    let error = comp.create_storage("/foo").err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+    let name = "ThisNameIsMostDefinitelyMuchTooLong";
+    let error = comp.create_stream(name).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    let error = comp.remove_storage("/foo").err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    assert!(!comp.is_poisoned());
+}
+
+#[test]
+fn failed_flush_on_drop_poisons() {
+    let (mut comp, writes_left) = open_faulty(1000);
+    let mut stream = comp.open_stream("/foo/bar").unwrap();
+    stream.write_all(b"buffered").unwrap();
+    writes_left.set(0);
+    drop(stream);
+    assert!(comp.is_poisoned());
+    writes_left.set(1000);
+    assert!(comp.create_storage("/new").is_err());
+}
+
+#[test]
+fn buffered_data_is_not_flushed_into_poisoned_file() {
+    let (mut comp, writes_left) = open_faulty(1000);
+    let mut stream = comp.open_stream("/foo/bar").unwrap();
+    stream.write_all(b"buffered").unwrap();
+    // Poison the file with a failure elsewhere while the stream still has
+    // unflushed data.
+    writes_left.set(0);
+    assert!(comp.set_state_bits("/foo", 7).is_err());
+    assert!(comp.is_poisoned());
+    writes_left.set(1000);
+    assert!(stream.flush().is_err());
+    drop(stream);
+    assert_eq!(writes_left.get(), 1000);
+}
+
+//===========================================================================//
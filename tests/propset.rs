@@ -0,0 +1,401 @@
+use cfb::propset::{
+    fmtid_to_stream_name, stream_name_to_fmtid, PropertySet,
+    PropertySetBuilder, PropertyValue, FMTID_DOC_SUMMARY_INFORMATION,
+    FMTID_SUMMARY_INFORMATION, FMTID_USER_DEFINED_PROPERTIES, PIDSI_AUTHOR,
+    PIDSI_CREATE_DTM, PIDSI_TITLE,
+};
+use cfb::CompoundFile;
+use cfb::Timestamp;
+use std::io::{Cursor, Write};
+use std::time::{Duration, UNIX_EPOCH};
+use uuid::Uuid;
+
+//===========================================================================//
+
+// 2001-09-09T01:46:40Z, as a FILETIME.
+const FILETIME: u64 = 116_444_736_000_000_000 + 10_000_000_000_000_000;
+
+fn typed(value_type: u16, body: &[u8]) -> Vec<u8> {
+    let mut data = value_type.to_le_bytes().to_vec();
+    data.extend_from_slice(&[0, 0]);
+    data.extend_from_slice(body);
+    while data.len() % 4 != 0 {
+        data.push(0);
+    }
+    data
+}
+
+fn lpstr(string: &[u8]) -> Vec<u8> {
+    let mut body = (string.len() as u32 + 1).to_le_bytes().to_vec();
+    body.extend_from_slice(string);
+    body.push(0);
+    body
+}
+
+fn i4(value: i32) -> Vec<u8> {
+    typed(0x03, &value.to_le_bytes())
+}
+
+fn section(properties: &[(u32, Vec<u8>)]) -> Vec<u8> {
+    let header_len = 8 + 8 * properties.len();
+    let mut header = Vec::new();
+    let mut body = Vec::new();
+    for (id, value) in properties {
+        header.extend_from_slice(&id.to_le_bytes());
+        header.extend_from_slice(
+            &((header_len + body.len()) as u32).to_le_bytes(),
+        );
+        body.extend_from_slice(value);
+    }
+    let mut data = ((header_len + body.len()) as u32).to_le_bytes().to_vec();
+    data.extend_from_slice(&(properties.len() as u32).to_le_bytes());
+    data.extend_from_slice(&header);
+    data.extend_from_slice(&body);
+    data
+}
+
+fn guid_bytes(guid: &Uuid) -> Vec<u8> {
+    let (d1, d2, d3, d4) = guid.as_fields();
+    let mut data = d1.to_le_bytes().to_vec();
+    data.extend_from_slice(&d2.to_le_bytes());
+    data.extend_from_slice(&d3.to_le_bytes());
+    data.extend_from_slice(d4);
+    data
+}
+
+fn property_set(sections: &[(Uuid, Vec<u8>)]) -> Vec<u8> {
+    let mut data = vec![0xfe, 0xff, 0, 0, 0x06, 0x01, 0x02, 0x00];
+    data.extend_from_slice(&[0; 16]);
+    data.extend_from_slice(&(sections.len() as u32).to_le_bytes());
+    let mut offset = 28 + 20 * sections.len();
+    for (fmtid, section) in sections {
+        data.extend_from_slice(&guid_bytes(fmtid));
+        data.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += section.len();
+    }
+    for (_, section) in sections {
+        data.extend_from_slice(section);
+    }
+    data
+}
+
+fn summary_information() -> Vec<u8> {
+    let mut filetime = (FILETIME as u32).to_le_bytes().to_vec();
+    filetime.extend_from_slice(&((FILETIME >> 32) as u32).to_le_bytes());
+    let mut edit_time = 600_000_000u32.to_le_bytes().to_vec();
+    edit_time.extend_from_slice(&[0; 4]);
+    let mut thumbnail = 8u32.to_le_bytes().to_vec();
+    thumbnail.extend_from_slice(&(-1i32).to_le_bytes());
+    thumbnail.extend_from_slice(&[3, 0, 0xab, 0xcd]);
+    property_set(&[(
+        FMTID_SUMMARY_INFORMATION,
+        section(&[
+            (1, typed(0x02, &1252u16.to_le_bytes())),
+            (2, typed(0x1e, &lpstr(b"Quarterly \x80 report"))),
+            (4, typed(0x1e, &lpstr(b"Jane Doe"))),
+            (10, typed(0x40, &edit_time)),
+            (12, typed(0x40, &filetime)),
+            (13, typed(0x40, &filetime)),
+            (14, i4(12)),
+            (17, typed(0x47, &thumbnail)),
+            (18, typed(0x1f, b"\x04\0\0\0A\0p\0p\0\0\0")),
+        ]),
+    )])
+}
+
+fn document_summary_information() -> Vec<u8> {
+    let mut heading_pairs = 2u32.to_le_bytes().to_vec();
+    heading_pairs.extend(typed(0x1e, &lpstr(b"Worksheets")));
+    heading_pairs.extend(i4(2));
+    let mut parts = 2u32.to_le_bytes().to_vec();
+    for part in &[&b"Sheet1"[..], &b"Sheet2"[..]] {
+        parts.extend(lpstr(part));
+        while parts.len() % 4 != 0 {
+            parts.push(0);
+        }
+    }
+    // A Unicode dictionary naming properties 2 and 3.
+    let mut dictionary = 2u32.to_le_bytes().to_vec();
+    for &(id, name) in &[(2u32, "Client"), (3, "Approved")] {
+        dictionary.extend_from_slice(&id.to_le_bytes());
+        let units: Vec<u16> =
+            name.encode_utf16().chain(std::iter::once(0)).collect();
+        dictionary.extend_from_slice(&(units.len() as u32).to_le_bytes());
+        for unit in units {
+            dictionary.extend_from_slice(&unit.to_le_bytes());
+        }
+        while dictionary.len() % 4 != 0 {
+            dictionary.push(0);
+        }
+    }
+    property_set(&[
+        (
+            FMTID_DOC_SUMMARY_INFORMATION,
+            section(&[
+                (1, typed(0x02, &1252u16.to_le_bytes())),
+                (2, typed(0x1e, &lpstr(b"Finance"))),
+                (12, typed(0x100c, &heading_pairs)),
+                (13, typed(0x101e, &parts)),
+                (15, typed(0x1e, &lpstr(b"Acme"))),
+                (16, typed(0x0b, &[0xff, 0xff])),
+            ]),
+        ),
+        (
+            FMTID_USER_DEFINED_PROPERTIES,
+            section(&[
+                (0, dictionary),
+                (1, typed(0x02, &1200u16.to_le_bytes())),
+                (2, typed(0x1f, b"\x05\0\0\0A\0c\0m\0e\0\0\0")),
+                (3, typed(0x0b, &[0, 0])),
+                (4, typed(0x41, b"\x03\0\0\0xyz")),
+            ]),
+        ),
+    ])
+}
+
+fn make_file() -> CompoundFile<Cursor<Vec<u8>>> {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_stream("\u{5}SummaryInformation")
+        .unwrap()
+        .write_all(&summary_information())
+        .unwrap();
+    comp.create_stream("\u{5}DocumentSummaryInformation")
+        .unwrap()
+        .write_all(&document_summary_information())
+        .unwrap();
+    comp
+}
+
+//===========================================================================//
+
+#[test]
+fn read_summary_information() {
+    let mut comp = make_file();
+    let summary = comp.summary_information().unwrap().unwrap();
+    assert_eq!(summary.title(), Some("Quarterly € report"));
+    assert_eq!(summary.author(), Some("Jane Doe"));
+    assert_eq!(summary.subject(), None);
+    assert_eq!(summary.application_name(), Some("App"));
+    let time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    assert_eq!(summary.created(), Some(time));
+    assert_eq!(summary.last_saved(), Some(time));
+    assert_eq!(summary.last_printed(), None);
+    assert_eq!(summary.edit_time(), Some(Duration::from_secs(60)));
+    assert_eq!(summary.page_count(), Some(12));
+    let thumbnail = summary.thumbnail().unwrap();
+    assert_eq!(thumbnail.format(), -1);
+    assert_eq!(thumbnail.data(), &[3, 0, 0xab, 0xcd]);
+    assert_eq!(summary.section().code_page(), 1252);
+}
+
+#[test]
+fn read_document_summary_information() {
+    let mut comp = make_file();
+    let summary = comp.document_summary_information().unwrap().unwrap();
+    assert_eq!(summary.category(), Some("Finance"));
+    assert_eq!(summary.company(), Some("Acme"));
+    assert_eq!(summary.manager(), None);
+    assert_eq!(summary.links_dirty(), Some(true));
+    assert_eq!(summary.heading_pairs(), Some(vec![("Worksheets", 2)]));
+    assert_eq!(summary.titles_of_parts(), Some(vec!["Sheet1", "Sheet2"]));
+
+    let user_defined = summary.user_defined_properties().unwrap();
+    assert_eq!(user_defined.code_page(), 1200);
+    assert_eq!(user_defined.property_name(2), Some("Client"));
+    assert_eq!(user_defined.property_name(4), None);
+    assert_eq!(
+        summary.custom_property("client"),
+        Some(&PropertyValue::LpWStr("Acme".to_string()))
+    );
+    assert_eq!(
+        summary.custom_property("Approved"),
+        Some(&PropertyValue::Bool(false))
+    );
+    assert_eq!(summary.custom_property("Missing"), None);
+    assert_eq!(
+        user_defined.property(4),
+        Some(&PropertyValue::Blob(b"xyz".to_vec()))
+    );
+}
+
+#[test]
+fn read_generic_property_set() {
+    let mut comp = make_file();
+    let set = comp.property_set("/\u{5}DocumentSummaryInformation").unwrap();
+    assert_eq!(set.version(), 0);
+    assert_eq!(set.system_identifier(), 0x0002_0106);
+    assert!(set.clsid().is_nil());
+    assert_eq!(set.sections().len(), 2);
+    assert_eq!(set.sections()[1].fmtid(), &FMTID_USER_DEFINED_PROPERTIES);
+    assert!(set.section(&FMTID_SUMMARY_INFORMATION).is_none());
+
+    let data = summary_information();
+    let set = PropertySet::read(data.as_slice()).unwrap();
+    let section = set.section(&FMTID_SUMMARY_INFORMATION).unwrap();
+    assert_eq!(section.properties().len(), 9);
+    assert_eq!(section.property(14), Some(&PropertyValue::I4(12)));
+}
+
+#[test]
+fn missing_streams_are_none() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    assert!(comp.summary_information().unwrap().is_none());
+    assert!(comp.document_summary_information().unwrap().is_none());
+    assert!(comp.property_set("/nope").is_err());
+}
+
+#[test]
+fn malformed_streams_are_errors() {
+    let mut comp = make_file();
+    let mut data = summary_information();
+    data.truncate(data.len() - 10);
+    comp.create_stream("\u{5}SummaryInformation")
+        .unwrap()
+        .write_all(&data)
+        .unwrap();
+    assert!(comp.summary_information().is_err());
+    // A summary information stream that's actually a different property set.
+    comp.create_stream("\u{5}SummaryInformation")
+        .unwrap()
+        .write_all(&document_summary_information())
+        .unwrap();
+    assert!(comp.summary_information().is_err());
+}
+
+#[test]
+fn nested_variant_vectors_are_errors() {
+    // A variant vector whose only element is another variant vector, and so
+    // on, deep enough to overflow the stack if parsed recursively.
+    let mut value = Vec::new();
+    for _ in 0..100_000 {
+        value.extend_from_slice(&[0x0c, 0x10, 0, 0]);
+        value.extend_from_slice(&1u32.to_le_bytes());
+    }
+    value.extend(i4(1));
+    let data = property_set(&[(
+        FMTID_SUMMARY_INFORMATION,
+        section(&[(1, typed(0x02, &1252u16.to_le_bytes())), (2, value)]),
+    )]);
+    let error = PropertySet::read(data.as_slice()).err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+    assert!(error.to_string().contains("vectors cannot be nested"));
+}
+
+#[test]
+fn write_summary_information() {
+    let mut builder = PropertySetBuilder::new();
+    let section = builder.section_mut(FMTID_SUMMARY_INFORMATION).unwrap();
+    section
+        .set_property(PIDSI_TITLE, PropertyValue::LpStr("Café".to_string()))
+        .unwrap();
+    let created = Timestamp::from_filetime(FILETIME);
+    section
+        .set_property(PIDSI_CREATE_DTM, PropertyValue::FileTime(created))
+        .unwrap();
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    comp.write_property_set("/", &builder).unwrap();
+    assert!(comp.is_stream("\u{5}SummaryInformation"));
+    let summary = comp.summary_information().unwrap().unwrap();
+    assert_eq!(summary.title(), Some("Café"));
+    assert_eq!(summary.created(), created.to_system_time());
+
+    // Writing again replaces the stream.
+    let section = builder.section_mut(FMTID_SUMMARY_INFORMATION).unwrap();
+    section
+        .set_property(PIDSI_AUTHOR, PropertyValue::LpStr("Bob".to_string()))
+        .unwrap();
+    comp.write_property_set("/", &builder).unwrap();
+    let summary = comp.summary_information().unwrap().unwrap();
+    assert_eq!(summary.author(), Some("Bob"));
+    assert_eq!(comp.walk().count(), 2);
+}
+
+#[test]
+fn edit_document_summary_information() {
+    let mut comp = make_file();
+    let set = comp.property_set("/\u{5}DocumentSummaryInformation").unwrap();
+    let mut builder = PropertySetBuilder::from_property_set(set);
+    assert_eq!(builder.build().unwrap(), document_summary_information());
+    let section = builder.section_mut(FMTID_USER_DEFINED_PROPERTIES).unwrap();
+    section
+        .set_named_property("Reviewer", PropertyValue::LpWStr("Zoë".into()))
+        .unwrap();
+    section.set_named_property("Approved", PropertyValue::Bool(true)).unwrap();
+    comp.write_property_set("/", &builder).unwrap();
+
+    let summary = comp.document_summary_information().unwrap().unwrap();
+    assert_eq!(summary.company(), Some("Acme"));
+    assert_eq!(
+        summary.custom_property("reviewer"),
+        Some(&PropertyValue::LpWStr("Zoë".to_string()))
+    );
+    assert_eq!(
+        summary.custom_property("Approved"),
+        Some(&PropertyValue::Bool(true))
+    );
+    assert_eq!(summary.heading_pairs(), Some(vec![("Worksheets", 2)]));
+}
+
+#[test]
+fn user_defined_section_requires_doc_summary_section() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    let mut builder = PropertySetBuilder::new();
+    builder
+        .section_mut(FMTID_USER_DEFINED_PROPERTIES)
+        .unwrap()
+        .set_named_property("Client", PropertyValue::LpStr("Acme".into()))
+        .unwrap();
+    let error = comp.write_property_set("/", &builder).err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+    assert!(!comp.exists("/\u{5}DocumentSummaryInformation"));
+
+    // Adding the document summary section after the user-defined one is
+    // still the wrong order.
+    builder.section_mut(FMTID_DOC_SUMMARY_INFORMATION).unwrap();
+    assert!(builder.build().is_err());
+    let user = builder.remove_section(&FMTID_USER_DEFINED_PROPERTIES).unwrap();
+    let fmtid = FMTID_USER_DEFINED_PROPERTIES;
+    *builder.section_mut(fmtid).unwrap() = user;
+    comp.write_property_set("/", &builder).unwrap();
+    let summary = comp.document_summary_information().unwrap().unwrap();
+    assert_eq!(
+        summary.custom_property("Client"),
+        Some(&PropertyValue::LpStr("Acme".to_string()))
+    );
+}
+
+#[test]
+fn custom_fmtids_use_encoded_stream_names() {
+    let fmtid = Uuid::from_u128(0x6e6f_7461_7265_6c6c_2d61_6e79_7468_696e);
+    let mut builder = PropertySetBuilder::new();
+    builder
+        .section_mut(fmtid)
+        .unwrap()
+        .set_property(2, PropertyValue::R8(2.5))
+        .unwrap();
+    let name = builder.stream_name().unwrap();
+    assert_eq!(name, fmtid_to_stream_name(&fmtid));
+    assert_eq!(stream_name_to_fmtid(&name), Some(fmtid));
+
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    comp.create_storage("/sub").unwrap();
+    comp.write_property_set("/sub", &builder).unwrap();
+    let entries: Vec<String> = comp
+        .read_storage("/sub")
+        .unwrap()
+        .map(|e| e.name().to_string())
+        .collect();
+    assert_eq!(entries, vec![name.clone()]);
+    let set = comp.property_set(format!("/sub/{}", name)).unwrap();
+    assert_eq!(
+        set.section(&fmtid).unwrap().property(2),
+        Some(&PropertyValue::R8(2.5))
+    );
+    assert!(comp.write_property_set("/nope", &builder).is_err());
+    assert!(comp.write_property_set("/", &PropertySetBuilder::new()).is_err());
+}
+
+//===========================================================================//
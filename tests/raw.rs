@@ -0,0 +1,131 @@
+use cfb::raw::{Color, ObjType};
+use cfb::{CompoundFile, Version};
+use std::io::{Cursor, Seek, SeekFrom, Write};
+
+//===========================================================================//
+
+#[test]
+fn raw_view_of_new_file() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create_with_version(Version::V3, cursor)
+        .expect("create");
+    comp.create_storage("/foo").unwrap();
+    comp.create_stream("/foo/bar").unwrap().write_all(&[1; 100]).unwrap();
+    comp.create_stream("/big").unwrap().write_all(&[2; 5000]).unwrap();
+    let raw = comp.raw().unwrap();
+
+    let header = raw.header();
+    assert_eq!(
+        header.magic(),
+        &[0xd0, 0xcf, 0x11, 0xe0, 0xa1, 0xb1, 0x1a, 0xe1]
+    );
+    assert_eq!(header.minor_version(), 0x3e);
+    assert_eq!(header.major_version(), 3);
+    assert_eq!(header.byte_order_mark(), 0xfffe);
+    assert_eq!(header.sector_shift(), 9);
+    assert_eq!(header.mini_sector_shift(), 6);
+    assert_eq!(header.mini_stream_cutoff(), 4096);
+    assert_eq!(header.num_fat_sectors(), 1);
+    assert_eq!(header.num_difat_sectors(), 0);
+    assert_eq!(header.first_difat_sector(), 0xfffffffe);
+    assert_eq!(header.num_minifat_sectors(), 1);
+    assert_eq!(header.initial_difat()[0], 0);
+    assert_eq!(header.initial_difat()[1], 0xffffffff);
+
+    assert_eq!(raw.difat(), &[0]);
+    assert!(raw.difat_sectors().is_empty());
+    assert_eq!(raw.fat()[0], 0xfffffffd);
+    assert_eq!(raw.minifat()[..2], [1, 0xfffffffe]);
+
+    let root = raw.dir_entry(0).unwrap();
+    assert_eq!(root.obj_type(), ObjType::Root);
+    assert_eq!(root.name(), "Root Entry");
+    assert_eq!(root.color(), Color::Black);
+    let foo = raw.dir_entry(root.child()).unwrap();
+    assert_eq!(foo.name(), "foo");
+    assert_eq!(foo.obj_type(), ObjType::Storage);
+    let bar = raw.dir_entry(foo.child()).unwrap();
+    assert_eq!(bar.name(), "bar");
+    assert_eq!(bar.obj_type(), ObjType::Stream);
+    assert_eq!(bar.stream_len(), 100);
+    assert_eq!(bar.start_sector(), 0);
+    assert_eq!(bar.left_sibling(), 0xffffffff);
+    assert_eq!(bar.right_sibling(), 0xffffffff);
+    let big = raw.dir_entry(foo.left_sibling()).unwrap();
+    assert_eq!(big.name(), "big");
+    assert_eq!(big.stream_len(), 5000);
+    assert_eq!(raw.fat()[big.start_sector() as usize], big.start_sector() + 1);
+    assert_eq!(raw.dir_entries().len(), 4);
+    assert!(raw.dir_entry(100).is_none());
+}
+
+#[test]
+fn raw_view_is_a_snapshot() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).expect("create");
+    let raw = comp.raw().unwrap();
+    comp.create_stream("/foo").unwrap();
+    assert_eq!(raw.dir_entry(0).unwrap().child(), 0xffffffff);
+    let raw = comp.raw().unwrap();
+    assert_eq!(raw.dir_entry(0).unwrap().child(), 1);
+    let unallocated = raw.dir_entry(2).unwrap();
+    assert_eq!(unallocated.obj_type(), ObjType::Unallocated);
+    assert_eq!(unallocated.name(), "");
+    assert_eq!(raw.header().major_version(), 4);
+    assert_eq!(raw.header().num_dir_sectors(), 1);
+}
+
+#[test]
+fn raw_view_with_difat() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create_with_version(Version::V3, cursor)
+        .expect("create");
+    // Each V3 FAT sector covers 128 sectors (64 kB), so 8 MB of data needs
+    // more FAT sectors than the 109 that the header can list.
+    let data = vec![0u8; 8 * 1024 * 1024];
+    comp.create_stream("/big").unwrap().write_all(&data).unwrap();
+    let raw = comp.raw().unwrap();
+    let header = raw.header();
+    assert!(header.num_fat_sectors() > 109);
+    assert_eq!(header.num_fat_sectors() as usize, raw.difat().len());
+    assert_eq!(header.num_difat_sectors() as usize, raw.difat_sectors().len());
+    assert_eq!(header.first_difat_sector(), raw.difat_sectors()[0]);
+    assert_eq!(header.initial_difat(), &raw.difat()[..109]);
+    for &sector in raw.difat_sectors() {
+        assert_eq!(raw.fat()[sector as usize], 0xfffffffc);
+    }
+}
+
+#[test]
+fn raw_view_matches_file_after_reopening() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create_with_version(Version::V3, cursor)
+        .expect("create");
+    // Enough small streams to need a second MiniFAT sector.
+    for index in 0..3 {
+        let path = format!("/small{}", index);
+        comp.create_stream(&path).unwrap().write_all(&[7; 3000]).unwrap();
+    }
+    comp.create_stream("/big").unwrap().write_all(&[8; 10000]).unwrap();
+    let comp = comp;
+    let before = comp.raw().unwrap();
+    assert_eq!(before.header().num_minifat_sectors(), 2);
+
+    let mut cursor = comp.into_inner();
+    // Set the transaction signature, which this crate never writes itself.
+    cursor.seek(SeekFrom::Start(52)).unwrap();
+    cursor.write_all(&[1, 2, 3, 4]).unwrap();
+    let comp = CompoundFile::open(cursor).unwrap();
+    let after = comp.raw().unwrap();
+    assert_eq!(after.header().transaction_signature(), 0x04030201);
+    let (before, after) = (before.header(), after.header());
+    assert_eq!(before.num_fat_sectors(), after.num_fat_sectors());
+    assert_eq!(before.first_dir_sector(), after.first_dir_sector());
+    assert_eq!(before.first_minifat_sector(), after.first_minifat_sector());
+    assert_eq!(before.num_minifat_sectors(), after.num_minifat_sectors());
+    assert_eq!(before.first_difat_sector(), after.first_difat_sector());
+    assert_eq!(before.num_difat_sectors(), after.num_difat_sectors());
+    assert_eq!(before.initial_difat(), after.initial_difat());
+}
+
+//===========================================================================//
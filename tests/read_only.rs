@@ -0,0 +1,103 @@
+use cfb::CompoundFile;
+use std::io::{self, Cursor, Read, Write};
+use std::time::{Duration, UNIX_EPOCH};
+use uuid::Uuid;
+
+//===========================================================================//
+
+fn make_file() -> Vec<u8> {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_storage("/foo").unwrap();
+    comp.create_stream("/foo/bar").unwrap().write_all(b"bar").unwrap();
+    comp.create_storage("/empty").unwrap();
+    comp.into_inner().into_inner()
+}
+
+fn assert_denied<T>(result: io::Result<T>) {
+    match result {
+        Ok(_) => panic!("mutation of read-only file succeeded"),
+        Err(error) => {
+            assert_eq!(error.kind(), io::ErrorKind::PermissionDenied)
+        }
+    }
+}
+
+//===========================================================================//
+
+#[test]
+fn mutations_are_rejected() {
+    let data = make_file();
+    let mut comp =
+        CompoundFile::open_read_only(Cursor::new(data.clone())).unwrap();
+    assert!(comp.is_read_only());
+    let time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+
+    assert_denied(comp.create_storage("/new"));
+    assert_denied(comp.create_storage_all("/new/sub"));
+    assert_denied(comp.create_storage_utf16("/", &[0xd800]));
+    assert_denied(comp.remove_storage("/empty"));
+    assert_denied(comp.remove_storage_all("/foo"));
+    assert_denied(comp.set_storage_clsid("/foo", Uuid::from_u128(1)));
+    assert_denied(comp.create_stream("/new"));
+    assert_denied(comp.create_stream("/foo/bar"));
+    assert_denied(comp.create_new_stream("/new"));
+    assert_denied(comp.create_stream_utf16("/", &[0xd800]));
+    assert_denied(comp.remove_stream("/foo/bar"));
+    assert_denied(comp.set_state_bits("/foo", 7));
+    assert_denied(comp.touch("/foo/bar"));
+    assert_denied(comp.set_created("/foo", time));
+    assert_denied(comp.set_modified("/foo", time));
+    comp.flush().unwrap();
+
+    // Streams can be read but not modified.
+    let mut stream = comp.open_stream("/foo/bar").unwrap();
+    assert_denied(stream.write(b"xyz"));
+    assert_denied(stream.set_len(0));
+    assert_denied(stream.set_len(100));
+    let mut contents = Vec::new();
+    stream.read_to_end(&mut contents).unwrap();
+    assert_eq!(contents, b"bar");
+    stream.flush().unwrap();
+    drop(stream);
+    let id = comp.entry("/foo/bar").unwrap().id();
+    assert_denied(comp.open_stream_by_id(id).unwrap().write(b"x"));
+    let cursor = comp.cursor("/foo/bar").unwrap();
+    assert_denied(cursor.open_stream().unwrap().write(b"x"));
+
+    // Nothing changed, either in memory or in the underlying file.
+    assert_eq!(comp.walk().count(), 4);
+    assert_eq!(comp.entry("/foo/bar").unwrap().len(), 3);
+    assert!(comp.entry("/foo").unwrap().clsid().is_nil());
+    assert_eq!(comp.entry("/foo").unwrap().state_bits(), 0);
+    assert_eq!(comp.into_inner().into_inner(), data);
+}
+
+#[test]
+fn open_is_writable() {
+    let mut comp = CompoundFile::open(Cursor::new(make_file())).unwrap();
+    assert!(!comp.is_read_only());
+    comp.create_stream("/new").unwrap().write_all(b"new").unwrap();
+    assert!(comp.is_stream("/new"));
+}
+
+#[test]
+fn open_path_is_read_only() {
+    let path = std::env::temp_dir()
+        .join(format!("cfb-read-only-test-{}.cfb", std::process::id()));
+    std::fs::write(&path, make_file()).unwrap();
+    let result = (|| {
+        let mut comp = cfb::open(&path)?;
+        assert!(comp.is_read_only());
+        assert_denied(comp.create_stream("/new"));
+        assert_denied(comp.open_stream("/foo/bar")?.write(b"x"));
+        let mut comp = cfb::open_rw(&path)?;
+        assert!(!comp.is_read_only());
+        comp.create_stream("/new")?;
+        comp.flush()
+    })();
+    std::fs::remove_file(&path).unwrap();
+    result.unwrap();
+}
+
+//===========================================================================//
@@ -0,0 +1,130 @@
+use cfb::{CompoundFile, CompoundFileBuilder, Version};
+use std::io::{Cursor, Seek, SeekFrom, Write};
+use std::path::Path;
+
+//===========================================================================//
+
+#[test]
+fn stats_of_new_file() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create_with_version(Version::V3, cursor)
+        .expect("create");
+    comp.create_storage("/foo").unwrap();
+    comp.create_stream("/foo/bar").unwrap().write_all(&[1; 100]).unwrap();
+    comp.create_stream("/big").unwrap().write_all(&[2; 5000]).unwrap();
+    let stats = comp.stats().unwrap();
+
+    assert_eq!(stats.num_fat_sectors(), 1);
+    assert_eq!(stats.num_difat_sectors(), 0);
+    assert_eq!(stats.num_minifat_sectors(), 1);
+    assert_eq!(stats.num_dir_sectors(), 1);
+    assert_eq!(stats.num_dir_entries(), 4);
+    assert_eq!(stats.num_unallocated_dir_entries(), 0);
+    assert_eq!(
+        stats.num_used_sectors() + stats.num_free_sectors(),
+        stats.num_sectors()
+    );
+    assert_eq!(stats.mini_stream_len() % 64, 0);
+    assert_eq!(
+        stats.num_free_mini_sectors() as u64,
+        stats.mini_stream_len() / 64 - 2
+    );
+
+    let streams = stats.streams();
+    assert_eq!(streams.len(), 2);
+    assert_eq!(streams[0].path(), Path::new("/big"));
+    assert_eq!(streams[0].len(), 5000);
+    assert_eq!(streams[0].allocated_len(), 5120);
+    assert!(!streams[0].is_mini());
+    assert_eq!(streams[0].num_fragments(), 1);
+    assert_eq!(streams[1].path(), Path::new("/foo/bar"));
+    assert_eq!(streams[1].len(), 100);
+    assert_eq!(streams[1].allocated_len(), 128);
+    assert!(streams[1].is_mini());
+    assert_eq!(streams[1].num_fragments(), 1);
+
+    let storages = stats.storages();
+    assert_eq!(storages.len(), 2);
+    assert_eq!(storages[0].path(), Path::new("/"));
+    assert_eq!(storages[0].total_len(), 5100);
+    assert_eq!(storages[0].allocated_len(), 5248);
+    assert_eq!(storages[0].num_streams(), 2);
+    assert_eq!(storages[1].path(), Path::new("/foo"));
+    assert_eq!(storages[1].total_len(), 100);
+    assert_eq!(storages[1].allocated_len(), 128);
+    assert_eq!(storages[1].num_streams(), 1);
+}
+
+#[test]
+fn stats_after_removal() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create_with_version(Version::V3, cursor)
+        .expect("create");
+    comp.create_stream("/a").unwrap().write_all(&[1; 5000]).unwrap();
+    comp.create_stream("/b").unwrap().write_all(&[2; 5000]).unwrap();
+    comp.create_stream("/c").unwrap().write_all(&[3; 500]).unwrap();
+    comp.create_stream("/d").unwrap().write_all(&[4; 100]).unwrap();
+    let before = comp.stats().unwrap();
+    assert_eq!(before.num_free_sectors(), 0);
+    comp.remove_stream("/a").unwrap();
+    comp.remove_stream("/c").unwrap();
+    let after = comp.stats().unwrap();
+    assert_eq!(after.num_sectors(), before.num_sectors());
+    assert_eq!(after.num_free_sectors(), 10);
+    assert_eq!(
+        after.num_free_mini_sectors(),
+        before.num_free_mini_sectors() + 8
+    );
+    assert_eq!(before.num_dir_entries(), 8);
+    assert_eq!(before.num_unallocated_dir_entries(), 3);
+    assert_eq!(after.num_unallocated_dir_entries(), 5);
+    assert_eq!(after.streams().len(), 2);
+    assert_eq!(after.storages()[0].total_len(), 5100);
+}
+
+#[test]
+fn stats_survive_reopening() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create_with_version(Version::V3, cursor)
+        .expect("create");
+    comp.create_stream("/big").unwrap().write_all(&[1; 50000]).unwrap();
+    comp.remove_stream("/big").unwrap();
+    let before = comp.stats().unwrap();
+    assert_eq!(before.num_sectors(), 100);
+    assert_eq!(before.num_free_sectors(), 98);
+    // Reopening drops the trailing free entries from the FAT, but the
+    // sectors themselves are still in the file.
+    let comp = CompoundFile::open(comp.into_inner()).unwrap();
+    let after = comp.stats().unwrap();
+    assert_eq!(after.num_sectors(), before.num_sectors());
+    assert_eq!(after.num_free_sectors(), before.num_free_sectors());
+    assert_eq!(after.num_used_sectors(), before.num_used_sectors());
+}
+
+#[test]
+fn stats_report_fragmentation() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create_with_version(Version::V3, cursor)
+        .expect("create");
+    comp.create_stream("/a").unwrap().write_all(&[1; 5000]).unwrap();
+    comp.create_stream("/b").unwrap().write_all(&[2; 5000]).unwrap();
+    let mut stream = comp.open_stream("/a").unwrap();
+    stream.seek(SeekFrom::End(0)).unwrap();
+    stream.write_all(&[1; 1000]).unwrap();
+    drop(stream);
+    let stats = comp.stats().unwrap();
+    assert_eq!(stats.streams()[0].path(), Path::new("/a"));
+    assert_eq!(stats.streams()[0].num_fragments(), 2);
+    assert_eq!(stats.streams()[1].num_fragments(), 1);
+
+    let mut builder = CompoundFileBuilder::with_version(Version::V3);
+    builder.create_stream("/a", vec![1; 6000]).unwrap();
+    builder.create_stream("/b", vec![2; 5000]).unwrap();
+    let raw = builder.build(Vec::new()).unwrap();
+    let comp = CompoundFile::open(Cursor::new(raw)).unwrap();
+    let stats = comp.stats().unwrap();
+    assert_eq!(stats.streams()[0].num_fragments(), 1);
+    assert_eq!(stats.num_free_sectors(), 0);
+}
+
+//===========================================================================//
@@ -0,0 +1,184 @@
+use cfb::{CompoundFile, StreamingWriter, Version};
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+use uuid::Uuid;
+
+//===========================================================================//
+
+fn create_data(len: usize) -> Vec<u8> {
+    let mut data = Vec::<u8>::new();
+    let mut number = 0u32;
+    while data.len() < len {
+        data.extend_from_slice(number.to_string().as_bytes());
+        number += 1;
+    }
+    data.truncate(len);
+    data
+}
+
+fn read_stream(
+    comp: &mut CompoundFile<Cursor<Vec<u8>>>,
+    path: &str,
+) -> Vec<u8> {
+    let mut stream = comp.open_stream(path).unwrap();
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).unwrap();
+    data
+}
+
+/// A writer that can only be written to sequentially.
+struct Sink(Vec<u8>);
+
+impl io::Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+//===========================================================================//
+
+#[test]
+fn empty_compound_file() {
+    for &version in &[Version::V3, Version::V4] {
+        let writer = StreamingWriter::with_version(version);
+        let data = writer.write_to(Vec::new()).unwrap();
+        // Header, one FAT sector, and one directory sector.
+        assert_eq!(data.len(), 3 * version.sector_len());
+        let comp = CompoundFile::open_strict(Cursor::new(data)).unwrap();
+        assert_eq!(comp.version(), version);
+        assert_eq!(comp.read_root_storage().count(), 0);
+    }
+}
+
+#[test]
+fn small_and_large_streams() {
+    for &version in &[Version::V3, Version::V4] {
+        let mut writer = StreamingWriter::with_version(version);
+        writer.add_storage("/foo").unwrap();
+        writer.add_storage("/foo/bar").unwrap();
+        writer.add_stream("/empty", Vec::new()).unwrap();
+        writer.add_stream("/foo/small", create_data(100)).unwrap();
+        writer.add_stream("/foo/bar/cutoff", create_data(4096)).unwrap();
+        writer.add_stream("/foo/large", create_data(50000)).unwrap();
+        writer.add_stream("/tiny", create_data(1)).unwrap();
+        writer.add_stream("/medium", create_data(4095)).unwrap();
+        let sink = writer.write_to(Sink(Vec::new())).unwrap();
+        assert_eq!(sink.0.len() % version.sector_len(), 0);
+
+        let mut comp = CompoundFile::open_strict(Cursor::new(sink.0)).unwrap();
+        assert_eq!(comp.version(), version);
+        assert_eq!(read_stream(&mut comp, "/empty"), Vec::<u8>::new());
+        assert_eq!(read_stream(&mut comp, "/foo/small"), create_data(100));
+        assert_eq!(
+            read_stream(&mut comp, "/foo/bar/cutoff"),
+            create_data(4096)
+        );
+        assert_eq!(read_stream(&mut comp, "/foo/large"), create_data(50000));
+        assert_eq!(read_stream(&mut comp, "/tiny"), create_data(1));
+        assert_eq!(read_stream(&mut comp, "/medium"), create_data(4095));
+        let names: Vec<String> = comp
+            .read_storage("/foo")
+            .unwrap()
+            .map(|entry| entry.name().to_string())
+            .collect();
+        assert_eq!(names, vec!["bar", "large", "small"]);
+    }
+}
+
+#[test]
+fn stream_from_reader() {
+    let large = create_data(10000);
+    let mut writer = StreamingWriter::new();
+    writer
+        .add_stream_from_reader("/known", Some(5000), large.as_slice())
+        .unwrap();
+    writer
+        .add_stream_from_reader(
+            "/unknown",
+            None,
+            Cursor::new(create_data(300)),
+        )
+        .unwrap();
+    let data = writer.write_to(Vec::new()).unwrap();
+    let mut comp = CompoundFile::open_strict(Cursor::new(data)).unwrap();
+    assert_eq!(read_stream(&mut comp, "/known"), create_data(5000));
+    assert_eq!(read_stream(&mut comp, "/unknown"), create_data(300));
+}
+
+#[test]
+fn stream_from_short_reader() {
+    let mut writer = StreamingWriter::new();
+    writer
+        .add_stream_from_reader("/short", Some(5000), Cursor::new(vec![0; 10]))
+        .unwrap();
+    let error = writer.write_to(Vec::new()).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn clsid_and_state_bits() {
+    let uuid =
+        Uuid::parse_str("F29F85E0-4FF9-1068-AB91-08002B27B3D9").unwrap();
+    let mut writer = StreamingWriter::new();
+    writer.add_storage("/foo").unwrap();
+    writer.add_stream("/foo/bar", vec![1, 2, 3]).unwrap();
+    writer.set_storage_clsid("/", uuid).unwrap();
+    writer.set_state_bits("/foo/bar", 0x1234).unwrap();
+    assert!(writer.set_storage_clsid("/foo/bar", uuid).is_err());
+    let data = writer.write_to(Vec::new()).unwrap();
+    let comp = CompoundFile::open_strict(Cursor::new(data)).unwrap();
+    assert_eq!(comp.root_entry().clsid(), &uuid);
+    assert_eq!(comp.entry("/foo/bar").unwrap().state_bits(), 0x1234);
+}
+
+#[test]
+fn invalid_declarations() {
+    let mut writer = StreamingWriter::new();
+    writer.add_stream("/foo", vec![]).unwrap();
+    let error = writer.add_storage("/FOO").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+    let error = writer.add_stream("/bar/baz", vec![]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    let error = writer.add_stream("/foo/baz", vec![]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    let error = writer.add_stream("/a:b", vec![]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn many_entries() {
+    let mut writer = StreamingWriter::with_version(Version::V3);
+    for index in 0..200 {
+        let path = format!("/{}", index);
+        writer.add_storage(&path).unwrap();
+        writer
+            .add_stream(Path::new(&path).join("data"), create_data(index * 7))
+            .unwrap();
+    }
+    let data = writer.write_to(Vec::new()).unwrap();
+    let mut comp = CompoundFile::open_strict(Cursor::new(data)).unwrap();
+    for index in 0..200 {
+        let path = format!("/{}/data", index);
+        assert_eq!(read_stream(&mut comp, &path), create_data(index * 7));
+    }
+}
+
+#[test]
+fn needs_difat_sectors() {
+    // A version 3 FAT sector covers 128 sectors, so 109 FAT sectors (the
+    // number that fit in the header) cover just under 7 MB.
+    let len = 8 * 1024 * 1024;
+    let mut writer = StreamingWriter::with_version(Version::V3);
+    writer.add_stream("/big", create_data(len)).unwrap();
+    writer.add_stream("/small", create_data(10)).unwrap();
+    let data = writer.write_to(Vec::new()).unwrap();
+    let mut comp = CompoundFile::open_strict(Cursor::new(data)).unwrap();
+    assert_eq!(read_stream(&mut comp, "/big"), create_data(len));
+    assert_eq!(read_stream(&mut comp, "/small"), create_data(10));
+}
+
+//===========================================================================//
@@ -0,0 +1,136 @@
+use cfb::{
+    CompoundFile, CompoundFileBuilder, StreamingWriter, Timestamp,
+    TimestampPolicy,
+};
+use std::io::{self, Cursor, Seek, SeekFrom, Write};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+//===========================================================================//
+
+fn generate(policy: TimestampPolicy) -> Vec<u8> {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.set_timestamp_policy(policy);
+    comp.create_storage("/foo").unwrap();
+    comp.create_stream("/foo/bar").unwrap().write_all(&[1; 5000]).unwrap();
+    comp.create_stream("/baz").unwrap().write_all(b"baz").unwrap();
+    comp.touch("/foo").unwrap();
+    comp.into_inner().into_inner()
+}
+
+fn some_time() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(1_500_000_000)
+}
+
+//===========================================================================//
+
+#[test]
+fn default_policy_is_now() {
+    let comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    assert_eq!(comp.timestamp_policy(), TimestampPolicy::Now);
+}
+
+#[test]
+fn reproducible_output() {
+    for &policy in &[
+        TimestampPolicy::Zero,
+        TimestampPolicy::Fixed(some_time()),
+        TimestampPolicy::Preserve,
+    ] {
+        let data1 = generate(policy);
+        std::thread::sleep(Duration::from_millis(10));
+        let data2 = generate(policy);
+        assert!(data1 == data2, "output differs for {:?}", policy);
+    }
+}
+
+#[test]
+fn fixed_policy() {
+    let policy = TimestampPolicy::Fixed(some_time());
+    let comp = CompoundFile::open(Cursor::new(generate(policy))).unwrap();
+    for path in &["/foo", "/foo/bar", "/baz"] {
+        let entry = comp.entry(path).unwrap();
+        assert_eq!(entry.created(), Some(some_time()));
+        assert_eq!(entry.modified(), Some(some_time()));
+    }
+}
+
+#[test]
+fn preserve_policy_keeps_existing_timestamps() {
+    let policy = TimestampPolicy::Fixed(some_time());
+    let cursor = Cursor::new(generate(policy));
+    let mut comp = CompoundFile::open(cursor).unwrap();
+    comp.set_timestamp_policy(TimestampPolicy::Preserve);
+    {
+        let mut stream = comp.open_stream("/foo/bar").unwrap();
+        stream.seek(SeekFrom::End(0)).unwrap();
+        stream.write_all(&[2; 100]).unwrap();
+        stream.set_len(10).unwrap();
+    }
+    comp.touch("/baz").unwrap();
+    comp.create_stream("/new").unwrap();
+    let some_time = Some(some_time());
+    assert_eq!(comp.entry("/foo/bar").unwrap().modified(), some_time);
+    assert_eq!(comp.entry("/baz").unwrap().modified(), some_time);
+    assert_eq!(comp.entry("/new").unwrap().created(), None);
+    assert_eq!(comp.entry("/new").unwrap().modified(), None);
+}
+
+#[test]
+fn modifications_use_policy() {
+    let cursor = Cursor::new(generate(TimestampPolicy::Zero));
+    let mut comp = CompoundFile::open(cursor).unwrap();
+    comp.set_timestamp_policy(TimestampPolicy::Fixed(some_time()));
+    comp.open_stream("/baz").unwrap().write_all(b"BAZ").unwrap();
+    let entry = comp.entry("/baz").unwrap();
+    assert_eq!(entry.modified(), Some(some_time()));
+    assert_eq!(entry.created(), None);
+}
+
+#[test]
+fn builder_and_streaming_writer() {
+    let mut builder = CompoundFileBuilder::new();
+    builder.set_timestamp_policy(TimestampPolicy::Fixed(some_time()));
+    builder.create_storage("/foo").unwrap();
+    builder.create_stream("/foo/bar", vec![1; 100]).unwrap();
+    let data1 = builder.build(Vec::new()).unwrap();
+
+    std::thread::sleep(Duration::from_millis(10));
+    let mut writer = StreamingWriter::new();
+    writer.set_timestamp_policy(TimestampPolicy::Fixed(some_time()));
+    writer.add_storage("/foo").unwrap();
+    writer.add_stream("/foo/bar", vec![1; 100]).unwrap();
+    let data2 = writer.write_to(Vec::new()).unwrap();
+    assert!(data1 == data2);
+
+    let comp = CompoundFile::open_strict(Cursor::new(data1)).unwrap();
+    assert_eq!(comp.entry("/foo/bar").unwrap().modified(), Some(some_time()));
+}
+
+#[test]
+fn set_timestamps() {
+    let cursor = Cursor::new(generate(TimestampPolicy::Now));
+    let mut comp = CompoundFile::open(cursor).unwrap();
+    comp.set_created("/foo/bar", some_time()).unwrap();
+    comp.set_modified("/foo/bar", Timestamp::from_filetime(1234)).unwrap();
+    comp.set_created("/baz", Timestamp::zero()).unwrap();
+    comp.set_modified("/", some_time()).unwrap();
+    let error = comp.set_created("/", some_time()).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    let error = comp.set_modified("/nope", some_time()).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+    let comp = CompoundFile::open(comp.into_inner()).unwrap();
+    let entry = comp.entry("/foo/bar").unwrap();
+    assert_eq!(entry.created(), Some(some_time()));
+    assert_eq!(entry.created_timestamp(), Timestamp::from(some_time()));
+    assert_eq!(entry.modified_timestamp().filetime(), 1234);
+    let entry = comp.entry("/baz").unwrap();
+    assert_eq!(entry.created(), None);
+    assert!(entry.created_timestamp().is_zero());
+    assert!(entry.modified().is_some());
+    assert_eq!(comp.root_entry().created(), None);
+    assert_eq!(comp.root_entry().modified(), Some(some_time()));
+}
+
+//===========================================================================//
@@ -0,0 +1,130 @@
+use cfb::CompoundFile;
+use std::io::{self, Cursor, Read, Write};
+use std::path::Path;
+
+//===========================================================================//
+
+// A name containing an unpaired high surrogate, which is not valid UTF-16
+// but is permitted in compound files.
+const BAD_NAME: &[u16] = &[0x41, 0xd800, 0x42];
+
+//===========================================================================//
+
+#[test]
+fn unpaired_surrogate_names_round_trip() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_storage("/foo").unwrap();
+    comp.create_stream_utf16("/foo", BAD_NAME)
+        .unwrap()
+        .write_all(b"data")
+        .unwrap();
+    comp.create_storage_utf16("/", &[0xdc00]).unwrap();
+    comp.create_stream("/foo/AB").unwrap();
+
+    let comp = CompoundFile::open_strict(comp.into_inner()).unwrap();
+    let entry = comp.entry_utf16("/foo", BAD_NAME).unwrap();
+    assert!(entry.is_stream());
+    assert_eq!(entry.name_utf16(), BAD_NAME);
+    assert_eq!(entry.name(), "A\u{fffd}B");
+    assert_eq!(entry.path(), Path::new("/foo/A\u{fffd}B"));
+    assert_eq!(entry.len(), 4);
+    let entry = comp.entry_utf16("/", &[0xdc00]).unwrap();
+    assert!(entry.is_storage());
+
+    let names: Vec<Vec<u16>> = comp
+        .read_storage("/foo")
+        .unwrap()
+        .map(|entry| entry.name_utf16().to_vec())
+        .collect();
+    assert_eq!(names, vec![vec![0x41, 0x42], BAD_NAME.to_vec()]);
+
+    let mut comp = comp;
+    let id = comp.entry_utf16("/foo", BAD_NAME).unwrap().id();
+    let mut data = Vec::new();
+    comp.open_stream_by_id(id).unwrap().read_to_end(&mut data).unwrap();
+    assert_eq!(data, b"data");
+    let cursor = comp.cursor("/foo").unwrap().child_utf16(BAD_NAME).unwrap();
+    assert_eq!(cursor.id(), id);
+}
+
+#[test]
+fn utf16_creation_errors() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_stream("/foo").unwrap();
+    comp.create_storage_utf16("/", BAD_NAME).unwrap();
+
+    let error = comp.create_storage_utf16("/", BAD_NAME).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+    let error = comp.create_stream_utf16("/", BAD_NAME).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+    let error = comp.create_stream_utf16("/foo", BAD_NAME).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    let error = comp.create_stream_utf16("/nope", BAD_NAME).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    let error = comp.create_stream_utf16("/", &[0x61, 0x2f]).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    let error = comp.create_stream_utf16("/", &[0x61; 32]).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    let error = comp.entry_utf16("/", &[0xd800]).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+
+    // Creating a stream over an existing one truncates it.
+    comp.create_stream_utf16("/", &[0xd800]).unwrap().write_all(b"x").unwrap();
+    let stream = comp.create_stream_utf16("/", &[0xd800]).unwrap();
+    assert_eq!(stream.len(), 0);
+}
+
+#[test]
+fn open_and_remove_by_utf16_name() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_stream_utf16("/", BAD_NAME).unwrap().write_all(b"x").unwrap();
+    comp.create_stream("/A\u{fffd}B").unwrap();
+    comp.create_storage_utf16("/", &[0xdc00]).unwrap();
+
+    let mut data = Vec::new();
+    comp.open_stream_utf16("/", BAD_NAME)
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(data, b"x");
+    let error = comp.open_stream_utf16("/", &[0xdc00]).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    let error = comp.remove_storage_utf16("/", BAD_NAME).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+    comp.remove_stream_utf16("/", BAD_NAME).unwrap();
+    comp.remove_storage_utf16("/", &[0xdc00]).unwrap();
+    let error = comp.remove_stream_utf16("/", BAD_NAME).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    let error = comp.open_stream_utf16("/", BAD_NAME).err().unwrap();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+    // The stream whose (valid) name matches the lossy form is untouched.
+    let names: Vec<String> =
+        comp.read_root_storage().map(|e| e.name().to_string()).collect();
+    assert_eq!(names, vec!["A\u{fffd}B"]);
+}
+
+#[test]
+fn remove_storage_all_with_unpaired_surrogates() {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_storage("/foo").unwrap();
+    comp.create_stream_utf16("/foo", BAD_NAME).unwrap();
+    comp.create_storage_utf16("/foo", &[0xdc00]).unwrap();
+    // A sibling whose name is what the invalid names turn into when decoded
+    // lossily must not be removed in their place.
+    comp.create_stream("/A\u{fffd}B").unwrap();
+    comp.create_storage("/\u{fffd}").unwrap();
+
+    comp.remove_storage_all("/foo").unwrap();
+    assert!(!comp.exists("/foo"));
+    assert!(comp.is_stream("/A\u{fffd}B"));
+    assert!(comp.is_storage("/\u{fffd}"));
+    let comp = CompoundFile::open_strict(comp.into_inner()).unwrap();
+    assert_eq!(comp.read_root_storage().count(), 2);
+}
+
+//===========================================================================//
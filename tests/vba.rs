@@ -0,0 +1,124 @@
+use cfb::vba::{self, ModuleKind};
+use cfb::CompoundFile;
+use std::io::{self, Cursor, Write};
+
+//===========================================================================//
+
+const MODULE1_SOURCE: &str = "Attribute VB_Name = \"Module1\"\r\n\
+    Sub AutoOpen()\r\n    MsgBox \"Caf\u{e9}\"\r\nEnd Sub\r\n";
+const THIS_DOCUMENT_SOURCE: &str = "Attribute VB_Name = \"ThisDocument\"\r\n\
+    Attribute VB_Base = \"1Normal.ThisDocument\"\r\n";
+
+fn record(data: &mut Vec<u8>, id: u16, body: &[u8]) {
+    data.extend_from_slice(&id.to_le_bytes());
+    data.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    data.extend_from_slice(body);
+}
+
+fn module_records(data: &mut Vec<u8>, name: &str, offset: u32, id: u16) {
+    record(data, 0x0019, name.as_bytes());
+    record(data, 0x001a, name.as_bytes());
+    record(data, 0x0031, &offset.to_le_bytes());
+    record(data, id, b"");
+    record(data, 0x002b, b"");
+}
+
+fn dir_stream(offsets: [u32; 2]) -> Vec<u8> {
+    let mut data = Vec::new();
+    record(&mut data, 0x0001, &1u32.to_le_bytes());
+    record(&mut data, 0x0003, &1252u16.to_le_bytes());
+    record(&mut data, 0x0004, b"Project");
+    record(&mut data, 0x0009, &0x1234u32.to_le_bytes());
+    data.extend_from_slice(&0x10u16.to_le_bytes());
+    record(&mut data, 0x000f, &2u16.to_le_bytes());
+    module_records(&mut data, "Module1", offsets[0], 0x0021);
+    module_records(&mut data, "ThisDocument", offsets[1], 0x0022);
+    record(&mut data, 0x0010, b"");
+    let mut compressed = Vec::new();
+    vba::compress(data.as_slice(), &mut compressed).unwrap();
+    compressed
+}
+
+fn module_stream(cache_len: usize, source: &str) -> Vec<u8> {
+    let mut data = vec![0xcc; cache_len];
+    let source: Vec<u8> = source
+        .chars()
+        .map(|chr| if chr == '\u{e9}' { 0xe9 } else { chr as u8 })
+        .collect();
+    vba::compress(source.as_slice(), &mut data).unwrap();
+    data
+}
+
+fn write_project(
+    comp: &mut CompoundFile<Cursor<Vec<u8>>>,
+    root: &str,
+    offsets: [u32; 2],
+) {
+    let storage = format!("{}/VBA", root.trim_end_matches('/'));
+    comp.create_storage_all(&storage).unwrap();
+    comp.create_stream(format!("{}/dir", storage))
+        .unwrap()
+        .write_all(&dir_stream(offsets))
+        .unwrap();
+    comp.create_stream(format!("{}/Module1", storage))
+        .unwrap()
+        .write_all(&module_stream(100, MODULE1_SOURCE))
+        .unwrap();
+    comp.create_stream(format!("{}/ThisDocument", storage))
+        .unwrap()
+        .write_all(&module_stream(0, THIS_DOCUMENT_SOURCE))
+        .unwrap();
+}
+
+//===========================================================================//
+
+#[test]
+fn read_word_project() {
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    assert!(comp.vba_project().unwrap().is_none());
+    write_project(&mut comp, "/Macros", [100, 0]);
+
+    let project = comp.vba_project().unwrap().unwrap();
+    assert_eq!(project.name(), "Project");
+    let names: Vec<&str> =
+        project.modules().iter().map(|module| module.name()).collect();
+    assert_eq!(names, vec!["Module1", "ThisDocument"]);
+    let module = project.module("Module1").unwrap();
+    assert_eq!(module.kind(), ModuleKind::Procedural);
+    assert_eq!(module.source(), MODULE1_SOURCE);
+    assert!(module.source_bytes().contains(&0xe9));
+    let module = project.module("ThisDocument").unwrap();
+    assert_eq!(module.kind(), ModuleKind::Document);
+    assert_eq!(module.source(), THIS_DOCUMENT_SOURCE);
+    assert_eq!(comp.vba_project_at("/Macros").unwrap(), project);
+}
+
+#[test]
+fn read_standalone_project() {
+    // A vbaProject.bin file keeps its project at the root.
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    write_project(&mut comp, "/", [100, 0]);
+    let project = comp.vba_project().unwrap().unwrap();
+    assert_eq!(project.modules().len(), 2);
+    assert_eq!(project.modules()[0].source(), MODULE1_SOURCE);
+}
+
+#[test]
+fn bad_module_offsets() {
+    let mut comp = CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+    // An offset that doesn't point at the compressed source.
+    write_project(&mut comp, "/_VBA_PROJECT_CUR", [99, 0]);
+    let error = comp.vba_project().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    // An offset past the end of the module stream.
+    write_project(&mut comp, "/_VBA_PROJECT_CUR", [100, 10_000]);
+    let error = comp.vba_project().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    // A missing module stream.
+    write_project(&mut comp, "/_VBA_PROJECT_CUR", [100, 0]);
+    comp.remove_stream("/_VBA_PROJECT_CUR/VBA/ThisDocument").unwrap();
+    let error = comp.vba_project_at("/_VBA_PROJECT_CUR").unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+}
+
+//===========================================================================//
@@ -0,0 +1,170 @@
+use cfb::{CompoundFile, WalkOptions, WalkOrder};
+use std::io::Cursor;
+use std::path::PathBuf;
+
+//===========================================================================//
+
+fn make_file() -> CompoundFile<Cursor<Vec<u8>>> {
+    let cursor = Cursor::new(Vec::new());
+    let mut comp = CompoundFile::create(cursor).unwrap();
+    comp.create_storage_all("/ObjectPool/_1234").unwrap();
+    comp.create_storage_all("/ObjectPool/_5678/sub").unwrap();
+    comp.create_stream("/ObjectPool/_1234/\u{1}Ole").unwrap();
+    comp.create_stream("/ObjectPool/_1234/\u{1}CompObj").unwrap();
+    comp.create_stream("/ObjectPool/_5678/\u{1}Ole10Native").unwrap();
+    comp.create_stream("/ObjectPool/_5678/sub/data.BIN").unwrap();
+    comp.create_stream("/a.bin").unwrap();
+    comp.create_stream("/zz").unwrap();
+    comp
+}
+
+fn walk(
+    comp: &CompoundFile<Cursor<Vec<u8>>>,
+    path: &str,
+    options: WalkOptions,
+) -> Vec<String> {
+    comp.walk_storage_with(path, options)
+        .unwrap()
+        .map(|entry| entry.path().to_string_lossy().into_owned())
+        .collect()
+}
+
+//===========================================================================//
+
+#[test]
+fn default_options_match_walk_storage() {
+    let comp = make_file();
+    let expected: Vec<PathBuf> =
+        comp.walk().map(|entry| entry.path().to_path_buf()).collect();
+    let actual: Vec<PathBuf> = comp
+        .walk_storage_with("/", WalkOptions::new())
+        .unwrap()
+        .map(|entry| entry.path().to_path_buf())
+        .collect();
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn postorder_and_breadth_first() {
+    let comp = make_file();
+    let options = WalkOptions::new().order(WalkOrder::Postorder);
+    assert_eq!(
+        walk(&comp, "/ObjectPool/_5678", options),
+        vec![
+            "/ObjectPool/_5678/sub/data.BIN",
+            "/ObjectPool/_5678/sub",
+            "/ObjectPool/_5678/\u{1}Ole10Native",
+            "/ObjectPool/_5678",
+        ]
+    );
+    let options = WalkOptions::new().order(WalkOrder::BreadthFirst);
+    assert_eq!(
+        walk(&comp, "/", options),
+        vec![
+            "/",
+            "/zz",
+            "/a.bin",
+            "/ObjectPool",
+            "/ObjectPool/_1234",
+            "/ObjectPool/_5678",
+            "/ObjectPool/_1234/\u{1}Ole",
+            "/ObjectPool/_1234/\u{1}CompObj",
+            "/ObjectPool/_5678/sub",
+            "/ObjectPool/_5678/\u{1}Ole10Native",
+            "/ObjectPool/_5678/sub/data.BIN",
+        ]
+    );
+}
+
+#[test]
+fn max_depth() {
+    let comp = make_file();
+    assert_eq!(walk(&comp, "/", WalkOptions::new().max_depth(0)), vec!["/"]);
+    assert_eq!(
+        walk(&comp, "/ObjectPool", WalkOptions::new().max_depth(1)),
+        vec!["/ObjectPool", "/ObjectPool/_1234", "/ObjectPool/_5678"]
+    );
+    let options = WalkOptions::new().order(WalkOrder::Postorder).max_depth(1);
+    assert_eq!(
+        walk(&comp, "/ObjectPool", options),
+        vec!["/ObjectPool/_1234", "/ObjectPool/_5678", "/ObjectPool"]
+    );
+}
+
+#[test]
+fn filter_prunes_subtrees() {
+    let comp = make_file();
+    let options =
+        WalkOptions::new().filter_entry(|entry| entry.name() != "_5678");
+    let paths = walk(&comp, "/ObjectPool", options);
+    assert_eq!(
+        paths,
+        vec![
+            "/ObjectPool",
+            "/ObjectPool/_1234",
+            "/ObjectPool/_1234/\u{1}Ole",
+            "/ObjectPool/_1234/\u{1}CompObj",
+        ]
+    );
+    let options = WalkOptions::new()
+        .order(WalkOrder::Postorder)
+        .filter_entry(|entry| entry.is_storage());
+    assert_eq!(
+        walk(&comp, "/ObjectPool/_5678", options),
+        vec!["/ObjectPool/_5678/sub", "/ObjectPool/_5678"]
+    );
+}
+
+#[test]
+fn glob_patterns() {
+    let comp = make_file();
+    let options = WalkOptions::new().pattern("/objectpool/*/\u{1}OLE*");
+    assert_eq!(
+        walk(&comp, "/", options),
+        vec![
+            "/ObjectPool/_1234/\u{1}Ole",
+            "/ObjectPool/_5678/\u{1}Ole10Native"
+        ]
+    );
+    let options = WalkOptions::new().pattern("**/*.bin");
+    assert_eq!(
+        walk(&comp, "/", options),
+        vec!["/a.bin", "/ObjectPool/_5678/sub/data.BIN"]
+    );
+    // Relative patterns are relative to the starting storage.
+    let options = WalkOptions::new().pattern("*/sub");
+    assert_eq!(
+        walk(&comp, "/ObjectPool", options),
+        vec!["/ObjectPool/_5678/sub"]
+    );
+    let options = WalkOptions::new().pattern("/ObjectPool/*");
+    assert_eq!(
+        walk(&comp, "/ObjectPool/_1234", options),
+        vec!["/ObjectPool/_1234"]
+    );
+    let options = WalkOptions::new().pattern("/zz/*");
+    assert!(walk(&comp, "/ObjectPool", options).is_empty());
+    let options = WalkOptions::new().pattern("../x");
+    assert!(comp.walk_storage_with("/", options).is_err());
+}
+
+#[test]
+fn remove_storage_all_in_postorder() {
+    let mut comp = make_file();
+    comp.remove_storage_all("/ObjectPool").unwrap();
+    assert!(!comp.exists("/ObjectPool"));
+    let paths: Vec<PathBuf> =
+        comp.walk().map(|entry| entry.path().to_path_buf()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            PathBuf::from("/"),
+            PathBuf::from("/zz"),
+            PathBuf::from("/a.bin")
+        ]
+    );
+    let comp = CompoundFile::open_strict(comp.into_inner()).unwrap();
+    assert_eq!(comp.walk().count(), 3);
+}
+
+//===========================================================================//